@@ -44,7 +44,7 @@ use sp_consensus::SyncOracle;
 use sp_consensus_subspace::{
     ChainConstants, FarmerPublicKey, FarmerSignature, SubspaceApi as SubspaceRuntimeApi,
 };
-use sp_core::crypto::ByteArray;
+use sp_core::crypto::{default_ss58_version, ByteArray};
 use sp_core::H256;
 use sp_objects::ObjectsApi;
 use sp_runtime::traits::Block as BlockT;
@@ -319,6 +319,7 @@ where
                     .as_duration()
                     .mul_f64(SlotNumber::from(chain_constants.block_authoring_delay()) as f64),
                 protocol_info,
+                ss58_format: Some(default_ss58_version().prefix()),
             }
         };
 
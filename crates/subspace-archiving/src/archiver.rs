@@ -846,36 +846,101 @@ pub fn is_piece_valid(
     segment_commitment: &SegmentCommitment,
     position: u32,
 ) -> bool {
-    let (record, commitment, witness) = piece.split();
-    let witness = match Witness::try_from_bytes(witness) {
-        Ok(witness) => witness,
-        _ => {
-            return false;
-        }
+    let Some((commitment_hash, witness)) = piece_commitment_hash_and_witness(kzg, piece) else {
+        return false;
+    };
+
+    let Ok(segment_commitment) = Commitment::try_from(segment_commitment) else {
+        return false;
+    };
+
+    kzg.verify(
+        &segment_commitment,
+        ArchivedHistorySegment::NUM_PIECES,
+        position,
+        &commitment_hash,
+        &witness,
+    )
+}
+
+/// Validate witnesses embedded within multiple pieces of the same segment produced by archiver.
+///
+/// Each piece is provided together with its position in the segment. Returns validity of each
+/// piece in the same order as pieces were provided.
+///
+/// This is equivalent to calling [`is_piece_valid()`] for each piece, but witnesses of all pieces
+/// are checked at once, which is much cheaper. If batch check fails, pieces are checked one by one
+/// to find which of them are invalid.
+pub fn are_pieces_valid(
+    kzg: &Kzg,
+    pieces: &[(u32, &PieceArray)],
+    segment_commitment: &SegmentCommitment,
+) -> Vec<bool> {
+    let Ok(segment_commitment) = Commitment::try_from(segment_commitment) else {
+        return vec![false; pieces.len()];
     };
 
+    #[cfg(not(feature = "parallel"))]
+    let iter = pieces.iter();
+    #[cfg(feature = "parallel")]
+    let iter = pieces.par_iter();
+
+    // Checking record commitments can't be amortized, but it is independent for each piece
+    let evaluations = iter
+        .map(|(position, piece)| {
+            piece_commitment_hash_and_witness(kzg, piece)
+                .map(|(commitment_hash, witness)| (*position, commitment_hash, witness))
+        })
+        .collect::<Vec<_>>();
+
+    let mut results = evaluations
+        .iter()
+        .map(|maybe_evaluation| maybe_evaluation.is_some())
+        .collect::<Vec<_>>();
+    let evaluations = evaluations.into_iter().flatten().collect::<Vec<_>>();
+
+    if kzg.verify_batch(
+        &segment_commitment,
+        ArchivedHistorySegment::NUM_PIECES,
+        &evaluations,
+    ) {
+        return results;
+    }
+
+    // At least one of the pieces is invalid, find which one(s)
+    results
+        .iter_mut()
+        .filter(|valid| **valid)
+        .zip(evaluations)
+        .for_each(|(valid, (position, commitment_hash, witness))| {
+            *valid = kzg.verify(
+                &segment_commitment,
+                ArchivedHistorySegment::NUM_PIECES,
+                position,
+                &commitment_hash,
+                &witness,
+            );
+        });
+
+    results
+}
+
+/// Check that record commitment embedded within a piece matches its record and return hash of the
+/// commitment together with witness, such that it can be checked against segment commitment.
+fn piece_commitment_hash_and_witness(kzg: &Kzg, piece: &PieceArray) -> Option<(Scalar, Witness)> {
+    let (record, commitment, witness) = piece.split();
+    let witness = Witness::try_from_bytes(witness).ok()?;
+
     let mut scalars = Vec::with_capacity(record.len().next_power_of_two());
 
     for record_chunk in record.iter() {
-        match Scalar::try_from(record_chunk) {
-            Ok(scalar) => {
-                scalars.push(scalar);
-            }
-            _ => {
-                return false;
-            }
-        }
+        scalars.push(Scalar::try_from(record_chunk).ok()?);
     }
 
     // Number of scalars for KZG must be a power of two elements
     scalars.resize(scalars.capacity(), Scalar::default());
 
-    let polynomial = match kzg.poly(&scalars) {
-        Ok(polynomial) => polynomial,
-        _ => {
-            return false;
-        }
-    };
+    let polynomial = kzg.poly(&scalars).ok()?;
 
     if kzg
         .commit(&polynomial)
@@ -883,22 +948,10 @@ pub fn is_piece_valid(
         .as_ref()
         != Ok(commitment)
     {
-        return false;
+        return None;
     }
 
-    let Ok(segment_commitment) = Commitment::try_from(segment_commitment) else {
-        return false;
-    };
-
-    let commitment_hash = blake3_254_hash_to_scalar(commitment.as_ref());
-
-    kzg.verify(
-        &segment_commitment,
-        ArchivedHistorySegment::NUM_PIECES,
-        position,
-        &commitment_hash,
-        &witness,
-    )
+    Some((blake3_254_hash_to_scalar(commitment.as_ref()), witness))
 }
 
 /// Validate witness for record commitment hash produced by archiver
@@ -11,8 +11,8 @@ use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
 use subspace_core_primitives::crypto::Scalar;
 use subspace_core_primitives::objects::{BlockObject, BlockObjectMapping, PieceObject};
 use subspace_core_primitives::{
-    ArchivedBlockProgress, ArchivedHistorySegment, Blake3Hash, LastArchivedBlock, PieceArray,
-    Record, RecordedHistorySegment, SegmentCommitment, SegmentHeader, SegmentIndex,
+    ArchivedBlockProgress, ArchivedHistorySegment, Blake3Hash, LastArchivedBlock, Piece,
+    PieceArray, Record, RecordedHistorySegment, SegmentCommitment, SegmentHeader, SegmentIndex,
 };
 
 fn extract_data<O: Into<u64>>(data: &[u8], offset: O) -> &[u8] {
@@ -664,3 +664,68 @@ fn object_on_the_edge_of_segment() {
         mapped_bytes
     );
 }
+
+#[test]
+fn batch_piece_validation() {
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let mut archiver = Archiver::new(kzg.clone()).unwrap();
+
+    let block = {
+        let mut block = vec![0u8; RecordedHistorySegment::SIZE];
+        thread_rng().fill(block.as_mut_slice());
+        block
+    };
+    let archived_segments = archiver.add_block(block, BlockObjectMapping::default(), true);
+    let archived_segment = archived_segments.first().unwrap();
+    let segment_commitment = archived_segment.segment_header.segment_commitment();
+
+    // Only a subset of pieces (both source and parity) to keep memory usage reasonable
+    let mut pieces = archived_segment
+        .pieces
+        .iter()
+        .enumerate()
+        .step_by(16)
+        .map(|(position, piece)| (position as u32, Piece::from(piece)))
+        .collect::<Vec<_>>();
+
+    let results = archiver::are_pieces_valid(
+        &kzg,
+        &pieces
+            .iter()
+            .map(|(position, piece)| (*position, &**piece))
+            .collect::<Vec<_>>(),
+        &segment_commitment,
+    );
+    assert_eq!(results, vec![true; pieces.len()]);
+
+    // Swap witnesses of two pieces and damage record of another one
+    {
+        let witness = *pieces[1].1.witness();
+        *pieces[1].1.witness_mut() = *pieces[2].1.witness();
+        *pieces[2].1.witness_mut() = witness;
+        pieces[5].1.record_mut()[0][0] ^= 1;
+    }
+
+    let results = archiver::are_pieces_valid(
+        &kzg,
+        &pieces
+            .iter()
+            .map(|(position, piece)| (*position, &**piece))
+            .collect::<Vec<_>>(),
+        &segment_commitment,
+    );
+    for (index, ((position, piece), valid)) in pieces.iter().zip(results).enumerate() {
+        assert_eq!(
+            valid,
+            archiver::is_piece_valid(&kzg, piece, &segment_commitment, *position),
+            "Batch and individual validation must agree for piece at position {position}"
+        );
+        assert_eq!(
+            valid,
+            ![1, 2, 5].contains(&index),
+            "Unexpected validity for piece at position {position}"
+        );
+    }
+
+    assert!(archiver::are_pieces_valid(&kzg, &[], &segment_commitment).is_empty());
+}
@@ -5,7 +5,7 @@ mod tests;
 
 extern crate alloc;
 
-use crate::crypto::Scalar;
+use crate::crypto::{blake3_254_hash_to_scalar, blake3_hash, Scalar};
 use alloc::collections::btree_map::Entry;
 use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
@@ -14,10 +14,12 @@ use alloc::vec::Vec;
 use core::mem;
 use derive_more::{AsMut, AsRef, Deref, DerefMut, From, Into};
 use kzg::eip_4844::{BYTES_PER_G1, BYTES_PER_G2};
-use kzg::{FFTFr, FFTSettings, Fr, KZGSettings, G1, G2};
+use kzg::{FFTFr, FFTSettings, Fr, G1Mul, KZGSettings, G1, G2};
 #[cfg(feature = "std")]
 use parking_lot::Mutex;
+use rust_kzg_blst::kzg_proofs::pairings_verify;
 use rust_kzg_blst::types::fft_settings::FsFFTSettings;
+use rust_kzg_blst::types::fr::FsFr;
 use rust_kzg_blst::types::g1::FsG1;
 use rust_kzg_blst::types::g2::FsG2;
 use rust_kzg_blst::types::kzg_settings::FsKZGSettings;
@@ -441,6 +443,84 @@ impl Kzg {
         }
     }
 
+    /// Verifies multiple evaluations of the same polynomial created from `num_values` values
+    /// matching the `commitment`, each evaluation is a tuple of `(index, value, witness)`.
+    ///
+    /// This is equivalent to calling [`Kzg::verify()`] for every evaluation, but all checks are
+    /// combined into a single pairing check using random linear combination, which is much cheaper.
+    /// Returns `false` if at least one evaluation is invalid, [`Kzg::verify()`] can be used to find
+    /// which one.
+    pub fn verify_batch(
+        &self,
+        commitment: &Commitment,
+        num_values: usize,
+        evaluations: &[(u32, Scalar, Witness)],
+    ) -> bool {
+        if evaluations.is_empty() {
+            return true;
+        }
+
+        let fft_settings = match self.get_fft_settings(num_values) {
+            Ok(fft_settings) => fft_settings,
+            Err(error) => {
+                debug!(error, "Failed to derive fft settings");
+                return false;
+            }
+        };
+
+        // Coefficients of linear combination are derived from all inputs, such that whoever
+        // created witnesses is not able to predict them
+        let seed = {
+            let mut transcript = Vec::with_capacity(
+                Commitment::SIZE
+                    + evaluations.len()
+                        * (mem::size_of::<u32>() + Scalar::FULL_BYTES + Witness::SIZE),
+            );
+            transcript.extend_from_slice(&commitment.to_bytes());
+            for (index, value, witness) in evaluations {
+                transcript.extend_from_slice(&index.to_le_bytes());
+                transcript.extend_from_slice(&value.to_bytes());
+                transcript.extend_from_slice(&witness.to_bytes());
+            }
+            blake3_hash(&transcript)
+        };
+
+        // Single evaluation check is `e(C - [y]G1, G2) == e(W, [s]G2 - [x]G2)`, which is the same
+        // as `e(C - [y]G1 + [x]W, G2) == e(W, [s]G2)`. With random coefficients `r` it becomes
+        // `e(Σr * C - [Σr * y]G1 + Σr * [x]W, G2) == e(Σr * W, [s]G2)`.
+        let mut lhs = FsG1::identity();
+        let mut witnesses_sum = FsG1::identity();
+        let mut values_sum = FsFr::zero();
+        let mut coefficients_sum = FsFr::zero();
+
+        for (position, (index, value, witness)) in evaluations.iter().enumerate() {
+            let mut coefficient_input = [0; Scalar::FULL_BYTES + mem::size_of::<u64>()];
+            coefficient_input[..Scalar::FULL_BYTES].copy_from_slice(&seed);
+            coefficient_input[Scalar::FULL_BYTES..]
+                .copy_from_slice(&(position as u64).to_le_bytes());
+            let coefficient = FsFr::from(blake3_254_hash_to_scalar(&coefficient_input));
+
+            let x = fft_settings.get_expanded_roots_of_unity_at(*index as usize);
+
+            let weighted_witness = witness.0.mul(&coefficient);
+            lhs = lhs.add_or_dbl(&weighted_witness.mul(&x));
+            witnesses_sum = witnesses_sum.add_or_dbl(&weighted_witness);
+            values_sum = values_sum.add(&coefficient.mul(&value.0));
+            coefficients_sum = coefficients_sum.add(&coefficient);
+        }
+
+        let lhs = lhs
+            .add_or_dbl(&commitment.0.mul(&coefficients_sum))
+            .sub(&FsG1::generator().mul(&values_sum));
+
+        let Some(secret_g2) = self.inner.kzg_settings.secret_g2.get(1) else {
+            debug!("KZG settings don't contain enough G2 powers");
+            return false;
+        };
+
+        pairings_verify(&lhs, &FsG2::generator(), &witnesses_sum, secret_g2)
+    }
+
     /// Get FFT settings for specified number of values, uses internal cache to avoid derivation
     /// every time.
     pub fn get_fft_settings(&self, num_values: usize) -> Result<Arc<FsFFTSettings>, String> {
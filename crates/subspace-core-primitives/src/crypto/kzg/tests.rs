@@ -24,3 +24,43 @@ fn basic() {
         );
     }
 }
+
+#[test]
+fn batch() {
+    let values = (0..8)
+        .map(|_| Scalar::from(rand::random::<[u8; Scalar::SAFE_BYTES]>()))
+        .collect::<Vec<_>>();
+
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let polynomial = kzg.poly(&values).unwrap();
+    let commitment = kzg.commit(&polynomial).unwrap();
+
+    let num_values = values.len();
+
+    let mut evaluations = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let index = index.try_into().unwrap();
+            let witness = kzg.create_witness(&polynomial, num_values, index).unwrap();
+
+            (index, *value, witness)
+        })
+        .collect::<Vec<_>>();
+
+    assert!(kzg.verify_batch(&commitment, num_values, &[]));
+    assert!(kzg.verify_batch(&commitment, num_values, &evaluations));
+    assert!(kzg.verify_batch(&commitment, num_values, &evaluations[..1]));
+
+    // Single bad value must fail the whole batch
+    evaluations[3].1 = Scalar::from(rand::random::<[u8; Scalar::SAFE_BYTES]>());
+    assert!(!kzg.verify_batch(&commitment, num_values, &evaluations));
+    assert!(kzg.verify_batch(&commitment, num_values, &evaluations[..3]));
+
+    // Swapped witnesses must fail too
+    evaluations[3].1 = values[3];
+    let witness = evaluations[3].2;
+    evaluations[3].2 = evaluations[4].2;
+    evaluations[4].2 = witness;
+    assert!(!kzg.verify_batch(&commitment, num_values, &evaluations));
+}
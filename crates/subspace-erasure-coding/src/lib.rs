@@ -5,12 +5,14 @@ mod tests;
 
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::num::NonZeroUsize;
-use kzg::{FFTSettings, PolyRecover, DAS, FFTG1, G1};
+use kzg::{FFTSettings, Fr, PolyRecover, DAS, FFTG1, G1};
 use rust_kzg_blst::types::fft_settings::FsFFTSettings;
+use rust_kzg_blst::types::fr::FsFr;
 use rust_kzg_blst::types::g1::FsG1;
 use rust_kzg_blst::types::poly::FsPoly;
 use subspace_core_primitives::crypto::kzg::{Commitment, Polynomial};
@@ -44,13 +46,51 @@ impl ErasureCoding {
     ///
     /// Returns parity data.
     pub fn extend(&self, source: &[Scalar]) -> Result<Vec<Scalar>, String> {
-        // TODO: das_fft_extension modifies buffer internally, it needs to change to use
-        //  pre-allocated buffer instead of allocating a new one
         self.fft_settings
             .das_fft_extension(Scalar::slice_to_repr(source))
             .map(Scalar::vec_from_repr)
     }
 
+    /// Extend sources using erasure coding, writing parity data into provided buffer.
+    ///
+    /// The same as [`ErasureCoding::extend()`], but doesn't allocate, `parity` must have exactly
+    /// the same length as `source`.
+    pub fn extend_into(&self, source: &[Scalar], parity: &mut [Scalar]) -> Result<(), String> {
+        if source.is_empty() {
+            return Err("Source can't be empty".to_string());
+        }
+        if !source.len().is_power_of_two() {
+            return Err("Source length must be a power of 2".to_string());
+        }
+        if source.len() * 2 > self.max_shards() {
+            return Err(format!(
+                "Source length {} is too large, at most {} shards are supported",
+                source.len(),
+                self.max_shards() / 2
+            ));
+        }
+        if parity.len() != source.len() {
+            return Err(format!(
+                "Parity length {} doesn't match source length {}",
+                parity.len(),
+                source.len()
+            ));
+        }
+
+        parity.copy_from_slice(source);
+        let parity = Scalar::slice_mut_to_repr(parity);
+
+        let stride = self.max_shards() / (parity.len() * 2);
+        self.fft_settings.das_fft_extension_stride(parity, stride);
+
+        let inverse_length = FsFr::from_u64(parity.len() as u64).inverse();
+        for value in parity.iter_mut() {
+            *value = value.mul(&inverse_length);
+        }
+
+        Ok(())
+    }
+
     /// Recovery of missing shards from given shards (at least 1/2 should be `Some`).
     ///
     /// Both in input and output source shards are interleaved with parity shards:
@@ -72,6 +72,32 @@ fn basic_data() {
     );
 }
 
+#[test]
+fn into_buffers() {
+    let scale = NonZeroUsize::new(8).unwrap();
+    let num_shards = 2usize.pow(scale.get() as u32);
+    let ec = ErasureCoding::new(scale).unwrap();
+
+    let source_shards = (0..num_shards / 2)
+        .map(|_| rand::random::<[u8; Scalar::SAFE_BYTES]>())
+        .map(Scalar::from)
+        .collect::<Vec<_>>();
+
+    let mut parity_shards = vec![Scalar::default(); num_shards / 2];
+    ec.extend_into(&source_shards, &mut parity_shards).unwrap();
+
+    assert_eq!(parity_shards, ec.extend(&source_shards).unwrap());
+
+    // Buffer must be reusable
+    ec.extend_into(&source_shards, &mut parity_shards).unwrap();
+    assert_eq!(parity_shards, ec.extend(&source_shards).unwrap());
+
+    // Wrong output size must be rejected
+    assert!(ec
+        .extend_into(&source_shards, &mut parity_shards[1..])
+        .is_err());
+}
+
 #[test]
 fn basic_commitments() {
     let scale = NonZeroUsize::new(7).unwrap();
@@ -106,6 +132,8 @@ fn bad_shards_number() {
     let source_shards = vec![Default::default(); num_shards - 1];
 
     assert!(ec.extend(&source_shards).is_err());
+    let mut parity_shards = vec![Default::default(); num_shards - 1];
+    assert!(ec.extend_into(&source_shards, &mut parity_shards).is_err());
 
     let partial_shards = vec![Default::default(); num_shards - 1];
     assert!(ec.recover(&partial_shards).is_err());
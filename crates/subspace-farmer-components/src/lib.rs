@@ -21,10 +21,13 @@ pub mod plotting;
 pub mod proving;
 pub mod reading;
 pub mod sector;
-mod segment_reconstruction;
+pub mod segment_reconstruction;
 
 use crate::file_ext::FileExt;
 use async_trait::async_trait;
+use futures::stream::{BoxStream, FuturesUnordered};
+use futures::StreamExt;
+use parity_scale_codec::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 use static_assertions::const_assert;
 use std::fs::File;
@@ -51,6 +54,16 @@ impl Default for PieceGetterRetryPolicy {
     }
 }
 
+/// Stream of pieces returned by [`PieceGetter::get_pieces`], each requested piece index is yielded
+/// exactly once together with result of piece retrieval
+pub type PiecesStream<'a> = BoxStream<
+    'a,
+    (
+        PieceIndex,
+        Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>,
+    ),
+>;
+
 /// Trait representing a way to get pieces
 #[async_trait]
 pub trait PieceGetter {
@@ -59,6 +72,27 @@ pub trait PieceGetter {
         piece_index: PieceIndex,
         retry_policy: PieceGetterRetryPolicy,
     ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>;
+
+    /// Get multiple pieces at once, pieces are yielded in arbitrary order as they are retrieved.
+    ///
+    /// Default implementation gets pieces one by one concurrently, implementations are encouraged
+    /// to override it with more efficient batched version.
+    async fn get_pieces<'a>(
+        &'a self,
+        piece_indices: Vec<PieceIndex>,
+        retry_policy: PieceGetterRetryPolicy,
+    ) -> Result<PiecesStream<'a>, Box<dyn Error + Send + Sync + 'static>>
+    where
+        Self: Sync,
+    {
+        Ok(piece_indices
+            .into_iter()
+            .map(|piece_index| async move {
+                (piece_index, self.get_piece(piece_index, retry_policy).await)
+            })
+            .collect::<FuturesUnordered<_>>()
+            .boxed())
+    }
 }
 
 #[async_trait]
@@ -73,6 +107,14 @@ where
     ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
         self.as_ref().get_piece(piece_index, retry_policy).await
     }
+
+    async fn get_pieces<'a>(
+        &'a self,
+        piece_indices: Vec<PieceIndex>,
+        retry_policy: PieceGetterRetryPolicy,
+    ) -> Result<PiecesStream<'a>, Box<dyn Error + Send + Sync + 'static>> {
+        self.as_ref().get_pieces(piece_indices, retry_policy).await
+    }
 }
 
 #[async_trait]
@@ -323,7 +365,7 @@ where
 const_assert!(std::mem::size_of::<usize>() >= std::mem::size_of::<u64>());
 
 /// Information about the protocol necessary for farmer operation
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Encode, Decode, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FarmerProtocolInfo {
     /// Size of the blockchain history
@@ -2,16 +2,17 @@ use crate::sector::{
     sector_record_chunks_size, sector_size, EncodedChunksUsed, RawSector, RecordMetadata,
     SectorContentsMap, SectorMetadata, SectorMetadataChecksummed,
 };
-use crate::segment_reconstruction::recover_missing_piece;
+use crate::segment_reconstruction::{recover_missing_piece, SegmentReconstructionCache};
 use crate::{FarmerProtocolInfo, PieceGetter, PieceGetterRetryPolicy};
 use async_lock::Mutex as AsyncMutex;
 use backoff::future::retry;
 use backoff::{Error as BackoffError, ExponentialBackoff};
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
-use parity_scale_codec::{Decode, Encode};
+use futures::{select, StreamExt};
+use parity_scale_codec::{Decode, Encode, Input, Output};
 use parking_lot::Mutex;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::mem;
 use std::simd::Simd;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -98,6 +99,12 @@ pub enum PlottingError {
         /// Lower-level error
         error: Box<dyn std::error::Error + Send + Sync + 'static>,
     },
+    /// Failed to retrieve pieces
+    #[error("Failed to retrieve pieces: {error}")]
+    FailedToRetrievePieces {
+        /// Lower-level error
+        error: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
     /// Failed to acquire permit
     #[error("Failed to acquire permit: {error}")]
     FailedToAcquirePermit {
@@ -147,7 +154,9 @@ where
     /// Semaphore for part of the plotting when farmer encodes downloaded sector, should typically
     /// allow one permit at a time for efficient CPU utilization
     pub encoding_semaphore: Option<&'a Semaphore>,
-    /// Proof of space table generators
+    /// Proof of space table generators, one table is generated at a time by each generator in
+    /// place, so memory allocated by generators is reused for subsequent records of the sector and
+    /// released once sector is encoded
     pub table_generators: &'a mut [PosTable::Generator],
     /// Whether encoding should be aborted early
     pub abort_early: &'a AtomicBool,
@@ -164,7 +173,7 @@ pub async fn plot_sector<PosTable, PG>(
 ) -> Result<PlottedSector, PlottingError>
 where
     PosTable: Table,
-    PG: PieceGetter,
+    PG: PieceGetter + Sync,
 {
     let PlotSectorOptions {
         public_key,
@@ -196,6 +205,7 @@ where
         farmer_protocol_info,
         kzg,
         pieces_in_sector,
+        segment_reconstruction_cache: None,
     });
 
     let _encoding_permit = match encoding_semaphore {
@@ -217,7 +227,9 @@ where
     )
 }
 
-/// Opaque sector downloaded and ready for encoding
+/// Opaque sector downloaded and ready for encoding.
+///
+/// Can be encoded with SCALE codec, for instance to store it on disk until it is encoded.
 pub struct DownloadedSector {
     sector_id: SectorId,
     piece_indices: Vec<PieceIndex>,
@@ -225,6 +237,91 @@ pub struct DownloadedSector {
     farmer_protocol_info: FarmerProtocolInfo,
 }
 
+impl Encode for DownloadedSector {
+    fn size_hint(&self) -> usize {
+        self.sector_id.size_hint()
+            + self.piece_indices.size_hint()
+            + self.farmer_protocol_info.size_hint()
+            + self.raw_sector.records.len() * Record::SIZE
+            + self.raw_sector.metadata.size_hint()
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        self.sector_id.encode_to(dest);
+        self.piece_indices.encode_to(dest);
+        self.farmer_protocol_info.encode_to(dest);
+        // Records are written as is, number of records is the same as number of piece indices
+        for record in &self.raw_sector.records {
+            dest.write(record.as_ref());
+        }
+        self.raw_sector.metadata.encode_to(dest);
+    }
+}
+
+impl Decode for DownloadedSector {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+        let sector_id = SectorId::decode(input)?;
+        let piece_indices = Vec::<PieceIndex>::decode(input)?;
+        if piece_indices.len() > usize::from(u16::MAX) {
+            return Err("Too many pieces in downloaded sector".into());
+        }
+        let farmer_protocol_info = FarmerProtocolInfo::decode(input)?;
+        let mut records = Record::new_zero_vec(piece_indices.len());
+        for record in &mut records {
+            input.read(record.as_mut())?;
+        }
+        let metadata = Vec::<RecordMetadata>::decode(input)?;
+        if metadata.len() != records.len() {
+            return Err("Number of record metadata doesn't match number of records".into());
+        }
+
+        Ok(Self {
+            sector_id,
+            piece_indices,
+            raw_sector: RawSector { records, metadata },
+            farmer_protocol_info,
+        })
+    }
+}
+
+impl DownloadedSector {
+    /// Estimated memory usage in bytes of downloaded sector with `pieces_in_sector` pieces
+    pub const fn memory_usage(pieces_in_sector: u16) -> usize {
+        pieces_in_sector as usize * (Record::SIZE + mem::size_of::<RecordMetadata>())
+    }
+
+    /// Sector ID of downloaded sector
+    pub fn sector_id(&self) -> SectorId {
+        self.sector_id
+    }
+
+    /// Number of pieces in downloaded sector
+    pub fn pieces_in_sector(&self) -> usize {
+        self.piece_indices.len()
+    }
+
+    /// Farmer protocol info sector was downloaded with, sector will be plotted with the same
+    /// history size
+    pub fn farmer_protocol_info(&self) -> &FarmerProtocolInfo {
+        &self.farmer_protocol_info
+    }
+}
+
+/// Estimated memory usage in bytes of [`encode_sector()`] for a sector with `pieces_in_sector`
+/// pieces and `record_encoding_concurrency` table generators, not including [`DownloadedSector`]
+/// that is being encoded.
+pub const fn sector_encoding_memory_usage<PosTable>(
+    pieces_in_sector: u16,
+    record_encoding_concurrency: usize,
+) -> usize
+where
+    PosTable: Table,
+{
+    sector_size(pieces_in_sector)
+        + record_encoding_concurrency
+            * (PosTable::GENERATOR_MEMORY_USAGE + RecordEncodingScratch::MEMORY_USAGE)
+}
+
 /// Options for sector downloading
 pub struct DownloadSectorOptions<'a, PG> {
     /// Public key corresponding to sector
@@ -241,6 +338,9 @@ pub struct DownloadSectorOptions<'a, PG> {
     pub kzg: &'a Kzg,
     /// How many pieces should sector contain
     pub pieces_in_sector: u16,
+    /// Cache of pieces fetched for reconstruction of missing pieces, typically shared by all
+    /// sectors that are downloaded concurrently
+    pub segment_reconstruction_cache: Option<&'a SegmentReconstructionCache>,
 }
 
 /// Download sector for plotting.
@@ -251,7 +351,7 @@ pub async fn download_sector<PG>(
     options: DownloadSectorOptions<'_, PG>,
 ) -> Result<DownloadedSector, PlottingError>
 where
-    PG: PieceGetter,
+    PG: PieceGetter + Sync,
 {
     let DownloadSectorOptions {
         public_key,
@@ -261,6 +361,7 @@ where
         farmer_protocol_info,
         kzg,
         pieces_in_sector,
+        segment_reconstruction_cache,
     } = options;
 
     let sector_id = SectorId::new(public_key.hash(), sector_index);
@@ -294,6 +395,7 @@ where
                 piece_getter,
                 piece_getter_retry_policy,
                 kzg,
+                segment_reconstruction_cache,
                 &mut incremental_piece_indices,
             )
             .await
@@ -349,7 +451,9 @@ where
     /// Where plotted sector metadata should be written, vector must either be empty (in which case
     /// it'll be resized to correct size automatically) or correctly sized from the beginning
     pub sector_metadata_output: &'a mut Vec<u8>,
-    /// Proof of space table generators
+    /// Proof of space table generators, one table is generated at a time by each generator in
+    /// place, so memory allocated by generators is reused for subsequent records of the sector and
+    /// released once sector is encoded
     pub table_generators: &'a mut [PosTable::Generator],
     /// Whether encoding should be aborted early
     pub abort_early: &'a AtomicBool,
@@ -415,7 +519,7 @@ where
         rayon::scope(|scope| {
             for table_generator in table_generators {
                 scope.spawn(|_scope| {
-                    let mut chunks_scratch = Vec::with_capacity(Record::NUM_S_BUCKETS);
+                    let mut scratch = RecordEncodingScratch::default();
 
                     loop {
                         // This instead of `while` above because otherwise mutex will be held for
@@ -423,7 +527,7 @@ where
                         let Some(((piece_offset, record), encoded_chunks_used)) =
                             iter.lock().next()
                         else {
-                            return;
+                            break;
                         };
                         let pos_seed = sector_id.derive_evaluation_seed(
                             piece_offset,
@@ -436,13 +540,17 @@ where
                             encoded_chunks_used,
                             table_generator,
                             erasure_coding,
-                            &mut chunks_scratch,
+                            &mut scratch,
                         );
 
                         if abort_early.load(Ordering::Relaxed) {
-                            return;
+                            break;
                         }
                     }
+
+                    // Table memory is only reused for records of the same sector, idle generators
+                    // between sectors shouldn't hold it
+                    table_generator.release_memory();
                 });
             }
         });
@@ -534,31 +642,59 @@ where
     })
 }
 
+/// Buffers reused across records encoded by the same thread in order to avoid allocations
+struct RecordEncodingScratch {
+    source_record_chunks: Vec<Scalar>,
+    parity_record_chunks: Vec<Scalar>,
+    chunks: Vec<Option<Simd<u8, 32>>>,
+}
+
+impl RecordEncodingScratch {
+    const MEMORY_USAGE: usize = Record::NUM_CHUNKS * mem::size_of::<Scalar>() * 2
+        + Record::NUM_S_BUCKETS * mem::size_of::<Option<Simd<u8, 32>>>();
+}
+
+impl Default for RecordEncodingScratch {
+    fn default() -> Self {
+        Self {
+            source_record_chunks: vec![Scalar::default(); Record::NUM_CHUNKS],
+            parity_record_chunks: vec![Scalar::default(); Record::NUM_CHUNKS],
+            chunks: Vec::with_capacity(Record::NUM_S_BUCKETS),
+        }
+    }
+}
+
 fn record_encoding<PosTable>(
     pos_seed: &PosSeed,
     record: &mut Record,
     mut encoded_chunks_used: EncodedChunksUsed<'_>,
     table_generator: &mut PosTable::Generator,
     erasure_coding: &ErasureCoding,
-    chunks_scratch: &mut Vec<Option<Simd<u8, 32>>>,
+    scratch: &mut RecordEncodingScratch,
 ) where
     PosTable: Table,
 {
+    let RecordEncodingScratch {
+        source_record_chunks,
+        parity_record_chunks,
+        chunks: chunks_scratch,
+    } = scratch;
+
     // Derive PoSpace table
-    let pos_table = table_generator.generate_parallel(pos_seed);
+    let pos_table = table_generator.generate_parallel_in_place(pos_seed);
 
-    let source_record_chunks = record
-        .iter()
-        .map(|scalar_bytes| {
-            Scalar::try_from(scalar_bytes).expect(
+    source_record_chunks
+        .iter_mut()
+        .zip(record.iter())
+        .for_each(|(output, scalar_bytes)| {
+            *output = Scalar::try_from(scalar_bytes).expect(
                 "Piece getter must returns valid pieces of history that contain proper \
                     scalar bytes; qed",
-            )
-        })
-        .collect::<Vec<_>>();
+            );
+        });
     // Erasure code source record chunks
-    let parity_record_chunks = erasure_coding
-        .extend(&source_record_chunks)
+    erasure_coding
+        .extend_into(source_record_chunks, parity_record_chunks)
         .expect("Instance was verified to be able to work with this many values earlier; qed");
 
     chunks_scratch.clear();
@@ -570,7 +706,7 @@ fn record_encoding<PosTable>(
         .zip(
             source_record_chunks
                 .par_iter()
-                .interleave(&parity_record_chunks),
+                .interleave(&*parity_record_chunks),
         )
         .map(|(s_bucket, record_chunk)| {
             let proof = pos_table.find_proof(s_bucket.into())?;
@@ -604,7 +740,7 @@ fn record_encoding<PosTable>(
     // remaining number of unencoded erasure coded record chunks to the end
     source_record_chunks
         .iter()
-        .zip(&parity_record_chunks)
+        .zip(parity_record_chunks.iter())
         .flat_map(|(a, b)| [a, b])
         .zip(encoded_chunks_used.iter())
         // Skip chunks that were used previously
@@ -623,81 +759,123 @@ fn record_encoding<PosTable>(
         });
 }
 
-async fn download_sector_internal<PG: PieceGetter>(
+async fn download_sector_internal<PG>(
     raw_sector: &mut RawSector,
     piece_getter: &PG,
     piece_getter_retry_policy: PieceGetterRetryPolicy,
     kzg: &Kzg,
+    segment_reconstruction_cache: Option<&SegmentReconstructionCache>,
     piece_indexes: &mut [Option<PieceIndex>],
-) -> Result<(), PlottingError> {
+) -> Result<(), PlottingError>
+where
+    PG: PieceGetter + Sync,
+{
     // TODO: Make configurable, likely allowing user to specify RAM usage expectations and inferring
     //  concurrency from there
-    let recovery_semaphore = Semaphore::new(RECONSTRUCTION_CONCURRENCY_LIMIT);
+    let recovery_semaphore = &Semaphore::new(RECONSTRUCTION_CONCURRENCY_LIMIT);
+
+    // The same piece index may in principle be used at multiple offsets of the sector, we skip
+    // pieces that we have already processed previously
+    let mut piece_offsets = HashMap::<PieceIndex, Vec<usize>>::new();
+    for (piece_offset, maybe_piece_index) in piece_indexes.iter().enumerate() {
+        if let Some(piece_index) = *maybe_piece_index {
+            piece_offsets
+                .entry(piece_index)
+                .or_default()
+                .push(piece_offset);
+        }
+    }
 
-    let mut pieces_receiving_futures = piece_indexes
-        .iter_mut()
-        .zip(raw_sector.records.iter_mut().zip(&mut raw_sector.metadata))
-        .map(|(maybe_piece_index, (record, metadata))| async {
-            // We skip pieces that we have already processed previously
-            let Some(piece_index) = *maybe_piece_index else {
-                return Ok(());
-            };
+    // Pieces are requested in batch such that piece getter can group requests to the same
+    // provider, pieces that were not retrieved are recovered from other pieces of their segment
+    let mut downloading_pieces = piece_getter
+        .get_pieces(
+            piece_offsets.keys().copied().collect(),
+            piece_getter_retry_policy,
+        )
+        .await
+        .map_err(|error| PlottingError::FailedToRetrievePieces { error })?
+        .fuse();
+    let mut recovering_pieces = FuturesUnordered::new();
 
-            let mut piece_result = piece_getter
-                .get_piece(piece_index, piece_getter_retry_policy)
-                .await;
-
-            let succeeded = piece_result
-                .as_ref()
-                .map(|piece| piece.is_some())
-                .unwrap_or_default();
-
-            // All retries failed
-            if !succeeded {
-                let _permit = match recovery_semaphore.acquire().await {
-                    Ok(permit) => permit,
-                    Err(error) => {
-                        let error = format!("Recovery semaphore was closed: {error}").into();
-                        return Err(PlottingError::FailedToRetrievePiece { piece_index, error });
-                    }
-                };
-                let recovered_piece =
-                    recover_missing_piece(piece_getter, kzg.clone(), piece_index).await;
+    let mut final_result = Ok(());
 
-                piece_result = recovered_piece.map(Some).map_err(Into::into);
+    loop {
+        let (piece_index, piece_result) = select! {
+            (piece_index, piece_result) = downloading_pieces.select_next_some() => {
+                if let Ok(Some(piece)) = piece_result {
+                    (piece_index, Ok(piece))
+                } else {
+                    recovering_pieces.push(async move {
+                        let _permit = match recovery_semaphore.acquire().await {
+                            Ok(permit) => permit,
+                            Err(error) => {
+                                let error = format!("Recovery semaphore was closed: {error}");
+                                return (
+                                    piece_index,
+                                    Err(PlottingError::FailedToRetrievePiece {
+                                        piece_index,
+                                        error: error.into(),
+                                    }),
+                                );
+                            }
+                        };
+                        let recovered_piece = recover_missing_piece(
+                            piece_getter,
+                            kzg.clone(),
+                            piece_index,
+                            segment_reconstruction_cache,
+                        )
+                        .await;
+
+                        (
+                            piece_index,
+                            recovered_piece.map_err(|error| {
+                                PlottingError::FailedToRetrievePiece {
+                                    piece_index,
+                                    error: error.into(),
+                                }
+                            }),
+                        )
+                    });
+
+                    continue;
+                }
             }
+            (piece_index, piece_result) = recovering_pieces.select_next_some() => {
+                (piece_index, piece_result)
+            }
+            complete => {
+                break;
+            }
+        };
 
-            let piece = piece_result
-                .map_err(|error| PlottingError::FailedToRetrievePiece { piece_index, error })?
-                .ok_or(PlottingError::PieceNotFound { piece_index })?;
+        let piece = match piece_result {
+            Ok(piece) => piece,
+            Err(error) => {
+                trace!(%error, "Failed to download piece");
+
+                if final_result.is_ok() {
+                    final_result = Err(error);
+                }
+                continue;
+            }
+        };
 
-            // Fancy way to insert value in order to avoid going through stack (if naive de-referencing
-            // is used) and potentially causing stack overflow as the result
-            record
+        for &piece_offset in piece_offsets.get(&piece_index).into_iter().flatten() {
+            // Fancy way to insert value in order to avoid going through stack (if naive
+            // de-referencing is used) and potentially causing stack overflow as the result
+            raw_sector.records[piece_offset]
                 .flatten_mut()
                 .copy_from_slice(piece.record().flatten());
-            *metadata = RecordMetadata {
+            raw_sector.metadata[piece_offset] = RecordMetadata {
                 commitment: *piece.commitment(),
                 witness: *piece.witness(),
                 piece_checksum: blake3_hash(piece.as_ref()),
             };
 
             // We have processed this piece index, clear it
-            maybe_piece_index.take();
-
-            Ok(())
-        })
-        .collect::<FuturesUnordered<_>>();
-
-    let mut final_result = Ok(());
-
-    while let Some(result) = pieces_receiving_futures.next().await {
-        if let Err(error) = result {
-            trace!(%error, "Failed to download piece");
-
-            if final_result.is_ok() {
-                final_result = Err(error);
-            }
+            piece_indexes[piece_offset].take();
         }
     }
 
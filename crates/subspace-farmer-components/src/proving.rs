@@ -1,5 +1,5 @@
 use crate::auditing::ChunkCandidate;
-use crate::reading::{read_record_metadata, read_sector_record_chunks, ReadingError};
+use crate::reading::{read_record_metadata, read_sector_record_chunks_into, ReadingError};
 use crate::sector::{
     SectorContentsMap, SectorContentsMapFromBytesError, SectorMetadataChecksummed,
 };
@@ -7,7 +7,7 @@ use crate::{ReadAt, ReadAtSync};
 use futures::FutureExt;
 use std::collections::VecDeque;
 use std::io;
-use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::crypto::kzg::{Kzg, Scalar};
 use subspace_core_primitives::{
     ChunkWitness, PieceOffset, PosSeed, PublicKey, Record, SBucket, SectorId, Solution,
     SolutionRange,
@@ -196,6 +196,8 @@ where
     count: usize,
     best_solution_distance: Option<SolutionRange>,
     table_generator: TableGenerator,
+    /// Buffer for record chunks reused between solutions, allocated on first use
+    sector_record_chunks: Option<Box<[Option<Scalar>; Record::NUM_S_BUCKETS]>>,
 }
 
 impl<'a, RewardAddress, PosTable, TableGenerator, Sector> ExactSizeIterator
@@ -235,15 +237,22 @@ where
         );
 
         let maybe_solution: Result<_, ProvingError> = try {
-            let sector_record_chunks_fut = read_sector_record_chunks(
+            let sector_record_chunks = self.sector_record_chunks.get_or_insert_with(|| {
+                vec![None; Record::NUM_S_BUCKETS]
+                    .into_boxed_slice()
+                    .try_into()
+                    .expect("Correct length; qed")
+            });
+            let sector_record_chunks_fut = read_sector_record_chunks_into(
                 piece_offset,
                 self.sector_metadata.pieces_in_sector,
                 &self.s_bucket_offsets,
                 &self.sector_contents_map,
                 &pos_table,
                 &self.sector,
+                sector_record_chunks,
             );
-            let sector_record_chunks = sector_record_chunks_fut
+            sector_record_chunks_fut
                 .now_or_never()
                 .expect("Sync reader; qed")?;
 
@@ -259,7 +268,6 @@ where
                     piece_offset,
                     error,
                 })?;
-            drop(sector_record_chunks);
 
             // NOTE: We do not check plot consistency using checksum because it is more
             // expensive and consensus will verify validity of the proof anyway
@@ -406,6 +414,7 @@ where
             count,
             best_solution_distance,
             table_generator,
+            sector_record_chunks: None,
         })
     }
 }
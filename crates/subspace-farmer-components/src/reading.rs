@@ -118,9 +118,52 @@ where
     S: ReadAtSync,
     A: ReadAtAsync,
 {
-    let mut record_chunks = vec![None; Record::NUM_S_BUCKETS];
+    let record_chunks = vec![None; Record::NUM_S_BUCKETS];
+    let mut record_chunks = ManuallyDrop::new(record_chunks);
+
+    // SAFETY: Original memory is not dropped, layout is exactly what we need here
+    let mut record_chunks = unsafe {
+        Box::from_raw(record_chunks.as_mut_ptr() as *mut [Option<Scalar>; Record::NUM_S_BUCKETS])
+    };
+
+    read_sector_record_chunks_into(
+        piece_offset,
+        pieces_in_sector,
+        s_bucket_offsets,
+        sector_contents_map,
+        pos_table,
+        sector,
+        &mut record_chunks,
+    )
+    .await?;
+
+    Ok(record_chunks)
+}
+
+/// Read sector record chunks into provided buffer, only plotted s-buckets are `Some` afterwards (in
+/// decoded form).
+///
+/// The same as [`read_sector_record_chunks()`], but allows to reuse the buffer when records are
+/// read repeatedly.
+pub async fn read_sector_record_chunks_into<PosTable, S, A>(
+    piece_offset: PieceOffset,
+    pieces_in_sector: u16,
+    s_bucket_offsets: &[u32; Record::NUM_S_BUCKETS],
+    sector_contents_map: &SectorContentsMap,
+    pos_table: &PosTable,
+    sector: &ReadAt<S, A>,
+    record_chunks: &mut [Option<Scalar>; Record::NUM_S_BUCKETS],
+) -> Result<(), ReadingError>
+where
+    PosTable: Table,
+    S: ReadAtSync,
+    A: ReadAtAsync,
+{
+    // Buffer might contain chunks of previously read record
+    record_chunks.fill(None);
 
     let read_chunks_inputs = record_chunks
+        .as_mut_slice()
         .par_iter_mut()
         .zip(sector_contents_map.par_iter_record_chunk_to_plot(piece_offset))
         .zip(
@@ -244,14 +287,7 @@ where
         }
     }
 
-    let mut record_chunks = ManuallyDrop::new(record_chunks);
-
-    // SAFETY: Original memory is not dropped, layout is exactly what we need here
-    let record_chunks = unsafe {
-        Box::from_raw(record_chunks.as_mut_ptr() as *mut [Option<Scalar>; Record::NUM_S_BUCKETS])
-    };
-
-    Ok(record_chunks)
+    Ok(())
 }
 
 /// Given sector record chunks recover extended record chunks (both source and parity)
@@ -1,16 +1,24 @@
+//! Reconstruction of missing pieces from other pieces of the same segment
+
+#[cfg(test)]
+mod tests;
+
 use crate::{PieceGetter, PieceGetterRetryPolicy};
-use futures::stream::FuturesOrdered;
+use async_lock::Mutex as AsyncMutex;
 use futures::StreamExt;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use subspace_archiving::piece_reconstructor::{PiecesReconstructor, ReconstructorError};
 use subspace_core_primitives::crypto::kzg::Kzg;
-use subspace_core_primitives::{ArchivedHistorySegment, Piece, PieceIndex, RecordedHistorySegment};
+use subspace_core_primitives::{
+    ArchivedHistorySegment, Piece, PieceIndex, RecordedHistorySegment, SegmentIndex,
+};
 use thiserror::Error;
-use tokio::sync::Semaphore;
-use tracing::{debug, error, info, trace, warn};
-
-// TODO: Probably should be made configurable
-const PARALLELISM_LEVEL: usize = 20;
+use tracing::{debug, error, info, trace};
 
 #[derive(Debug, Error)]
 pub(crate) enum SegmentReconstructionError {
@@ -23,90 +31,273 @@ pub(crate) enum SegmentReconstructionError {
     PieceRetrievalFailed(#[from] ReconstructorError),
 }
 
-pub(crate) async fn recover_missing_piece<PG: PieceGetter>(
+/// Pieces of a segment that were fetched for reconstruction, at least half of them are present
+type SegmentPieces = Arc<Vec<Option<Piece>>>;
+
+/// Result of looking up pieces of a segment in [`SegmentReconstructionCache`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SegmentReconstructionCacheLookup {
+    /// Pieces of the segment were already fetched
+    Hit,
+    /// Pieces of the segment had to be fetched
+    Miss,
+}
+
+/// Callback that is called on every lookup in [`SegmentReconstructionCache`]
+pub type SegmentReconstructionCacheLookupCallback =
+    Box<dyn Fn(SegmentReconstructionCacheLookup) + Send + Sync + 'static>;
+
+#[derive(Debug, Default)]
+enum CachedSegment {
+    /// Pieces are not fetched yet or are being fetched right now
+    #[default]
+    Pending,
+    Fetched(SegmentPieces),
+    /// Fetching failed, recoveries that were waiting for it fail too instead of fetching again
+    Failed,
+}
+
+struct Inner {
+    segments: Mutex<LruCache<SegmentIndex, Arc<AsyncMutex<CachedSegment>>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    on_lookup: Option<SegmentReconstructionCacheLookupCallback>,
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("segments", &self.segments)
+            .field("hits", &self.hits)
+            .field("misses", &self.misses)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Cache of pieces fetched for reconstruction of recent segments.
+///
+/// Recovering a missing piece requires fetching half of the pieces of its segment, cache allows to
+/// recover other missing pieces of the same segment without fetching them again. Cache is meant to
+/// be shared by all sectors that are downloaded concurrently, concurrent recoveries from the same
+/// segment wait for the same pieces to be fetched once. If fetching fails, recoveries that were
+/// waiting for it fail as well, the next recovery from the same segment will try to fetch pieces
+/// again.
+///
+/// Each cached segment holds roughly half of [`ArchivedHistorySegment`] in memory.
+#[derive(Debug, Clone)]
+pub struct SegmentReconstructionCache {
+    inner: Arc<Inner>,
+}
+
+impl SegmentReconstructionCache {
+    /// Create new instance that holds pieces of up to `capacity` segments
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self::new_internal(capacity, None)
+    }
+
+    /// Create new instance that holds pieces of up to `capacity` segments and calls `on_lookup`
+    /// on every lookup (useful for metrics)
+    pub fn with_lookup_callback(
+        capacity: NonZeroUsize,
+        on_lookup: SegmentReconstructionCacheLookupCallback,
+    ) -> Self {
+        Self::new_internal(capacity, Some(on_lookup))
+    }
+
+    fn new_internal(
+        capacity: NonZeroUsize,
+        on_lookup: Option<SegmentReconstructionCacheLookupCallback>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                segments: Mutex::new(LruCache::new(capacity)),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                on_lookup,
+            }),
+        }
+    }
+
+    /// Estimated memory usage in bytes of the cache that holds pieces of up to `capacity` segments
+    pub const fn memory_usage(capacity: NonZeroUsize) -> usize {
+        capacity.get() * RecordedHistorySegment::NUM_RAW_RECORDS * Piece::SIZE
+    }
+
+    /// Number of recoveries that used pieces already fetched for the same segment
+    pub fn hits(&self) -> u64 {
+        self.inner.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of recoveries that had to fetch pieces of the segment
+    pub fn misses(&self) -> u64 {
+        self.inner.misses.load(Ordering::Relaxed)
+    }
+
+    async fn get_or_fetch<PG: PieceGetter + Sync>(
+        &self,
+        piece_getter: &PG,
+        segment_index: SegmentIndex,
+    ) -> Result<SegmentPieces, SegmentReconstructionError> {
+        let entry = Arc::clone(
+            self.inner
+                .segments
+                .lock()
+                .get_or_insert(segment_index, Arc::default),
+        );
+        // Held while fetching, such that concurrent recoveries from the same segment wait for it
+        let mut cached_segment = entry.lock().await;
+
+        match &*cached_segment {
+            CachedSegment::Pending => {
+                // Fetching below
+            }
+            CachedSegment::Fetched(segment_pieces) => {
+                self.inner.hits.fetch_add(1, Ordering::Relaxed);
+                self.on_lookup(SegmentReconstructionCacheLookup::Hit);
+                debug!(%segment_index, "Reusing cached segment pieces");
+
+                return Ok(Arc::clone(segment_pieces));
+            }
+            CachedSegment::Failed => {
+                debug!(%segment_index, "Fetching segment pieces has just failed, not retrying");
+
+                return Err(SegmentReconstructionError::NotEnoughPiecesAcquired);
+            }
+        }
+
+        self.inner.misses.fetch_add(1, Ordering::Relaxed);
+        self.on_lookup(SegmentReconstructionCacheLookup::Miss);
+        match fetch_segment_pieces(piece_getter, segment_index).await {
+            Ok(segment_pieces) => {
+                let segment_pieces = Arc::new(segment_pieces);
+                *cached_segment = CachedSegment::Fetched(Arc::clone(&segment_pieces));
+
+                Ok(segment_pieces)
+            }
+            Err(error) => {
+                *cached_segment = CachedSegment::Failed;
+                // Recoveries that are already waiting will see failure, but the following ones
+                // will try to fetch pieces again
+                let mut segments = self.inner.segments.lock();
+                if segments
+                    .peek(&segment_index)
+                    .is_some_and(|cached_entry| Arc::ptr_eq(cached_entry, &entry))
+                {
+                    segments.pop(&segment_index);
+                }
+
+                Err(error)
+            }
+        }
+    }
+
+    fn on_lookup(&self, lookup: SegmentReconstructionCacheLookup) {
+        if let Some(on_lookup) = &self.inner.on_lookup {
+            on_lookup(lookup);
+        }
+    }
+}
+
+/// Recover missing piece, pieces of the segment are taken from and stored in the cache if
+/// provided
+pub(crate) async fn recover_missing_piece<PG: PieceGetter + Sync>(
     piece_getter: &PG,
     kzg: Kzg,
     missing_piece_index: PieceIndex,
+    segment_reconstruction_cache: Option<&SegmentReconstructionCache>,
 ) -> Result<Piece, SegmentReconstructionError> {
     info!(%missing_piece_index, "Recovering missing piece...");
     let segment_index = missing_piece_index.segment_index();
     let position = missing_piece_index.position();
 
-    let semaphore = &Semaphore::new(PARALLELISM_LEVEL);
-    let acquired_pieces_counter = &AtomicUsize::default();
-    let required_pieces_number = RecordedHistorySegment::NUM_RAW_RECORDS;
+    let segment_pieces = match segment_reconstruction_cache {
+        Some(segment_reconstruction_cache) => {
+            segment_reconstruction_cache
+                .get_or_fetch(piece_getter, segment_index)
+                .await
+        }
+        None => fetch_segment_pieces(piece_getter, segment_index)
+            .await
+            .map(Arc::new),
+    }
+    .map_err(|error| {
+        error!(%missing_piece_index, %error, "Recovering missing piece failed.");
+        error
+    })?;
 
-    let mut received_segment_pieces = segment_index
-        .segment_piece_indexes()
-        .map(|piece_index| async move {
-            let _permit = match semaphore.acquire().await {
-                Ok(permit) => permit,
-                Err(error) => {
-                    warn!(
-                        %piece_index,
-                        %error,
-                        "Semaphore was closed, interrupting piece recover..."
-                    );
-                    return None;
-                }
-            };
+    let archiver = PiecesReconstructor::new(kzg).expect("Internal constructor call must succeed.");
 
-            if acquired_pieces_counter.load(Ordering::SeqCst) >= required_pieces_number {
-                trace!(%piece_index, "Skipped piece acquiring.");
+    let result = archiver.reconstruct_piece(&segment_pieces, position as usize)?;
 
-                return None;
-            }
+    info!(%missing_piece_index, "Recovering missing piece succeeded.");
 
-            let piece = piece_getter
-                .get_piece(piece_index, PieceGetterRetryPolicy::Limited(0))
-                .await;
+    Ok(result)
+}
+
+/// Fetch enough pieces of the segment to reconstruct any other piece of it
+async fn fetch_segment_pieces<PG>(
+    piece_getter: &PG,
+    segment_index: SegmentIndex,
+) -> Result<Vec<Option<Piece>>, SegmentReconstructionError>
+where
+    PG: PieceGetter + Sync,
+{
+    let required_pieces_number = RecordedHistorySegment::NUM_RAW_RECORDS;
+    let mut segment_pieces = vec![None::<Piece>; ArchivedHistorySegment::NUM_PIECES];
+    let mut received_pieces = 0;
 
-            match piece {
-                Ok(piece) => {
-                    if let Some(piece) = piece {
-                        acquired_pieces_counter.fetch_add(1, Ordering::SeqCst);
+    // Request only as many pieces as still necessary in a batch, pieces that were not retrieved
+    // are replaced with the next pieces of the segment in the following batch
+    let mut piece_indexes = segment_index.segment_piece_indexes().into_iter();
+    while received_pieces < required_pieces_number {
+        let batch = piece_indexes
+            .by_ref()
+            .take(required_pieces_number - received_pieces)
+            .collect::<Vec<_>>();
+        if batch.is_empty() {
+            break;
+        }
 
-                        Some((piece_index, piece))
-                    } else {
-                        None
-                    }
+        let mut pieces_stream = match piece_getter
+            .get_pieces(batch, PieceGetterRetryPolicy::Limited(0))
+            .await
+        {
+            Ok(pieces_stream) => pieces_stream,
+            Err(error) => {
+                debug!(?error, %segment_index, "Failed to get pieces");
+                break;
+            }
+        };
+
+        while let Some((piece_index, piece_result)) = pieces_stream.next().await {
+            match piece_result {
+                Ok(Some(piece)) => {
+                    segment_pieces
+                        .get_mut(piece_index.position() as usize)
+                        .expect("Piece position is by definition within segment; qed")
+                        .replace(piece);
+                    received_pieces += 1;
+                }
+                Ok(None) => {
+                    trace!(%piece_index, "Piece not found");
                 }
                 Err(error) => {
                     debug!(?error, %piece_index, "Failed to get piece");
-                    None
                 }
             }
-        })
-        .into_iter()
-        .collect::<FuturesOrdered<_>>();
-
-    let mut segment_pieces = vec![None::<Piece>; ArchivedHistorySegment::NUM_PIECES];
-    while let Some(maybe_received_piece) = received_segment_pieces.next().await {
-        if let Some((piece_index, received_piece)) = maybe_received_piece {
-            segment_pieces
-                .get_mut(piece_index.position() as usize)
-                .expect("Piece position is by definition within segment; qed")
-                .replace(received_piece);
         }
     }
 
-    let received_pieces = acquired_pieces_counter.load(Ordering::SeqCst);
     if received_pieces < required_pieces_number {
-        error!(
-            %missing_piece_index,
+        debug!(
+            %segment_index,
             %received_pieces,
             %required_pieces_number,
-            "Recovering missing piece failed."
+            "Not enough pieces of the segment acquired"
         );
 
         return Err(SegmentReconstructionError::NotEnoughPiecesAcquired);
     }
 
-    let archiver = PiecesReconstructor::new(kzg).expect("Internal constructor call must succeed.");
-
-    let result = archiver.reconstruct_piece(&segment_pieces, position as usize)?;
-
-    info!(%missing_piece_index, "Recovering missing piece succeeded.");
-
-    Ok(result)
+    Ok(segment_pieces)
 }
@@ -0,0 +1,112 @@
+use crate::segment_reconstruction::{SegmentReconstructionCache, SegmentReconstructionCacheLookup};
+use crate::{PieceGetter, PieceGetterRetryPolicy};
+use async_trait::async_trait;
+use futures::future::join_all;
+use parking_lot::Mutex;
+use std::error::Error;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use subspace_core_primitives::{
+    ArchivedHistorySegment, Piece, PieceIndex, RecordedHistorySegment, SegmentIndex,
+};
+
+const RECOVERIES: usize = 4;
+
+/// Piece getter that yields to other tasks before returning each piece, such that concurrent
+/// recoveries have a chance to wait for pieces that are being fetched
+#[derive(Default)]
+struct TestPieceGetter {
+    unavailable: AtomicBool,
+    requested_pieces: AtomicUsize,
+}
+
+#[async_trait]
+impl PieceGetter for TestPieceGetter {
+    async fn get_piece(
+        &self,
+        _piece_index: PieceIndex,
+        _retry_policy: PieceGetterRetryPolicy,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
+        self.requested_pieces.fetch_add(1, Ordering::Relaxed);
+        tokio::task::yield_now().await;
+
+        if self.unavailable.load(Ordering::Relaxed) {
+            Ok(None)
+        } else {
+            Ok(Some(Piece::default()))
+        }
+    }
+}
+
+async fn concurrent_recoveries(
+    cache: &SegmentReconstructionCache,
+    piece_getter: &TestPieceGetter,
+    segment_index: SegmentIndex,
+) -> usize {
+    join_all((0..RECOVERIES).map(|_| cache.get_or_fetch(piece_getter, segment_index)))
+        .await
+        .into_iter()
+        .filter(Result::is_ok)
+        .count()
+}
+
+#[tokio::test]
+async fn concurrent_recoveries_fetch_once() {
+    let lookups = Arc::new(Mutex::new(Vec::new()));
+    let cache = SegmentReconstructionCache::with_lookup_callback(
+        NonZeroUsize::new(2).unwrap(),
+        Box::new({
+            let lookups = Arc::clone(&lookups);
+
+            move |lookup| lookups.lock().push(lookup)
+        }),
+    );
+    let piece_getter = TestPieceGetter::default();
+
+    assert_eq!(
+        concurrent_recoveries(&cache, &piece_getter, SegmentIndex::ZERO).await,
+        RECOVERIES
+    );
+    // Only half of the segment was requested once, other recoveries waited for it
+    assert_eq!(
+        piece_getter.requested_pieces.load(Ordering::Relaxed),
+        RecordedHistorySegment::NUM_RAW_RECORDS
+    );
+    assert_eq!(cache.misses(), 1);
+    assert_eq!(cache.hits(), RECOVERIES as u64 - 1);
+    // Callback is called on every lookup as it happens
+    assert_eq!(lookups.lock().len(), RECOVERIES);
+    assert_eq!(lookups.lock()[0], SegmentReconstructionCacheLookup::Miss);
+    assert!(lookups.lock()[1..]
+        .iter()
+        .all(|lookup| *lookup == SegmentReconstructionCacheLookup::Hit));
+}
+
+#[tokio::test]
+async fn concurrent_recoveries_fail_once() {
+    let cache = SegmentReconstructionCache::new(NonZeroUsize::new(2).unwrap());
+    let piece_getter = TestPieceGetter::default();
+    piece_getter.unavailable.store(true, Ordering::Relaxed);
+
+    assert_eq!(
+        concurrent_recoveries(&cache, &piece_getter, SegmentIndex::ZERO).await,
+        0
+    );
+    // All pieces of the segment were requested once, other recoveries didn't fetch them again
+    assert_eq!(
+        piece_getter.requested_pieces.load(Ordering::Relaxed),
+        ArchivedHistorySegment::NUM_PIECES
+    );
+    assert_eq!(cache.misses(), 1);
+    assert_eq!(cache.hits(), 0);
+
+    // The following recoveries try to fetch pieces again
+    piece_getter.unavailable.store(false, Ordering::Relaxed);
+    assert_eq!(
+        concurrent_recoveries(&cache, &piece_getter, SegmentIndex::ZERO).await,
+        RECOVERIES
+    );
+    assert_eq!(cache.misses(), 2);
+    assert_eq!(cache.hits(), RECOVERIES as u64 - 1);
+}
@@ -1,8 +1,15 @@
+pub(crate) mod address;
 pub(crate) mod benchmark;
+pub(crate) mod config;
 pub(crate) mod farm;
+#[cfg(unix)]
+pub(crate) mod farm_control;
+pub(crate) mod identity;
 mod info;
 mod scrub;
 mod shared;
+#[cfg(unix)]
+pub(crate) mod signer;
 
 pub(crate) use info::info;
 pub(crate) use scrub::scrub;
@@ -0,0 +1,98 @@
+use anyhow::anyhow;
+use clap::Subcommand;
+use ss58_registry::Ss58AddressFormat;
+use subspace_core_primitives::{PublicKey, PUBLIC_KEY_LENGTH};
+use subspace_farmer::utils::ss58::{
+    decode_ss58, encode_ss58_reward_address, Ss58Address, Ss58ParsingError,
+};
+
+/// Arguments for address management
+#[derive(Debug, Subcommand)]
+pub(crate) enum AddressArgs {
+    /// Decode SS58 address, print its network and public key, explain why it is invalid otherwise
+    Decode {
+        /// Expected network, either name (like `subspace`) or numeric prefix, address is checked
+        /// against it if specified
+        #[arg(long, value_parser = network_parser)]
+        network: Option<Ss58AddressFormat>,
+        /// SS58 address
+        address: String,
+    },
+    /// Encode hex-encoded public key as SS58 address for specified network
+    Encode {
+        /// Network, either name (like `subspace`) or numeric prefix
+        #[arg(long, value_parser = network_parser)]
+        network: Ss58AddressFormat,
+        /// Hex-encoded public key
+        public_key: String,
+    },
+}
+
+fn network_parser(s: &str) -> anyhow::Result<Ss58AddressFormat> {
+    if let Ok(prefix) = s.parse::<u16>() {
+        return Ok(Ss58AddressFormat::custom(prefix));
+    }
+
+    Ss58AddressFormat::try_from(s).map_err(|_error| anyhow!("Unknown network \"{s}\""))
+}
+
+pub(crate) fn address(address_args: AddressArgs) -> anyhow::Result<()> {
+    match address_args {
+        AddressArgs::Decode { network, address } => decode(network, &address),
+        AddressArgs::Encode {
+            network,
+            public_key,
+        } => encode(network, &public_key),
+    }
+}
+
+fn decode(expected_format: Option<Ss58AddressFormat>, address: &str) -> anyhow::Result<()> {
+    let Ss58Address { format, account } =
+        decode_ss58(address.trim()).map_err(|error| anyhow!("Invalid address: {error}"))?;
+
+    println!("Network: {format} (prefix {})", format.prefix());
+    println!("Account: 0x{}", hex::encode(&account));
+
+    if account.len() != PUBLIC_KEY_LENGTH {
+        return Err(anyhow!(
+            "Invalid address: {}",
+            Ss58ParsingError::UnexpectedAccountLength {
+                length: account.len()
+            }
+        ));
+    }
+
+    if let Some(expected_format) = expected_format {
+        if format != expected_format {
+            return Err(anyhow!(
+                "Invalid address: {}",
+                Ss58ParsingError::WrongNetwork {
+                    address_format: format,
+                    expected_format,
+                }
+            ));
+        }
+    }
+
+    println!("Address is a valid reward address");
+
+    Ok(())
+}
+
+fn encode(format: Ss58AddressFormat, public_key: &str) -> anyhow::Result<()> {
+    let public_key = hex::decode(public_key.trim().trim_start_matches("0x"))
+        .map_err(|error| anyhow!("Public key is not valid hex: {error}"))?;
+    let public_key = <[u8; PUBLIC_KEY_LENGTH]>::try_from(public_key.as_slice()).map_err(|_| {
+        anyhow!(
+            "Public key must be {PUBLIC_KEY_LENGTH} bytes, {} bytes provided",
+            public_key.len()
+        )
+    })?;
+
+    println!(
+        "{}",
+        encode_ss58_reward_address(format, &PublicKey::from(public_key))?
+    );
+
+    Ok(())
+}
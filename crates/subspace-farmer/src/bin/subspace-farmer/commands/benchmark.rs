@@ -2,20 +2,43 @@ use crate::PosTable;
 use anyhow::anyhow;
 use clap::Subcommand;
 use criterion::{black_box, BatchSize, Criterion, Throughput};
+use futures::executor::block_on;
 use parking_lot::Mutex;
+use rand::prelude::*;
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::fs::OpenOptions;
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use std::{iter, thread};
+use subspace_archiving::archiver::Archiver;
 use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
-use subspace_core_primitives::{Record, SolutionRange};
+use subspace_core_primitives::crypto::Scalar;
+use subspace_core_primitives::{
+    HistorySize, PosSeed, PublicKey, Record, RecordedHistorySegment, SegmentIndex, SolutionRange,
+};
 use subspace_erasure_coding::ErasureCoding;
 use subspace_farmer::single_disk_farm::farming::rayon_files::RayonFiles;
 use subspace_farmer::single_disk_farm::farming::{PlotAudit, PlotAuditOptions};
 use subspace_farmer::single_disk_farm::{SingleDiskFarm, SingleDiskFarmSummary};
+use subspace_farmer::utils::{
+    all_cpu_cores, create_plotting_thread_pool_manager, recommended_record_encoding_concurrency,
+};
+use subspace_farmer_components::plotting::{
+    download_sector, encode_sector, DownloadSectorOptions, EncodeSectorOptions,
+};
 use subspace_farmer_components::sector::sector_size;
-use subspace_proof_of_space::Table;
+use subspace_farmer_components::{FarmerProtocolInfo, PieceGetterRetryPolicy};
+use subspace_proof_of_space::{Table, TableGenerator};
 use subspace_rpc_primitives::SlotInfo;
 
+/// Number of pieces in sector used by plotting benchmark by default
+const DEFAULT_PIECES_IN_SECTOR: u16 = 1000;
+/// Number of sectors each thread pool encodes when measuring plotting throughput
+const THROUGHPUT_SECTORS_PER_THREAD_POOL: usize = 2;
+
 /// Arguments for benchmark
 #[derive(Debug, Subcommand)]
 pub(crate) enum BenchmarkArgs {
@@ -55,6 +78,31 @@ pub(crate) enum BenchmarkArgs {
         #[arg(long)]
         limit_sector_count: Option<usize>,
     },
+    /// Plotting benchmark, measures sector encoding, proof of space table generation and erasure
+    /// coding separately using random pieces, doesn't require node or existing farm
+    Plot {
+        /// Number of samples to collect for benchmarking purposes
+        #[arg(long, default_value_t = 10)]
+        sample_size: usize,
+        /// Number of pieces in sector
+        #[arg(long, default_value_t = DEFAULT_PIECES_IN_SECTOR)]
+        pieces_in_sector: u16,
+        /// Defines how many record farmer will encode in a single sector concurrently, defaults to
+        /// the same value farmer would use on this machine
+        #[arg(long)]
+        record_encoding_concurrency: Option<NonZeroUsize>,
+        /// Optional filter for benchmarks, must correspond to a part of benchmark name in order for benchmark to run
+        filter: Option<String>,
+    },
+    /// Proof of space table generation benchmark, measures table generation with different
+    /// concurrency in order to suggest the best record encoding concurrency for this machine
+    PosTable {
+        /// Number of samples to collect for benchmarking purposes
+        #[arg(long, default_value_t = 10)]
+        sample_size: usize,
+        /// Optional filter for benchmarks, must correspond to a part of benchmark name in order for benchmark to run
+        filter: Option<String>,
+    },
 }
 
 pub(crate) fn benchmark(benchmark_args: BenchmarkArgs) -> anyhow::Result<()> {
@@ -78,6 +126,21 @@ pub(crate) fn benchmark(benchmark_args: BenchmarkArgs) -> anyhow::Result<()> {
             filter,
             limit_sector_count,
         ),
+        BenchmarkArgs::Plot {
+            sample_size,
+            pieces_in_sector,
+            record_encoding_concurrency,
+            filter,
+        } => plot(
+            sample_size,
+            pieces_in_sector,
+            record_encoding_concurrency,
+            filter,
+        ),
+        BenchmarkArgs::PosTable {
+            sample_size,
+            filter,
+        } => pos_table(sample_size, filter),
     }
 }
 
@@ -341,3 +404,331 @@ fn prove(
 
     Ok(())
 }
+
+fn plot(
+    sample_size: usize,
+    pieces_in_sector: u16,
+    record_encoding_concurrency: Option<NonZeroUsize>,
+    filter: Option<String>,
+) -> anyhow::Result<()> {
+    if pieces_in_sector == 0 {
+        return Err(anyhow!("Number of pieces in sector must be non-zero"));
+    }
+
+    let public_key = PublicKey::default();
+    let sector_size = sector_size(pieces_in_sector);
+    let kzg = Kzg::new(embedded_kzg_settings());
+    let erasure_coding = ErasureCoding::new(
+        NonZeroUsize::new(Record::NUM_S_BUCKETS.next_power_of_two().ilog2() as usize)
+            .expect("Not zero; qed"),
+    )
+    .map_err(|error| anyhow::anyhow!(error))?;
+
+    println!("Archiving random history...");
+    let archived_history_segment = {
+        let mut archiver = Archiver::new(kzg.clone())
+            .map_err(|error| anyhow!("Failed to instantiate archiver: {error}"))?;
+        let mut input = RecordedHistorySegment::new_boxed();
+        thread_rng().fill(AsMut::<[u8]>::as_mut(input.as_mut()));
+
+        archiver
+            .add_block(
+                AsRef::<[u8]>::as_ref(input.as_ref()).to_vec(),
+                Default::default(),
+                true,
+            )
+            .into_iter()
+            .next()
+            .expect("Block is as large as recorded history segment, hence archived; qed")
+            .pieces
+    };
+    let farmer_protocol_info = FarmerProtocolInfo {
+        history_size: HistorySize::from(SegmentIndex::ZERO),
+        max_pieces_in_sector: pieces_in_sector,
+        recent_segments: HistorySize::from(NonZeroU64::new(5).expect("Not zero; qed")),
+        recent_history_fraction: (
+            HistorySize::from(NonZeroU64::new(1).expect("Not zero; qed")),
+            HistorySize::from(NonZeroU64::new(10).expect("Not zero; qed")),
+        ),
+        min_sector_lifetime: HistorySize::from(NonZeroU64::new(4).expect("Not zero; qed")),
+    };
+    let download_random_sector = |sector_index| {
+        block_on(download_sector(DownloadSectorOptions {
+            public_key: &public_key,
+            sector_index,
+            piece_getter: &archived_history_segment,
+            piece_getter_retry_policy: PieceGetterRetryPolicy::default(),
+            farmer_protocol_info,
+            kzg: &kzg,
+            pieces_in_sector,
+            segment_reconstruction_cache: None,
+        }))
+        .map_err(|error| anyhow!("Failed to download sector: {error}"))
+    };
+
+    let cpu_core_sets = all_cpu_cores();
+    let record_encoding_concurrency = record_encoding_concurrency.unwrap_or_else(|| {
+        recommended_record_encoding_concurrency(
+            cpu_core_sets
+                .first()
+                .expect("Guaranteed to have some CPU cores; qed"),
+        )
+    });
+    let thread_pools = cpu_core_sets.len();
+    let plotting_thread_pool_manager = create_plotting_thread_pool_manager(
+        cpu_core_sets
+            .into_iter()
+            .map(|cpu_core_set| (cpu_core_set.clone(), cpu_core_set)),
+    )?;
+
+    let mut criterion = Criterion::default().sample_size(sample_size);
+    if let Some(filter) = filter {
+        criterion = criterion.with_filter(filter);
+    }
+    {
+        let mut group = criterion.benchmark_group("plot");
+        group.throughput(Throughput::Bytes(sector_size as u64));
+        {
+            let thread_pools = plotting_thread_pool_manager.get_thread_pools();
+            let mut table_generators = (0..record_encoding_concurrency.get())
+                .map(|_| PosTable::generator())
+                .collect::<Vec<_>>();
+            let mut sector_output = Vec::new();
+            let mut sector_metadata_output = Vec::new();
+
+            group.bench_function("encode", |b| {
+                b.iter_batched(
+                    || download_random_sector(0).unwrap(),
+                    |downloaded_sector| {
+                        thread_pools.plotting.install(|| {
+                            encode_sector::<PosTable>(
+                                black_box(downloaded_sector),
+                                black_box(EncodeSectorOptions {
+                                    sector_index: 0,
+                                    erasure_coding: &erasure_coding,
+                                    pieces_in_sector,
+                                    sector_output: &mut sector_output,
+                                    sector_metadata_output: &mut sector_metadata_output,
+                                    table_generators: &mut table_generators,
+                                    abort_early: &AtomicBool::new(false),
+                                }),
+                            )
+                            .unwrap()
+                        })
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+
+        group.throughput(Throughput::Elements(1));
+        {
+            let mut table_generator = PosTable::generator();
+
+            group.bench_function("table-generation", |b| {
+                b.iter_batched(
+                    || PosSeed::from(rand::random::<[u8; PosSeed::SIZE]>()),
+                    |seed| {
+                        table_generator.generate_in_place(black_box(&seed));
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        {
+            let source = (0..Record::NUM_CHUNKS)
+                .map(|_| Scalar::from(rand::random::<[u8; Scalar::SAFE_BYTES]>()))
+                .collect::<Vec<_>>();
+            let mut parity = vec![Scalar::default(); Record::NUM_CHUNKS];
+
+            group.bench_function("erasure-coding", |b| {
+                b.iter(|| {
+                    erasure_coding
+                        .extend_into(black_box(&source), black_box(&mut parity))
+                        .unwrap();
+                })
+            });
+        }
+    }
+
+    criterion.final_summary();
+
+    println!("Measuring plotting throughput using {thread_pools} thread pool(s) concurrently...");
+    let sectors_per_hour = thread::scope(|scope| {
+        let handles = (0..thread_pools)
+            .map(|_| {
+                scope.spawn(|| {
+                    let thread_pools = plotting_thread_pool_manager.get_thread_pools();
+                    let mut table_generators = (0..record_encoding_concurrency.get())
+                        .map(|_| PosTable::generator())
+                        .collect::<Vec<_>>();
+                    let mut sector_output = Vec::new();
+                    let mut sector_metadata_output = Vec::new();
+                    let mut encoding_time = Duration::ZERO;
+
+                    for _ in 0..THROUGHPUT_SECTORS_PER_THREAD_POOL {
+                        // Downloading is not included into measurement
+                        let downloaded_sector = download_random_sector(0)?;
+
+                        let start = Instant::now();
+                        thread_pools.plotting.install(|| {
+                            encode_sector::<PosTable>(
+                                downloaded_sector,
+                                EncodeSectorOptions {
+                                    sector_index: 0,
+                                    erasure_coding: &erasure_coding,
+                                    pieces_in_sector,
+                                    sector_output: &mut sector_output,
+                                    sector_metadata_output: &mut sector_metadata_output,
+                                    table_generators: &mut table_generators,
+                                    abort_early: &AtomicBool::new(false),
+                                },
+                            )
+                        })?;
+                        encoding_time += start.elapsed();
+                    }
+
+                    anyhow::Ok(
+                        THROUGHPUT_SECTORS_PER_THREAD_POOL as f64 / encoding_time.as_secs_f64()
+                            * 3600.0,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_error| anyhow!("Plotting benchmark thread panicked"))?
+            })
+            .sum::<anyhow::Result<f64>>()
+    })?;
+
+    println!(
+        "Plotting throughput: ~{sectors_per_hour:.2} sectors/hour (~{}/hour)",
+        bytesize::to_string((sectors_per_hour * sector_size as f64) as u64, true)
+    );
+    println!(
+        "Suggested options: --sector-encoding-concurrency {thread_pools} \
+        --sector-downloading-concurrency {} --record-encoding-concurrency {}",
+        thread_pools + 1,
+        record_encoding_concurrency
+    );
+
+    Ok(())
+}
+
+fn pos_table(sample_size: usize, filter: Option<String>) -> anyhow::Result<()> {
+    let cpu_core_sets = all_cpu_cores();
+    let cpu_cores = cpu_core_sets
+        .first()
+        .expect("Guaranteed to have some CPU cores; qed")
+        .cpu_cores()
+        .len();
+    let plotting_thread_pool_manager = create_plotting_thread_pool_manager(
+        cpu_core_sets
+            .into_iter()
+            .map(|cpu_core_set| (cpu_core_set.clone(), cpu_core_set)),
+    )?;
+    let thread_pools = plotting_thread_pool_manager.get_thread_pools();
+    // Powers of two up to the number of CPU cores in one thread pool
+    let concurrency_options = iter::successors(Some(1_usize), |concurrency| {
+        Some(concurrency * 2).filter(|&concurrency| concurrency <= cpu_cores)
+    })
+    .collect::<Vec<_>>();
+
+    let mut criterion = Criterion::default().sample_size(sample_size);
+    if let Some(filter) = filter {
+        criterion = criterion.with_filter(filter);
+    }
+    {
+        let mut group = criterion.benchmark_group("pos-table");
+        group.throughput(Throughput::Elements(1));
+        {
+            let mut table_generator = PosTable::generator();
+
+            group.bench_function("single", |b| {
+                b.iter_batched(
+                    || PosSeed::from(rand::random::<[u8; PosSeed::SIZE]>()),
+                    |seed| {
+                        table_generator.generate_in_place(black_box(&seed));
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        {
+            let mut table_generator = PosTable::generator();
+
+            group.bench_function("parallel", |b| {
+                b.iter_batched(
+                    || PosSeed::from(rand::random::<[u8; PosSeed::SIZE]>()),
+                    |seed| {
+                        thread_pools.plotting.install(|| {
+                            table_generator.generate_parallel_in_place(black_box(&seed));
+                        })
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        for &concurrency in &concurrency_options {
+            let mut table_generators = (0..concurrency)
+                .map(|_| PosTable::generator())
+                .collect::<Vec<_>>();
+
+            group.throughput(Throughput::Elements(concurrency as u64));
+            group.bench_function(format!("concurrent/{concurrency}"), |b| {
+                b.iter(|| {
+                    generate_tables_concurrently(&thread_pools.plotting, &mut table_generators);
+                })
+            });
+        }
+    }
+
+    criterion.final_summary();
+
+    println!("Measuring table generation throughput with different concurrency...");
+    let mut best = None::<(usize, f64)>;
+    for &concurrency in &concurrency_options {
+        let mut table_generators = (0..concurrency)
+            .map(|_| PosTable::generator())
+            .collect::<Vec<_>>();
+        // Warm up so that memory of tables is allocated
+        generate_tables_concurrently(&thread_pools.plotting, &mut table_generators);
+
+        let start = Instant::now();
+        for _ in 0..sample_size {
+            generate_tables_concurrently(&thread_pools.plotting, &mut table_generators);
+        }
+        let tables_per_second = (sample_size * concurrency) as f64 / start.elapsed().as_secs_f64();
+
+        println!("Concurrency {concurrency}: ~{tables_per_second:.2} tables/s");
+
+        if best.map_or(true, |(_, best_tables_per_second)| {
+            tables_per_second > best_tables_per_second
+        }) {
+            best.replace((concurrency, tables_per_second));
+        }
+    }
+
+    if let Some((concurrency, _tables_per_second)) = best {
+        println!("Suggested options: --record-encoding-concurrency {concurrency}");
+    }
+
+    Ok(())
+}
+
+fn generate_tables_concurrently(
+    thread_pool: &ThreadPool,
+    table_generators: &mut [<PosTable as Table>::Generator],
+) {
+    thread_pool.install(|| {
+        table_generators.par_iter_mut().for_each(|table_generator| {
+            let seed = PosSeed::from(rand::random::<[u8; PosSeed::SIZE]>());
+            black_box(table_generator.generate_in_place(&seed));
+        });
+    });
+}
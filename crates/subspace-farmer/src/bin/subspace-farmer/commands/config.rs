@@ -0,0 +1,42 @@
+use crate::commands::farm::config::FarmerConfig;
+use anyhow::anyhow;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+/// Arguments for configuration file management
+#[derive(Debug, Subcommand)]
+pub(crate) enum ConfigArgs {
+    /// Check configuration file of `farm` command (specified with `--config`), report errors found
+    /// along with line numbers (all validation errors, but only the first syntax or schema error)
+    Check {
+        /// Path to configuration file
+        path: PathBuf,
+    },
+}
+
+pub(crate) fn config(config_args: ConfigArgs) -> anyhow::Result<()> {
+    match config_args {
+        ConfigArgs::Check { path } => check(path),
+    }
+}
+
+fn check(path: PathBuf) -> anyhow::Result<()> {
+    match FarmerConfig::read(&path) {
+        Ok(_farmer_config) => {
+            println!("Configuration file {} is valid", path.display());
+
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}: {error}", path.display());
+            }
+
+            Err(anyhow!(
+                "Configuration file {} has {} error(s)",
+                path.display(),
+                errors.len()
+            ))
+        }
+    }
+}
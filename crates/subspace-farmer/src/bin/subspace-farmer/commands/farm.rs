@@ -1,53 +1,83 @@
+pub(crate) mod config;
+#[cfg(unix)]
+pub(crate) mod control;
 mod dsn;
+mod farms;
 mod metrics;
+mod plotting_concurrency;
 
+use crate::commands::farm::config::FarmerConfig;
 use crate::commands::farm::dsn::configure_dsn;
-use crate::commands::farm::metrics::{FarmerMetrics, SectorState};
+use crate::commands::farm::farms::{CreateFarmOptions, FarmRewardAddresses, Farms};
+use crate::commands::farm::metrics::FarmerMetrics;
+use crate::commands::farm::plotting_concurrency::{
+    PlottingConcurrency, PlottingConcurrencyOptions,
+};
+use crate::commands::shared::{
+    derive_libp2p_keypair, open_or_create_network_keypair, IdentitySecretArgs,
+};
 use crate::utils::shutdown_signal;
 use anyhow::anyhow;
 use bytesize::ByteSize;
-use clap::{Parser, ValueHint};
-use futures::channel::oneshot;
-use futures::stream::{FuturesOrdered, FuturesUnordered};
-use futures::{FutureExt, StreamExt};
+use clap::{ArgMatches, Parser, ValueHint};
+use futures::channel::{mpsc, oneshot};
+use futures::FutureExt;
 use parking_lot::Mutex;
 use prometheus_client::registry::Registry;
+use ss58_registry::{Ss58AddressFormat, Ss58AddressFormatRegistry};
+use std::collections::HashMap;
 use std::fs;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
-use std::num::{NonZeroU8, NonZeroUsize};
+use std::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
 use std::path::PathBuf;
 use std::pin::pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use subspace_core_primitives::crypto::kzg::{embedded_kzg_settings, Kzg};
-use subspace_core_primitives::{PublicKey, Record, SectorIndex};
+use subspace_core_primitives::{PublicKey, Record};
 use subspace_erasure_coding::ErasureCoding;
 use subspace_farmer::farmer_cache::FarmerCache;
-use subspace_farmer::single_disk_farm::farming::FarmingNotification;
+#[cfg(unix)]
+use subspace_farmer::reward_signing::remote::RemoteSigner;
+#[cfg(unix)]
+use subspace_farmer::reward_signing::reward_signing;
+use subspace_farmer::reward_signing::RewardSignerProvider;
+use subspace_farmer::single_disk_farm::download_staging::DownloadStagingOptions;
+use subspace_farmer::single_disk_farm::farming::reward_addresses::{
+    RewardAddressRotation, RewardAddresses,
+};
+#[cfg(unix)]
+use subspace_farmer::single_disk_farm::SingleDiskFarmInfo;
 use subspace_farmer::single_disk_farm::{
-    SectorExpirationDetails, SectorPlottingDetails, SectorUpdate, SingleDiskFarm,
-    SingleDiskFarmError, SingleDiskFarmOptions,
+    SingleDiskFarm, SingleDiskFarmError, SingleDiskFarmOptions,
 };
 use subspace_farmer::utils::farmer_piece_getter::FarmerPieceGetter;
 use subspace_farmer::utils::piece_validator::SegmentCommitmentPieceValidator;
-use subspace_farmer::utils::plotted_pieces::PlottedPieces;
-use subspace_farmer::utils::ss58::parse_ss58_reward_address;
+use subspace_farmer::utils::plotted_pieces::DiskFarmIndex;
+use subspace_farmer::utils::plotting_schedule::{PlottingSchedule, PlottingWindow};
+use subspace_farmer::utils::ss58::{parse_ss58_reward_address_with_format, Ss58ParsingError};
 use subspace_farmer::utils::{
     all_cpu_cores, create_plotting_thread_pool_manager, parse_cpu_cores_sets,
     recommended_number_of_farming_threads, run_future_in_dedicated_thread,
     thread_pool_core_indices, AsyncJoinOnDrop, CpuCoreSet,
 };
 use subspace_farmer::{Identity, NodeClient, NodeRpcClient};
-use subspace_farmer_components::plotting::PlottedSector;
+use subspace_farmer_components::segment_reconstruction::SegmentReconstructionCache;
 use subspace_metrics::{start_prometheus_metrics_server, RegistryAdapter};
-use subspace_networking::libp2p::identity::{ed25519, Keypair};
 use subspace_networking::libp2p::multiaddr::Protocol;
 use subspace_networking::libp2p::Multiaddr;
+use subspace_networking::utils::peer_reputation::{PeerReputation, PeerReputationConfig};
 use subspace_networking::utils::piece_provider::PieceProvider;
 use subspace_proof_of_space::Table;
-use tokio::sync::Semaphore;
-use tracing::{debug, error, info, info_span, warn};
-use zeroize::Zeroizing;
+#[cfg(unix)]
+use tracing::error;
+use tracing::{debug, info, warn};
+
+/// Number of segments which pieces fetched for reconstruction of missing pieces are kept in memory,
+/// each taking roughly 128 MiB
+const SEGMENT_RECONSTRUCTION_CACHE_SIZE: NonZeroUsize =
+    NonZeroUsize::new(2).expect("Not zero; qed");
 
 fn should_farm_during_initial_plotting() -> bool {
     let total_cpu_cores = all_cpu_cores()
@@ -70,16 +100,44 @@ pub(crate) struct FarmingArgs {
     ///
     ///   path=/path/to/directory,size=5T
     ///
+    /// Farm can optionally have its own reward addresses (`reward_address` can be repeated),
+    /// overriding `--reward-address`:
+    ///
+    ///   path=/path/to/directory,size=5T,reward_address=st...
+    ///
+    /// Farm can also have its own cache percentage, overriding `--cache-percentage`:
+    ///
+    ///   path=/path/to/directory,size=5T,cache_percentage=10
+    ///
+    /// Cache-only farm (for instance on fast SSD) uses all of its space for piece cache, it has
+    /// neither plot nor identity and serves pieces to other peers and plotting:
+    ///
+    ///   path=/path/to/directory,size=500G,cache_only=true
+    ///
     /// `size` is max allocated size in human readable format (e.g. 10GB, 2TiB) or just bytes that
     /// farmer will make sure not not exceed (and will pre-allocated all the space on startup to
     /// ensure it will not run out of space in runtime).
     disk_farms: Vec<DiskFarm>,
+    /// Path to configuration file in TOML format, see `config check` command.
+    ///
+    /// Options specified on command line take precedence over configuration file, farms specified
+    /// on command line replace farms from configuration file.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
     /// WebSocket RPC URL of the Subspace node to connect to
     #[arg(long, value_hint = ValueHint::Url, default_value = "ws://127.0.0.1:9944")]
     node_rpc_url: String,
-    /// Address for farming rewards
-    #[arg(long, value_parser = parse_ss58_reward_address)]
-    reward_address: PublicKey,
+    /// Address for farming rewards, can be specified multiple times to split rewards between
+    /// multiple addresses according to `--reward-address-rotation`.
+    ///
+    /// Used for farms that don't have reward addresses specified explicitly.
+    #[arg(long, value_parser = reward_address_parser)]
+    reward_address: Vec<RewardAddress>,
+    /// Policy of choosing reward address when farm has more than one: `per-solution` uses next
+    /// address for every solution, `window=<duration>` (e.g. `window=1h`, supported units are
+    /// `s`, `m`, `h` and `d`) switches to next address every time window
+    #[arg(long, default_value = "per-solution", value_parser = reward_address_rotation_parser)]
+    reward_address_rotation: RewardAddressRotation,
     /// Percentage of allocated space dedicated for caching purposes, 99% max
     #[arg(long, default_value = "1", value_parser = cache_percentage_parser)]
     cache_percentage: NonZeroU8,
@@ -131,6 +189,16 @@ pub(crate) struct FarmingArgs {
     /// usage and typically more efficient CPU utilization.
     #[arg(long)]
     record_encoding_concurrency: Option<NonZeroUsize>,
+    /// Memory limit for plotting process, for example "16GiB". When specified, concurrency of
+    /// sector downloading, sector encoding and record encoding is derived from it such that
+    /// estimated memory usage of plotting doesn't exceed the limit, while explicitly specified
+    /// `--sector-downloading-concurrency`, `--sector-encoding-concurrency` and
+    /// `--record-encoding-concurrency` are treated as upper bounds. Sector downloading and sector
+    /// encoding concurrency is adjusted when farms are added or removed while farmer is running.
+    ///
+    /// Memory used by farming and piece cache is not included.
+    #[arg(long)]
+    plotting_memory_limit: Option<ByteSize>,
     /// Allows to enable farming during initial plotting. Not used by default on machines with 8 or
     /// less logical cores because plotting is so intense on CPU and memory that farming will likely
     /// not work properly, yet it will significantly impact plotting speed, delaying the time when
@@ -184,9 +252,137 @@ pub(crate) struct FarmingArgs {
     /// each with a pair of CPU cores.
     #[arg(long, conflicts_with_all = &["sector_encoding_concurrency", "replotting_thread_pool_size"])]
     replotting_cpu_cores: Option<String>,
+    /// Time window in UTC during which plotting and replotting of new sectors doesn't start, in
+    /// `HH:MM-HH:MM` format (for example `09:00-17:00`), can be specified multiple times. Window
+    /// can cross midnight (for example `22:00-06:00`).
+    ///
+    /// Sectors that are already being plotted are finished. Plotting can also be paused and
+    /// resumed explicitly with `farm-control` command.
+    #[arg(long, value_name = "WINDOW")]
+    plotting_pause_window: Vec<PlottingWindow>,
+    /// CPU usage of other processes (in % of all CPU cores) above which plotting and replotting of
+    /// new sectors doesn't start, CPU usage of the farmer itself is not taken into account.
+    ///
+    /// Only supported on Linux.
+    #[arg(long, value_name = "PERCENT", value_parser = plotting_pause_cpu_usage_parser)]
+    plotting_pause_cpu_usage: Option<NonZeroU8>,
+    /// Scratch directory where downloaded sectors are stored until they are encoded, each farm
+    /// uses its own subdirectory.
+    ///
+    /// Allows to download many sectors ahead of encoding without holding them in RAM, downloaded
+    /// sectors are also kept across restarts. Roughly 1 GiB of disk space is needed per staged
+    /// sector.
+    ///
+    /// Staged sectors of farms that are no longer used are removed, so directory must not be
+    /// shared by multiple farmers.
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::DirPath)]
+    download_staging_directory: Option<PathBuf>,
+    /// How many sectors each farm downloads ahead of encoding when
+    /// `--download-staging-directory` is specified
+    #[arg(
+        long,
+        value_name = "SECTORS",
+        default_value = "8",
+        requires = "download_staging_directory"
+    )]
+    download_staging_sectors: NonZeroUsize,
     /// Disable farm locking, for example if file system doesn't support it
     #[arg(long)]
     disable_farm_locking: bool,
+    /// Number of times a failed farm (for instance due to I/O error on dying disk) is restarted,
+    /// with exponential backoff between attempts.
+    ///
+    /// Failed farm is quarantined (excluded from farming, plotting and serving pieces) while other
+    /// farms keep working. Failed farms are not restarted by default.
+    #[arg(long, default_value_t = 0)]
+    failed_farm_restarts: u32,
+    /// Time in seconds farmer waits on shutdown for sectors that are being plotted to be finished
+    /// and persisted, such that plotting progress is not lost.
+    ///
+    /// No new sectors are started after first shutdown signal, second signal exits immediately. Set
+    /// to 0 to exit immediately on first signal.
+    #[arg(long, value_name = "SECONDS", default_value_t = 1800)]
+    graceful_shutdown_timeout: u64,
+    /// Path to Unix socket that allows to add and remove farms while farmer is running, see
+    /// `farm-control` command
+    #[cfg(unix)]
+    #[arg(long)]
+    control_socket: Option<PathBuf>,
+    /// Secret to unlock encrypted identity with, newly created identities are encrypted with it
+    /// too
+    #[clap(flatten)]
+    identity_secret: IdentitySecretArgs,
+    /// Path to Unix socket of `subspace-farmer signer` that holds identity and signs rewards, farm
+    /// directories don't need to contain identity in this case
+    #[cfg(unix)]
+    #[arg(
+        long,
+        value_name = "SOCKET",
+        conflicts_with_all = &["identity_passphrase_env", "identity_passphrase_stdin", "identity_keyfile"]
+    )]
+    remote_signer: Option<PathBuf>,
+    /// Hex-encoded public key of identity that remote signer should sign with, can be specified
+    /// multiple times, all identities signer holds are used by default.
+    ///
+    /// Each farm uses identity it was created with, new farms use identity that is not used by
+    /// other farms yet.
+    #[cfg(unix)]
+    #[arg(long, requires = "remote_signer", value_parser = public_key_parser)]
+    remote_signer_public_key: Vec<PublicKey>,
+}
+
+#[cfg(unix)]
+fn public_key_parser(s: &str) -> anyhow::Result<PublicKey> {
+    let public_key = <[u8; 32]>::try_from(hex::decode(s.trim_start_matches("0x"))?)
+        .map_err(|_error| anyhow!("Public key must be exactly 32 bytes"))?;
+
+    Ok(PublicKey::from(public_key))
+}
+
+/// Reward address along with address format (network) it was encoded for
+#[derive(Debug, Copy, Clone)]
+struct RewardAddress {
+    public_key: PublicKey,
+    format: Ss58AddressFormat,
+}
+
+fn reward_address_parser(s: &str) -> Result<RewardAddress, Ss58ParsingError> {
+    let (public_key, format) = parse_ss58_reward_address_with_format(s)?;
+
+    Ok(RewardAddress { public_key, format })
+}
+
+fn reward_address_rotation_parser(s: &str) -> anyhow::Result<RewardAddressRotation> {
+    if s == "per-solution" {
+        return Ok(RewardAddressRotation::PerSolution);
+    }
+
+    let window = s
+        .strip_prefix("window=")
+        .ok_or_else(|| anyhow!("Rotation must be either `per-solution` or `window=<duration>`"))?;
+    let unit_position = window
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Time window \"{window}\" is missing unit"))?;
+    let (value, unit) = window.split_at(unit_position);
+    let value = value.parse::<u64>()?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        unit => {
+            return Err(anyhow!(
+                "Unsupported time window unit \"{unit}\", only `s`, `m`, `h` and `d` are supported"
+            ));
+        }
+    };
+    if value == 0 {
+        return Err(anyhow!("Time window must not be zero"));
+    }
+
+    Ok(RewardAddressRotation::TimeWindow(Duration::from_secs(
+        value.saturating_mul(unit_seconds),
+    )))
 }
 
 fn cache_percentage_parser(s: &str) -> anyhow::Result<NonZeroU8> {
@@ -199,6 +395,16 @@ fn cache_percentage_parser(s: &str) -> anyhow::Result<NonZeroU8> {
     Ok(cache_percentage)
 }
 
+fn plotting_pause_cpu_usage_parser(s: &str) -> anyhow::Result<NonZeroU8> {
+    let cpu_usage = NonZeroU8::from_str(s)?;
+
+    if cpu_usage.get() > 100 {
+        return Err(anyhow::anyhow!("CPU usage can't exceed 100%"));
+    }
+
+    Ok(cpu_usage)
+}
+
 /// Arguments for DSN
 #[derive(Debug, Parser)]
 struct DsnArgs {
@@ -224,6 +430,10 @@ struct DsnArgs {
     /// Kademlia DHT.
     #[arg(long, default_value_t = false)]
     allow_private_ips: bool,
+    /// Discover other farmers in local network using mDNS and prefer them when retrieving pieces
+    /// they provide, requires `--allow-private-ips`.
+    #[arg(long, default_value_t = false, requires = "allow_private_ips")]
+    enable_mdns: bool,
     /// Multiaddrs of reserved nodes to maintain a connection to, multiple are supported
     #[arg(long)]
     reserved_peers: Vec<Multiaddr>,
@@ -245,14 +455,51 @@ struct DsnArgs {
     /// Defines whether we should run blocking Kademlia bootstrap() operation before other requests.
     #[arg(long, default_value_t = false)]
     disable_bootstrap_on_start: bool,
+    /// Max upload rate per second of the DSN node (for instance `2MiB`), unlimited by default.
+    ///
+    /// Small writes like outgoing piece requests are not delayed, such that serving pieces to
+    /// other peers doesn't starve plotting.
+    #[arg(long)]
+    upload_rate_limit: Option<ByteSize>,
+    /// Max download rate per second of the DSN node (for instance `10MiB`), unlimited by default.
+    #[arg(long)]
+    download_rate_limit: Option<ByteSize>,
+    /// Max number of piece requests from other peers processed concurrently (per request type)
+    #[arg(long, default_value = "10")]
+    piece_serving_concurrency: NonZeroUsize,
+    /// Max number of piece requests from other peers waiting to be processed (per request type),
+    /// requests beyond this limit are rejected
+    #[arg(long, default_value_t = 50)]
+    piece_serving_queue_size: usize,
+    /// Max number of piece requests per second a single peer can make, unlimited by default.
+    /// Requests beyond this limit are rejected.
+    #[arg(long)]
+    piece_serving_peer_rate: Option<NonZeroU32>,
+    /// Max number of pieces read from plots concurrently when serving pieces to other peers.
+    ///
+    /// Pieces found in cache are served without waiting, such that slow reads from plots don't
+    /// delay cache hits. Up to `--piece-serving-queue-size` reads from plots can wait, pieces
+    /// beyond that are not served.
+    #[arg(long, default_value = "2")]
+    piece_serving_plot_read_concurrency: NonZeroUsize,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct DiskFarm {
     /// Path to directory where data is stored.
     directory: PathBuf,
-    /// How much space in bytes can farm use for plots (metadata space is not included)
+    /// How much space in bytes can farm use for plots (metadata space is not included), for
+    /// cache-only farm this is the size of piece cache
     allocated_plotting_space: u64,
+    /// Reward addresses specific to this farm, global ones are used if empty
+    reward_addresses: Vec<RewardAddress>,
+    /// Cache percentage specific to this farm, global one is used if not specified
+    cache_percentage: Option<NonZeroU8>,
+    /// CPU cores used exclusively for plotting and replotting of this farm, global thread pools
+    /// are used if not specified
+    plotting_cpu_cores: Option<Vec<CpuCoreSet>>,
+    /// Farm only contains piece cache, without plot or identity
+    cache_only: bool,
 }
 
 impl FromStr for DiskFarm {
@@ -260,12 +507,15 @@ impl FromStr for DiskFarm {
 
     fn from_str(s: &str) -> anyhow::Result<Self, Self::Err> {
         let parts = s.split(',').collect::<Vec<_>>();
-        if parts.len() != 2 {
-            return Err("Must contain 2 coma-separated components".to_string());
+        if parts.len() < 2 {
+            return Err("Must contain at least 2 coma-separated components".to_string());
         }
 
         let mut plot_directory = None;
         let mut allocated_plotting_space = None;
+        let mut reward_addresses = Vec::new();
+        let mut cache_percentage = None;
+        let mut cache_only = false;
 
         for part in parts {
             let part = part.splitn(2, '=').collect::<Vec<_>>();
@@ -290,14 +540,36 @@ impl FromStr for DiskFarm {
                             .as_u64(),
                     );
                 }
+                "reward_address" => {
+                    reward_addresses.push(reward_address_parser(value).map_err(|error| {
+                        format!("Failed to parse `reward_address` \"{value}\": {error}")
+                    })?);
+                }
+                "cache_percentage" => {
+                    cache_percentage.replace(cache_percentage_parser(value).map_err(|error| {
+                        format!("Failed to parse `cache_percentage` \"{value}\": {error}")
+                    })?);
+                }
+                "cache_only" => {
+                    cache_only = value.parse::<bool>().map_err(|error| {
+                        format!("Failed to parse `cache_only` \"{value}\": {error}")
+                    })?;
+                }
                 key => {
                     return Err(format!(
-                        "Key \"{key}\" is not supported, only `path` or `size`"
+                        "Key \"{key}\" is not supported, only `path`, `size`, `reward_address`, \
+                        `cache_percentage` or `cache_only`"
                     ));
                 }
             }
         }
 
+        if cache_only && (cache_percentage.is_some() || !reward_addresses.is_empty()) {
+            return Err(
+                "Cache-only farm can't have `cache_percentage` or `reward_address`".to_string(),
+            );
+        }
+
         Ok(DiskFarm {
             directory: plot_directory.ok_or({
                 "`path` key is required with path to directory where plots will be stored"
@@ -305,21 +577,48 @@ impl FromStr for DiskFarm {
             allocated_plotting_space: allocated_plotting_space.ok_or({
                 "`size` key is required with path to directory where plots will be stored"
             })?,
+            reward_addresses,
+            cache_percentage,
+            plotting_cpu_cores: None,
+            cache_only,
         })
     }
 }
 
 /// Start farming by using multiple replica plot in specified path and connecting to WebSocket
 /// server at specified address.
-pub(crate) async fn farm<PosTable>(farming_args: FarmingArgs) -> anyhow::Result<()>
+///
+/// `matches` are used to find out which options were specified on command line explicitly and
+/// must not be overridden by configuration file.
+pub(crate) async fn farm<PosTable>(
+    mut farming_args: FarmingArgs,
+    matches: &ArgMatches,
+) -> anyhow::Result<()>
 where
     PosTable: Table,
 {
     let signal = shutdown_signal();
 
+    if let Some(config) = farming_args.config.take() {
+        let farmer_config = FarmerConfig::read(&config).map_err(|errors| {
+            anyhow!(
+                "Invalid configuration file {}:\n{}",
+                config.display(),
+                errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        })?;
+        farmer_config.apply(&mut farming_args, matches);
+    }
+
     let FarmingArgs {
+        config: _,
         node_rpc_url,
         reward_address,
+        reward_address_rotation,
         max_pieces_in_sector,
         mut dsn,
         cache_percentage,
@@ -331,13 +630,27 @@ where
         sector_downloading_concurrency,
         sector_encoding_concurrency,
         record_encoding_concurrency,
+        plotting_memory_limit,
         farm_during_initial_plotting,
         farming_thread_pool_size,
         plotting_thread_pool_size,
         plotting_cpu_cores,
         replotting_thread_pool_size,
         replotting_cpu_cores,
+        plotting_pause_window,
+        plotting_pause_cpu_usage,
+        download_staging_directory,
+        download_staging_sectors,
         disable_farm_locking,
+        failed_farm_restarts,
+        graceful_shutdown_timeout,
+        #[cfg(unix)]
+        control_socket,
+        identity_secret,
+        #[cfg(unix)]
+        remote_signer,
+        #[cfg(unix)]
+        remote_signer_public_key,
     } = farming_args;
 
     // Override flags with `--dev`
@@ -352,6 +665,10 @@ where
         disk_farms = vec![DiskFarm {
             directory: tmp_directory.as_ref().to_path_buf(),
             allocated_plotting_space: plot_size.as_u64(),
+            reward_addresses: Vec::new(),
+            cache_percentage: None,
+            plotting_cpu_cores: None,
+            cache_only: false,
         }];
 
         Some(tmp_directory)
@@ -374,6 +691,49 @@ where
         None
     };
 
+    let (cache_only_disk_farms, disk_farms) = disk_farms
+        .into_iter()
+        .partition::<Vec<_>, _>(|disk_farm| disk_farm.cache_only);
+    if disk_farms.is_empty() {
+        return Err(anyhow!(
+            "There must be at least one disk farm provided that is not cache-only"
+        ));
+    }
+
+    let cache_only_farms = cache_only_disk_farms
+        .iter()
+        .map(|disk_farm| {
+            let cache_only_farm = SingleDiskFarm::open_cache_only(
+                &disk_farm.directory,
+                disk_farm.allocated_plotting_space,
+                disable_farm_locking,
+            )
+            .map_err(|error| {
+                anyhow!(
+                    "Failed to open cache-only farm {}: {error}",
+                    disk_farm.directory.display()
+                )
+            })?;
+
+            if !no_info {
+                println!("Cache-only farm:");
+                println!("  Directory: {}", disk_farm.directory.display());
+                println!(
+                    "  Allocated space: {} ({})",
+                    bytesize::to_string(disk_farm.allocated_plotting_space, true),
+                    bytesize::to_string(disk_farm.allocated_plotting_space, false)
+                );
+            }
+
+            Ok((disk_farm.directory.clone(), cache_only_farm))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let farms_reward_addresses = disk_farms
+        .iter()
+        .map(|disk_farm| farm_reward_addresses(disk_farm, &reward_address, reward_address_rotation))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
     let plotted_pieces = Arc::new(Mutex::new(None));
 
     info!(url = %node_rpc_url, "Connecting to node RPC");
@@ -384,14 +744,62 @@ where
         .await
         .map_err(|error| anyhow::anyhow!(error))?;
 
+    // Point out addresses of a different network early, they are likely a mistake
+    check_reward_addresses_network(
+        reward_address.iter().chain(
+            disk_farms
+                .iter()
+                .flat_map(|disk_farm| &disk_farm.reward_addresses),
+        ),
+        farmer_app_info.ss58_format,
+    );
+
     let first_farm_directory = &disk_farms
         .first()
         .expect("Disk farm collection is not be empty as checked above; qed")
         .directory;
 
-    let identity = Identity::open_or_create(first_farm_directory)
-        .map_err(|error| anyhow!("Failed to open or create identity: {error}"))?;
-    let keypair = derive_libp2p_keypair(identity.secret_key());
+    #[cfg(unix)]
+    let remote_signer = match remote_signer {
+        Some(remote_signer) => {
+            let remote_signer = RemoteSigner::connect(remote_signer, &remote_signer_public_key)
+                .await
+                .map_err(|error| anyhow!("Failed to connect to remote signer: {error}"))?;
+            for public_key in remote_signer.public_keys() {
+                info!(%public_key, "Connected to remote signer");
+            }
+
+            // Identities of existing farms are not given to new farms, farms that can't be
+            // opened will report an error later
+            for disk_farm in &disk_farms {
+                if let Ok(Some(single_disk_farm_info)) =
+                    SingleDiskFarmInfo::load_from(&disk_farm.directory)
+                {
+                    remote_signer.reserve_public_key(*single_disk_farm_info.public_key());
+                }
+            }
+
+            Some(Arc::new(remote_signer))
+        }
+        None => None,
+    };
+    #[cfg(unix)]
+    let reward_signer_provider = remote_signer
+        .clone()
+        .map(|remote_signer| remote_signer as Arc<dyn RewardSignerProvider>);
+    #[cfg(not(unix))]
+    let reward_signer_provider = None::<Arc<dyn RewardSignerProvider>>;
+
+    let identity_secret = identity_secret.secret()?;
+    let keypair = if reward_signer_provider.is_some() {
+        // Identity is not available locally, so networking uses its own keypair
+        open_or_create_network_keypair(first_farm_directory)?
+    } else {
+        let identity =
+            Identity::open_or_create_with_secret(first_farm_directory, identity_secret.as_ref())
+                .map_err(|error| anyhow!("Failed to open or create identity: {error}"))?;
+        derive_libp2p_keypair(identity.secret_key())
+    };
     let peer_id = keypair.public().to_peer_id();
 
     let (farmer_cache, farmer_cache_worker) = FarmerCache::new(node_client.clone(), peer_id);
@@ -399,6 +807,9 @@ where
     // Metrics
     let mut prometheus_metrics_registry = Registry::default();
     let farmer_metrics = FarmerMetrics::new(&mut prometheus_metrics_registry);
+    for (directory, cache_only_farm) in &cache_only_farms {
+        farmer_metrics.update_cache_only_farm_capacity(directory, cache_only_farm.capacity());
+    }
     let should_start_prometheus_server = !prometheus_listen_on.is_empty();
 
     let (node, mut node_runner) = {
@@ -418,6 +829,28 @@ where
         )?
     };
 
+    // Persisted alongside known peers of the networking stack
+    let peer_reputation = PeerReputation::new(
+        PeerReputationConfig {
+            path: Some(
+                first_farm_directory
+                    .join("peer_reputation.bin")
+                    .into_boxed_path(),
+            ),
+            ..PeerReputationConfig::default()
+        },
+        should_start_prometheus_server.then_some(&mut prometheus_metrics_registry),
+    )
+    .map_err(|error| anyhow!("Failed to open peer reputation: {error}"))?;
+    let _peer_reputation_worker = AsyncJoinOnDrop::new(
+        tokio::spawn({
+            let peer_reputation = peer_reputation.clone();
+
+            async move { peer_reputation.run().await }
+        }),
+        true,
+    );
+
     let _prometheus_worker = if should_start_prometheus_server {
         let prometheus_task = start_prometheus_metrics_server(
             prometheus_listen_on,
@@ -441,7 +874,8 @@ where
         node_client.clone(),
         kzg.clone(),
     ));
-    let piece_provider = PieceProvider::new(node.clone(), validator.clone());
+    let piece_provider =
+        PieceProvider::new(node.clone(), validator.clone()).with_peer_reputation(peer_reputation);
 
     let piece_getter = FarmerPieceGetter::new(
         piece_provider,
@@ -478,16 +912,51 @@ where
         None => farmer_app_info.protocol_info.max_pieces_in_sector,
     };
 
-    let mut plotting_thread_pool_core_indices;
-    let mut replotting_thread_pool_core_indices;
+    // CPU cores used exclusively by some farms must not be used by global thread pools
+    let dedicated_cpu_cores = disk_farms
+        .iter()
+        .filter_map(|disk_farm| disk_farm.plotting_cpu_cores.as_ref())
+        .flatten()
+        .flat_map(|cpu_core_set| cpu_core_set.cpu_cores())
+        .copied()
+        .collect::<Vec<_>>();
+    let without_dedicated_cpu_cores = |cpu_core_sets: Vec<CpuCoreSet>| {
+        let all_cpu_core_sets = cpu_core_sets.clone();
+        let cpu_core_sets = cpu_core_sets
+            .into_iter()
+            .filter_map(|mut cpu_core_set| {
+                cpu_core_set
+                    .remove_cpu_cores(&dedicated_cpu_cores)
+                    .then_some(cpu_core_set)
+            })
+            .collect::<Vec<_>>();
+
+        if cpu_core_sets.is_empty() {
+            // Nothing is left for global thread pools, they are only used by farms that don't
+            // have dedicated CPU cores
+            warn!(
+                "All CPU cores of global plotting thread pools are dedicated to farms, farms \
+                without dedicated CPU cores will share them"
+            );
+            all_cpu_core_sets
+        } else {
+            cpu_core_sets
+        }
+    };
+
+    let plotting_thread_pool_core_indices;
+    let replotting_thread_pool_core_indices;
+    let regroup_cpu_cores = plotting_cpu_cores.is_none();
     if let Some(plotting_cpu_cores) = plotting_cpu_cores {
-        plotting_thread_pool_core_indices = parse_cpu_cores_sets(&plotting_cpu_cores)
-            .map_err(|error| anyhow::anyhow!("Failed to parse `--plotting-cpu-cores`: {error}"))?;
+        plotting_thread_pool_core_indices =
+            without_dedicated_cpu_cores(parse_cpu_cores_sets(&plotting_cpu_cores).map_err(
+                |error| anyhow::anyhow!("Failed to parse `--plotting-cpu-cores`: {error}"),
+            )?);
         replotting_thread_pool_core_indices = match replotting_cpu_cores {
             Some(replotting_cpu_cores) => {
-                parse_cpu_cores_sets(&replotting_cpu_cores).map_err(|error| {
-                    anyhow::anyhow!("Failed to parse `--replotting-cpu-cores`: {error}")
-                })?
+                without_dedicated_cpu_cores(parse_cpu_cores_sets(&replotting_cpu_cores).map_err(
+                    |error| anyhow::anyhow!("Failed to parse `--replotting-cpu-cores`: {error}"),
+                )?)
             }
             None => plotting_thread_pool_core_indices.clone(),
         };
@@ -499,11 +968,14 @@ where
             ));
         }
     } else {
-        plotting_thread_pool_core_indices =
-            thread_pool_core_indices(plotting_thread_pool_size, sector_encoding_concurrency);
+        plotting_thread_pool_core_indices = without_dedicated_cpu_cores(thread_pool_core_indices(
+            plotting_thread_pool_size,
+            sector_encoding_concurrency,
+        ));
         replotting_thread_pool_core_indices = {
-            let mut replotting_thread_pool_core_indices =
-                thread_pool_core_indices(replotting_thread_pool_size, sector_encoding_concurrency);
+            let mut replotting_thread_pool_core_indices = without_dedicated_cpu_cores(
+                thread_pool_core_indices(replotting_thread_pool_size, sector_encoding_concurrency),
+            );
             if replotting_thread_pool_size.is_none() {
                 // The default behavior is to use all CPU cores, but for replotting we just want half
                 replotting_thread_pool_core_indices
@@ -516,74 +988,144 @@ where
         if plotting_thread_pool_core_indices.len() > 1 {
             info!(
                 l3_cache_groups = %plotting_thread_pool_core_indices.len(),
-                "Multiple L3 cache groups detected"
+                "Multiple L3 cache groups detected, CPU cores are regrouped to match number of \
+                farms, more farms may leverage CPU more efficiently"
             );
-
-            if plotting_thread_pool_core_indices.len() > disk_farms.len() {
-                plotting_thread_pool_core_indices =
-                    CpuCoreSet::regroup(&plotting_thread_pool_core_indices, disk_farms.len());
-                replotting_thread_pool_core_indices =
-                    CpuCoreSet::regroup(&replotting_thread_pool_core_indices, disk_farms.len());
-
-                info!(
-                    farms_count = %disk_farms.len(),
-                    "Regrouped CPU cores to match number of farms, more farms may leverage CPU more efficiently"
-                );
-            }
         }
     }
 
-    let downloading_semaphore = Arc::new(Semaphore::new(
-        sector_downloading_concurrency
-            .map(|sector_downloading_concurrency| sector_downloading_concurrency.get())
-            .unwrap_or(plotting_thread_pool_core_indices.len() + 1),
-    ));
-
-    let record_encoding_concurrency = record_encoding_concurrency.unwrap_or_else(|| {
-        let cpu_cores = plotting_thread_pool_core_indices
-            .first()
-            .expect("Guaranteed to have some CPU cores; qed");
-
-        NonZeroUsize::new((cpu_cores.cpu_cores().len() / 2).max(1).min(8)).expect("Not zero; qed")
-    });
-
-    let plotting_thread_pool_manager = create_plotting_thread_pool_manager(
-        plotting_thread_pool_core_indices
-            .into_iter()
-            .zip(replotting_thread_pool_core_indices),
+    let plotting_concurrency = PlottingConcurrency::new::<PosTable>(
+        PlottingConcurrencyOptions {
+            plotting_memory_limit,
+            max_pieces_in_sector,
+            segment_reconstruction_cache_capacity: SEGMENT_RECONSTRUCTION_CACHE_SIZE,
+            sector_downloading_concurrency,
+            record_encoding_concurrency,
+            plotting_thread_pool_core_indices,
+            replotting_thread_pool_core_indices,
+            regroup_cpu_cores,
+        },
+        disk_farms.len(),
+        disk_farms
+            .iter()
+            .filter(|disk_farm| disk_farm.plotting_cpu_cores.is_none())
+            .count(),
     )?;
+    let record_encoding_concurrency = plotting_concurrency.record_encoding_concurrency();
+    let downloading_semaphore = Arc::clone(plotting_concurrency.downloading_semaphore());
+    let plotting_thread_pool_manager = plotting_concurrency.plotting_thread_pool_manager().clone();
+    // Farms with dedicated CPU cores use the same cores for both plotting and replotting
+    let farm_plotting_thread_pool_managers = disk_farms
+        .iter()
+        .filter_map(|disk_farm| {
+            let plotting_cpu_cores = disk_farm.plotting_cpu_cores.as_ref()?;
+
+            Some(
+                create_plotting_thread_pool_manager(
+                    plotting_cpu_cores
+                        .iter()
+                        .cloned()
+                        .zip(plotting_cpu_cores.iter().cloned()),
+                )
+                .map(|plotting_thread_pool_manager| {
+                    (disk_farm.directory.clone(), plotting_thread_pool_manager)
+                }),
+            )
+        })
+        .collect::<Result<HashMap<_, _>, _>>()?;
     let farming_thread_pool_size = farming_thread_pool_size
         .map(|farming_thread_pool_size| farming_thread_pool_size.get())
         .unwrap_or_else(recommended_number_of_farming_threads);
 
-    let mut plotting_delay_senders = Vec::with_capacity(disk_farms.len());
+    if cfg!(not(target_os = "linux")) && plotting_pause_cpu_usage.is_some() {
+        return Err(anyhow!(
+            "`--plotting-pause-cpu-usage` is only supported on Linux"
+        ));
+    }
+    for plotting_pause_window in &plotting_pause_window {
+        info!(%plotting_pause_window, "Plotting of new sectors will be paused in UTC time window");
+    }
+    let plotting_schedule = PlottingSchedule::new(
+        plotting_pause_window,
+        plotting_pause_cpu_usage.map(|cpu_usage| f32::from(cpu_usage.get())),
+    );
 
-    for (disk_farm_index, disk_farm) in disk_farms.into_iter().enumerate() {
-        debug!(url = %node_rpc_url, %disk_farm_index, "Connecting to node RPC");
-        let node_client = NodeRpcClient::new(&node_rpc_url).await?;
-        let (plotting_delay_sender, plotting_delay_receiver) = oneshot::channel();
-        plotting_delay_senders.push(plotting_delay_sender);
+    let download_staging = download_staging_directory.map(|directory| DownloadStagingOptions {
+        directory,
+        sectors_ahead: download_staging_sectors,
+    });
 
-        let single_disk_farm_fut = SingleDiskFarm::new::<_, _, PosTable>(
+    let segment_reconstruction_cache = SegmentReconstructionCache::with_lookup_callback(
+        SEGMENT_RECONSTRUCTION_CACHE_SIZE,
+        Box::new({
+            let farmer_metrics = farmer_metrics.clone();
+
+            move |lookup| farmer_metrics.observe_segment_reconstruction_cache_lookup(lookup)
+        }),
+    );
+
+    let create_single_disk_farm_options: CreateFarmOptions<_> = Arc::new({
+        let farmer_app_info = farmer_app_info.clone();
+        let download_staging = download_staging.clone();
+        let kzg = kzg.clone();
+        let erasure_coding = erasure_coding.clone();
+        let piece_getter = piece_getter.clone();
+        let plotting_thread_pool_manager = plotting_thread_pool_manager.clone();
+        let plotting_schedule = plotting_schedule.clone();
+
+        move |disk_farm: &DiskFarm,
+              reward_addresses: &RewardAddresses,
+              node_client: NodeRpcClient,
+              plotting_delay: Option<oneshot::Receiver<()>>| {
             SingleDiskFarmOptions {
                 directory: disk_farm.directory.clone(),
                 farmer_app_info: farmer_app_info.clone(),
                 allocated_space: disk_farm.allocated_plotting_space,
                 max_pieces_in_sector,
                 node_client,
-                reward_address,
+                reward_addresses: reward_addresses.clone(),
                 kzg: kzg.clone(),
                 erasure_coding: erasure_coding.clone(),
                 piece_getter: piece_getter.clone(),
-                cache_percentage,
+                cache_percentage: disk_farm.cache_percentage.unwrap_or(cache_percentage),
                 downloading_semaphore: Arc::clone(&downloading_semaphore),
                 record_encoding_concurrency,
                 farm_during_initial_plotting,
                 farming_thread_pool_size,
-                plotting_thread_pool_manager: plotting_thread_pool_manager.clone(),
-                plotting_delay: Some(plotting_delay_receiver),
+                plotting_thread_pool_manager: farm_plotting_thread_pool_managers
+                    .get(&disk_farm.directory)
+                    .unwrap_or(&plotting_thread_pool_manager)
+                    .clone(),
+                plotting_delay,
+                plotting_schedule: plotting_schedule.clone(),
+                download_staging: download_staging.clone(),
+                segment_reconstruction_cache: segment_reconstruction_cache.clone(),
                 disable_farm_locking,
-            },
+                identity_secret: identity_secret.clone(),
+                reward_signer_provider: reward_signer_provider.clone(),
+            }
+        }
+    });
+
+    let disk_farms = disk_farms
+        .into_iter()
+        .zip(farms_reward_addresses)
+        .collect::<Vec<_>>();
+    let mut plotting_delay_senders = Vec::with_capacity(disk_farms.len());
+
+    for (disk_farm_index, (disk_farm, reward_addresses)) in disk_farms.iter().enumerate() {
+        debug!(url = %node_rpc_url, %disk_farm_index, "Connecting to node RPC");
+        let node_client = NodeRpcClient::new(&node_rpc_url).await?;
+        let (plotting_delay_sender, plotting_delay_receiver) = oneshot::channel();
+        plotting_delay_senders.push(plotting_delay_sender);
+
+        let single_disk_farm_fut = SingleDiskFarm::new::<_, _, PosTable>(
+            create_single_disk_farm_options(
+                disk_farm,
+                reward_addresses,
+                node_client,
+                Some(plotting_delay_receiver),
+            ),
             disk_farm_index,
         );
 
@@ -620,235 +1162,116 @@ where
                 bytesize::to_string(info.allocated_space(), false)
             );
             println!("  Directory: {}", disk_farm.directory.display());
+            println!("  Reward addresses:");
+            for reward_address in reward_addresses.addresses() {
+                println!("    0x{}", hex::encode(reward_address));
+            }
         }
 
         single_disk_farms.push(single_disk_farm);
     }
 
-    let cache_acknowledgement_receiver = farmer_cache
-        .replace_backing_caches(
-            single_disk_farms
-                .iter()
-                .map(|single_disk_farm| single_disk_farm.piece_cache())
-                .collect(),
-            single_disk_farms
-                .iter()
-                .map(|single_disk_farm| single_disk_farm.plot_cache())
-                .collect(),
-        )
-        .await;
-    drop(farmer_cache);
-
-    // Wait for cache initialization before starting plotting
-    tokio::spawn(async move {
-        if cache_acknowledgement_receiver.await.is_ok() {
-            for plotting_delay_sender in plotting_delay_senders {
-                // Doesn't matter if receiver is gone
-                let _ = plotting_delay_sender.send(());
-            }
-        }
-    });
-
-    // Store piece readers so we can reference them later
-    let piece_readers = single_disk_farms
-        .iter()
-        .map(|single_disk_farm| single_disk_farm.piece_reader())
-        .collect::<Vec<_>>();
-
-    info!("Collecting already plotted pieces (this will take some time)...");
-
-    // Collect already plotted pieces
-    {
-        let mut future_plotted_pieces = PlottedPieces::new(piece_readers);
-
-        for (disk_farm_index, single_disk_farm) in single_disk_farms.iter().enumerate() {
-            let disk_farm_index = disk_farm_index.try_into().map_err(|_error| {
-                anyhow!(
-                    "More than 256 plots are not supported, consider running multiple farmer \
-                    instances"
-                )
-            })?;
+    if DiskFarmIndex::try_from(single_disk_farms.len().saturating_sub(1)).is_err() {
+        return Err(anyhow!(
+            "More than 65536 farms are not supported, consider running multiple farmer instances"
+        ));
+    }
 
-            (0 as SectorIndex..)
-                .zip(single_disk_farm.plotted_sectors().await)
-                .for_each(
-                    |(sector_index, plotted_sector_result)| match plotted_sector_result {
-                        Ok(plotted_sector) => {
-                            future_plotted_pieces.add_sector(disk_farm_index, &plotted_sector);
-                        }
-                        Err(error) => {
-                            error!(
-                                %error,
-                                %disk_farm_index,
-                                %sector_index,
-                                "Failed reading plotted sector on startup, skipping"
-                            );
-                        }
-                    },
-                );
+    if let Some(download_staging) = &download_staging {
+        // Farms that were removed or wiped since last start have staged sectors that will never be
+        // used
+        let farm_ids = single_disk_farms
+            .iter()
+            .map(|single_disk_farm| *single_disk_farm.id())
+            .collect::<Vec<_>>();
+        if let Err(error) = download_staging.retain_farms(&farm_ids) {
+            warn!(%error, "Failed to remove staged sectors of farms that are no longer used");
         }
-
-        plotted_pieces.lock().replace(future_plotted_pieces);
     }
 
-    info!("Finished collecting already plotted pieces successfully");
-
-    let total_and_plotted_sectors = single_disk_farms
-        .iter()
-        .map(|single_disk_farm| async {
-            let total_sector_count = single_disk_farm.total_sectors_count();
-            let plotted_sectors_count = single_disk_farm.plotted_sectors_count().await;
-
-            (total_sector_count, plotted_sectors_count)
+    let initial_farms = disk_farms
+        .into_iter()
+        .zip(single_disk_farms)
+        .map(|((disk_farm, reward_addresses), single_disk_farm)| {
+            (disk_farm, reward_addresses, single_disk_farm)
         })
-        .collect::<FuturesOrdered<_>>()
-        .collect::<Vec<_>>()
-        .await;
+        .collect::<Vec<_>>();
+    let runtime_farm_reward_addresses: FarmRewardAddresses = {
+        let ss58_format = farmer_app_info.ss58_format;
 
-    let mut single_disk_farms_stream = single_disk_farms
-        .into_iter()
-        .enumerate()
-        .zip(total_and_plotted_sectors)
-        .map(|((disk_farm_index, single_disk_farm), sector_counts)| {
-            let disk_farm_index = disk_farm_index.try_into().expect(
-                "More than 256 plots are not supported, this is checked above already; qed",
-            );
-            let plotted_pieces = Arc::clone(&plotted_pieces);
-            let span = info_span!("", %disk_farm_index);
-
-            // Collect newly plotted pieces
-            let on_plotted_sector_callback =
-                move |plotted_sector: &PlottedSector,
-                      maybe_old_plotted_sector: &Option<PlottedSector>| {
-                    let _span_guard = span.enter();
-
-                    {
-                        let mut plotted_pieces = plotted_pieces.lock();
-                        let plotted_pieces = plotted_pieces
-                            .as_mut()
-                            .expect("Initial value was populated above; qed");
-
-                        if let Some(old_plotted_sector) = &maybe_old_plotted_sector {
-                            plotted_pieces.delete_sector(disk_farm_index, old_plotted_sector);
-                        }
-                        plotted_pieces.add_sector(disk_farm_index, plotted_sector);
-                    }
-                };
-
-            let (total_sector_count, plotted_sectors_count) = sector_counts;
-            farmer_metrics.update_sectors_total(
-                single_disk_farm.id(),
-                total_sector_count - plotted_sectors_count,
-                SectorState::NotPlotted,
-            );
-            farmer_metrics.update_sectors_total(
-                single_disk_farm.id(),
-                plotted_sectors_count,
-                SectorState::Plotted,
-            );
-            single_disk_farm
-                .on_sector_update(Arc::new({
-                    let single_disk_farm_id = *single_disk_farm.id();
-                    let farmer_metrics = farmer_metrics.clone();
-
-                    move |(_sector_index, sector_state)| match sector_state {
-                        SectorUpdate::Plotting(SectorPlottingDetails::Starting { .. }) => {
-                            farmer_metrics.sector_plotting.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Downloading) => {
-                            farmer_metrics.sector_downloading.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Downloaded(time)) => {
-                            farmer_metrics
-                                .observe_sector_downloading_time(&single_disk_farm_id, time);
-                            farmer_metrics.sector_downloaded.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Encoding) => {
-                            farmer_metrics.sector_encoding.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Encoded(time)) => {
-                            farmer_metrics.observe_sector_encoding_time(&single_disk_farm_id, time);
-                            farmer_metrics.sector_encoded.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Writing) => {
-                            farmer_metrics.sector_writing.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Written(time)) => {
-                            farmer_metrics.observe_sector_writing_time(&single_disk_farm_id, time);
-                            farmer_metrics.sector_written.inc();
-                        }
-                        SectorUpdate::Plotting(SectorPlottingDetails::Finished {
-                            plotted_sector,
-                            old_plotted_sector,
-                            time,
-                        }) => {
-                            on_plotted_sector_callback(plotted_sector, old_plotted_sector);
-                            farmer_metrics.observe_sector_plotting_time(&single_disk_farm_id, time);
-                            farmer_metrics.sector_plotted.inc();
-                            farmer_metrics
-                                .update_sector_state(&single_disk_farm_id, SectorState::Plotted);
-                        }
-                        SectorUpdate::Expiration(SectorExpirationDetails::AboutToExpire) => {
-                            farmer_metrics.update_sector_state(
-                                &single_disk_farm_id,
-                                SectorState::AboutToExpire,
-                            );
-                        }
-                        SectorUpdate::Expiration(SectorExpirationDetails::Expired) => {
-                            farmer_metrics
-                                .update_sector_state(&single_disk_farm_id, SectorState::Expired);
-                        }
-                        SectorUpdate::Expiration(SectorExpirationDetails::Determined {
-                            ..
-                        }) => {
-                            // Not interested in here
-                        }
-                    }
-                }))
-                .detach();
-
-            single_disk_farm
-                .on_farming_notification(Arc::new({
-                    let single_disk_farm_id = *single_disk_farm.id();
-                    let farmer_metrics = farmer_metrics.clone();
-
-                    move |farming_notification| match farming_notification {
-                        FarmingNotification::Auditing(auditing_details) => {
-                            farmer_metrics.observe_auditing_time(
-                                &single_disk_farm_id,
-                                &auditing_details.time,
-                            );
-                        }
-                        FarmingNotification::Proving(proving_details) => {
-                            farmer_metrics.observe_proving_time(
-                                &single_disk_farm_id,
-                                &proving_details.time,
-                                proving_details.result,
-                            );
-                        }
-                        FarmingNotification::NonFatalError(error) => {
-                            farmer_metrics.note_farming_error(&single_disk_farm_id, error);
-                        }
-                    }
-                }))
-                .detach();
-
-            single_disk_farm.run()
+        Box::new(move |disk_farm: &DiskFarm| {
+            check_reward_addresses_network(disk_farm.reward_addresses.iter(), ss58_format);
+            farm_reward_addresses(disk_farm, &reward_address, reward_address_rotation)
         })
-        .collect::<FuturesUnordered<_>>();
+    };
 
-    // Drop original instance such that the only remaining instances are in `SingleDiskFarm`
-    // event handlers
-    drop(plotted_pieces);
+    // Rewards are signed once per identity of remote signer rather than by every farm that uses it
+    #[cfg(unix)]
+    let _reward_signing_workers = {
+        let mut reward_signing_workers = Vec::new();
+        for reward_signer in remote_signer
+            .iter()
+            .flat_map(|remote_signer| remote_signer.reward_signers())
+        {
+            let reward_signing_fut = reward_signing(node_client.clone(), reward_signer)
+                .await
+                .map_err(|error| {
+                    anyhow!("Failed to subscribe to reward signing notifications: {error}")
+                })?;
+
+            reward_signing_workers
+                .push(AsyncJoinOnDrop::new(tokio::spawn(reward_signing_fut), true));
+        }
+
+        reward_signing_workers
+    };
+
+    let (farms_command_sender, farms_command_receiver) = mpsc::channel(8);
+    #[cfg(unix)]
+    let _control_socket_worker = control_socket.map(|control_socket| {
+        AsyncJoinOnDrop::new(
+            tokio::spawn(async move {
+                if let Err(error) =
+                    control::run_control_socket(&control_socket, farms_command_sender).await
+                {
+                    error!(%error, "Control socket exited with error");
+                }
+            }),
+            true,
+        )
+    });
+    // Without control socket farms can't be added or removed
+    #[cfg(not(unix))]
+    drop(farms_command_sender);
 
     let farm_fut = run_future_in_dedicated_thread(
-        move || async move {
-            while let Some(result) = single_disk_farms_stream.next().await {
-                let id = result?;
+        {
+            let plotting_schedule = plotting_schedule.clone();
+
+            move || async move {
+                let farms = Farms::<PosTable, _>::new(
+                    initial_farms,
+                    plotting_delay_senders,
+                    farmer_cache,
+                    plotted_pieces,
+                    cache_only_farms
+                        .into_iter()
+                        .map(|(_directory, cache_only_farm)| cache_only_farm)
+                        .collect(),
+                    farmer_metrics,
+                    node_rpc_url,
+                    failed_farm_restarts,
+                    create_single_disk_farm_options,
+                    runtime_farm_reward_addresses,
+                    plotting_schedule,
+                    segment_reconstruction_cache,
+                    plotting_concurrency,
+                    download_staging,
+                )
+                .await;
 
-                info!(%id, "Farm exited successfully");
+                farms.run(farms_command_receiver).await
             }
-            anyhow::Ok(())
         },
         "farmer-farm".to_string(),
     )?;
@@ -863,40 +1286,128 @@ where
     let farm_fut = farm_fut;
     let farmer_cache_worker_fut = farmer_cache_worker_fut;
 
-    let networking_fut = pin!(networking_fut);
-    let farm_fut = pin!(farm_fut);
-    let farmer_cache_worker_fut = pin!(farmer_cache_worker_fut);
+    let mut networking_fut = pin!(networking_fut.fuse());
+    let mut farm_fut = pin!(farm_fut.fuse());
+    let mut farmer_cache_worker_fut = pin!(farmer_cache_worker_fut.fuse());
 
     futures::select!(
         // Signal future
         _ = signal.fuse() => {},
 
         // Networking future
-        _ = networking_fut.fuse() => {
-            info!("Node runner exited.")
+        _ = networking_fut => {
+            info!("Node runner exited.");
+            return Ok(());
         },
 
         // Farm future
-        result = farm_fut.fuse() => {
+        result = farm_fut => {
             result??;
+            return Ok(());
         },
 
         // Piece cache worker future
-        _ = farmer_cache_worker_fut.fuse() => {
-            info!("Farmer cache worker exited.")
+        _ = farmer_cache_worker_fut => {
+            info!("Farmer cache worker exited.");
+            return Ok(());
         },
     );
 
-    anyhow::Ok(())
-}
+    // Plotting is stopped and interrupted sectors are lost once farms are dropped, give sectors
+    // that are being plotted a chance to finish, while networking is still running to download
+    // remaining pieces
+    plotting_schedule.stop();
+    let sectors_in_progress = plotting_schedule.sectors_in_progress();
+    if graceful_shutdown_timeout == 0 || sectors_in_progress == 0 {
+        return Ok(());
+    }
 
-fn derive_libp2p_keypair(schnorrkel_sk: &schnorrkel::SecretKey) -> Keypair {
-    let mut secret_bytes = Zeroizing::new(schnorrkel_sk.to_ed25519_bytes());
+    info!(
+        %sectors_in_progress,
+        "Waiting for sectors that are being plotted to finish, send signal again to exit \
+        immediately"
+    );
 
-    let keypair = ed25519::Keypair::from(
-        ed25519::SecretKey::try_from_bytes(&mut secret_bytes.as_mut()[..32])
-            .expect("Secret key is exactly 32 bytes in size; qed"),
+    futures::select!(
+        _ = plotting_schedule.wait_for_sectors_in_progress().fuse() => {
+            info!("Sectors that were being plotted are finished");
+        },
+        _ = tokio::time::sleep(Duration::from_secs(graceful_shutdown_timeout)).fuse() => {
+            warn!(
+                sectors_in_progress = %plotting_schedule.sectors_in_progress(),
+                "Timed out waiting for sectors that are being plotted to finish"
+            );
+        },
+        _ = shutdown_signal().fuse() => {
+            info!("Exiting without waiting for sectors that are being plotted");
+        },
+
+        _ = networking_fut => {
+            info!("Node runner exited.");
+        },
+        result = farm_fut => {
+            result??;
+        },
+        _ = farmer_cache_worker_fut => {
+            info!("Farmer cache worker exited.");
+        },
     );
 
-    Keypair::from(keypair)
+    anyhow::Ok(())
+}
+
+/// Reward addresses of the farm, global reward addresses are used unless farm has its own
+fn farm_reward_addresses(
+    disk_farm: &DiskFarm,
+    reward_address: &[RewardAddress],
+    reward_address_rotation: RewardAddressRotation,
+) -> anyhow::Result<RewardAddresses> {
+    let reward_addresses = if disk_farm.reward_addresses.is_empty() {
+        reward_address
+    } else {
+        &disk_farm.reward_addresses
+    };
+    let reward_addresses = reward_addresses
+        .iter()
+        .map(|reward_address| reward_address.public_key)
+        .collect();
+
+    RewardAddresses::new(reward_addresses, reward_address_rotation).map_err(|error| {
+        anyhow!(
+            "Invalid reward addresses of farm {} (specify `--reward-address` or \
+            `reward_address` in farm specification): {error}",
+            disk_farm.directory.display()
+        )
+    })
+}
+
+/// Check that reward addresses were encoded for the network node is running, if node reports its
+/// address format.
+///
+/// Address only determines public key rewards are paid to, so mismatch doesn't make rewards
+/// unavailable, but likely indicates a mistake (like address copied from a different wallet), so
+/// warning is printed. Generic Substrate addresses are commonly used and accepted silently.
+fn check_reward_addresses_network<'a>(
+    reward_addresses: impl Iterator<Item = &'a RewardAddress>,
+    ss58_format: Option<u16>,
+) {
+    let Some(ss58_format) = ss58_format else {
+        return;
+    };
+    let expected_format = Ss58AddressFormat::custom(ss58_format);
+    let generic_format = Ss58AddressFormat::from(Ss58AddressFormatRegistry::SubstrateAccount);
+
+    for reward_address in reward_addresses {
+        if reward_address.format != expected_format && reward_address.format != generic_format {
+            warn!(
+                public_key = %hex::encode(reward_address.public_key),
+                "Reward address doesn't match the network: {}, make sure this is an address you \
+                control",
+                Ss58ParsingError::WrongNetwork {
+                    address_format: reward_address.format,
+                    expected_format,
+                }
+            );
+        }
+    }
 }
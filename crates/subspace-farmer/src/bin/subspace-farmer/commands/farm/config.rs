@@ -0,0 +1,412 @@
+//! Configuration file for `farm` command.
+//!
+//! Keys are the same as names of command line options with `-` replaced by `_`, DSN options are in
+//! `[dsn]` table and farms are specified as `[[farm]]` tables. Options specified on command line
+//! take precedence over configuration file, farms specified on command line replace farms from
+//! configuration file.
+
+#[cfg(test)]
+mod tests;
+
+use crate::commands::farm::{
+    reward_address_parser, reward_address_rotation_parser, DiskFarm, FarmingArgs, RewardAddress,
+};
+use bytesize::ByteSize;
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fs, io};
+use subspace_farmer::single_disk_farm::farming::reward_addresses::RewardAddressRotation;
+use subspace_farmer::utils::parse_cpu_cores_sets;
+use subspace_farmer::utils::plotting_schedule::{PlottingWindow, PlottingWindowParseError};
+use subspace_networking::libp2p::Multiaddr;
+use thiserror::Error;
+use toml::Spanned;
+
+/// Error in configuration file
+#[derive(Debug, Error)]
+pub(crate) enum ConfigError {
+    /// Failed to read configuration file
+    #[error("Failed to read configuration file: {0}")]
+    Io(#[from] io::Error),
+    /// Configuration file doesn't match schema, error contains line and column
+    #[error("{0}")]
+    Toml(#[from] toml::de::Error),
+    /// Configuration is invalid
+    #[error("{message} at line {line}")]
+    Invalid {
+        /// Line number (starting from 1)
+        line: usize,
+        /// Error message
+        message: String,
+    },
+}
+
+/// Value that is specified as string in configuration file and parsed the same way as
+/// corresponding command line option
+trait ConfigValue: Sized {
+    fn parse(s: &str) -> Result<Self, String>;
+}
+
+impl ConfigValue for ByteSize {
+    fn parse(s: &str) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl ConfigValue for Multiaddr {
+    fn parse(s: &str) -> Result<Self, String> {
+        Multiaddr::from_str(s).map_err(|error| error.to_string())
+    }
+}
+
+impl ConfigValue for RewardAddress {
+    fn parse(s: &str) -> Result<Self, String> {
+        reward_address_parser(s).map_err(|error| error.to_string())
+    }
+}
+
+impl ConfigValue for PlottingWindow {
+    fn parse(s: &str) -> Result<Self, String> {
+        s.parse()
+            .map_err(|error: PlottingWindowParseError| error.to_string())
+    }
+}
+
+impl ConfigValue for RewardAddressRotation {
+    fn parse(s: &str) -> Result<Self, String> {
+        reward_address_rotation_parser(s).map_err(|error| error.to_string())
+    }
+}
+
+fn deserialize_value<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ConfigValue,
+{
+    let s = String::deserialize(deserializer)?;
+    T::parse(&s).map_err(|error| D::Error::custom(format!("invalid value \"{s}\": {error}")))
+}
+
+fn deserialize_optional_value<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ConfigValue,
+{
+    deserialize_value(deserializer).map(Some)
+}
+
+fn deserialize_values<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ConfigValue,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|s| {
+            T::parse(&s)
+                .map_err(|error| D::Error::custom(format!("invalid value \"{s}\": {error}")))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+fn deserialize_cache_percentage<'de, D>(deserializer: D) -> Result<Option<NonZeroU8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let cache_percentage = u8::deserialize(deserializer)?;
+
+    NonZeroU8::new(cache_percentage)
+        .filter(|cache_percentage| cache_percentage.get() <= 99)
+        .map(Some)
+        .ok_or_else(|| {
+            D::Error::custom(format!(
+                "invalid cache percentage {cache_percentage}, must be between 1 and 99"
+            ))
+        })
+}
+
+fn deserialize_plotting_pause_cpu_usage<'de, D>(
+    deserializer: D,
+) -> Result<Option<NonZeroU8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let cpu_usage = u8::deserialize(deserializer)?;
+
+    NonZeroU8::new(cpu_usage)
+        .filter(|cpu_usage| cpu_usage.get() <= 100)
+        .map(Some)
+        .ok_or_else(|| {
+            D::Error::custom(format!(
+                "invalid CPU usage {cpu_usage}, must be between 1 and 100"
+            ))
+        })
+}
+
+fn deserialize_cpu_cores<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let cpu_cores = String::deserialize(deserializer)?;
+
+    parse_cpu_cores_sets(&cpu_cores)
+        .map_err(|error| D::Error::custom(format!("invalid CPU cores \"{cpu_cores}\": {error}")))?;
+
+    Ok(Some(cpu_cores))
+}
+
+/// Farm in configuration file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FarmConfig {
+    /// Path to directory where farm is stored
+    path: Spanned<PathBuf>,
+    /// Allocated space
+    #[serde(deserialize_with = "deserialize_value")]
+    size: ByteSize,
+    /// Cache percentage of this farm, global one is used if not specified
+    #[serde(default, deserialize_with = "deserialize_cache_percentage")]
+    cache_percentage: Option<NonZeroU8>,
+    /// Reward addresses of this farm, global ones are used if not specified
+    #[serde(default, deserialize_with = "deserialize_values")]
+    reward_address: Option<Vec<RewardAddress>>,
+    /// CPU cores used exclusively for plotting and replotting of this farm
+    #[serde(default, deserialize_with = "deserialize_cpu_cores")]
+    plotting_cpu_cores: Option<String>,
+    /// Farm is only used for piece cache and doesn't have a plot
+    #[serde(default)]
+    cache_only: bool,
+}
+
+impl From<FarmConfig> for DiskFarm {
+    fn from(farm_config: FarmConfig) -> Self {
+        Self {
+            directory: farm_config.path.into_inner(),
+            allocated_plotting_space: farm_config.size.as_u64(),
+            reward_addresses: farm_config.reward_address.unwrap_or_default(),
+            cache_percentage: farm_config.cache_percentage,
+            plotting_cpu_cores: farm_config.plotting_cpu_cores.map(|plotting_cpu_cores| {
+                parse_cpu_cores_sets(&plotting_cpu_cores)
+                    .expect("Checked during deserialization; qed")
+            }),
+            cache_only: farm_config.cache_only,
+        }
+    }
+}
+
+/// DSN options in configuration file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DsnConfig {
+    #[serde(default, deserialize_with = "deserialize_values")]
+    bootstrap_nodes: Option<Vec<Multiaddr>>,
+    #[serde(default, deserialize_with = "deserialize_values")]
+    listen_on: Option<Vec<Multiaddr>>,
+    allow_private_ips: Option<bool>,
+    enable_mdns: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_values")]
+    reserved_peers: Option<Vec<Multiaddr>>,
+    in_connections: Option<u32>,
+    out_connections: Option<u32>,
+    pending_in_connections: Option<u32>,
+    pending_out_connections: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_values")]
+    external_addresses: Option<Vec<Multiaddr>>,
+    disable_bootstrap_on_start: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_value")]
+    upload_rate_limit: Option<ByteSize>,
+    #[serde(default, deserialize_with = "deserialize_optional_value")]
+    download_rate_limit: Option<ByteSize>,
+    piece_serving_concurrency: Option<NonZeroUsize>,
+    piece_serving_queue_size: Option<usize>,
+    piece_serving_peer_rate: Option<NonZeroU32>,
+    piece_serving_plot_read_concurrency: Option<NonZeroUsize>,
+}
+
+/// Configuration file of `farm` command
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FarmerConfig {
+    #[serde(default)]
+    farm: Vec<FarmConfig>,
+    node_rpc_url: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_values")]
+    reward_address: Option<Vec<RewardAddress>>,
+    #[serde(default, deserialize_with = "deserialize_optional_value")]
+    reward_address_rotation: Option<RewardAddressRotation>,
+    #[serde(default, deserialize_with = "deserialize_cache_percentage")]
+    cache_percentage: Option<NonZeroU8>,
+    dev: Option<bool>,
+    max_pieces_in_sector: Option<u16>,
+    no_info: Option<bool>,
+    prometheus_listen_on: Option<Vec<SocketAddr>>,
+    sector_downloading_concurrency: Option<NonZeroUsize>,
+    sector_encoding_concurrency: Option<NonZeroUsize>,
+    record_encoding_concurrency: Option<NonZeroUsize>,
+    #[serde(default, deserialize_with = "deserialize_optional_value")]
+    plotting_memory_limit: Option<ByteSize>,
+    farm_during_initial_plotting: Option<bool>,
+    farming_thread_pool_size: Option<NonZeroUsize>,
+    plotting_thread_pool_size: Option<NonZeroUsize>,
+    #[serde(default, deserialize_with = "deserialize_cpu_cores")]
+    plotting_cpu_cores: Option<String>,
+    replotting_thread_pool_size: Option<NonZeroUsize>,
+    #[serde(default, deserialize_with = "deserialize_cpu_cores")]
+    replotting_cpu_cores: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_values")]
+    plotting_pause_window: Option<Vec<PlottingWindow>>,
+    #[serde(default, deserialize_with = "deserialize_plotting_pause_cpu_usage")]
+    plotting_pause_cpu_usage: Option<NonZeroU8>,
+    download_staging_directory: Option<PathBuf>,
+    download_staging_sectors: Option<NonZeroUsize>,
+    disable_farm_locking: Option<bool>,
+    failed_farm_restarts: Option<u32>,
+    graceful_shutdown_timeout: Option<u64>,
+    #[cfg(unix)]
+    control_socket: Option<PathBuf>,
+    #[serde(default)]
+    dsn: DsnConfig,
+}
+
+impl FarmerConfig {
+    /// Read and validate configuration file
+    pub(crate) fn read(path: &Path) -> Result<Self, Vec<ConfigError>> {
+        let contents = fs::read_to_string(path).map_err(|error| vec![ConfigError::Io(error)])?;
+
+        Self::parse(&contents)
+    }
+
+    /// Parse and validate contents of configuration file.
+    ///
+    /// Parsing stops at the first syntax or schema error (unknown key, invalid value), in which case
+    /// it is the only error returned, otherwise all validation errors found are returned.
+    pub(crate) fn parse(contents: &str) -> Result<Self, Vec<ConfigError>> {
+        let config =
+            toml::from_str::<Self>(contents).map_err(|error| vec![ConfigError::Toml(error)])?;
+
+        let mut errors = Vec::new();
+        let mut farm_lines = HashMap::<&Path, usize>::new();
+        for farm in &config.farm {
+            let line = line_number(contents, farm.path.start());
+            if let Some(first_line) = farm_lines.insert(farm.path.get_ref(), line) {
+                errors.push(ConfigError::Invalid {
+                    line,
+                    message: format!(
+                        "Farm {} is already specified at line {first_line}",
+                        farm.path.get_ref().display()
+                    ),
+                });
+            }
+
+            if farm.cache_only {
+                let plotting_options = [
+                    ("cache_percentage", farm.cache_percentage.is_some()),
+                    ("reward_address", farm.reward_address.is_some()),
+                    ("plotting_cpu_cores", farm.plotting_cpu_cores.is_some()),
+                ];
+                for (option, _) in plotting_options.iter().filter(|(_, set)| *set) {
+                    errors.push(ConfigError::Invalid {
+                        line,
+                        message: format!(
+                            "Farm {} is cache-only, `{option}` can't be specified",
+                            farm.path.get_ref().display()
+                        ),
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Apply configuration to arguments, options that were explicitly specified on command line
+    /// are not changed
+    pub(crate) fn apply(self, farming_args: &mut FarmingArgs, matches: &ArgMatches) {
+        let from_command_line =
+            |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        macro_rules! apply {
+            ($config:expr => $args:expr, $($field:ident),+ $(,)?) => {
+                $(
+                    if let Some(value) = $config
+                        .$field
+                        .filter(|_| !from_command_line(stringify!($field)))
+                    {
+                        $args.$field = value.into();
+                    }
+                )+
+            };
+        }
+
+        if farming_args.disk_farms.is_empty() {
+            farming_args.disk_farms = self.farm.into_iter().map(DiskFarm::from).collect();
+        }
+        apply!(
+            self => farming_args,
+            node_rpc_url,
+            reward_address,
+            reward_address_rotation,
+            cache_percentage,
+            dev,
+            max_pieces_in_sector,
+            no_info,
+            prometheus_listen_on,
+            sector_downloading_concurrency,
+            sector_encoding_concurrency,
+            record_encoding_concurrency,
+            plotting_memory_limit,
+            farm_during_initial_plotting,
+            farming_thread_pool_size,
+            plotting_thread_pool_size,
+            plotting_cpu_cores,
+            replotting_thread_pool_size,
+            replotting_cpu_cores,
+            plotting_pause_window,
+            plotting_pause_cpu_usage,
+            download_staging_directory,
+            download_staging_sectors,
+            disable_farm_locking,
+            failed_farm_restarts,
+            graceful_shutdown_timeout,
+        );
+        #[cfg(unix)]
+        apply!(self => farming_args, control_socket);
+        apply!(
+            self.dsn => farming_args.dsn,
+            bootstrap_nodes,
+            listen_on,
+            allow_private_ips,
+            enable_mdns,
+            reserved_peers,
+            in_connections,
+            out_connections,
+            pending_in_connections,
+            pending_out_connections,
+            external_addresses,
+            disable_bootstrap_on_start,
+            upload_rate_limit,
+            download_rate_limit,
+            piece_serving_concurrency,
+            piece_serving_queue_size,
+            piece_serving_peer_rate,
+            piece_serving_plot_read_concurrency,
+        );
+    }
+}
+
+/// Line number (starting from 1) of the byte offset
+fn line_number(contents: &str, offset: usize) -> usize {
+    contents[..offset].matches('\n').count() + 1
+}
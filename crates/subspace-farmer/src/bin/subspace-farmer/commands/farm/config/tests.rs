@@ -0,0 +1,180 @@
+use crate::commands::farm::config::{ConfigError, FarmerConfig};
+use crate::commands::farm::FarmingArgs;
+use clap::{CommandFactory, FromArgMatches};
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::path::Path;
+
+const CONFIG: &str = r#"
+node_rpc_url = "ws://10.0.0.1:9944"
+cache_percentage = 5
+no_info = true
+sector_encoding_concurrency = 2
+
+[dsn]
+in_connections = 10
+out_connections = 20
+
+[[farm]]
+path = "/path/to/farm1"
+size = "100G"
+
+[[farm]]
+path = "/path/to/farm2"
+size = "2T"
+cache_percentage = 2
+plotting_cpu_cores = "0,1,2,3"
+
+[[farm]]
+path = "/path/to/cache"
+size = "50G"
+cache_only = true
+"#;
+
+/// Parse command line arguments the same way `farm` command does, returning arguments along with
+/// matches that are needed to apply configuration file
+fn parse_command_line(args: &[&str]) -> (FarmingArgs, clap::ArgMatches) {
+    let matches = FarmingArgs::command()
+        .try_get_matches_from(["farm"].iter().chain(args).copied())
+        .unwrap();
+    let farming_args = FarmingArgs::from_arg_matches(&matches).unwrap();
+
+    (farming_args, matches)
+}
+
+#[test]
+fn parse() {
+    let config = FarmerConfig::parse(CONFIG).unwrap();
+
+    assert_eq!(config.node_rpc_url.as_deref(), Some("ws://10.0.0.1:9944"));
+    assert_eq!(config.cache_percentage, NonZeroU8::new(5));
+    assert_eq!(config.dsn.in_connections, Some(10));
+    assert_eq!(config.farm.len(), 3);
+    assert_eq!(config.farm[1].path.get_ref(), Path::new("/path/to/farm2"));
+    assert_eq!(config.farm[1].cache_percentage, NonZeroU8::new(2));
+    assert_eq!(
+        config.farm[1].plotting_cpu_cores.as_deref(),
+        Some("0,1,2,3")
+    );
+    assert!(config.farm[2].cache_only);
+
+    // Empty configuration file is valid
+    assert!(FarmerConfig::parse("").is_ok());
+}
+
+#[test]
+fn schema_errors() {
+    // Line points to the beginning of the table containing invalid key, key itself is part of
+    // the message
+    for (contents, line, key) in [
+        // Unknown key
+        ("no_info = true\nunknown_key = 1\n", 1, "unknown_key"),
+        // Invalid values parsed the same way as command line options
+        (
+            "no_info = true\n\n[dsn]\nlisten_on = [\"not a multiaddr\"]\n",
+            3,
+            "dsn.listen_on",
+        ),
+        (
+            "no_info = true\n\n[[farm]]\npath = \"/path/to/farm\"\nsize = \"5X\"\n",
+            3,
+            "farm.size",
+        ),
+        ("cache_percentage = 100\n", 1, "cache_percentage"),
+        ("plotting_cpu_cores = \"0,a\"\n", 1, "plotting_cpu_cores"),
+        // Syntax error points to exact location
+        ("no_info = true\nno_info\n", 2, ""),
+    ] {
+        let errors = FarmerConfig::parse(contents).unwrap_err();
+        assert_eq!(errors.len(), 1, "{contents}");
+        let ConfigError::Toml(error) = &errors[0] else {
+            panic!("Unexpected error {:?} for {contents}", errors[0]);
+        };
+        assert_eq!(
+            error.line_col().map(|(line, _column)| line + 1),
+            Some(line),
+            "{contents}"
+        );
+        assert!(error.to_string().contains(key), "{contents}");
+    }
+}
+
+#[test]
+fn validation_errors() {
+    let contents = r#"
+[[farm]]
+path = "/path/to/farm"
+size = "100G"
+
+[[farm]]
+path = "/path/to/cache"
+size = "50G"
+cache_only = true
+cache_percentage = 2
+plotting_cpu_cores = "0,1"
+
+[[farm]]
+path = "/path/to/farm"
+size = "200G"
+"#;
+
+    // All validation errors are reported with lines of corresponding farms' paths
+    let errors = FarmerConfig::parse(contents).unwrap_err();
+    let lines = errors
+        .iter()
+        .map(|error| match error {
+            ConfigError::Invalid { line, .. } => *line,
+            error => panic!("Unexpected error {error:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [7, 7, 14]);
+    assert!(errors[2]
+        .to_string()
+        .ends_with("is already specified at line 3 at line 14"));
+}
+
+#[test]
+fn command_line_precedence() {
+    let (mut farming_args, matches) = parse_command_line(&[
+        "--cache-percentage",
+        "10",
+        "--in-connections",
+        "30",
+        "path=/path/to/cli-farm,size=1T",
+    ]);
+    FarmerConfig::parse(CONFIG)
+        .unwrap()
+        .apply(&mut farming_args, &matches);
+
+    // Explicitly specified on command line
+    assert_eq!(farming_args.cache_percentage.get(), 10);
+    assert_eq!(farming_args.dsn.in_connections, 30);
+    // Farms from command line replace farms from configuration file
+    assert_eq!(farming_args.disk_farms.len(), 1);
+    assert_eq!(
+        farming_args.disk_farms[0].directory,
+        Path::new("/path/to/cli-farm")
+    );
+    // Not specified on command line, taken from configuration file instead of defaults
+    assert_eq!(farming_args.node_rpc_url, "ws://10.0.0.1:9944");
+    assert!(farming_args.no_info);
+    assert_eq!(
+        farming_args.sector_encoding_concurrency,
+        NonZeroUsize::new(2)
+    );
+    assert_eq!(farming_args.dsn.out_connections, 20);
+
+    // Farms are taken from configuration file when none are specified on command line
+    let (mut farming_args, matches) = parse_command_line(&[]);
+    FarmerConfig::parse(CONFIG)
+        .unwrap()
+        .apply(&mut farming_args, &matches);
+
+    assert_eq!(farming_args.cache_percentage.get(), 5);
+    assert_eq!(farming_args.disk_farms.len(), 3);
+    assert_eq!(
+        farming_args.disk_farms[1].cache_percentage,
+        NonZeroU8::new(2)
+    );
+    assert!(farming_args.disk_farms[1].plotting_cpu_cores.is_some());
+    assert!(farming_args.disk_farms[2].cache_only);
+}
@@ -0,0 +1,112 @@
+//! Control socket that allows to add and remove farms and to pause and resume plotting while
+//! farmer is running.
+//!
+//! Protocol is line-based: each request is a single line (`add <disk farm>`, `remove <path>`,
+//! `list`, `pause-plotting` or `resume-plotting`), response is zero or more lines of output
+//! followed by either `ok` or `error: <reason>` line.
+
+#[cfg(test)]
+mod tests;
+
+use crate::commands::farm::farms::{FarmsCommand, FarmsRequest};
+use crate::commands::farm::DiskFarm;
+use futures::channel::{mpsc, oneshot};
+use futures::SinkExt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use subspace_farmer::utils::bind_private_unix_socket;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tracing::{debug, info};
+
+/// Line that terminates successful response
+pub(crate) const RESPONSE_OK: &str = "ok";
+/// Prefix of the line that terminates failed response
+pub(crate) const RESPONSE_ERROR_PREFIX: &str = "error: ";
+
+/// Listen on Unix socket at specified path and forward requests to the farm loop until error
+/// happens.
+///
+/// Socket file that might be left after previous run is replaced, new socket is only accessible by
+/// the current user.
+pub(super) async fn run_control_socket(
+    socket_path: &Path,
+    farms_command_sender: mpsc::Sender<FarmsCommand>,
+) -> io::Result<()> {
+    let listener = bind_private_unix_socket(socket_path)?;
+
+    info!(path = %socket_path.display(), "Control socket is listening");
+
+    loop {
+        let (stream, _address) = listener.accept().await?;
+        let farms_command_sender = farms_command_sender.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = handle_connection(stream, farms_command_sender).await {
+                debug!(%error, "Control connection closed with error");
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    mut farms_command_sender: mpsc::Sender<FarmsCommand>,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let response = match parse_request(&line) {
+            Ok(request) => {
+                let (response_sender, response_receiver) = oneshot::channel();
+                let farms_command = FarmsCommand {
+                    request,
+                    response_sender,
+                };
+
+                if farms_command_sender.send(farms_command).await.is_err() {
+                    Err("Farmer is shutting down".to_string())
+                } else {
+                    response_receiver
+                        .await
+                        .unwrap_or_else(|_canceled| Err("Farmer is shutting down".to_string()))
+                }
+            }
+            Err(error) => Err(error),
+        };
+
+        let response = match response {
+            Ok(output) if output.is_empty() => format!("{RESPONSE_OK}\n"),
+            Ok(output) => format!("{output}\n{RESPONSE_OK}\n"),
+            Err(error) => format!("{RESPONSE_ERROR_PREFIX}{error}\n"),
+        };
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+fn parse_request(line: &str) -> Result<FarmsRequest, String> {
+    let line = line.trim();
+    let (command, argument) = line
+        .split_once(' ')
+        .map(|(command, argument)| (command, argument.trim()))
+        .unwrap_or((line, ""));
+
+    match command {
+        "add" => DiskFarm::from_str(argument)
+            .map(FarmsRequest::AddFarm)
+            .map_err(|error| format!("Invalid farm \"{argument}\": {error}")),
+        "remove" if !argument.is_empty() => Ok(FarmsRequest::RemoveFarm(PathBuf::from(argument))),
+        "remove" => Err("Path of the farm to remove is required".to_string()),
+        "list" => Ok(FarmsRequest::ListFarms),
+        "pause-plotting" => Ok(FarmsRequest::PausePlotting),
+        "resume-plotting" => Ok(FarmsRequest::ResumePlotting),
+        command => Err(format!(
+            "Unknown command \"{command}\", only `add`, `remove`, `list`, `pause-plotting` or \
+            `resume-plotting` are supported"
+        )),
+    }
+}
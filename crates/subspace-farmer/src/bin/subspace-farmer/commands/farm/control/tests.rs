@@ -0,0 +1,184 @@
+use crate::commands::farm::control::{
+    parse_request, run_control_socket, RESPONSE_ERROR_PREFIX, RESPONSE_OK,
+};
+use crate::commands::farm::farms::{FarmsCommand, FarmsRequest};
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+use tokio::time::timeout;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn requests() {
+    assert!(matches!(
+        parse_request("add path=/path/to/farm,size=5T"),
+        Ok(FarmsRequest::AddFarm(disk_farm)) if disk_farm.directory == Path::new("/path/to/farm")
+    ));
+    assert!(parse_request("add path=/path/to/farm").is_err());
+    assert!(parse_request("add").is_err());
+
+    assert!(matches!(
+        parse_request("remove  /path/to/farm "),
+        Ok(FarmsRequest::RemoveFarm(directory)) if directory == Path::new("/path/to/farm")
+    ));
+    assert!(parse_request("remove").is_err());
+
+    assert!(matches!(parse_request("list"), Ok(FarmsRequest::ListFarms)));
+    assert!(matches!(
+        parse_request("pause-plotting"),
+        Ok(FarmsRequest::PausePlotting)
+    ));
+    assert!(matches!(
+        parse_request("resume-plotting\r"),
+        Ok(FarmsRequest::ResumePlotting)
+    ));
+    assert!(parse_request("stop").is_err());
+}
+
+async fn request(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+    writer: &mut OwnedWriteHalf,
+    request: &str,
+) -> Vec<String> {
+    writer
+        .write_all(format!("{request}\n").as_bytes())
+        .await
+        .unwrap();
+
+    let mut response = Vec::new();
+    loop {
+        let line = timeout(TIMEOUT, lines.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let last = line == RESPONSE_OK || line.starts_with(RESPONSE_ERROR_PREFIX);
+        response.push(line);
+        if last {
+            return response;
+        }
+    }
+}
+
+#[tokio::test]
+async fn control_socket() {
+    let directory = tempfile::tempdir().unwrap();
+    let socket_path = directory.path().join("control.sock");
+
+    let (farms_command_sender, mut farms_command_receiver) = mpsc::channel::<FarmsCommand>(1);
+    let control_socket = tokio::spawn({
+        let socket_path = socket_path.clone();
+
+        async move { run_control_socket(&socket_path, farms_command_sender).await }
+    });
+
+    // Farm loop that knows a single farm
+    let farm_loop = tokio::spawn(async move {
+        let mut farms = vec![PathBuf::from("/path/to/farm")];
+
+        while let Some(FarmsCommand {
+            request,
+            response_sender,
+        }) = farms_command_receiver.next().await
+        {
+            let response = match request {
+                FarmsRequest::AddFarm(disk_farm) => {
+                    farms.push(disk_farm.directory);
+                    Ok(String::new())
+                }
+                FarmsRequest::RemoveFarm(directory) => {
+                    match farms.iter().position(|farm| *farm == directory) {
+                        Some(index) => {
+                            farms.remove(index);
+                            Ok(String::new())
+                        }
+                        None => Err(format!("Farm {} is not known", directory.display())),
+                    }
+                }
+                FarmsRequest::ListFarms => Ok(farms
+                    .iter()
+                    .map(|farm| farm.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                FarmsRequest::PausePlotting | FarmsRequest::ResumePlotting => Ok(String::new()),
+            };
+
+            let _ = response_sender.send(response);
+
+            if farms.is_empty() {
+                // Simulate farmer shutdown
+                break;
+            }
+        }
+    });
+
+    let stream = timeout(TIMEOUT, async {
+        loop {
+            match UnixStream::connect(&socket_path).await {
+                Ok(stream) => {
+                    break stream;
+                }
+                Err(_error) => {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        }
+    })
+    .await
+    .unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    assert_eq!(
+        request(&mut lines, &mut writer, "list").await,
+        ["/path/to/farm", RESPONSE_OK]
+    );
+    assert_eq!(
+        request(
+            &mut lines,
+            &mut writer,
+            "add path=/path/to/other-farm,size=5T"
+        )
+        .await,
+        [RESPONSE_OK]
+    );
+    assert_eq!(
+        request(&mut lines, &mut writer, "list").await,
+        ["/path/to/farm", "/path/to/other-farm", RESPONSE_OK]
+    );
+    assert_eq!(
+        request(&mut lines, &mut writer, "pause-plotting").await,
+        [RESPONSE_OK]
+    );
+
+    // Errors of the farm loop and of request parsing are both reported, connection stays usable
+    let response = request(&mut lines, &mut writer, "remove /path/to/missing-farm").await;
+    assert_eq!(response.len(), 1);
+    assert!(response[0].starts_with(RESPONSE_ERROR_PREFIX));
+    let response = request(&mut lines, &mut writer, "stop").await;
+    assert_eq!(response.len(), 1);
+    assert!(response[0].starts_with(RESPONSE_ERROR_PREFIX));
+
+    assert_eq!(
+        request(&mut lines, &mut writer, "remove /path/to/farm").await,
+        [RESPONSE_OK]
+    );
+    assert_eq!(
+        request(&mut lines, &mut writer, "remove /path/to/other-farm").await,
+        [RESPONSE_OK]
+    );
+
+    // Farm loop exited
+    timeout(TIMEOUT, farm_loop).await.unwrap().unwrap();
+    assert_eq!(
+        request(&mut lines, &mut writer, "list").await,
+        [format!("{RESPONSE_ERROR_PREFIX}Farmer is shutting down")]
+    );
+
+    control_socket.abort();
+}
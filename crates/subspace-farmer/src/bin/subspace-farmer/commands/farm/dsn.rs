@@ -1,11 +1,15 @@
 use crate::commands::farm::DsnArgs;
+use futures::{stream, StreamExt};
 use parking_lot::Mutex;
 use prometheus_client::registry::Registry;
 use std::collections::HashSet;
+use std::num::NonZeroU64;
 use std::path::Path;
 use std::sync::{Arc, Weak};
+use subspace_core_primitives::{Piece, PieceIndex};
 use subspace_farmer::farmer_cache::FarmerCache;
 use subspace_farmer::node_client::NodeClientExt;
+use subspace_farmer::utils::piece_serving::PieceServingLimiter;
 use subspace_farmer::utils::plotted_pieces::PlottedPieces;
 use subspace_farmer::{NodeClient, NodeRpcClient, KNOWN_PEERS_CACHE_SIZE};
 use subspace_networking::libp2p::identity::Keypair;
@@ -14,9 +18,11 @@ use subspace_networking::libp2p::multiaddr::Protocol;
 use subspace_networking::utils::multihash::ToMultihash;
 use subspace_networking::utils::strip_peer_id;
 use subspace_networking::{
-    construct, Config, KademliaMode, KnownPeersManager, KnownPeersManagerConfig, Node, NodeRunner,
-    PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
+    construct, BandwidthLimits, Config, KademliaMode, KnownPeersManager, KnownPeersManagerConfig,
+    Node, NodeRunner, PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
+    PiecesByIndexesRequestHandler, PiecesByIndexesResponse, RequestHandlerLimits,
     SegmentHeaderBySegmentIndexesRequestHandler, SegmentHeaderRequest, SegmentHeaderResponse,
+    MAX_PIECES_PER_RESPONSE, MAX_PIECE_INDEXES_PER_REQUEST,
 };
 use subspace_rpc_primitives::MAX_SEGMENT_HEADERS_PER_REQUEST;
 use tracing::{debug, error, info, Instrument};
@@ -35,6 +41,7 @@ pub(super) fn configure_dsn(
         listen_on,
         bootstrap_nodes,
         allow_private_ips,
+        enable_mdns,
         reserved_peers,
         in_connections,
         out_connections,
@@ -42,6 +49,12 @@ pub(super) fn configure_dsn(
         pending_out_connections,
         external_addresses,
         disable_bootstrap_on_start,
+        upload_rate_limit,
+        download_rate_limit,
+        piece_serving_concurrency,
+        piece_serving_queue_size,
+        piece_serving_peer_rate,
+        piece_serving_plot_read_concurrency,
     }: DsnArgs,
     weak_plotted_pieces: Weak<Mutex<Option<PlottedPieces>>>,
     node_client: NodeRpcClient,
@@ -65,60 +78,121 @@ pub(super) fn configure_dsn(
         farmer_cache.clone(),
         prometheus_metrics_registry,
     );
-    let config = Config {
-        reserved_peers,
-        listen_on,
-        allow_non_global_addresses_in_dht: allow_private_ips,
-        networking_parameters_registry,
-        request_response_protocols: vec![
-            PieceByIndexRequestHandler::create(move |_, &PieceByIndexRequest { piece_index }| {
-                debug!(?piece_index, "Piece request received. Trying cache...");
+    let piece_serving_limiter = PieceServingLimiter::new(
+        piece_serving_peer_rate,
+        piece_serving_plot_read_concurrency,
+        piece_serving_queue_size,
+    );
+    // Requests waiting for reads from plots occupy processing slots, extra slots are reserved for
+    // them such that requests served from cache can always be processed concurrently
+    let piece_serving_limits = RequestHandlerLimits {
+        max_concurrent_requests: piece_serving_concurrency
+            .saturating_add(piece_serving_limiter.max_pending_plot_reads()),
+        queue_size: piece_serving_queue_size,
+    };
+
+    let pieces_by_indexes_request_handler =
+        PiecesByIndexesRequestHandler::create_with_limits(piece_serving_limits, {
+            let weak_plotted_pieces = weak_plotted_pieces.clone();
+            let farmer_cache = farmer_cache.clone();
+            let piece_serving_limiter = piece_serving_limiter.clone();
 
+            move |peer_id, request| {
+                debug!(
+                    %peer_id,
+                    piece_indexes_count = %request.piece_indexes.len(),
+                    "Pieces request received."
+                );
+
+                let piece_indexes = request.piece_indexes.clone();
                 let weak_plotted_pieces = weak_plotted_pieces.clone();
                 let farmer_cache = farmer_cache.clone();
+                let piece_serving_limiter = piece_serving_limiter.clone();
 
                 async move {
-                    let key = RecordKey::from(piece_index.to_multihash());
-                    let piece_from_cache = farmer_cache.get_piece(key).await;
+                    if !piece_serving_limiter.allow_request(peer_id) {
+                        debug!(%peer_id, "Peer exceeded piece request rate, rejecting");
+
+                        return None;
+                    }
 
-                    if let Some(piece) = piece_from_cache {
-                        Some(PieceByIndexResponse { piece: Some(piece) })
-                    } else {
+                    if piece_indexes.len() > MAX_PIECE_INDEXES_PER_REQUEST {
                         debug!(
-                            ?piece_index,
-                            "No piece in the cache. Trying archival storage..."
+                            piece_indexes_count = %piece_indexes.len(),
+                            "Piece indexes count exceeded the limit."
                         );
 
-                        let read_piece_fut = {
-                            let plotted_pieces = match weak_plotted_pieces.upgrade() {
-                                Some(plotted_pieces) => plotted_pieces,
-                                None => {
-                                    debug!("A readers and pieces are already dropped");
-                                    return None;
-                                }
-                            };
-                            let plotted_pieces = plotted_pieces.lock();
-                            let plotted_pieces = match plotted_pieces.as_ref() {
-                                Some(plotted_pieces) => plotted_pieces,
-                                None => {
-                                    debug!(
-                                        ?piece_index,
-                                        "Readers and pieces are not initialized yet"
-                                    );
-                                    return None;
-                                }
-                            };
+                        return None;
+                    }
 
-                            plotted_pieces.read_piece(&piece_index)?.in_current_span()
+                    // Pieces are read concurrently, but collected in order since response
+                    // corresponds to the beginning of requested piece indexes
+                    let mut pieces_stream = stream::iter(piece_indexes)
+                        .map(|piece_index| {
+                            read_piece(
+                                piece_index,
+                                &farmer_cache,
+                                &weak_plotted_pieces,
+                                &piece_serving_limiter,
+                            )
+                        })
+                        .buffered(MAX_PIECES_PER_RESPONSE);
+                    let mut pieces = Vec::new();
+                    let mut pieces_found = 0;
+                    // Response is partial, the rest of pieces will be requested again
+                    while pieces_found < MAX_PIECES_PER_RESPONSE {
+                        let Some(maybe_piece) = pieces_stream.next().await else {
+                            break;
                         };
+                        if maybe_piece.is_some() {
+                            pieces_found += 1;
+                        }
+                        pieces.push(maybe_piece);
+                    }
 
-                        let piece = read_piece_fut.await;
+                    Some(PiecesByIndexesResponse { pieces })
+                }
+                .in_current_span()
+            }
+        });
+
+    let config = Config {
+        reserved_peers,
+        listen_on,
+        allow_non_global_addresses_in_dht: allow_private_ips,
+        enable_mdns,
+        networking_parameters_registry,
+        request_response_protocols: vec![
+            PieceByIndexRequestHandler::create_with_limits(
+                piece_serving_limits,
+                move |peer_id, &PieceByIndexRequest { piece_index }| {
+                    debug!(%peer_id, ?piece_index, "Piece request received.");
+
+                    let weak_plotted_pieces = weak_plotted_pieces.clone();
+                    let farmer_cache = farmer_cache.clone();
+                    let piece_serving_limiter = piece_serving_limiter.clone();
+
+                    async move {
+                        if !piece_serving_limiter.allow_request(peer_id) {
+                            debug!(%peer_id, "Peer exceeded piece request rate, rejecting");
+
+                            return None;
+                        }
+
+                        let piece = read_piece(
+                            piece_index,
+                            &farmer_cache,
+                            &weak_plotted_pieces,
+                            &piece_serving_limiter,
+                        )
+                        .await;
 
                         Some(PieceByIndexResponse { piece })
                     }
-                }
-                .in_current_span()
-            }),
+                    .in_current_span()
+                },
+            ),
+            pieces_by_indexes_request_handler,
             SegmentHeaderBySegmentIndexesRequestHandler::create(move |_, req| {
                 debug!(?req, "Segment headers request received.");
 
@@ -181,6 +255,12 @@ pub(super) fn configure_dsn(
         kademlia_mode: KademliaMode::Dynamic,
         external_addresses,
         disable_bootstrap_on_start,
+        bandwidth_limits: BandwidthLimits {
+            upload: upload_rate_limit
+                .and_then(|upload_rate_limit| NonZeroU64::new(upload_rate_limit.as_u64())),
+            download: download_rate_limit
+                .and_then(|download_rate_limit| NonZeroU64::new(download_rate_limit.as_u64())),
+        },
         ..default_config
     };
 
@@ -203,3 +283,48 @@ pub(super) fn configure_dsn(
         })
         .map_err(Into::into)
 }
+
+/// Read piece from farmer cache or from plotted pieces if it is not in the cache.
+///
+/// Reads from plotted pieces are limited by piece serving limiter, while cache hits are returned
+/// right away.
+async fn read_piece(
+    piece_index: PieceIndex,
+    farmer_cache: &FarmerCache,
+    weak_plotted_pieces: &Weak<Mutex<Option<PlottedPieces>>>,
+    piece_serving_limiter: &PieceServingLimiter,
+) -> Option<Piece> {
+    let key = RecordKey::from(piece_index.to_multihash());
+    if let Some(piece) = farmer_cache.get_piece(key).await {
+        return Some(piece);
+    }
+
+    debug!(
+        ?piece_index,
+        "No piece in the cache. Trying archival storage..."
+    );
+
+    let Some(_plot_read_permit) = piece_serving_limiter.plot_read_permit().await else {
+        debug!(
+            ?piece_index,
+            "Too many pending reads from plots, skipping piece"
+        );
+        return None;
+    };
+
+    let read_piece_fut = {
+        let Some(plotted_pieces) = weak_plotted_pieces.upgrade() else {
+            debug!("A readers and pieces are already dropped");
+            return None;
+        };
+        let plotted_pieces = plotted_pieces.lock();
+        let Some(plotted_pieces) = plotted_pieces.as_ref() else {
+            debug!(?piece_index, "Readers and pieces are not initialized yet");
+            return None;
+        };
+
+        plotted_pieces.read_piece(&piece_index)?.in_current_span()
+    };
+
+    read_piece_fut.await
+}
@@ -0,0 +1,929 @@
+//! Farms run by the farmer: quarantine of failed farms and their restarts, as well as adding and
+//! removing farms while farmer is running
+
+use crate::commands::farm::metrics::{FarmerMetrics, SectorState};
+use crate::commands::farm::plotting_concurrency::PlottingConcurrency;
+use crate::commands::farm::DiskFarm;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{AbortHandle, Abortable, Either, LocalBoxFuture};
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
+use parking_lot::Mutex;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, fs};
+use subspace_core_primitives::SectorIndex;
+use subspace_farmer::farmer_cache::FarmerCache;
+use subspace_farmer::single_disk_farm::download_staging::DownloadStagingOptions;
+use subspace_farmer::single_disk_farm::farming::reward_addresses::RewardAddresses;
+use subspace_farmer::single_disk_farm::farming::FarmingNotification;
+use subspace_farmer::single_disk_farm::piece_cache::DiskPieceCache;
+use subspace_farmer::single_disk_farm::plot_cache::DiskPlotCache;
+use subspace_farmer::single_disk_farm::{
+    CacheOnlyFarm, SectorExpirationDetails, SectorPlottingDetails, SectorUpdate, SingleDiskFarm,
+    SingleDiskFarmId, SingleDiskFarmOptions,
+};
+use subspace_farmer::utils::plotted_pieces::{DiskFarmIndex, PlottedPieces};
+use subspace_farmer::utils::plotting_schedule::PlottingSchedule;
+use subspace_farmer::NodeRpcClient;
+use subspace_farmer_components::plotting::PlottedSector;
+use subspace_farmer_components::segment_reconstruction::SegmentReconstructionCache;
+use subspace_farmer_components::PieceGetter;
+use subspace_proof_of_space::Table;
+use tracing::{error, info, info_span, warn};
+
+/// Delay before the first restart of failed farm
+const FARM_RESTART_INITIAL_DELAY: Duration = Duration::from_secs(30);
+/// Max delay before restart of failed farm
+const FARM_RESTART_MAX_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// Creates options for a farm, the same for farms created on startup, restarted or added later
+pub(super) type CreateFarmOptions<PG> = Arc<
+    dyn Fn(
+            &DiskFarm,
+            &RewardAddresses,
+            NodeRpcClient,
+            Option<oneshot::Receiver<()>>,
+        ) -> SingleDiskFarmOptions<NodeRpcClient, PG>
+        + Send
+        + Sync,
+>;
+
+/// Derives reward addresses of the farm added while farmer is running
+pub(super) type FarmRewardAddresses =
+    Box<dyn Fn(&DiskFarm) -> anyhow::Result<RewardAddresses> + Send>;
+
+/// Request sent to the farm loop to add or remove farms or control plotting while farmer is running
+#[derive(Debug)]
+pub(super) enum FarmsRequest {
+    /// Add new farm
+    AddFarm(DiskFarm),
+    /// Remove farm located at specified path
+    RemoveFarm(PathBuf),
+    /// List farms
+    ListFarms,
+    /// Pause plotting and replotting of new sectors
+    PausePlotting,
+    /// Resume plotting and replotting of new sectors
+    ResumePlotting,
+}
+
+/// Request to the farm loop together with sender for textual response
+#[derive(Debug)]
+pub(super) struct FarmsCommand {
+    pub(super) request: FarmsRequest,
+    pub(super) response_sender: oneshot::Sender<Result<String, String>>,
+}
+
+/// Event of a farm running in the farm loop
+enum FarmEvent {
+    /// Farm exited, either successfully or with fatal error
+    Exited {
+        disk_farm_index: DiskFarmIndex,
+        result: anyhow::Result<SingleDiskFarmId>,
+    },
+    /// Farm that was added or failed before was started
+    Started {
+        disk_farm_index: DiskFarmIndex,
+        result: anyhow::Result<SingleDiskFarm>,
+    },
+    /// Farm or its pending start was aborted due to farm removal
+    Removed { disk_farm_index: DiskFarmIndex },
+}
+
+/// State of the farm in the farm loop
+struct Farm {
+    disk_farm: DiskFarm,
+    reward_addresses: RewardAddresses,
+    /// `None` until farm added while farmer is running is started for the first time
+    id: Option<SingleDiskFarmId>,
+    /// Caches of the farm, `None` while farm is starting or quarantined after failure
+    caches: Option<(DiskPieceCache, DiskPlotCache)>,
+    restarts: u32,
+    /// Aborts farm or its pending start
+    abort_handle: Option<AbortHandle>,
+    removing: bool,
+    /// Sender of the response to request that added the farm, used once farm is started
+    start_response_sender: Option<oneshot::Sender<Result<String, String>>>,
+}
+
+impl fmt::Display for Farm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.removing {
+            "removing"
+        } else if self.caches.is_some() {
+            "running"
+        } else if self.abort_handle.is_some() {
+            "starting"
+        } else {
+            "quarantined"
+        };
+
+        write!(f, "{} ", self.disk_farm.directory.display())?;
+        match &self.id {
+            Some(id) => write!(f, "{id}")?,
+            None => f.write_str("-")?,
+        }
+        write!(f, " {state}")
+    }
+}
+
+/// Farms run by the farmer.
+///
+/// Failed farms are quarantined: their caches are removed from farmer cache and their pieces from
+/// plotted pieces, while other farms keep working. Failed farms can be restarted with exponential
+/// backoff. Farms can also be added and removed while farmer is running.
+///
+/// Plotting concurrency shared by farms is adjusted when farms are added or removed.
+pub(super) struct Farms<PosTable, PG> {
+    farms: Vec<Option<Farm>>,
+    farms_stream: FuturesUnordered<LocalBoxFuture<'static, FarmEvent>>,
+    farmer_cache: FarmerCache,
+    plotted_pieces: Arc<Mutex<Option<PlottedPieces>>>,
+    /// Cache-only farms, these are only specified on startup and never fail
+    cache_only_farms: Vec<CacheOnlyFarm>,
+    farmer_metrics: FarmerMetrics,
+    node_rpc_url: String,
+    failed_farm_restarts: u32,
+    create_farm_options: CreateFarmOptions<PG>,
+    farm_reward_addresses: FarmRewardAddresses,
+    plotting_schedule: PlottingSchedule,
+    segment_reconstruction_cache: SegmentReconstructionCache,
+    plotting_concurrency: PlottingConcurrency,
+    /// Staged sectors of farms are removed together with farms
+    download_staging: Option<DownloadStagingOptions>,
+    _phantom: PhantomData<PosTable>,
+}
+
+impl<PosTable, PG> Farms<PosTable, PG>
+where
+    PosTable: Table,
+    PG: PieceGetter + Clone + Send + Sync + 'static,
+{
+    /// Create new instance with farms that were created on startup.
+    ///
+    /// Plotting delay senders are notified once farmer cache is initialized.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn new(
+        initial_farms: Vec<(DiskFarm, RewardAddresses, SingleDiskFarm)>,
+        plotting_delay_senders: Vec<oneshot::Sender<()>>,
+        farmer_cache: FarmerCache,
+        plotted_pieces: Arc<Mutex<Option<PlottedPieces>>>,
+        cache_only_farms: Vec<CacheOnlyFarm>,
+        farmer_metrics: FarmerMetrics,
+        node_rpc_url: String,
+        failed_farm_restarts: u32,
+        create_farm_options: CreateFarmOptions<PG>,
+        farm_reward_addresses: FarmRewardAddresses,
+        plotting_schedule: PlottingSchedule,
+        segment_reconstruction_cache: SegmentReconstructionCache,
+        plotting_concurrency: PlottingConcurrency,
+        download_staging: Option<DownloadStagingOptions>,
+    ) -> Self {
+        let mut single_disk_farms = Vec::with_capacity(initial_farms.len());
+        let farms = initial_farms
+            .into_iter()
+            .map(|(disk_farm, reward_addresses, single_disk_farm)| {
+                let farm = Farm {
+                    disk_farm,
+                    reward_addresses,
+                    id: Some(*single_disk_farm.id()),
+                    caches: Some((
+                        single_disk_farm.piece_cache(),
+                        single_disk_farm.plot_cache(),
+                    )),
+                    restarts: 0,
+                    abort_handle: None,
+                    removing: false,
+                    start_response_sender: None,
+                };
+                single_disk_farms.push(single_disk_farm);
+
+                Some(farm)
+            })
+            .collect();
+
+        let mut farms = Self {
+            farms,
+            farms_stream: FuturesUnordered::new(),
+            farmer_cache,
+            plotted_pieces,
+            cache_only_farms,
+            farmer_metrics,
+            node_rpc_url,
+            failed_farm_restarts,
+            create_farm_options,
+            farm_reward_addresses,
+            plotting_schedule,
+            segment_reconstruction_cache,
+            plotting_concurrency,
+            download_staging,
+            _phantom: PhantomData,
+        };
+
+        let cache_acknowledgement_receiver = farms.replace_backing_caches().await;
+
+        // Wait for cache initialization before starting plotting
+        tokio::spawn(async move {
+            if cache_acknowledgement_receiver.await.is_ok() {
+                for plotting_delay_sender in plotting_delay_senders {
+                    // Doesn't matter if receiver is gone
+                    let _ = plotting_delay_sender.send(());
+                }
+            }
+        });
+
+        info!("Collecting already plotted pieces...");
+
+        // Collect already plotted pieces
+        {
+            let mut future_plotted_pieces = PlottedPieces::new(
+                single_disk_farms
+                    .iter()
+                    .map(|single_disk_farm| single_disk_farm.piece_reader())
+                    .collect(),
+            );
+
+            for (disk_farm_index, single_disk_farm) in single_disk_farms.iter().enumerate() {
+                let disk_farm_index = disk_farm_index.try_into().expect(
+                    "More than 65536 farms are not supported, this is checked on startup; qed",
+                );
+
+                future_plotted_pieces.add_sectors(
+                    disk_farm_index,
+                    &read_plotted_sectors(single_disk_farm, disk_farm_index).await,
+                );
+            }
+
+            farms.plotted_pieces.lock().replace(future_plotted_pieces);
+        }
+
+        info!("Finished collecting already plotted pieces successfully");
+
+        for (disk_farm_index, single_disk_farm) in single_disk_farms.into_iter().enumerate() {
+            let disk_farm_index = disk_farm_index
+                .try_into()
+                .expect("More than 65536 farms are not supported, this is checked on startup; qed");
+
+            farms.run_farm(disk_farm_index, single_disk_farm).await;
+        }
+
+        farms
+    }
+
+    /// Run farms and process requests to add or remove farms until all farms exit and no more
+    /// requests can be received
+    pub(super) async fn run(
+        mut self,
+        mut farms_commands: mpsc::Receiver<FarmsCommand>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let event_or_command = futures::select! {
+                farm_event = self.farms_stream.select_next_some() => Either::Left(farm_event),
+                farms_command = farms_commands.select_next_some() => Either::Right(farms_command),
+                complete => break,
+            };
+
+            match event_or_command {
+                Either::Left(farm_event) => {
+                    self.on_farm_event(farm_event).await;
+                }
+                Either::Right(farms_command) => {
+                    self.on_farms_command(farms_command).await;
+                }
+            }
+        }
+
+        let mut farms = self.farms.iter().flatten().peekable();
+        if farms.peek().is_some() && farms.all(|farm| farm.caches.is_none()) {
+            return Err(anyhow::anyhow!("All farms have failed"));
+        }
+
+        Ok(())
+    }
+
+    async fn on_farm_event(&mut self, farm_event: FarmEvent) {
+        match farm_event {
+            FarmEvent::Exited {
+                disk_farm_index,
+                result,
+            } => {
+                let Some(farm) = self.farm_mut(disk_farm_index) else {
+                    return;
+                };
+                farm.abort_handle.take();
+
+                if farm.removing {
+                    self.remove_farm(disk_farm_index).await;
+                    return;
+                }
+
+                match result {
+                    Ok(id) => {
+                        info!(%id, "Farm exited successfully");
+                    }
+                    Err(error) => {
+                        self.on_farm_failure(disk_farm_index, error).await;
+                    }
+                }
+            }
+            FarmEvent::Started {
+                disk_farm_index,
+                result,
+            } => {
+                let Some(farm) = self.farm_mut(disk_farm_index) else {
+                    return;
+                };
+                farm.abort_handle.take();
+
+                if farm.removing {
+                    // Farm (if started) is dropped here
+                    drop(result);
+                    self.remove_farm(disk_farm_index).await;
+                    return;
+                }
+
+                match result {
+                    Ok(single_disk_farm) => {
+                        self.on_farm_started(disk_farm_index, single_disk_farm)
+                            .await;
+                    }
+                    Err(error) if farm.id.is_none() => {
+                        warn!(
+                            %disk_farm_index,
+                            directory = %farm.disk_farm.directory.display(),
+                            %error,
+                            "Failed to start added farm"
+                        );
+
+                        if let Some(response_sender) = farm.start_response_sender.take() {
+                            // Doesn't matter if client is gone
+                            let _ =
+                                response_sender.send(Err(format!("Failed to start farm: {error}")));
+                        }
+                        self.farms[usize::from(disk_farm_index)].take();
+                    }
+                    Err(error) => {
+                        self.on_farm_failure(disk_farm_index, error).await;
+                    }
+                }
+            }
+            FarmEvent::Removed { disk_farm_index } => {
+                self.remove_farm(disk_farm_index).await;
+            }
+        }
+    }
+
+    async fn on_farms_command(&mut self, farms_command: FarmsCommand) {
+        let FarmsCommand {
+            request,
+            response_sender,
+        } = farms_command;
+
+        let response = match request {
+            FarmsRequest::AddFarm(disk_farm) => {
+                match self.add_farm(disk_farm, response_sender) {
+                    Ok(()) => {
+                        // Response will be sent once farm starts
+                        return;
+                    }
+                    Err((error, response_sender)) => {
+                        // Doesn't matter if client is gone
+                        let _ = response_sender.send(Err(error));
+                        return;
+                    }
+                }
+            }
+            FarmsRequest::RemoveFarm(directory) => self.start_farm_removal(&directory).await,
+            FarmsRequest::ListFarms => Ok(self
+                .farms
+                .iter()
+                .enumerate()
+                .filter_map(|(disk_farm_index, farm)| {
+                    farm.as_ref()
+                        .map(|farm| format!("{disk_farm_index}: {farm}"))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            FarmsRequest::PausePlotting => {
+                info!("Pausing plotting");
+                self.plotting_schedule.pause();
+                Ok(String::new())
+            }
+            FarmsRequest::ResumePlotting => {
+                info!("Resuming plotting");
+                self.plotting_schedule.resume();
+                Ok(String::new())
+            }
+        };
+
+        // Doesn't matter if client is gone
+        let _ = response_sender.send(response);
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn add_farm(
+        &mut self,
+        disk_farm: DiskFarm,
+        response_sender: oneshot::Sender<Result<String, String>>,
+    ) -> Result<(), (String, oneshot::Sender<Result<String, String>>)> {
+        if disk_farm.cache_only {
+            return Err((
+                "Cache-only farms can only be specified on startup".to_string(),
+                response_sender,
+            ));
+        }
+
+        if self
+            .farms
+            .iter()
+            .flatten()
+            .any(|farm| farm.disk_farm.directory == disk_farm.directory)
+        {
+            return Err((
+                format!("Farm at {} is already added", disk_farm.directory.display()),
+                response_sender,
+            ));
+        }
+
+        let reward_addresses = match (self.farm_reward_addresses)(&disk_farm) {
+            Ok(reward_addresses) => reward_addresses,
+            Err(error) => {
+                return Err((error.to_string(), response_sender));
+            }
+        };
+
+        if !disk_farm.directory.exists() {
+            if let Err(error) = fs::create_dir(&disk_farm.directory) {
+                return Err((
+                    format!(
+                        "Directory {} doesn't exist and can't be created: {error}",
+                        disk_farm.directory.display()
+                    ),
+                    response_sender,
+                ));
+            }
+        }
+
+        let disk_farm_index = self
+            .farms
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(self.farms.len());
+        let Ok(disk_farm_index) = DiskFarmIndex::try_from(disk_farm_index) else {
+            return Err((
+                "More than 65536 farms are not supported, consider running multiple farmer \
+                instances"
+                    .to_string(),
+                response_sender,
+            ));
+        };
+
+        info!(
+            %disk_farm_index,
+            directory = %disk_farm.directory.display(),
+            "Adding farm"
+        );
+
+        let farm = Farm {
+            disk_farm,
+            reward_addresses,
+            id: None,
+            caches: None,
+            restarts: 0,
+            abort_handle: None,
+            removing: false,
+            start_response_sender: Some(response_sender),
+        };
+        match self.farms.get_mut(usize::from(disk_farm_index)) {
+            Some(slot) => {
+                slot.replace(farm);
+            }
+            None => {
+                self.farms.push(Some(farm));
+            }
+        }
+        self.adjust_plotting_concurrency();
+
+        self.start_farm(disk_farm_index, Duration::ZERO);
+
+        Ok(())
+    }
+
+    async fn start_farm_removal(&mut self, directory: &Path) -> Result<String, String> {
+        let Some(disk_farm_index) = self.farms.iter().position(|farm| {
+            farm.as_ref()
+                .is_some_and(|farm| farm.disk_farm.directory == directory)
+        }) else {
+            return Err(format!("Farm at {} is not found", directory.display()));
+        };
+        let disk_farm_index = DiskFarmIndex::try_from(disk_farm_index)
+            .expect("Farm indices never exceed `DiskFarmIndex`; qed");
+        let farm = self
+            .farm_mut(disk_farm_index)
+            .expect("Farm was just found; qed");
+
+        if farm.removing {
+            return Err(format!(
+                "Farm at {} is already being removed",
+                directory.display()
+            ));
+        }
+        farm.removing = true;
+
+        info!(
+            %disk_farm_index,
+            directory = %directory.display(),
+            "Removing farm"
+        );
+
+        match farm.abort_handle.take() {
+            Some(abort_handle) => {
+                // Farm will be removed once it is stopped
+                abort_handle.abort();
+                Ok(format!("Removing farm {disk_farm_index}"))
+            }
+            None => {
+                self.remove_farm(disk_farm_index).await;
+                Ok(format!("Farm {disk_farm_index} removed"))
+            }
+        }
+    }
+
+    async fn remove_farm(&mut self, disk_farm_index: DiskFarmIndex) {
+        let Some(farm) = self.farms[usize::from(disk_farm_index)].take() else {
+            return;
+        };
+
+        info!(
+            %disk_farm_index,
+            directory = %farm.disk_farm.directory.display(),
+            "Farm removed"
+        );
+        self.adjust_plotting_concurrency();
+
+        if let Some(response_sender) = farm.start_response_sender {
+            // Doesn't matter if client is gone
+            let _ = response_sender.send(Err("Farm was removed before it started".to_string()));
+        }
+
+        if farm.caches.is_some() {
+            // Farmer cache will re-initialize in the background
+            drop(self.replace_backing_caches().await);
+        }
+        if let Some(plotted_pieces) = self.plotted_pieces.lock().as_mut() {
+            plotted_pieces.delete_farm(disk_farm_index);
+        }
+        if let (Some(download_staging), Some(id)) = (&self.download_staging, farm.id) {
+            if let Err(error) = download_staging.remove_farm(&id) {
+                warn!(%id, %error, "Failed to remove staged sectors of removed farm");
+            }
+        }
+    }
+
+    async fn on_farm_failure(&mut self, disk_farm_index: DiskFarmIndex, error: anyhow::Error) {
+        let Some(farm) = self.farm_mut(disk_farm_index) else {
+            return;
+        };
+        let id = farm.id.expect("Only farms that were started can fail; qed");
+        let was_running = farm.caches.take().is_some();
+
+        error!(
+            %disk_farm_index,
+            %id,
+            %error,
+            "Farm failed and was quarantined, other farms continue working"
+        );
+        self.farmer_metrics.note_farm_failure(&id);
+
+        if was_running {
+            // Farmer cache will re-initialize in the background
+            drop(self.replace_backing_caches().await);
+        }
+        if let Some(plotted_pieces) = self.plotted_pieces.lock().as_mut() {
+            plotted_pieces.delete_farm(disk_farm_index);
+        }
+
+        let farm = self
+            .farm_mut(disk_farm_index)
+            .expect("Farm was just found; qed");
+        if farm.restarts == self.failed_farm_restarts {
+            return;
+        }
+
+        let delay = farm_restart_delay(farm.restarts);
+        farm.restarts += 1;
+        info!(
+            %disk_farm_index,
+            %id,
+            attempt = %farm.restarts,
+            ?delay,
+            "Farm will be restarted"
+        );
+
+        self.start_farm(disk_farm_index, delay);
+    }
+
+    async fn on_farm_started(
+        &mut self,
+        disk_farm_index: DiskFarmIndex,
+        single_disk_farm: SingleDiskFarm,
+    ) {
+        let id = *single_disk_farm.id();
+        let farm = self
+            .farm_mut(disk_farm_index)
+            .expect("Only existing farms are started; qed");
+
+        let restarted = farm.id.replace(id).is_some();
+        farm.caches.replace((
+            single_disk_farm.piece_cache(),
+            single_disk_farm.plot_cache(),
+        ));
+        if let Some(response_sender) = farm.start_response_sender.take() {
+            // Doesn't matter if client is gone
+            let _ =
+                response_sender.send(Ok(format!("Farm {disk_farm_index} with ID {id} started")));
+        }
+
+        if restarted {
+            info!(%disk_farm_index, %id, "Farm restarted successfully");
+            self.farmer_metrics.note_farm_restart(&id);
+        } else {
+            info!(%disk_farm_index, %id, "Farm added successfully");
+        }
+        // Farmer cache will re-initialize in the background
+        drop(self.replace_backing_caches().await);
+
+        let plotted_sectors = read_plotted_sectors(&single_disk_farm, disk_farm_index).await;
+        if let Some(plotted_pieces) = self.plotted_pieces.lock().as_mut() {
+            plotted_pieces.set_reader(disk_farm_index, single_disk_farm.piece_reader());
+            plotted_pieces.add_sectors(disk_farm_index, &plotted_sectors);
+        }
+
+        self.run_farm(disk_farm_index, single_disk_farm).await;
+    }
+
+    /// Start farm (that was added or failed before) after specified delay
+    fn start_farm(&mut self, disk_farm_index: DiskFarmIndex, delay: Duration) {
+        let farm = self.farms[usize::from(disk_farm_index)]
+            .as_mut()
+            .expect("Only existing farms are started; qed");
+        let disk_farm = farm.disk_farm.clone();
+        let reward_addresses = farm.reward_addresses.clone();
+        let node_rpc_url = self.node_rpc_url.clone();
+        let create_farm_options = Arc::clone(&self.create_farm_options);
+
+        let start_fut = async move {
+            tokio::time::sleep(delay).await;
+
+            let result = async {
+                let node_client = NodeRpcClient::new(&node_rpc_url).await?;
+
+                anyhow::Ok(
+                    SingleDiskFarm::new::<_, _, PosTable>(
+                        create_farm_options(&disk_farm, &reward_addresses, node_client, None),
+                        usize::from(disk_farm_index),
+                    )
+                    .await?,
+                )
+            }
+            .await;
+
+            FarmEvent::Started {
+                disk_farm_index,
+                result,
+            }
+        };
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        farm.abort_handle.replace(abort_handle);
+        self.farms_stream.push(
+            Abortable::new(start_fut, abort_registration)
+                .map(move |result| result.unwrap_or(FarmEvent::Removed { disk_farm_index }))
+                .boxed_local(),
+        );
+    }
+
+    /// Subscribe to farm updates for metrics and plotted pieces tracking and run the farm until it
+    /// exits
+    async fn run_farm(&mut self, disk_farm_index: DiskFarmIndex, single_disk_farm: SingleDiskFarm) {
+        let plotted_pieces = Arc::clone(&self.plotted_pieces);
+        let farmer_metrics = &self.farmer_metrics;
+        let span = info_span!("", %disk_farm_index);
+
+        // Collect newly plotted pieces
+        let on_plotted_sector_callback =
+            move |plotted_sector: &PlottedSector,
+                  maybe_old_plotted_sector: &Option<PlottedSector>| {
+                let _span_guard = span.enter();
+
+                {
+                    let mut plotted_pieces = plotted_pieces.lock();
+                    let plotted_pieces = plotted_pieces
+                        .as_mut()
+                        .expect("Initial value was populated above; qed");
+
+                    if let Some(old_plotted_sector) = &maybe_old_plotted_sector {
+                        plotted_pieces.delete_sector(disk_farm_index, old_plotted_sector);
+                    }
+                    plotted_pieces.add_sector(disk_farm_index, plotted_sector);
+                }
+            };
+
+        let total_sector_count = single_disk_farm.total_sectors_count();
+        let plotted_sectors_count = single_disk_farm.plotted_sectors_count().await;
+        farmer_metrics.update_sectors_total(
+            single_disk_farm.id(),
+            total_sector_count - plotted_sectors_count,
+            SectorState::NotPlotted,
+        );
+        farmer_metrics.update_sectors_total(
+            single_disk_farm.id(),
+            plotted_sectors_count,
+            SectorState::Plotted,
+        );
+        single_disk_farm
+            .on_sector_update(Arc::new({
+                let single_disk_farm_id = *single_disk_farm.id();
+                let farmer_metrics = farmer_metrics.clone();
+
+                move |(_sector_index, sector_state)| match sector_state {
+                    SectorUpdate::Plotting(SectorPlottingDetails::Starting { .. }) => {
+                        farmer_metrics.sector_plotting.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Paused) => {
+                        farmer_metrics.sector_paused.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Resumed(time)) => {
+                        farmer_metrics.observe_sector_paused_time(&single_disk_farm_id, time);
+                        farmer_metrics.sector_resumed.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Downloading) => {
+                        farmer_metrics.sector_downloading.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Downloaded(time)) => {
+                        farmer_metrics.observe_sector_downloading_time(&single_disk_farm_id, time);
+                        farmer_metrics.sector_downloaded.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Encoding) => {
+                        farmer_metrics.sector_encoding.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Encoded(time)) => {
+                        farmer_metrics.observe_sector_encoding_time(&single_disk_farm_id, time);
+                        farmer_metrics.sector_encoded.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Writing) => {
+                        farmer_metrics.sector_writing.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Written(time)) => {
+                        farmer_metrics.observe_sector_writing_time(&single_disk_farm_id, time);
+                        farmer_metrics.sector_written.inc();
+                    }
+                    SectorUpdate::Plotting(SectorPlottingDetails::Finished {
+                        plotted_sector,
+                        old_plotted_sector,
+                        time,
+                    }) => {
+                        on_plotted_sector_callback(plotted_sector, old_plotted_sector);
+                        farmer_metrics.observe_sector_plotting_time(&single_disk_farm_id, time);
+                        farmer_metrics.sector_plotted.inc();
+                        farmer_metrics
+                            .update_sector_state(&single_disk_farm_id, SectorState::Plotted);
+                    }
+                    SectorUpdate::Expiration(SectorExpirationDetails::AboutToExpire) => {
+                        farmer_metrics
+                            .update_sector_state(&single_disk_farm_id, SectorState::AboutToExpire);
+                    }
+                    SectorUpdate::Expiration(SectorExpirationDetails::Expired) => {
+                        farmer_metrics
+                            .update_sector_state(&single_disk_farm_id, SectorState::Expired);
+                    }
+                    SectorUpdate::Expiration(SectorExpirationDetails::Determined { .. }) => {
+                        // Not interested in here
+                    }
+                }
+            }))
+            .detach();
+
+        single_disk_farm
+            .on_solution(Arc::new({
+                let single_disk_farm_id = *single_disk_farm.id();
+                let farmer_metrics = farmer_metrics.clone();
+
+                move |solution_response| {
+                    farmer_metrics.note_solution(
+                        &single_disk_farm_id,
+                        &solution_response.solution.reward_address,
+                    );
+                }
+            }))
+            .detach();
+
+        single_disk_farm
+            .on_farming_notification(Arc::new({
+                let single_disk_farm_id = *single_disk_farm.id();
+                let farmer_metrics = farmer_metrics.clone();
+
+                move |farming_notification| match farming_notification {
+                    FarmingNotification::Auditing(auditing_details) => {
+                        farmer_metrics
+                            .observe_auditing_time(&single_disk_farm_id, &auditing_details.time);
+                    }
+                    FarmingNotification::Proving(proving_details) => {
+                        farmer_metrics.observe_proving_time(
+                            &single_disk_farm_id,
+                            &proving_details.time,
+                            proving_details.result,
+                        );
+                    }
+                    FarmingNotification::NonFatalError(error) => {
+                        farmer_metrics.note_farming_error(&single_disk_farm_id, error);
+                    }
+                }
+            }))
+            .detach();
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        if let Some(farm) = self.farm_mut(disk_farm_index) {
+            farm.abort_handle.replace(abort_handle);
+        }
+        self.farms_stream.push(
+            Abortable::new(single_disk_farm.run(), abort_registration)
+                .map(move |result| match result {
+                    Ok(result) => FarmEvent::Exited {
+                        disk_farm_index,
+                        result,
+                    },
+                    Err(_aborted) => FarmEvent::Removed { disk_farm_index },
+                })
+                .boxed_local(),
+        );
+    }
+
+    fn adjust_plotting_concurrency(&mut self) {
+        let farms = self.farms.iter().flatten().count();
+        let shared_farms = self
+            .farms
+            .iter()
+            .flatten()
+            .filter(|farm| farm.disk_farm.plotting_cpu_cores.is_none())
+            .count();
+        self.plotting_concurrency
+            .adjust::<PosTable>(farms, shared_farms);
+    }
+
+    fn farm_mut(&mut self, disk_farm_index: DiskFarmIndex) -> Option<&mut Farm> {
+        self.farms
+            .get_mut(usize::from(disk_farm_index))
+            .and_then(Option::as_mut)
+    }
+
+    /// Replace backing caches of farmer cache with caches of farms that are running and caches of
+    /// cache-only farms
+    async fn replace_backing_caches(&self) -> oneshot::Receiver<()> {
+        let (mut piece_caches, plot_caches) = self
+            .farms
+            .iter()
+            .flatten()
+            .filter_map(|farm| farm.caches.clone())
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        piece_caches.extend(self.cache_only_farms.iter().map(CacheOnlyFarm::piece_cache));
+
+        self.farmer_cache
+            .replace_backing_caches(piece_caches, plot_caches)
+            .await
+    }
+}
+
+/// Delay before restarting failed farm, doubles with every restart attempt
+fn farm_restart_delay(farm_restarts: u32) -> Duration {
+    FARM_RESTART_INITIAL_DELAY
+        .saturating_mul(2_u32.saturating_pow(farm_restarts))
+        .min(FARM_RESTART_MAX_DELAY)
+}
+
+/// Read already plotted sectors of the farm, sectors that can't be read are skipped
+async fn read_plotted_sectors(
+    single_disk_farm: &SingleDiskFarm,
+    disk_farm_index: DiskFarmIndex,
+) -> Vec<PlottedSector> {
+    (0 as SectorIndex..)
+        .zip(single_disk_farm.plotted_sectors().await)
+        .filter_map(
+            |(sector_index, plotted_sector_result)| match plotted_sector_result {
+                Ok(plotted_sector) => Some(plotted_sector),
+                Err(error) => {
+                    error!(
+                        %error,
+                        %disk_farm_index,
+                        %sector_index,
+                        "Failed reading plotted sector, skipping"
+                    );
+                    None
+                }
+            },
+        )
+        .collect()
+}
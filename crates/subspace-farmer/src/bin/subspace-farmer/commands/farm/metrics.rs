@@ -4,11 +4,13 @@ use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
 use prometheus_client::registry::{Registry, Unit};
 use std::fmt;
+use std::path::Path;
 use std::sync::atomic::{AtomicI64, AtomicU64};
 use std::time::Duration;
-use subspace_core_primitives::SectorIndex;
+use subspace_core_primitives::{PublicKey, SectorIndex};
 use subspace_farmer::single_disk_farm::farming::ProvingResult;
 use subspace_farmer::single_disk_farm::{FarmingError, SingleDiskFarmId};
+use subspace_farmer_components::segment_reconstruction::SegmentReconstructionCacheLookup;
 
 #[derive(Debug, Copy, Clone)]
 pub(super) enum SectorState {
@@ -34,11 +36,17 @@ pub(super) struct FarmerMetrics {
     auditing_time: Family<Vec<(String, String)>, Histogram>,
     proving_time: Family<Vec<(String, String)>, Histogram>,
     farming_errors: Family<Vec<(String, String)>, Counter<u64, AtomicU64>>,
+    solutions: Family<Vec<(String, String)>, Counter<u64, AtomicU64>>,
+    farm_failures: Family<Vec<(String, String)>, Counter<u64, AtomicU64>>,
+    farm_restarts: Family<Vec<(String, String)>, Counter<u64, AtomicU64>>,
     sector_downloading_time: Family<Vec<(String, String)>, Histogram>,
     sector_encoding_time: Family<Vec<(String, String)>, Histogram>,
     sector_writing_time: Family<Vec<(String, String)>, Histogram>,
     sector_plotting_time: Family<Vec<(String, String)>, Histogram>,
+    sector_paused_time: Family<Vec<(String, String)>, Histogram>,
     sectors_total: Family<Vec<(String, String)>, Gauge<i64, AtomicI64>>,
+    pub(super) sector_paused: Counter<u64, AtomicU64>,
+    pub(super) sector_resumed: Counter<u64, AtomicU64>,
     pub(super) sector_downloading: Counter<u64, AtomicU64>,
     pub(super) sector_downloaded: Counter<u64, AtomicU64>,
     pub(super) sector_encoding: Counter<u64, AtomicU64>,
@@ -47,6 +55,9 @@ pub(super) struct FarmerMetrics {
     pub(super) sector_written: Counter<u64, AtomicU64>,
     pub(super) sector_plotting: Counter<u64, AtomicU64>,
     pub(super) sector_plotted: Counter<u64, AtomicU64>,
+    segment_reconstruction_cache_hits: Counter<u64, AtomicU64>,
+    segment_reconstruction_cache_misses: Counter<u64, AtomicU64>,
+    cache_only_farm_capacity: Family<Vec<(String, String)>, Gauge<i64, AtomicI64>>,
 }
 
 impl FarmerMetrics {
@@ -83,6 +94,30 @@ impl FarmerMetrics {
             farming_errors.clone(),
         );
 
+        let solutions = Family::<_, _>::new_with_constructor(Counter::<_, _>::default);
+
+        sub_registry.register(
+            "solutions",
+            "Solutions produced by the farm for each reward address",
+            solutions.clone(),
+        );
+
+        let farm_failures = Family::<_, _>::new_with_constructor(Counter::<_, _>::default);
+
+        sub_registry.register(
+            "farm_failures",
+            "Fatal farm errors after which farm was quarantined",
+            farm_failures.clone(),
+        );
+
+        let farm_restarts = Family::<_, _>::new_with_constructor(Counter::<_, _>::default);
+
+        sub_registry.register(
+            "farm_restarts",
+            "Successful restarts of previously failed farm",
+            farm_restarts.clone(),
+        );
+
         let sector_downloading_time = Family::<_, _>::new_with_constructor(|| {
             Histogram::new(exponential_buckets(0.1, 2.0, 15))
         });
@@ -127,6 +162,17 @@ impl FarmerMetrics {
             sector_plotting_time.clone(),
         );
 
+        let sector_paused_time = Family::<_, _>::new_with_constructor(|| {
+            Histogram::new(exponential_buckets(1.0, 2.0, 17))
+        });
+
+        sub_registry.register_with_unit(
+            "sector_paused_time",
+            "Time plotting of sector was paused before starting",
+            Unit::Seconds,
+            sector_paused_time.clone(),
+        );
+
         let sectors_total = Family::<_, _>::new_with_constructor(Gauge::<_, _>::default);
 
         sub_registry.register_with_unit(
@@ -136,6 +182,24 @@ impl FarmerMetrics {
             sectors_total.clone(),
         );
 
+        let sector_paused = Counter::<_, _>::default();
+
+        sub_registry.register_with_unit(
+            "sector_paused_counter",
+            "Number of sectors which plotting was paused",
+            Unit::Other("sectors".to_string()),
+            sector_paused.clone(),
+        );
+
+        let sector_resumed = Counter::<_, _>::default();
+
+        sub_registry.register_with_unit(
+            "sector_resumed_counter",
+            "Number of sectors which plotting was resumed",
+            Unit::Other("sectors".to_string()),
+            sector_resumed.clone(),
+        );
+
         let sector_downloading = Counter::<_, _>::default();
 
         sub_registry.register_with_unit(
@@ -208,15 +272,46 @@ impl FarmerMetrics {
             sector_plotted.clone(),
         );
 
+        let segment_reconstruction_cache_hits = Counter::<_, _>::default();
+
+        sub_registry.register(
+            "segment_reconstruction_cache_hits",
+            "Missing pieces recovered using pieces of the segment that were already fetched",
+            segment_reconstruction_cache_hits.clone(),
+        );
+
+        let segment_reconstruction_cache_misses = Counter::<_, _>::default();
+
+        sub_registry.register(
+            "segment_reconstruction_cache_misses",
+            "Missing pieces recoveries that required fetching pieces of the segment",
+            segment_reconstruction_cache_misses.clone(),
+        );
+
+        let cache_only_farm_capacity = Family::<_, _>::new_with_constructor(Gauge::<_, _>::default);
+
+        sub_registry.register_with_unit(
+            "cache_only_farm_capacity",
+            "Number of pieces piece cache of cache-only farm can store",
+            Unit::Other("pieces".to_string()),
+            cache_only_farm_capacity.clone(),
+        );
+
         Self {
             auditing_time,
             proving_time,
             farming_errors,
+            solutions,
+            farm_failures,
+            farm_restarts,
             sector_downloading_time,
             sector_encoding_time,
             sector_writing_time,
             sector_plotting_time,
+            sector_paused_time,
             sectors_total,
+            sector_paused,
+            sector_resumed,
             sector_downloading,
             sector_downloaded,
             sector_encoding,
@@ -225,6 +320,9 @@ impl FarmerMetrics {
             sector_written,
             sector_plotting,
             sector_plotted,
+            segment_reconstruction_cache_hits,
+            segment_reconstruction_cache_misses,
+            cache_only_farm_capacity,
         }
     }
 
@@ -255,6 +353,19 @@ impl FarmerMetrics {
             .observe(time.as_secs_f64());
     }
 
+    pub(super) fn note_solution(
+        &self,
+        single_disk_farm_id: &SingleDiskFarmId,
+        reward_address: &PublicKey,
+    ) {
+        self.solutions
+            .get_or_create(&vec![
+                ("farm_id".to_string(), single_disk_farm_id.to_string()),
+                ("reward_address".to_string(), reward_address.to_string()),
+            ])
+            .inc();
+    }
+
     pub(super) fn note_farming_error(
         &self,
         single_disk_farm_id: &SingleDiskFarmId,
@@ -268,6 +379,24 @@ impl FarmerMetrics {
             .inc();
     }
 
+    pub(super) fn note_farm_failure(&self, single_disk_farm_id: &SingleDiskFarmId) {
+        self.farm_failures
+            .get_or_create(&vec![(
+                "farm_id".to_string(),
+                single_disk_farm_id.to_string(),
+            )])
+            .inc();
+    }
+
+    pub(super) fn note_farm_restart(&self, single_disk_farm_id: &SingleDiskFarmId) {
+        self.farm_restarts
+            .get_or_create(&vec![(
+                "farm_id".to_string(),
+                single_disk_farm_id.to_string(),
+            )])
+            .inc();
+    }
+
     pub(super) fn update_sectors_total(
         &self,
         single_disk_farm_id: &SingleDiskFarmId,
@@ -282,6 +411,16 @@ impl FarmerMetrics {
             .set(i64::from(sectors));
     }
 
+    pub(super) fn update_cache_only_farm_capacity(&self, directory: &Path, capacity: u32) {
+        // Cache-only farms don't have an ID, so directory is used instead
+        self.cache_only_farm_capacity
+            .get_or_create(&vec![(
+                "directory".to_string(),
+                directory.display().to_string(),
+            )])
+            .set(i64::from(capacity));
+    }
+
     pub(super) fn update_sector_state(
         &self,
         single_disk_farm_id: &SingleDiskFarmId,
@@ -392,4 +531,31 @@ impl FarmerMetrics {
             )])
             .observe(time.as_secs_f64());
     }
+
+    pub(super) fn observe_sector_paused_time(
+        &self,
+        single_disk_farm_id: &SingleDiskFarmId,
+        time: &Duration,
+    ) {
+        self.sector_paused_time
+            .get_or_create(&vec![(
+                "farm_id".to_string(),
+                single_disk_farm_id.to_string(),
+            )])
+            .observe(time.as_secs_f64());
+    }
+
+    pub(super) fn observe_segment_reconstruction_cache_lookup(
+        &self,
+        lookup: SegmentReconstructionCacheLookup,
+    ) {
+        match lookup {
+            SegmentReconstructionCacheLookup::Hit => {
+                self.segment_reconstruction_cache_hits.inc();
+            }
+            SegmentReconstructionCacheLookup::Miss => {
+                self.segment_reconstruction_cache_misses.inc();
+            }
+        }
+    }
 }
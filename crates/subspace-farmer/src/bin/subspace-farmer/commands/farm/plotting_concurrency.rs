@@ -0,0 +1,341 @@
+//! Plotting concurrency shared by farms, adjusted while farmer is running as farms are added or
+//! removed
+
+#[cfg(test)]
+mod tests;
+
+use bytesize::ByteSize;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use subspace_farmer::thread_pool_manager::PlottingThreadPoolManager;
+use subspace_farmer::utils::plotting_memory::PlottingMemoryPlan;
+use subspace_farmer::utils::{
+    create_plotting_thread_pool_manager, recommended_record_encoding_concurrency,
+    replace_plotting_thread_pools, CpuCoreSet,
+};
+use subspace_proof_of_space::Table;
+use tokio::sync::Semaphore;
+use tracing::{error, info};
+
+/// Options of plotting concurrency
+pub(super) struct PlottingConcurrencyOptions {
+    pub(super) plotting_memory_limit: Option<ByteSize>,
+    pub(super) max_pieces_in_sector: u16,
+    /// Capacity of segment reconstruction cache shared by all farms
+    pub(super) segment_reconstruction_cache_capacity: NonZeroUsize,
+    /// Explicitly specified sector downloading concurrency
+    pub(super) sector_downloading_concurrency: Option<NonZeroUsize>,
+    /// Explicitly specified record encoding concurrency
+    pub(super) record_encoding_concurrency: Option<NonZeroUsize>,
+    pub(super) plotting_thread_pool_core_indices: Vec<CpuCoreSet>,
+    pub(super) replotting_thread_pool_core_indices: Vec<CpuCoreSet>,
+    /// CPU core sets are L3 cache groups that can be regrouped into fewer thread pools to match
+    /// number of farms (as opposed to explicitly specified CPU cores)
+    pub(super) regroup_cpu_cores: bool,
+}
+
+/// Plotting concurrency shared by farms that don't have dedicated CPU cores.
+///
+/// Estimated memory usage of plotting depends on the number of farms, so when memory limit is
+/// specified, memory plan is derived again when farms are added or removed and downloading
+/// semaphore and plotting thread pools are adjusted accordingly. Similarly, L3 cache groups are
+/// regrouped again to match the number of farms that share plotting thread pools.
+///
+/// NOTE: Record encoding concurrency is derived once on startup and doesn't change afterwards since
+/// farms create table generators when they start.
+pub(super) struct PlottingConcurrency {
+    plotting_memory_limit: Option<ByteSize>,
+    max_pieces_in_sector: u16,
+    segment_reconstruction_cache_capacity: NonZeroUsize,
+    max_sector_downloading_concurrency: Option<NonZeroUsize>,
+    record_encoding_concurrency: NonZeroUsize,
+    plotting_thread_pool_core_indices: Vec<CpuCoreSet>,
+    replotting_thread_pool_core_indices: Vec<CpuCoreSet>,
+    regroup_cpu_cores: bool,
+    downloading_semaphore: Arc<Semaphore>,
+    downloading_permits: usize,
+    thread_pool_core_indices: Vec<(CpuCoreSet, CpuCoreSet)>,
+    plotting_thread_pool_manager: PlottingThreadPoolManager,
+}
+
+impl PlottingConcurrency {
+    /// Derive plotting concurrency for farms created on startup, `shared_farms` is the number of
+    /// farms that don't have dedicated CPU cores
+    pub(super) fn new<PosTable>(
+        options: PlottingConcurrencyOptions,
+        farms: usize,
+        shared_farms: usize,
+    ) -> anyhow::Result<Self>
+    where
+        PosTable: Table,
+    {
+        let PlottingConcurrencyOptions {
+            plotting_memory_limit,
+            max_pieces_in_sector,
+            segment_reconstruction_cache_capacity,
+            sector_downloading_concurrency: max_sector_downloading_concurrency,
+            record_encoding_concurrency,
+            plotting_thread_pool_core_indices,
+            replotting_thread_pool_core_indices,
+            regroup_cpu_cores,
+        } = options;
+
+        let (cpu_core_sets, replotting_cpu_core_sets) = cpu_core_sets(
+            &plotting_thread_pool_core_indices,
+            &replotting_thread_pool_core_indices,
+            regroup_cpu_cores,
+            shared_farms,
+        );
+        let mut record_encoding_concurrency = record_encoding_concurrency.unwrap_or_else(|| {
+            recommended_record_encoding_concurrency(
+                cpu_core_sets
+                    .first()
+                    .expect("Guaranteed to have some CPU cores; qed"),
+            )
+        });
+
+        let max_sector_encoding_concurrency =
+            NonZeroUsize::new(cpu_core_sets.len()).expect("Guaranteed to have some CPU cores; qed");
+
+        let mut sector_downloading_concurrency = max_sector_downloading_concurrency;
+        let mut sector_encoding_concurrency = max_sector_encoding_concurrency;
+        if let Some(plotting_memory_limit) = plotting_memory_limit {
+            let plan = PlottingMemoryPlan::derive::<PosTable>(
+                plotting_memory_limit.as_u64(),
+                max_pieces_in_sector,
+                farms,
+                segment_reconstruction_cache_capacity,
+                max_sector_downloading_concurrency,
+                max_sector_encoding_concurrency,
+                record_encoding_concurrency,
+            )?;
+
+            sector_downloading_concurrency.replace(plan.sector_downloading_concurrency);
+            sector_encoding_concurrency = plan.sector_encoding_concurrency;
+            record_encoding_concurrency = plan.record_encoding_concurrency;
+
+            info!(
+                memory_limit = %plotting_memory_limit,
+                %farms,
+                estimated_memory_usage = %ByteSize::b(plan.estimated_memory_usage),
+                sector_downloading_concurrency = %plan.sector_downloading_concurrency,
+                sector_encoding_concurrency = %plan.sector_encoding_concurrency,
+                record_encoding_concurrency = %plan.record_encoding_concurrency,
+                "Derived plotting concurrency from memory limit"
+            );
+        }
+
+        let downloading_permits = sector_downloading_concurrency
+            .map(|sector_downloading_concurrency| sector_downloading_concurrency.get())
+            .unwrap_or(cpu_core_sets.len() + 1);
+
+        let thread_pool_core_indices = thread_pool_core_indices(
+            &cpu_core_sets,
+            &replotting_cpu_core_sets,
+            sector_encoding_concurrency,
+        );
+        let plotting_thread_pool_manager =
+            create_plotting_thread_pool_manager(thread_pool_core_indices.iter().cloned())?;
+
+        Ok(Self {
+            plotting_memory_limit,
+            max_pieces_in_sector,
+            segment_reconstruction_cache_capacity,
+            max_sector_downloading_concurrency,
+            record_encoding_concurrency,
+            plotting_thread_pool_core_indices,
+            replotting_thread_pool_core_indices,
+            regroup_cpu_cores,
+            downloading_semaphore: Arc::new(Semaphore::new(downloading_permits)),
+            downloading_permits,
+            thread_pool_core_indices,
+            plotting_thread_pool_manager,
+        })
+    }
+
+    /// Semaphore that limits sector downloading concurrency
+    pub(super) fn downloading_semaphore(&self) -> &Arc<Semaphore> {
+        &self.downloading_semaphore
+    }
+
+    /// Plotting thread pool manager that limits sector encoding concurrency
+    pub(super) fn plotting_thread_pool_manager(&self) -> &PlottingThreadPoolManager {
+        &self.plotting_thread_pool_manager
+    }
+
+    /// Record encoding concurrency of each farm
+    pub(super) fn record_encoding_concurrency(&self) -> NonZeroUsize {
+        self.record_encoding_concurrency
+    }
+
+    /// Derive plotting concurrency again after number of farms has changed and adjust downloading
+    /// semaphore and plotting thread pools accordingly, `shared_farms` is the number of farms that
+    /// don't have dedicated CPU cores
+    pub(super) fn adjust<PosTable>(&mut self, farms: usize, shared_farms: usize)
+    where
+        PosTable: Table,
+    {
+        let (cpu_core_sets, replotting_cpu_core_sets) = cpu_core_sets(
+            &self.plotting_thread_pool_core_indices,
+            &self.replotting_thread_pool_core_indices,
+            self.regroup_cpu_cores,
+            shared_farms,
+        );
+        let max_sector_encoding_concurrency =
+            NonZeroUsize::new(cpu_core_sets.len()).expect("Checked in constructor; qed");
+
+        let (sector_downloading_concurrency, sector_encoding_concurrency) =
+            match self.plotting_memory_limit {
+                Some(plotting_memory_limit) => match PlottingMemoryPlan::derive::<PosTable>(
+                    plotting_memory_limit.as_u64(),
+                    self.max_pieces_in_sector,
+                    farms,
+                    self.segment_reconstruction_cache_capacity,
+                    self.max_sector_downloading_concurrency,
+                    max_sector_encoding_concurrency,
+                    self.record_encoding_concurrency,
+                ) {
+                    Ok(plan) => {
+                        info!(
+                            memory_limit = %plotting_memory_limit,
+                            %farms,
+                            estimated_memory_usage = %ByteSize::b(plan.estimated_memory_usage),
+                            sector_downloading_concurrency = %plan.sector_downloading_concurrency,
+                            sector_encoding_concurrency = %plan.sector_encoding_concurrency,
+                            "Adjusted plotting concurrency after number of farms has changed"
+                        );
+
+                        (
+                            plan.sector_downloading_concurrency,
+                            plan.sector_encoding_concurrency,
+                        )
+                    }
+                    Err(error) => {
+                        error!(
+                            %error,
+                            %farms,
+                            "Plotting memory limit is too low for current number of farms, \
+                            plotting one sector at a time"
+                        );
+
+                        (NonZeroUsize::MIN, NonZeroUsize::MIN)
+                    }
+                },
+                None => (
+                    self.max_sector_downloading_concurrency
+                        .unwrap_or(max_sector_encoding_concurrency.saturating_add(1)),
+                    max_sector_encoding_concurrency,
+                ),
+            };
+
+        let downloading_permits = sector_downloading_concurrency.get();
+        if downloading_permits > self.downloading_permits {
+            self.downloading_semaphore
+                .add_permits(downloading_permits - self.downloading_permits);
+        } else if downloading_permits < self.downloading_permits {
+            // Permits that are in use can't be revoked, instead they are acquired and forgotten
+            // once released
+            let excess_permits = (self.downloading_permits - downloading_permits) as u32;
+            let downloading_semaphore = Arc::clone(&self.downloading_semaphore);
+            tokio::spawn(async move {
+                if let Ok(permits) = downloading_semaphore
+                    .acquire_many_owned(excess_permits)
+                    .await
+                {
+                    permits.forget();
+                }
+            });
+        }
+        self.downloading_permits = downloading_permits;
+
+        let thread_pool_core_indices = thread_pool_core_indices(
+            &cpu_core_sets,
+            &replotting_cpu_core_sets,
+            sector_encoding_concurrency,
+        );
+        if !same_thread_pool_core_indices(&thread_pool_core_indices, &self.thread_pool_core_indices)
+        {
+            info!(
+                %shared_farms,
+                thread_pools = %thread_pool_core_indices.len(),
+                "Replacing plotting thread pools after number of farms has changed"
+            );
+
+            match replace_plotting_thread_pools(
+                &self.plotting_thread_pool_manager,
+                thread_pool_core_indices.iter().cloned(),
+            ) {
+                Ok(()) => {
+                    self.thread_pool_core_indices = thread_pool_core_indices;
+                }
+                Err(error) => {
+                    error!(%error, "Failed to replace plotting thread pools");
+                }
+            }
+        }
+    }
+}
+
+/// Plotting and replotting CPU core sets to use for `shared_farms` farms.
+///
+/// L3 cache groups are regrouped into at most as many sets as there are farms, such that each farm
+/// can leverage all of the CPU cores in the set while plotting a sector.
+fn cpu_core_sets(
+    plotting_thread_pool_core_indices: &[CpuCoreSet],
+    replotting_thread_pool_core_indices: &[CpuCoreSet],
+    regroup_cpu_cores: bool,
+    shared_farms: usize,
+) -> (Vec<CpuCoreSet>, Vec<CpuCoreSet>) {
+    let target_sets = shared_farms.max(1);
+    if regroup_cpu_cores && plotting_thread_pool_core_indices.len() > target_sets {
+        (
+            CpuCoreSet::regroup(plotting_thread_pool_core_indices, target_sets),
+            CpuCoreSet::regroup(replotting_thread_pool_core_indices, target_sets),
+        )
+    } else {
+        (
+            plotting_thread_pool_core_indices.to_vec(),
+            replotting_thread_pool_core_indices.to_vec(),
+        )
+    }
+}
+
+fn same_thread_pool_core_indices(
+    a: &[(CpuCoreSet, CpuCoreSet)],
+    b: &[(CpuCoreSet, CpuCoreSet)],
+) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|((a_plotting, a_replotting), (b_plotting, b_replotting))| {
+                a_plotting.cpu_cores() == b_plotting.cpu_cores()
+                    && a_replotting.cpu_cores() == b_replotting.cpu_cores()
+            })
+}
+
+/// Pairs of plotting and replotting CPU core sets for `sector_encoding_concurrency` thread pools,
+/// CPU cores are regrouped into fewer thread pools instead of leaving some of them idle
+fn thread_pool_core_indices(
+    plotting_thread_pool_core_indices: &[CpuCoreSet],
+    replotting_thread_pool_core_indices: &[CpuCoreSet],
+    sector_encoding_concurrency: NonZeroUsize,
+) -> Vec<(CpuCoreSet, CpuCoreSet)> {
+    if sector_encoding_concurrency.get() < plotting_thread_pool_core_indices.len() {
+        CpuCoreSet::regroup(
+            plotting_thread_pool_core_indices,
+            sector_encoding_concurrency.get(),
+        )
+        .into_iter()
+        .zip(CpuCoreSet::regroup(
+            replotting_thread_pool_core_indices,
+            sector_encoding_concurrency.get(),
+        ))
+        .collect()
+    } else {
+        plotting_thread_pool_core_indices
+            .iter()
+            .cloned()
+            .zip(replotting_thread_pool_core_indices.iter().cloned())
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+use crate::commands::farm::plotting_concurrency::{cpu_core_sets, same_thread_pool_core_indices};
+use subspace_farmer::utils::{parse_cpu_cores_sets, CpuCoreSet};
+
+fn cores(cpu_core_sets: &[CpuCoreSet]) -> Vec<Vec<usize>> {
+    cpu_core_sets
+        .iter()
+        .map(|cpu_core_set| cpu_core_set.cpu_cores().to_vec())
+        .collect()
+}
+
+#[test]
+fn regroup_as_farms_change() {
+    let plotting = parse_cpu_cores_sets("0,1 2,3 4,5 6,7").unwrap();
+    let replotting = parse_cpu_cores_sets("0 2 4 6").unwrap();
+
+    // Single farm uses all CPU cores in one thread pool
+    let (regrouped_plotting, regrouped_replotting) = cpu_core_sets(&plotting, &replotting, true, 1);
+    assert_eq!(cores(&regrouped_plotting), [vec![0, 1, 2, 3, 4, 5, 6, 7]]);
+    assert_eq!(cores(&regrouped_replotting), [vec![0, 2, 4, 6]]);
+
+    // Farm added
+    let (regrouped_plotting, regrouped_replotting) = cpu_core_sets(&plotting, &replotting, true, 2);
+    assert_eq!(
+        cores(&regrouped_plotting),
+        [vec![0, 1, 2, 3], vec![4, 5, 6, 7]]
+    );
+    assert_eq!(cores(&regrouped_replotting), [vec![0, 2], vec![4, 6]]);
+
+    // More farms than L3 cache groups and no farms at all
+    for shared_farms in [0, 4, 10] {
+        let (regrouped_plotting, _) = cpu_core_sets(&plotting, &replotting, true, shared_farms);
+        let expected = if shared_farms == 0 {
+            vec![vec![0, 1, 2, 3, 4, 5, 6, 7]]
+        } else {
+            cores(&plotting)
+        };
+        assert_eq!(cores(&regrouped_plotting), expected);
+    }
+
+    // Explicitly specified CPU cores are never regrouped
+    let (regrouped_plotting, _) = cpu_core_sets(&plotting, &replotting, false, 1);
+    assert_eq!(cores(&regrouped_plotting), cores(&plotting));
+}
+
+#[test]
+fn thread_pool_core_indices_comparison() {
+    let a = parse_cpu_cores_sets("0,1 2,3").unwrap();
+    let b = parse_cpu_cores_sets("0,1,2,3").unwrap();
+
+    let pairs = |sets: &[CpuCoreSet]| {
+        sets.iter()
+            .cloned()
+            .zip(sets.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(same_thread_pool_core_indices(&pairs(&a), &pairs(&a)));
+    assert!(!same_thread_pool_core_indices(&pairs(&a), &pairs(&b)));
+    assert!(!same_thread_pool_core_indices(&pairs(&a), &pairs(&a[..1])));
+}
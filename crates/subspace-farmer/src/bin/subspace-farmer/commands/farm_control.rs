@@ -0,0 +1,76 @@
+use crate::commands::farm::control::{RESPONSE_ERROR_PREFIX, RESPONSE_OK};
+use anyhow::anyhow;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+/// Arguments for farm control
+#[derive(Debug, Parser)]
+pub(crate) struct FarmControlArgs {
+    /// Path of Unix socket farmer listens on, specified with `--control-socket` of `farm` command
+    #[arg(long, value_name = "SOCKET")]
+    socket: PathBuf,
+    #[clap(subcommand)]
+    command: FarmControlCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum FarmControlCommand {
+    /// Add farm, it will be initialized (if necessary) and started in the background
+    Add {
+        /// Farm in the same format as for `farm` command, cache-only farms are not supported.
+        ///
+        /// Example:
+        ///   path=/path/to/directory,size=5T
+        disk_farm: String,
+    },
+    /// Remove farm, its contents on disk are kept
+    Remove {
+        /// Path to directory of the farm, exactly as it was specified when farm was added
+        directory: PathBuf,
+    },
+    /// List farms with their IDs and states
+    List,
+    /// Pause plotting and replotting of new sectors, sectors that are already being plotted are
+    /// finished
+    PausePlotting,
+    /// Resume plotting and replotting paused with `pause-plotting`, pause windows and CPU usage
+    /// threshold still apply
+    ResumePlotting,
+}
+
+pub(crate) async fn farm_control(farm_control_args: FarmControlArgs) -> anyhow::Result<()> {
+    let FarmControlArgs { socket, command } = farm_control_args;
+
+    let request = match command {
+        FarmControlCommand::Add { disk_farm } => format!("add {disk_farm}"),
+        FarmControlCommand::Remove { directory } => format!("remove {}", directory.display()),
+        FarmControlCommand::List => "list".to_string(),
+        FarmControlCommand::PausePlotting => "pause-plotting".to_string(),
+        FarmControlCommand::ResumePlotting => "resume-plotting".to_string(),
+    };
+
+    let stream = UnixStream::connect(&socket).await.map_err(|error| {
+        anyhow!(
+            "Failed to connect to control socket {}: {error}",
+            socket.display()
+        )
+    })?;
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(format!("{request}\n").as_bytes()).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line == RESPONSE_OK {
+            return Ok(());
+        }
+        if let Some(error) = line.strip_prefix(RESPONSE_ERROR_PREFIX) {
+            return Err(anyhow!("{error}"));
+        }
+
+        println!("{line}");
+    }
+
+    Err(anyhow!("Farmer closed connection without response"))
+}
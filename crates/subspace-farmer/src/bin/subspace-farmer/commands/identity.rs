@@ -0,0 +1,302 @@
+use crate::commands::shared::{derive_libp2p_keypair, read_stdin_line, IdentitySecretArgs};
+use anyhow::anyhow;
+use clap::Subcommand;
+use std::fs;
+use std::path::{Path, PathBuf};
+use subspace_core_primitives::PublicKey;
+use subspace_farmer::single_disk_farm::SingleDiskFarmInfo;
+use subspace_farmer::{Identity, IdentityError, IdentitySecret};
+use tracing::warn;
+
+/// Arguments for identity management
+#[derive(Debug, Subcommand)]
+pub(crate) enum IdentityArgs {
+    /// Show public key and libp2p peer ID of identity of each farm
+    Show {
+        /// Secret to unlock encrypted identity with, public key is shown even without it
+        #[clap(flatten)]
+        identity_secret: IdentitySecretArgs,
+        /// One or more farm located at specified path.
+        ///
+        /// Example:
+        ///   /path/to/directory
+        disk_farms: Vec<PathBuf>,
+    },
+    /// Print BIP39 mnemonic of farm identity, it can be used to restore identity with `import`
+    /// command, keep it secret
+    Export {
+        /// Secret to unlock encrypted identity with
+        #[clap(flatten)]
+        identity_secret: IdentitySecretArgs,
+        /// Farm located at specified path.
+        ///
+        /// Example:
+        ///   /path/to/directory
+        disk_farm: PathBuf,
+    },
+    /// Import identity into a farm.
+    ///
+    /// BIP39 mnemonic is read from standard input unless `--from` is specified. Farms that share
+    /// identity produce identical sector IDs and therefore duplicate plots, so importing identity
+    /// into more than one farm requires `--allow-shared-identity`.
+    Import {
+        /// Copy identity from farm located at specified path instead of reading mnemonic
+        #[arg(long)]
+        from: Option<PathBuf>,
+        /// Override identity of farms that don't have any plotted data yet, but already have a
+        /// different identity
+        #[arg(long)]
+        force: bool,
+        /// Allow importing the same identity into more than one farm
+        #[arg(long)]
+        allow_shared_identity: bool,
+        /// Secret to encrypt imported identity with (and to unlock identity specified with
+        /// `--from`), identity is stored in plaintext otherwise
+        #[clap(flatten)]
+        identity_secret: IdentitySecretArgs,
+        /// One or more farm located at specified path.
+        ///
+        /// Example:
+        ///   /path/to/directory
+        disk_farms: Vec<PathBuf>,
+    },
+    /// Encrypt existing plaintext identity of one or more farms, identities that are already
+    /// encrypted are left as is
+    Encrypt {
+        /// Secret to encrypt identity with, one of the options is required
+        #[clap(flatten)]
+        identity_secret: IdentitySecretArgs,
+        /// One or more farm located at specified path.
+        ///
+        /// Example:
+        ///   /path/to/directory
+        disk_farms: Vec<PathBuf>,
+    },
+}
+
+pub(crate) fn identity(identity_args: IdentityArgs) -> anyhow::Result<()> {
+    match identity_args {
+        IdentityArgs::Show {
+            identity_secret,
+            disk_farms,
+        } => show(identity_secret.secret()?.as_ref(), &disk_farms),
+        IdentityArgs::Export {
+            identity_secret,
+            disk_farm,
+        } => export(identity_secret.secret()?.as_ref(), &disk_farm),
+        IdentityArgs::Import {
+            from,
+            force,
+            allow_shared_identity,
+            identity_secret,
+            disk_farms,
+        } => import(
+            from.as_deref(),
+            force,
+            allow_shared_identity,
+            identity_secret.secret()?.as_ref(),
+            &disk_farms,
+        ),
+        IdentityArgs::Encrypt {
+            identity_secret,
+            disk_farms,
+        } => {
+            if !identity_secret.is_specified() {
+                return Err(anyhow!(
+                    "Passphrase or keyfile to encrypt identity with must be specified"
+                ));
+            }
+            let identity_secret = identity_secret
+                .secret()?
+                .expect("Presence of secret was checked above; qed");
+
+            encrypt(&identity_secret, &disk_farms)
+        }
+    }
+}
+
+fn open_identity(
+    identity_secret: Option<&IdentitySecret>,
+    disk_farm: &Path,
+) -> anyhow::Result<Identity> {
+    Identity::open_with_secret(disk_farm, identity_secret)
+        .map_err(|error| {
+            anyhow!(
+                "Failed to open identity of farm {}: {error}",
+                disk_farm.display()
+            )
+        })?
+        .ok_or_else(|| anyhow!("Farm {} has no identity", disk_farm.display()))
+}
+
+fn show(identity_secret: Option<&IdentitySecret>, disk_farms: &[PathBuf]) -> anyhow::Result<()> {
+    for (disk_farm_index, disk_farm) in disk_farms.iter().enumerate() {
+        if disk_farm_index > 0 {
+            println!();
+        }
+
+        println!("Identity of farm {disk_farm_index}:");
+        println!("  Directory: {}", disk_farm.display());
+        match Identity::open_with_secret(disk_farm, identity_secret) {
+            Ok(Some(identity)) => {
+                let peer_id = derive_libp2p_keypair(identity.secret_key())
+                    .public()
+                    .to_peer_id();
+
+                println!(
+                    "  Public key: 0x{}",
+                    hex::encode(identity.public_key().to_bytes())
+                );
+                println!("  Peer ID: {peer_id}");
+            }
+            Ok(None) => {
+                println!("  No identity found here yet");
+            }
+            Err(IdentityError::Locked) => match Identity::read_public_key(disk_farm) {
+                Ok(Some(public_key)) => {
+                    println!("  Public key: 0x{}", hex::encode(public_key.to_bytes()));
+                    println!("  Peer ID: <identity is encrypted, unlock it to show>");
+                }
+                Ok(None) => {
+                    println!("  No identity found here yet");
+                }
+                Err(error) => {
+                    println!("  Failed to read public key of identity: {error}");
+                }
+            },
+            Err(error) => {
+                println!("  Failed to open identity: {error}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn export(identity_secret: Option<&IdentitySecret>, disk_farm: &Path) -> anyhow::Result<()> {
+    let identity = open_identity(identity_secret, disk_farm)?;
+
+    eprintln!("Anyone with this mnemonic can farm on your behalf, never share it with anyone");
+    println!("{}", identity.mnemonic().as_str());
+
+    Ok(())
+}
+
+fn import(
+    from: Option<&Path>,
+    force: bool,
+    allow_shared_identity: bool,
+    identity_secret: Option<&IdentitySecret>,
+    disk_farms: &[PathBuf],
+) -> anyhow::Result<()> {
+    if disk_farms.is_empty() {
+        return Err(anyhow!("No farm was specified, so there is nothing to do"));
+    }
+    if disk_farms.len() > 1 {
+        if !allow_shared_identity {
+            return Err(anyhow!(
+                "Importing the same identity into {} farms, use `--allow-shared-identity` if \
+                this is intended",
+                disk_farms.len()
+            ));
+        }
+
+        warn!(
+            farms = %disk_farms.len(),
+            "Importing the same identity into multiple farms, farms that share identity produce \
+            duplicate plots if used at the same time"
+        );
+    }
+
+    let identity = match from {
+        Some(from) => open_identity(identity_secret, from)?,
+        None => {
+            let mnemonic = read_stdin_line("Enter mnemonic:")?;
+            // Nothing is written until all farms are checked
+            Identity::parse_mnemonic(mnemonic.trim())?
+        }
+    };
+    let public_key = PublicKey::from(identity.public_key().to_bytes());
+
+    // Check all farms upfront, such that identity is either imported into all farms or none
+    for disk_farm in disk_farms {
+        if let Some(single_disk_farm_info) = SingleDiskFarmInfo::load_from(disk_farm)? {
+            if single_disk_farm_info.public_key() != &public_key {
+                return Err(anyhow!(
+                    "Farm {} was created with a different identity 0x{}, it can't be changed \
+                    without wiping the farm",
+                    disk_farm.display(),
+                    hex::encode(single_disk_farm_info.public_key())
+                ));
+            }
+        }
+
+        if !force {
+            if let Some(existing_public_key) = Identity::read_public_key(disk_farm)? {
+                if PublicKey::from(existing_public_key.to_bytes()) != public_key {
+                    return Err(anyhow!(
+                        "Farm {} already has a different identity, use `--force` to override it",
+                        disk_farm.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    for disk_farm in disk_farms {
+        fs::create_dir_all(disk_farm)?;
+        identity.write_to(disk_farm, identity_secret)?;
+    }
+
+    println!(
+        "Imported identity with public key 0x{} into {} farm(s)",
+        hex::encode(public_key),
+        disk_farms.len()
+    );
+
+    Ok(())
+}
+
+fn encrypt(identity_secret: &IdentitySecret, disk_farms: &[PathBuf]) -> anyhow::Result<()> {
+    if disk_farms.is_empty() {
+        return Err(anyhow!("No farm was specified, so there is nothing to do"));
+    }
+
+    for disk_farm in disk_farms {
+        let identity = match Identity::open(disk_farm) {
+            Ok(Some(identity)) => identity,
+            Ok(None) => {
+                println!("Farm {} has no identity, skipping", disk_farm.display());
+                continue;
+            }
+            Err(IdentityError::Locked) => {
+                println!(
+                    "Identity of farm {} is already encrypted, skipping",
+                    disk_farm.display()
+                );
+                continue;
+            }
+            Err(error) => {
+                return Err(anyhow!(
+                    "Failed to open identity of farm {}: {error}",
+                    disk_farm.display()
+                ));
+            }
+        };
+
+        identity.write_to(disk_farm, Some(identity_secret))?;
+
+        // Make sure encrypted identity can actually be unlocked before moving on
+        let encrypted_identity = open_identity(Some(identity_secret), disk_farm)?;
+        if encrypted_identity.public_key() != identity.public_key() {
+            return Err(anyhow!(
+                "Encrypted identity of farm {} doesn't match original, this is a bug",
+                disk_farm.display()
+            ));
+        }
+
+        println!("Encrypted identity of farm {}", disk_farm.display());
+    }
+
+    Ok(())
+}
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use subspace_farmer::single_disk_farm::SingleDiskFarm;
 use tracing::{error, info, info_span};
 
-pub(crate) fn scrub(disk_farms: &[PathBuf], disable_farm_locking: bool) {
+pub(crate) fn scrub(disk_farms: &[PathBuf], disable_farm_locking: bool, remote_signer: bool) {
     disk_farms
         .into_par_iter()
         .enumerate()
@@ -15,7 +15,7 @@ pub(crate) fn scrub(disk_farms: &[PathBuf], disable_farm_locking: bool) {
                 "Start scrubbing farm"
             );
 
-            match SingleDiskFarm::scrub(directory, disable_farm_locking) {
+            match SingleDiskFarm::scrub(directory, disable_farm_locking, remote_signer) {
                 Ok(()) => {
                     info!(
                         path = %directory.display(),
@@ -1,5 +1,95 @@
-use std::path::PathBuf;
+use anyhow::anyhow;
+use clap::Args;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
 use subspace_farmer::single_disk_farm::{SingleDiskFarm, SingleDiskFarmSummary};
+use subspace_farmer::IdentitySecret;
+use subspace_networking::libp2p::identity::{ed25519, Keypair};
+use tempfile::NamedTempFile;
+use zeroize::Zeroizing;
+
+/// File with networking keypair used when identity is held by remote signer
+const NETWORK_KEYPAIR_FILE: &str = "network_keypair.bin";
+
+/// Arguments for unlocking encrypted identity, at most one of them can be specified
+#[derive(Debug, Default, Args)]
+#[group(multiple = false)]
+pub(crate) struct IdentitySecretArgs {
+    /// Read identity passphrase from specified environment variable
+    #[arg(long, value_name = "VARIABLE")]
+    identity_passphrase_env: Option<String>,
+    /// Read identity passphrase from the first line of standard input
+    #[arg(long)]
+    identity_passphrase_stdin: bool,
+    /// Use contents of specified file as identity secret
+    #[arg(long, value_name = "PATH")]
+    identity_keyfile: Option<PathBuf>,
+}
+
+impl IdentitySecretArgs {
+    /// Whether any source of the secret was specified
+    pub(crate) fn is_specified(&self) -> bool {
+        self.identity_passphrase_env.is_some()
+            || self.identity_passphrase_stdin
+            || self.identity_keyfile.is_some()
+    }
+
+    /// Read secret from specified source, returns `Ok(None)` if none was specified
+    pub(crate) fn secret(&self) -> anyhow::Result<Option<IdentitySecret>> {
+        if let Some(variable) = &self.identity_passphrase_env {
+            let passphrase = Zeroizing::new(env::var(variable).map_err(|error| {
+                anyhow!("Failed to read identity passphrase from environment variable {variable}: {error}")
+            })?);
+            // Environment variable is not removed here: `env::remove_var()` is not safe to call
+            // once other threads are running, which is already the case in async runtime
+
+            return Ok(Some(IdentitySecret::from_passphrase(&passphrase)));
+        }
+
+        if self.identity_passphrase_stdin {
+            let passphrase = read_stdin_line("Enter identity passphrase:")?;
+            if passphrase.is_empty() {
+                return Err(anyhow!("Identity passphrase can't be empty"));
+            }
+
+            return Ok(Some(IdentitySecret::from_passphrase(&passphrase)));
+        }
+
+        if let Some(keyfile) = &self.identity_keyfile {
+            return IdentitySecret::from_keyfile(keyfile)
+                .map(Some)
+                .map_err(|error| {
+                    anyhow!(
+                        "Failed to read identity keyfile {}: {error}",
+                        keyfile.display()
+                    )
+                });
+        }
+
+        Ok(None)
+    }
+}
+
+/// Read a single line from standard input with trailing new line removed.
+///
+/// Only secrets are read this way, so if standard input is a terminal, `prompt` is printed first
+/// and input is not echoed.
+pub(crate) fn read_stdin_line(prompt: &str) -> anyhow::Result<Zeroizing<String>> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(Zeroizing::new(rpassword::prompt_password(format!(
+            "{prompt} "
+        ))?));
+    }
+
+    let mut line = Zeroizing::new(String::new());
+    stdin.read_line(&mut line)?;
+    let trimmed_length = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(trimmed_length);
+
+    Ok(line)
+}
 
 pub(crate) fn print_disk_farm_info(directory: PathBuf, disk_farm_index: usize) {
     println!("Single disk farm {disk_farm_index}:");
@@ -25,3 +115,45 @@ pub(crate) fn print_disk_farm_info(directory: PathBuf, disk_farm_index: usize) {
         }
     }
 }
+
+pub(crate) fn derive_libp2p_keypair(schnorrkel_sk: &schnorrkel::SecretKey) -> Keypair {
+    let mut secret_bytes = Zeroizing::new(schnorrkel_sk.to_ed25519_bytes());
+
+    let keypair = ed25519::Keypair::from(
+        ed25519::SecretKey::try_from_bytes(&mut secret_bytes.as_mut()[..32])
+            .expect("Secret key is exactly 32 bytes in size; qed"),
+    );
+
+    Keypair::from(keypair)
+}
+
+/// Open or create libp2p keypair used for networking when identity is not available locally (held
+/// by remote signer)
+pub(crate) fn open_or_create_network_keypair(directory: &Path) -> anyhow::Result<Keypair> {
+    let keypair_file = directory.join(NETWORK_KEYPAIR_FILE);
+
+    if keypair_file.exists() {
+        let bytes = Zeroizing::new(fs::read(&keypair_file)?);
+        return Keypair::from_protobuf_encoding(&bytes).map_err(|error| {
+            anyhow!(
+                "Failed to decode network keypair at {}: {error}",
+                keypair_file.display()
+            )
+        });
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    let bytes = Zeroizing::new(
+        keypair
+            .to_protobuf_encoding()
+            .expect("Ed25519 keypair can always be encoded; qed"),
+    );
+    // Temporary file is only accessible by the current user and only moved into place once keypair
+    // is fully written
+    let mut temporary_file = NamedTempFile::new_in(directory)?;
+    temporary_file.write_all(&bytes)?;
+    temporary_file.as_file().sync_all()?;
+    temporary_file.persist(keypair_file)?;
+
+    Ok(keypair)
+}
@@ -0,0 +1,90 @@
+use crate::commands::shared::IdentitySecretArgs;
+use crate::utils::shutdown_signal;
+use anyhow::anyhow;
+use clap::Parser;
+use futures::{select, FutureExt};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use subspace_farmer::reward_signing::signer_server::{SignerPolicy, SignerServer};
+use subspace_farmer::Identity;
+use tracing::info;
+
+/// Arguments for signer
+#[derive(Debug, Parser)]
+pub(crate) struct SignerArgs {
+    /// Path of Unix socket to listen on, farmers connect to it with `--remote-signer`
+    #[arg(long, value_name = "SOCKET")]
+    socket: PathBuf,
+    /// Max number of signatures per identity per minute, requests above the limit are refused
+    #[arg(long, default_value_t = SignerPolicy::default().max_signatures_per_minute)]
+    max_signatures_per_minute: NonZeroU32,
+    /// Append audit log of all signing requests to specified file, one JSON object per line
+    #[arg(long, value_name = "PATH")]
+    audit_log: Option<PathBuf>,
+    /// Secret to unlock encrypted identities with
+    #[clap(flatten)]
+    identity_secret: IdentitySecretArgs,
+    /// One or more directories with identity file (for example farm directory where identity
+    /// was created originally), signer will only sign for these identities.
+    ///
+    /// Example:
+    ///   /path/to/directory
+    identities: Vec<PathBuf>,
+}
+
+pub(crate) async fn signer(signer_args: SignerArgs) -> anyhow::Result<()> {
+    let SignerArgs {
+        socket,
+        max_signatures_per_minute,
+        audit_log,
+        identity_secret,
+        identities,
+    } = signer_args;
+
+    if identities.is_empty() {
+        return Err(anyhow!(
+            "No identity was specified, so there is nothing to do"
+        ));
+    }
+
+    let identity_secret = identity_secret.secret()?;
+    let identities = identities
+        .iter()
+        .map(|directory| {
+            let identity = Identity::open_with_secret(directory, identity_secret.as_ref())
+                .map_err(|error| {
+                    anyhow!(
+                        "Failed to open identity at {}: {error}",
+                        directory.display()
+                    )
+                })?
+                .ok_or_else(|| anyhow!("No identity found at {}", directory.display()))?;
+
+            info!(
+                public_key = %hex::encode(identity.public_key().to_bytes()),
+                "Loaded identity"
+            );
+
+            Ok(identity)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let signer_server = SignerServer::new(
+        identities,
+        SignerPolicy {
+            max_signatures_per_minute,
+        },
+        audit_log.as_deref(),
+    )?;
+
+    select! {
+        result = signer_server.run(&socket).fuse() => {
+            result?;
+        }
+        _ = shutdown_signal().fuse() => {
+            info!("Exiting");
+        }
+    }
+
+    Ok(())
+}
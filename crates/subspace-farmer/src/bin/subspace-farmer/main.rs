@@ -3,7 +3,7 @@
 mod commands;
 mod utils;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use std::path::PathBuf;
 use std::{env, fs};
 use subspace_farmer::single_disk_farm::SingleDiskFarm;
@@ -24,9 +24,25 @@ type PosTable = ChiaTable;
 enum Command {
     /// Start a farmer, does plotting and farming
     Farm(commands::farm::FarmingArgs),
+    /// Decode and encode SS58 reward addresses, explaining why address is invalid if it is
+    #[clap(subcommand)]
+    Address(commands::address::AddressArgs),
     /// Run various benchmarks
     #[clap(subcommand)]
     Benchmark(commands::benchmark::BenchmarkArgs),
+    /// Check configuration file of `farm` command
+    #[clap(subcommand)]
+    Config(commands::config::ConfigArgs),
+    /// Manage identity of farms: show, export as mnemonic or import from mnemonic
+    #[clap(subcommand)]
+    Identity(commands::identity::IdentityArgs),
+    /// Add and remove farms of running farmer started with `--control-socket`
+    #[cfg(unix)]
+    FarmControl(commands::farm_control::FarmControlArgs),
+    /// Hold identities and sign rewards for farmers connected with `--remote-signer`, such that
+    /// farming hosts never hold key material
+    #[cfg(unix)]
+    Signer(commands::signer::SignerArgs),
     /// Print information about farm and its content
     Info {
         /// One or more farm located at specified path.
@@ -45,6 +61,9 @@ enum Command {
         /// Disable farm locking, for example if file system doesn't support it
         #[arg(long)]
         disable_farm_locking: bool,
+        /// Farms use remote signer, so identity file is not expected to exist
+        #[arg(long)]
+        remote_signer: bool,
     },
     /// Wipes the farm
     Wipe {
@@ -82,15 +101,36 @@ async fn main() -> anyhow::Result<()> {
         .init();
     utils::raise_fd_limit();
 
-    let command = Command::parse();
+    let matches = Command::command().get_matches();
+    let command = Command::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
 
     match command {
         Command::Farm(farming_args) => {
-            commands::farm::farm::<PosTable>(farming_args).await?;
+            let farm_matches = matches
+                .subcommand_matches("farm")
+                .expect("Farm command was parsed above; qed");
+            commands::farm::farm::<PosTable>(farming_args, farm_matches).await?;
+        }
+        Command::Address(address_args) => {
+            commands::address::address(address_args)?;
         }
         Command::Benchmark(benchmark_args) => {
             commands::benchmark::benchmark(benchmark_args)?;
         }
+        Command::Config(config_args) => {
+            commands::config::config(config_args)?;
+        }
+        Command::Identity(identity_args) => {
+            commands::identity::identity(identity_args)?;
+        }
+        #[cfg(unix)]
+        Command::FarmControl(farm_control_args) => {
+            commands::farm_control::farm_control(farm_control_args).await?;
+        }
+        #[cfg(unix)]
+        Command::Signer(signer_args) => {
+            commands::signer::signer(signer_args).await?;
+        }
         Command::Info { disk_farms } => {
             if disk_farms.is_empty() {
                 info!("No farm was specified, so there is nothing to do");
@@ -101,11 +141,12 @@ async fn main() -> anyhow::Result<()> {
         Command::Scrub {
             disk_farms,
             disable_farm_locking,
+            remote_signer,
         } => {
             if disk_farms.is_empty() {
                 info!("No farm was specified, so there is nothing to do");
             } else {
-                commands::scrub(&disk_farms, disable_farm_locking);
+                commands::scrub(&disk_farms, disable_farm_locking, remote_signer);
             }
         }
         Command::Wipe { disk_farms } => {
@@ -120,6 +161,10 @@ async fn main() -> anyhow::Result<()> {
                     info!("Wiping known addresses");
                     let _ = fs::remove_file(disk_farm.join("known_addresses.bin"));
                 }
+                if disk_farm.join("network_keypair.bin").exists() {
+                    info!("Wiping network keypair");
+                    let _ = fs::remove_file(disk_farm.join("network_keypair.bin"));
+                }
 
                 SingleDiskFarm::wipe(disk_farm)?;
             }
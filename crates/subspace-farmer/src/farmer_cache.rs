@@ -177,7 +177,7 @@ where
             WorkerCommand::ForgetKey { key } => {
                 let mut caches = self.caches.write();
 
-                for (disk_farm_index, cache) in caches.iter_mut().enumerate() {
+                for (cache_index, cache) in caches.iter_mut().enumerate() {
                     let Some(offset) = cache.stored_pieces.remove(&key) else {
                         // Not this disk farm
                         continue;
@@ -191,7 +191,7 @@ where
                         }
                         Ok(None) => {
                             warn!(
-                                %disk_farm_index,
+                                %cache_index,
                                 %offset,
                                 "Piece index out of range, this is likely an implementation bug, \
                                 not freeing heap element"
@@ -200,7 +200,7 @@ where
                         Err(error) => {
                             error!(
                                 %error,
-                                %disk_farm_index,
+                                %cache_index,
                                 ?key,
                                 %offset,
                                 "Error while reading piece from cache, might be a disk corruption"
@@ -442,7 +442,7 @@ where
             // Sort piece caches by number of stored pieces to fill those that are less
             // populated first
             sorted_caches.sort_by_key(|(_, cache)| cache.stored_pieces.len());
-            if !sorted_caches.into_iter().any(|(disk_farm_index, cache)| {
+            if !sorted_caches.into_iter().any(|(cache_index, cache)| {
                 let Some(offset) = cache.free_offsets.pop_front() else {
                     return false;
                 };
@@ -450,7 +450,7 @@ where
                 if let Err(error) = cache.backend.write_piece(offset, piece_index, &piece) {
                     error!(
                         %error,
-                        %disk_farm_index,
+                        %cache_index,
                         %piece_index,
                         %offset,
                         "Failed to write piece into cache"
@@ -681,7 +681,7 @@ where
         match worker_state.heap.insert(heap_key) {
             // Entry is already occupied, we need to find and replace old piece with new one
             Some(KeyWrapper(old_piece_index)) => {
-                for (disk_farm_index, cache) in caches.iter_mut().enumerate() {
+                for (cache_index, cache) in caches.iter_mut().enumerate() {
                     let old_record_key = RecordKey::from(old_piece_index.to_multihash());
                     let Some(offset) = cache.stored_pieces.remove(&old_record_key) else {
                         // Not this disk farm
@@ -691,14 +691,14 @@ where
                     if let Err(error) = cache.backend.write_piece(offset, piece_index, &piece) {
                         error!(
                             %error,
-                            %disk_farm_index,
+                            %cache_index,
                             %piece_index,
                             %offset,
                             "Failed to write piece into cache"
                         );
                     } else {
                         trace!(
-                            %disk_farm_index,
+                            %cache_index,
                             %old_piece_index,
                             %piece_index,
                             %offset,
@@ -722,7 +722,7 @@ where
                 // Sort piece caches by number of stored pieces to fill those that are less
                 // populated first
                 sorted_caches.sort_by_key(|(_, cache)| cache.stored_pieces.len());
-                for (disk_farm_index, cache) in sorted_caches {
+                for (cache_index, cache) in sorted_caches {
                     let Some(offset) = cache.free_offsets.pop_front() else {
                         // Not this disk farm
                         continue;
@@ -731,14 +731,14 @@ where
                     if let Err(error) = cache.backend.write_piece(offset, piece_index, &piece) {
                         error!(
                             %error,
-                            %disk_farm_index,
+                            %cache_index,
                             %piece_index,
                             %offset,
                             "Failed to write piece into cache"
                         );
                     } else {
                         trace!(
-                            %disk_farm_index,
+                            %cache_index,
                             %piece_index,
                             %offset,
                             "Successfully stored piece in cache"
@@ -816,7 +816,7 @@ impl FarmerCache {
             move || {
                 {
                     let piece_caches = piece_caches.read();
-                    for (disk_farm_index, cache) in piece_caches.iter().enumerate() {
+                    for (cache_index, cache) in piece_caches.iter().enumerate() {
                         let Some(&offset) = cache.stored_pieces.get(&key) else {
                             continue;
                         };
@@ -827,7 +827,7 @@ impl FarmerCache {
                             Err(error) => {
                                 error!(
                                     %error,
-                                    %disk_farm_index,
+                                    %cache_index,
                                     ?key,
                                     %offset,
                                     "Error while reading piece from cache, might be a disk corruption"
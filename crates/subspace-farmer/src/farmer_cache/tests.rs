@@ -54,6 +54,7 @@ impl NodeClient for MockNodeClient {
                 ),
                 min_sector_lifetime: HistorySize::from(NonZeroU64::new(4).unwrap()),
             },
+            ss58_format: None,
         })
     }
 
@@ -1,23 +1,164 @@
+#[cfg(test)]
+mod tests;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use bip39::Mnemonic;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{Key, KeyInit, XChaCha20Poly1305, XNonce};
 use parity_scale_codec::{Decode, Encode};
 use schnorrkel::context::SigningContext;
 use schnorrkel::{ExpansionMode, Keypair, PublicKey, SecretKey, Signature};
+use std::io::Write;
 use std::ops::Deref;
 use std::path::Path;
-use std::{fs, io};
+use std::{fmt, fs, io};
 use subspace_core_primitives::REWARD_SIGNING_CONTEXT;
 use substrate_bip39::mini_secret_from_entropy;
+use tempfile::NamedTempFile;
 use thiserror::Error;
 use tracing::debug;
 use zeroize::Zeroizing;
 
 /// Entropy used for identity generation.
 const ENTROPY_LENGTH: usize = 32;
+/// Magic bytes at the beginning of encrypted identity file, plaintext identity file never starts
+/// with them.
+const ENCRYPTED_IDENTITY_MAGIC: [u8; 4] = *b"SSID";
+/// Current version of encrypted identity file format.
+const ENCRYPTED_IDENTITY_VERSION: u8 = 1;
+/// Argon2 memory cost in KiB for newly encrypted identities.
+const ARGON2_M_COST: u32 = 64 * 1024;
+/// Argon2 number of iterations for newly encrypted identities.
+const ARGON2_T_COST: u32 = 3;
+/// Argon2 degree of parallelism for newly encrypted identities.
+const ARGON2_P_COST: u32 = 1;
+/// Maximum Argon2 memory cost in KiB accepted when unlocking identity, such that corrupted or
+/// malicious identity file can't exhaust memory.
+const MAX_ARGON2_M_COST: u32 = 1024 * 1024;
+/// Maximum Argon2 number of iterations accepted when unlocking identity.
+const MAX_ARGON2_T_COST: u32 = 64;
+/// Maximum Argon2 degree of parallelism accepted when unlocking identity.
+const MAX_ARGON2_P_COST: u32 = 16;
+/// Size of Poly1305 authentication tag appended to ciphertext.
+const TAG_SIZE: usize = 16;
 
 #[derive(Debug, Encode, Decode)]
 struct IdentityFileContents {
     entropy: Vec<u8>,
 }
 
+/// Header of encrypted identity file that follows magic bytes and version, authenticated
+/// (though not encrypted) together with encrypted contents.
+#[derive(Debug, Encode, Decode)]
+struct EncryptedIdentityHeader {
+    /// Public key is not secret, storing it allows to check identity without unlocking it
+    public_key: [u8; 32],
+    argon2_m_cost: u32,
+    argon2_t_cost: u32,
+    argon2_p_cost: u32,
+    salt: [u8; 16],
+    nonce: [u8; 24],
+}
+
+impl EncryptedIdentityHeader {
+    fn derive_key(&self, secret: &IdentitySecret) -> Result<Zeroizing<[u8; 32]>, IdentityError> {
+        let params = Params::new(
+            self.argon2_m_cost,
+            self.argon2_t_cost,
+            self.argon2_p_cost,
+            Some(32),
+        )?;
+        let mut key = Zeroizing::new([0; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+            &secret.0,
+            &self.salt,
+            key.as_mut(),
+        )?;
+
+        Ok(key)
+    }
+}
+
+enum IdentityFile {
+    Plaintext(IdentityFileContents),
+    Encrypted {
+        header: EncryptedIdentityHeader,
+        /// Magic bytes, version and header
+        associated_data: Vec<u8>,
+        ciphertext: Vec<u8>,
+    },
+}
+
+impl IdentityFile {
+    fn read(path: &Path) -> Result<Option<Self>, IdentityError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes = Zeroizing::new(fs::read(path)?);
+        let Some(remaining_bytes) = bytes.strip_prefix(&ENCRYPTED_IDENTITY_MAGIC) else {
+            return Ok(Some(Self::Plaintext(IdentityFileContents::decode(
+                &mut bytes.as_ref(),
+            )?)));
+        };
+
+        let (&version, mut remaining_bytes) = remaining_bytes
+            .split_first()
+            .ok_or(parity_scale_codec::Error::from("Version is missing"))?;
+        if version != ENCRYPTED_IDENTITY_VERSION {
+            return Err(IdentityError::UnsupportedVersion(version));
+        }
+        let header = EncryptedIdentityHeader::decode(&mut remaining_bytes)?;
+        if header.argon2_m_cost > MAX_ARGON2_M_COST
+            || header.argon2_t_cost > MAX_ARGON2_T_COST
+            || header.argon2_p_cost > MAX_ARGON2_P_COST
+        {
+            return Err(IdentityError::UnsupportedKeyDerivationParameters {
+                m_cost: header.argon2_m_cost,
+                t_cost: header.argon2_t_cost,
+                p_cost: header.argon2_p_cost,
+            });
+        }
+        let header_end = bytes.len() - remaining_bytes.len();
+
+        Ok(Some(Self::Encrypted {
+            header,
+            associated_data: bytes[..header_end].to_vec(),
+            ciphertext: remaining_bytes.to_vec(),
+        }))
+    }
+}
+
+/// Secret used to encrypt and decrypt identity file: passphrase or contents of a keyfile.
+#[derive(Clone)]
+pub struct IdentitySecret(Zeroizing<Vec<u8>>);
+
+impl fmt::Debug for IdentitySecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IdentitySecret").finish_non_exhaustive()
+    }
+}
+
+impl IdentitySecret {
+    /// Create secret from passphrase
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self(Zeroizing::new(passphrase.as_bytes().to_vec()))
+    }
+
+    /// Create secret from contents of keyfile
+    pub fn from_keyfile<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let contents = Zeroizing::new(fs::read(path)?);
+        if contents.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Keyfile is empty",
+            ));
+        }
+
+        Ok(Self(contents))
+    }
+}
+
 fn keypair_from_entropy(entropy: &[u8]) -> Keypair {
     mini_secret_from_entropy(entropy, "")
         .expect("32 bytes can always build a key; qed")
@@ -33,6 +174,34 @@ pub enum IdentityError {
     /// Decoding error
     #[error("Decoding error: {0}")]
     Decoding(#[from] parity_scale_codec::Error),
+    /// Invalid mnemonic
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(#[from] bip39::Error),
+    /// Identity is encrypted and can't be opened without secret
+    #[error("Identity is encrypted, passphrase or keyfile is required to unlock it")]
+    Locked,
+    /// Wrong secret or corrupted identity file
+    #[error("Failed to decrypt identity, wrong passphrase/keyfile or corrupted identity file")]
+    WrongSecret,
+    /// Unsupported version of encrypted identity file
+    #[error("Unsupported encrypted identity file version {0}")]
+    UnsupportedVersion(u8),
+    /// Key derivation parameters of encrypted identity file exceed supported maximum
+    #[error(
+        "Unsupported key derivation parameters (memory {m_cost} KiB, {t_cost} iterations, \
+        parallelism {p_cost})"
+    )]
+    UnsupportedKeyDerivationParameters {
+        /// Memory cost in KiB
+        m_cost: u32,
+        /// Number of iterations
+        t_cost: u32,
+        /// Degree of parallelism
+        p_cost: u32,
+    },
+    /// Key derivation error
+    #[error("Key derivation error: {0}")]
+    KeyDerivation(#[from] argon2::Error),
 }
 
 /// `Identity` struct is an abstraction of public & secret key related operations.
@@ -58,59 +227,151 @@ impl Deref for Identity {
 impl Identity {
     pub(crate) const FILE_NAME: &'static str = "identity.bin";
 
-    /// Size of the identity file on disk
+    /// Size of the identity file on disk, encrypted identity file is larger than plaintext, so its
+    /// size is returned
     pub fn file_size() -> usize {
-        IdentityFileContents {
+        let contents_size = IdentityFileContents {
             entropy: vec![0; ENTROPY_LENGTH],
         }
-        .encoded_size()
+        .encoded_size();
+        let header_size = EncryptedIdentityHeader {
+            public_key: [0; 32],
+            argon2_m_cost: 0,
+            argon2_t_cost: 0,
+            argon2_p_cost: 0,
+            salt: [0; 16],
+            nonce: [0; 24],
+        }
+        .encoded_size();
+
+        ENCRYPTED_IDENTITY_MAGIC.len() + 1 + header_size + contents_size + TAG_SIZE
     }
 
     /// Opens the existing identity, or creates a new one.
     pub fn open_or_create<B: AsRef<Path>>(base_directory: B) -> Result<Self, IdentityError> {
-        if let Some(identity) = Self::open(base_directory.as_ref())? {
+        Self::open_or_create_with_secret(base_directory, None)
+    }
+
+    /// Opens the existing identity, or creates a new one, encrypted with `secret` if provided.
+    pub fn open_or_create_with_secret<B: AsRef<Path>>(
+        base_directory: B,
+        secret: Option<&IdentitySecret>,
+    ) -> Result<Self, IdentityError> {
+        if let Some(identity) = Self::open_with_secret(base_directory.as_ref(), secret)? {
             Ok(identity)
         } else {
-            Self::create(base_directory)
+            debug!("Generating new keypair");
+            let entropy = rand::random::<[u8; ENTROPY_LENGTH]>().to_vec();
+
+            let identity = Self::new(entropy);
+            identity.write_to(base_directory, secret)?;
+
+            Ok(identity)
         }
     }
 
     /// Opens the existing identity, returns `Ok(None)` if it doesn't exist.
+    ///
+    /// Returns [`IdentityError::Locked`] if identity is encrypted.
     pub fn open<B: AsRef<Path>>(base_directory: B) -> Result<Option<Self>, IdentityError> {
+        Self::open_with_secret(base_directory, None)
+    }
+
+    /// Opens the existing identity, unlocking it with `secret` if it is encrypted, returns
+    /// `Ok(None)` if it doesn't exist.
+    pub fn open_with_secret<B: AsRef<Path>>(
+        base_directory: B,
+        secret: Option<&IdentitySecret>,
+    ) -> Result<Option<Self>, IdentityError> {
         let identity_file = base_directory.as_ref().join(Self::FILE_NAME);
-        if identity_file.exists() {
-            debug!("Opening existing keypair");
-            let bytes = Zeroizing::new(fs::read(identity_file)?);
-            let IdentityFileContents { entropy } =
-                IdentityFileContents::decode(&mut bytes.as_ref())?;
-
-            Ok(Some(Self {
-                keypair: Zeroizing::new(keypair_from_entropy(&entropy)),
-                entropy: Zeroizing::new(entropy),
-                substrate_ctx: schnorrkel::context::signing_context(REWARD_SIGNING_CONTEXT),
-            }))
-        } else {
+        let Some(identity_file) = IdentityFile::read(&identity_file)? else {
             debug!("Existing keypair not found");
-            Ok(None)
-        }
+            return Ok(None);
+        };
+
+        debug!("Opening existing keypair");
+        let IdentityFileContents { entropy } = match identity_file {
+            IdentityFile::Plaintext(identity_file_contents) => identity_file_contents,
+            IdentityFile::Encrypted {
+                header,
+                associated_data,
+                ciphertext,
+            } => {
+                let secret = secret.ok_or(IdentityError::Locked)?;
+                let key = header.derive_key(secret)?;
+                let plaintext = Zeroizing::new(
+                    XChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+                        .decrypt(
+                            XNonce::from_slice(&header.nonce),
+                            Payload {
+                                msg: &ciphertext,
+                                aad: &associated_data,
+                            },
+                        )
+                        .map_err(|_error| IdentityError::WrongSecret)?,
+                );
+
+                IdentityFileContents::decode(&mut plaintext.as_slice())?
+            }
+        };
+
+        Ok(Some(Self::new(entropy)))
+    }
+
+    /// Reads public key of the existing identity without unlocking it, returns `Ok(None)` if
+    /// identity doesn't exist.
+    pub fn read_public_key<B: AsRef<Path>>(
+        base_directory: B,
+    ) -> Result<Option<PublicKey>, IdentityError> {
+        let identity_file = base_directory.as_ref().join(Self::FILE_NAME);
+        let public_key = match IdentityFile::read(&identity_file)? {
+            Some(IdentityFile::Plaintext(IdentityFileContents { entropy })) => {
+                Self::new(entropy).keypair.public
+            }
+            Some(IdentityFile::Encrypted { header, .. }) => {
+                PublicKey::from_bytes(&header.public_key)
+                    .map_err(|_error| parity_scale_codec::Error::from("Invalid public key"))?
+            }
+            None => {
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(public_key))
     }
 
     /// Creates new identity, overrides identity that might already exist.
     pub fn create<B: AsRef<Path>>(base_directory: B) -> Result<Self, IdentityError> {
-        let identity_file = base_directory.as_ref().join(Self::FILE_NAME);
         debug!("Generating new keypair");
         let entropy = rand::random::<[u8; ENTROPY_LENGTH]>().to_vec();
 
-        let identity_file_contents = IdentityFileContents { entropy };
-        fs::write(identity_file, identity_file_contents.encode())?;
+        let identity = Self::new(entropy);
+        identity.write_to(base_directory, None)?;
 
-        let IdentityFileContents { entropy } = identity_file_contents;
+        Ok(identity)
+    }
 
-        Ok(Self {
-            keypair: Zeroizing::new(keypair_from_entropy(&entropy)),
-            entropy: Zeroizing::new(entropy),
-            substrate_ctx: schnorrkel::context::signing_context(REWARD_SIGNING_CONTEXT),
-        })
+    /// Create identity from BIP39 mnemonic phrase, overrides identity that might already exist.
+    ///
+    /// Mnemonic of any standard length is accepted, though 24 words are required to restore
+    /// identity created with [`Self::create()`].
+    pub fn from_mnemonic<B: AsRef<Path>>(
+        base_directory: B,
+        phrase: &str,
+    ) -> Result<Self, IdentityError> {
+        let identity = Self::parse_mnemonic(phrase)?;
+        identity.write_to(base_directory, None)?;
+
+        Ok(identity)
+    }
+
+    /// Restore identity from BIP39 mnemonic phrase in memory, nothing is written to disk until
+    /// [`Self::write_to()`] is called.
+    pub fn parse_mnemonic(phrase: &str) -> Result<Self, IdentityError> {
+        debug!("Creating identity from provided mnemonic");
+        let mnemonic = Mnemonic::parse(phrase)?;
+
+        Ok(Self::new(mnemonic.to_entropy()))
     }
 
     /// Create identity from given entropy, overrides identity that might already exist.
@@ -121,19 +382,82 @@ impl Identity {
         base_directory: B,
         entropy: Vec<u8>,
     ) -> Result<Self, IdentityError> {
-        let identity_file = base_directory.as_ref().join(Self::FILE_NAME);
         debug!("Creating identity from provided entropy");
 
-        let identity_file_contents = IdentityFileContents { entropy };
-        fs::write(identity_file, identity_file_contents.encode())?;
+        let identity = Self::new(entropy);
+        identity.write_to(base_directory, None)?;
 
-        let IdentityFileContents { entropy } = identity_file_contents;
+        Ok(identity)
+    }
 
-        Ok(Self {
+    fn new(entropy: Vec<u8>) -> Self {
+        Self {
             keypair: Zeroizing::new(keypair_from_entropy(&entropy)),
             entropy: Zeroizing::new(entropy),
             substrate_ctx: schnorrkel::context::signing_context(REWARD_SIGNING_CONTEXT),
-        })
+        }
+    }
+
+    /// Write identity into directory (for instance another farm that should share the same
+    /// identity or to encrypt plaintext identity), overrides identity that might already exist
+    /// there.
+    ///
+    /// Identity is encrypted with `secret` if provided, otherwise it is stored in plaintext.
+    pub fn write_to<B: AsRef<Path>>(
+        &self,
+        base_directory: B,
+        secret: Option<&IdentitySecret>,
+    ) -> Result<(), IdentityError> {
+        let identity_file = base_directory.as_ref().join(Self::FILE_NAME);
+
+        let identity_file_contents = Zeroizing::new(
+            IdentityFileContents {
+                entropy: self.entropy.to_vec(),
+            }
+            .encode(),
+        );
+        let bytes = match secret {
+            Some(secret) => {
+                let header = EncryptedIdentityHeader {
+                    public_key: self.keypair.public.to_bytes(),
+                    argon2_m_cost: ARGON2_M_COST,
+                    argon2_t_cost: ARGON2_T_COST,
+                    argon2_p_cost: ARGON2_P_COST,
+                    salt: rand::random(),
+                    nonce: rand::random(),
+                };
+                let key = header.derive_key(secret)?;
+
+                let mut bytes = ENCRYPTED_IDENTITY_MAGIC.to_vec();
+                bytes.push(ENCRYPTED_IDENTITY_VERSION);
+                header.encode_to(&mut bytes);
+                let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+                    .encrypt(
+                        XNonce::from_slice(&header.nonce),
+                        Payload {
+                            msg: &identity_file_contents,
+                            aad: &bytes,
+                        },
+                    )
+                    .expect("Identity is tiny and can always be encrypted; qed");
+                bytes.extend_from_slice(&ciphertext);
+
+                Zeroizing::new(bytes)
+            }
+            None => identity_file_contents,
+        };
+
+        // Temporary file is only accessible by the current user and only moved into place once
+        // identity is fully written, such that existing identity is never lost due to partially
+        // written file
+        let mut temporary_file = NamedTempFile::new_in(base_directory)?;
+        temporary_file.write_all(&bytes)?;
+        temporary_file.as_file().sync_all()?;
+        temporary_file
+            .persist(identity_file)
+            .map_err(|error| error.error)?;
+
+        Ok(())
     }
 
     /// Returns the public key of the identity.
@@ -151,6 +475,16 @@ impl Identity {
         &self.entropy
     }
 
+    /// Returns BIP39 mnemonic phrase that can be used to restore identity with
+    /// [`Self::from_mnemonic()`].
+    pub fn mnemonic(&self) -> Zeroizing<String> {
+        Zeroizing::new(
+            Mnemonic::from_entropy(&self.entropy)
+                .expect("Entropy of identity is always of valid length; qed")
+                .to_string(),
+        )
+    }
+
     /// Sign reward hash.
     pub fn sign_reward_hash(&self, header_hash: &[u8]) -> Signature {
         self.keypair.sign(self.substrate_ctx.bytes(header_hash))
@@ -0,0 +1,118 @@
+use crate::identity::{Identity, IdentityError, IdentitySecret, ENCRYPTED_IDENTITY_MAGIC};
+use std::fs;
+use tempfile::tempdir;
+
+/// Public key goes right after magic bytes and version
+const ENCRYPTED_HEADER_PUBLIC_KEY_OFFSET: usize = ENCRYPTED_IDENTITY_MAGIC.len() + 1;
+/// Argon2 memory cost goes right after public key
+const ENCRYPTED_HEADER_ARGON2_M_COST_OFFSET: usize = ENCRYPTED_HEADER_PUBLIC_KEY_OFFSET + 32;
+
+#[test]
+fn mnemonic_roundtrip() {
+    let original_directory = tempdir().unwrap();
+    let restored_directory = tempdir().unwrap();
+
+    let identity = Identity::create(original_directory.as_ref()).unwrap();
+    let mnemonic = identity.mnemonic();
+    assert_eq!(mnemonic.split_whitespace().count(), 24);
+
+    let restored_identity =
+        Identity::from_mnemonic(restored_directory.as_ref(), &mnemonic).unwrap();
+    assert_eq!(restored_identity.public_key(), identity.public_key());
+    assert_eq!(restored_identity.entropy(), identity.entropy());
+
+    // Restored identity is persisted
+    let opened_identity = Identity::open(restored_directory.as_ref())
+        .unwrap()
+        .unwrap();
+    assert_eq!(opened_identity.public_key(), identity.public_key());
+
+    assert!(matches!(
+        Identity::from_mnemonic(restored_directory.as_ref(), "not a valid mnemonic"),
+        Err(IdentityError::InvalidMnemonic(_))
+    ));
+}
+
+#[test]
+fn shared_identity() {
+    let first_directory = tempdir().unwrap();
+    let second_directory = tempdir().unwrap();
+
+    let identity = Identity::create(first_directory.as_ref()).unwrap();
+    identity.write_to(second_directory.as_ref(), None).unwrap();
+
+    let shared_identity = Identity::open_or_create(second_directory.as_ref()).unwrap();
+    assert_eq!(shared_identity.public_key(), identity.public_key());
+}
+
+#[test]
+fn encrypted_identity() {
+    let directory = tempdir().unwrap();
+    let secret = IdentitySecret::from_passphrase("correct horse battery staple");
+    let wrong_secret = IdentitySecret::from_passphrase("wrong");
+
+    let identity = Identity::create(directory.as_ref()).unwrap();
+    identity
+        .write_to(directory.as_ref(), Some(&secret))
+        .unwrap();
+
+    // Public key is available without unlocking
+    assert_eq!(
+        Identity::read_public_key(directory.as_ref()).unwrap(),
+        Some(*identity.public_key())
+    );
+
+    assert!(matches!(
+        Identity::open(directory.as_ref()),
+        Err(IdentityError::Locked)
+    ));
+    assert!(matches!(
+        Identity::open_with_secret(directory.as_ref(), Some(&wrong_secret)),
+        Err(IdentityError::WrongSecret)
+    ));
+
+    let unlocked_identity = Identity::open_with_secret(directory.as_ref(), Some(&secret))
+        .unwrap()
+        .unwrap();
+    assert_eq!(unlocked_identity.public_key(), identity.public_key());
+    assert_eq!(unlocked_identity.entropy(), identity.entropy());
+
+    // Encrypted identity is accounted for in space usage
+    let identity_file = directory.as_ref().join(Identity::FILE_NAME);
+    let original_bytes = fs::read(&identity_file).unwrap();
+    assert_eq!(original_bytes.len(), Identity::file_size());
+
+    // Tampering with public key in the header is detected
+    let mut bytes = original_bytes.clone();
+    bytes[ENCRYPTED_HEADER_PUBLIC_KEY_OFFSET] ^= 1;
+    fs::write(&identity_file, bytes).unwrap();
+    assert!(matches!(
+        Identity::open_with_secret(directory.as_ref(), Some(&secret)),
+        Err(IdentityError::WrongSecret)
+    ));
+
+    // Excessive key derivation parameters are rejected before deriving the key
+    let mut bytes = original_bytes;
+    bytes[ENCRYPTED_HEADER_ARGON2_M_COST_OFFSET..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write(&identity_file, bytes).unwrap();
+    assert!(matches!(
+        Identity::open_with_secret(directory.as_ref(), Some(&secret)),
+        Err(IdentityError::UnsupportedKeyDerivationParameters { .. })
+    ));
+}
+
+#[test]
+fn new_identity_encrypted() {
+    let directory = tempdir().unwrap();
+    let secret = IdentitySecret::from_passphrase("passphrase");
+
+    let identity = Identity::open_or_create_with_secret(directory.as_ref(), Some(&secret)).unwrap();
+
+    assert!(matches!(
+        Identity::open(directory.as_ref()),
+        Err(IdentityError::Locked)
+    ));
+    let opened_identity =
+        Identity::open_or_create_with_secret(directory.as_ref(), Some(&secret)).unwrap();
+    assert_eq!(opened_identity.public_key(), identity.public_key());
+}
@@ -47,7 +47,7 @@ pub mod utils;
 /// Size of the LRU cache for peers.
 pub const KNOWN_PEERS_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(100).expect("Not zero; qed");
 
-pub use identity::Identity;
+pub use identity::{Identity, IdentityError, IdentitySecret};
 pub use jsonrpsee;
 pub use node_client::node_rpc_client::NodeRpcClient;
 pub use node_client::{Error as RpcClientError, NodeClient};
@@ -1,13 +1,93 @@
-use crate::identity::Identity;
+pub mod local;
+#[cfg(unix)]
+pub mod remote;
+#[cfg(unix)]
+pub mod signer_server;
+#[cfg(all(test, unix))]
+mod tests;
+
 use crate::node_client::NodeClient;
+use async_trait::async_trait;
 use futures::StreamExt;
+use parity_scale_codec::{Decode, Encode};
 use std::future::Future;
+use std::sync::Arc;
+use std::{fmt, io};
+use subspace_core_primitives::{Blake3Hash, PublicKey, RewardSignature};
 use subspace_rpc_primitives::{RewardSignatureResponse, RewardSigningInfo};
+use thiserror::Error;
 use tracing::{info, warn};
 
+/// Errors happening during reward signing
+#[derive(Debug, Error)]
+pub enum RewardSignerError {
+    /// I/O error occurred
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// Decoding error
+    #[error("Decoding error: {0}")]
+    Decoding(#[from] parity_scale_codec::Error),
+    /// Signer refused to sign
+    #[error("Signer refused to sign: {0}")]
+    Rejected(#[from] SigningRejection),
+    /// Unexpected response from signer
+    #[error("Unexpected response from signer")]
+    UnexpectedResponse,
+    /// Signer didn't respond in time
+    #[error("Signer didn't respond in time")]
+    RequestTimeout,
+    /// All identities signer holds are already used by other farms
+    #[error(
+        "All {public_keys} identities of signer are already used by other farms, add a new \
+        identity to signer to create another farm"
+    )]
+    NoUnusedPublicKey {
+        /// Number of identities signer holds
+        public_keys: usize,
+    },
+}
+
+/// Reason for signer to refuse signing
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Encode, Decode, Error)]
+pub enum SigningRejection {
+    /// Signer doesn't know requested public key
+    #[error("Unknown public key")]
+    UnknownPublicKey,
+    /// Too many signing requests for this public key
+    #[error("Rate limited")]
+    RateLimited,
+}
+
+/// Abstraction of the reward signer that holds key material, which can live in the same process
+/// as farmer or somewhere else
+#[async_trait]
+pub trait RewardSigner: fmt::Debug + Send + Sync + 'static {
+    /// Public key that signer signs rewards with
+    fn public_key(&self) -> PublicKey;
+
+    /// Sign reward hash
+    async fn sign_reward_hash(
+        &self,
+        hash: Blake3Hash,
+    ) -> Result<RewardSignature, RewardSignerError>;
+}
+
+/// Provider of reward signers for farms that don't hold identity in farm directory.
+///
+/// Signers are shared by all farms with the same public key, so reward signing is done by the owner
+/// of the provider once per public key rather than by every farm.
+pub trait RewardSignerProvider: fmt::Debug + Send + Sync + 'static {
+    /// Reward signer for farm that was created with `public_key`, or for a new farm if `None`, in
+    /// which case public key not used by other farms is picked
+    fn reward_signer(
+        &self,
+        public_key: Option<&PublicKey>,
+    ) -> Result<Arc<dyn RewardSigner>, RewardSignerError>;
+}
+
 pub async fn reward_signing<NC>(
     node_client: NC,
-    identity: Identity,
+    reward_signer: Arc<dyn RewardSigner>,
 ) -> Result<impl Future<Output = ()>, Box<dyn std::error::Error + Send + Sync>>
 where
     NC: NodeClient,
@@ -15,22 +95,35 @@ where
     info!("Subscribing to reward signing notifications");
 
     let mut reward_signing_info_notifications = node_client.subscribe_reward_signing().await?;
+    let public_key = reward_signer.public_key();
 
     let reward_signing_fut = async move {
-        while let Some(RewardSigningInfo { hash, public_key }) =
-            reward_signing_info_notifications.next().await
+        while let Some(RewardSigningInfo {
+            hash,
+            public_key: requested_public_key,
+        }) = reward_signing_info_notifications.next().await
         {
             // Multiple plots might have solved, only sign with correct one
-            if identity.public_key().to_bytes() != public_key {
+            if *public_key != requested_public_key {
                 continue;
             }
 
-            let signature = identity.sign_reward_hash(&hash);
+            let signature = match reward_signer.sign_reward_hash(hash).await {
+                Ok(signature) => signature,
+                Err(error) => {
+                    warn!(
+                        %error,
+                        "Failed to sign reward hash 0x{}",
+                        hex::encode(hash),
+                    );
+                    continue;
+                }
+            };
 
             match node_client
                 .submit_reward_signature(RewardSignatureResponse {
                     hash,
-                    signature: Some(signature.to_bytes().into()),
+                    signature: Some(signature),
                 })
                 .await
             {
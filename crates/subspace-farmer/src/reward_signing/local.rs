@@ -0,0 +1,43 @@
+//! Reward signer that holds identity in the same process as farmer
+
+use crate::identity::Identity;
+use crate::reward_signing::{RewardSigner, RewardSignerError};
+use async_trait::async_trait;
+use std::fmt;
+use subspace_core_primitives::{Blake3Hash, PublicKey, RewardSignature};
+
+/// Reward signer that signs with identity loaded in-process
+pub struct LocalRewardSigner {
+    identity: Identity,
+}
+
+impl fmt::Debug for LocalRewardSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalRewardSigner")
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LocalRewardSigner {
+    /// Create new instance
+    pub fn new(identity: Identity) -> Self {
+        Self { identity }
+    }
+}
+
+#[async_trait]
+impl RewardSigner for LocalRewardSigner {
+    fn public_key(&self) -> PublicKey {
+        PublicKey::from(self.identity.public_key().to_bytes())
+    }
+
+    async fn sign_reward_hash(
+        &self,
+        hash: Blake3Hash,
+    ) -> Result<RewardSignature, RewardSignerError> {
+        Ok(RewardSignature::from(
+            self.identity.sign_reward_hash(&hash).to_bytes(),
+        ))
+    }
+}
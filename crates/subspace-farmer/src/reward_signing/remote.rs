@@ -0,0 +1,263 @@
+//! Reward signer that forwards signing requests to a separate signer process over Unix socket, such
+//! that farmer itself never holds key material
+
+use crate::reward_signing::{
+    RewardSigner, RewardSignerError, RewardSignerProvider, SigningRejection,
+};
+use async_lock::Mutex as AsyncMutex;
+use async_trait::async_trait;
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use subspace_core_primitives::{Blake3Hash, PublicKey, RewardSignature};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tracing::debug;
+
+/// Max size of a single message in either direction
+const MAX_MESSAGE_SIZE: u32 = 64 * 1024;
+/// Timeout of a single request to signer, such that farming doesn't get stuck if signer hangs
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Request sent by farmer to signer
+#[derive(Debug, Encode, Decode)]
+pub(super) enum SignerRequest {
+    /// Request public keys signer holds identities for
+    PublicKeys,
+    /// Request signature of reward hash
+    SignRewardHash {
+        /// Public key that should sign the hash
+        public_key: PublicKey,
+        /// Hash to sign
+        hash: Blake3Hash,
+    },
+}
+
+/// Response sent by signer to farmer
+#[derive(Debug, Encode, Decode)]
+pub(super) enum SignerResponse {
+    /// Public keys signer holds identities for
+    PublicKeys(Vec<PublicKey>),
+    /// Reward signature
+    Signature(RewardSignature),
+    /// Signer refused to sign
+    Rejected(SigningRejection),
+}
+
+/// Write length-prefixed SCALE-encoded message
+pub(super) async fn write_message<W, M>(writer: &mut W, message: &M) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    M: Encode,
+{
+    let encoded = message.encode();
+    writer.write_u32_le(encoded.len() as u32).await?;
+    writer.write_all(&encoded).await?;
+    writer.flush().await
+}
+
+/// Read length-prefixed SCALE-encoded message
+pub(super) async fn read_message<R, M>(reader: &mut R) -> Result<M, RewardSignerError>
+where
+    R: AsyncRead + Unpin,
+    M: Decode,
+{
+    let length = reader.read_u32_le().await?;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(RewardSignerError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message size {length} exceeds limit of {MAX_MESSAGE_SIZE} bytes"),
+        )));
+    }
+
+    let mut buffer = vec![0; length as usize];
+    reader.read_exact(&mut buffer).await?;
+
+    Ok(M::decode(&mut buffer.as_slice())?)
+}
+
+/// Connection to signer shared by reward signers of all identities signer holds
+#[derive(Debug)]
+struct SignerConnection {
+    socket_path: PathBuf,
+    connection: AsyncMutex<Option<UnixStream>>,
+}
+
+impl SignerConnection {
+    async fn request(&self, request: &SignerRequest) -> Result<SignerResponse, RewardSignerError> {
+        let mut connection = self.connection.lock().await;
+
+        // Signer might have been restarted since last request, so try to reconnect once
+        for attempt in 0..2 {
+            let result = tokio::time::timeout(REQUEST_TIMEOUT, async {
+                let stream = match connection.as_mut() {
+                    Some(stream) => stream,
+                    None => connection.insert(UnixStream::connect(&self.socket_path).await?),
+                };
+
+                write_message(stream, request).await?;
+                read_message(stream).await
+            })
+            .await
+            .unwrap_or(Err(RewardSignerError::RequestTimeout));
+
+            match result {
+                Ok(response) => {
+                    return Ok(response);
+                }
+                Err(RewardSignerError::Io(error)) if attempt == 0 => {
+                    debug!(%error, "Signer connection failed, reconnecting");
+                    connection.take();
+                }
+                Err(error) => {
+                    // Connection might be in the middle of a message, so it can't be reused
+                    connection.take();
+                    return Err(error);
+                }
+            }
+        }
+
+        unreachable!("Last attempt always returns; qed")
+    }
+}
+
+/// Reward signer that forwards signing requests for one of the identities to
+/// `subspace-farmer signer` process
+#[derive(Debug)]
+pub struct RemoteRewardSigner {
+    public_key: PublicKey,
+    connection: Arc<SignerConnection>,
+}
+
+#[async_trait]
+impl RewardSigner for RemoteRewardSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    async fn sign_reward_hash(
+        &self,
+        hash: Blake3Hash,
+    ) -> Result<RewardSignature, RewardSignerError> {
+        let request = SignerRequest::SignRewardHash {
+            public_key: self.public_key,
+            hash,
+        };
+
+        match self.connection.request(&request).await? {
+            SignerResponse::Signature(signature) => Ok(signature),
+            SignerResponse::Rejected(rejection) => Err(rejection.into()),
+            SignerResponse::PublicKeys(_) => Err(RewardSignerError::UnexpectedResponse),
+        }
+    }
+}
+
+/// Client of `subspace-farmer signer` process that holds identities of farms.
+///
+/// Each farm is signed for with identity it was created with, new farms get identity that is not
+/// used by any other farm yet.
+#[derive(Debug)]
+pub struct RemoteSigner {
+    reward_signers: Vec<Arc<RemoteRewardSigner>>,
+    used_public_keys: Mutex<HashSet<PublicKey>>,
+}
+
+impl RewardSignerProvider for RemoteSigner {
+    fn reward_signer(
+        &self,
+        public_key: Option<&PublicKey>,
+    ) -> Result<Arc<dyn RewardSigner>, RewardSignerError> {
+        let mut used_public_keys = self.used_public_keys.lock();
+        let reward_signer = match public_key {
+            Some(public_key) => self
+                .reward_signers
+                .iter()
+                .find(|reward_signer| &reward_signer.public_key == public_key)
+                .ok_or(SigningRejection::UnknownPublicKey)?,
+            None => self
+                .reward_signers
+                .iter()
+                .find(|reward_signer| !used_public_keys.contains(&reward_signer.public_key))
+                .ok_or(RewardSignerError::NoUnusedPublicKey {
+                    public_keys: self.reward_signers.len(),
+                })?,
+        };
+        used_public_keys.insert(reward_signer.public_key);
+
+        Ok(Arc::clone(reward_signer) as Arc<dyn RewardSigner>)
+    }
+}
+
+impl RemoteSigner {
+    /// Connect to signer listening on specified socket.
+    ///
+    /// Only identities with specified public keys are used, all identities signer holds are used
+    /// if `public_keys` is empty.
+    pub async fn connect(
+        socket_path: PathBuf,
+        public_keys: &[PublicKey],
+    ) -> Result<Self, RewardSignerError> {
+        let connection = Arc::new(SignerConnection {
+            socket_path,
+            connection: AsyncMutex::new(None),
+        });
+
+        let signer_public_keys = match connection.request(&SignerRequest::PublicKeys).await? {
+            SignerResponse::PublicKeys(public_keys) => public_keys,
+            SignerResponse::Signature(_) | SignerResponse::Rejected(_) => {
+                return Err(RewardSignerError::UnexpectedResponse);
+            }
+        };
+
+        let public_keys = if public_keys.is_empty() {
+            signer_public_keys
+        } else {
+            if !public_keys
+                .iter()
+                .all(|public_key| signer_public_keys.contains(public_key))
+            {
+                return Err(SigningRejection::UnknownPublicKey.into());
+            }
+
+            public_keys.to_vec()
+        };
+
+        let reward_signers = public_keys
+            .into_iter()
+            .map(|public_key| {
+                Arc::new(RemoteRewardSigner {
+                    public_key,
+                    connection: Arc::clone(&connection),
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            reward_signers,
+            used_public_keys: Mutex::default(),
+        })
+    }
+
+    /// Public keys of identities used for signing
+    pub fn public_keys(&self) -> impl Iterator<Item = PublicKey> + '_ {
+        self.reward_signers
+            .iter()
+            .map(|reward_signer| reward_signer.public_key)
+    }
+
+    /// Mark public key as used by existing farm, such that it is not given to new farms
+    pub fn reserve_public_key(&self, public_key: PublicKey) {
+        self.used_public_keys.lock().insert(public_key);
+    }
+
+    /// Reward signers of all used identities, one per public key
+    pub fn reward_signers(&self) -> impl Iterator<Item = Arc<dyn RewardSigner>> + '_ {
+        self.reward_signers
+            .iter()
+            .map(|reward_signer| Arc::clone(reward_signer) as Arc<dyn RewardSigner>)
+    }
+}
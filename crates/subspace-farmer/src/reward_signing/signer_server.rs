@@ -0,0 +1,232 @@
+//! Signer that holds identities and serves signing requests of remote farmers over Unix socket,
+//! see [`RemoteRewardSigner`](super::remote::RemoteRewardSigner) for client side
+
+use crate::identity::Identity;
+use crate::reward_signing::remote::{read_message, write_message, SignerRequest, SignerResponse};
+use crate::reward_signing::{RewardSignerError, SigningRejection};
+use crate::utils::bind_private_unix_socket;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use subspace_core_primitives::{Blake3Hash, PublicKey, RewardSignature};
+use tokio::net::UnixStream;
+use tracing::{debug, info, warn};
+
+/// Window in which number of signatures is limited
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Policy enforced by signer
+#[derive(Debug, Copy, Clone)]
+pub struct SignerPolicy {
+    /// Max number of signatures per public key per minute
+    pub max_signatures_per_minute: NonZeroU32,
+}
+
+impl Default for SignerPolicy {
+    fn default() -> Self {
+        Self {
+            max_signatures_per_minute: NonZeroU32::new(30).expect("Not zero; qed"),
+        }
+    }
+}
+
+struct Inner {
+    identities: HashMap<PublicKey, Identity>,
+    policy: SignerPolicy,
+    recent_signatures: Mutex<HashMap<PublicKey, VecDeque<Instant>>>,
+    audit_log: Option<Mutex<File>>,
+}
+
+/// Signer server that only signs for identities it holds, enforces rate limits and writes audit
+/// log of all signing requests
+#[derive(Clone)]
+pub struct SignerServer {
+    inner: Arc<Inner>,
+}
+
+impl SignerServer {
+    /// Create new instance.
+    ///
+    /// Audit log is appended to the file at `audit_log` if specified, one JSON object per line.
+    pub fn new(
+        identities: Vec<Identity>,
+        policy: SignerPolicy,
+        audit_log: Option<&Path>,
+    ) -> io::Result<Self> {
+        let identities = identities
+            .into_iter()
+            .map(|identity| (PublicKey::from(identity.public_key().to_bytes()), identity))
+            .collect();
+        let audit_log = audit_log
+            .map(|audit_log| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(audit_log)
+                    .map(Mutex::new)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                identities,
+                policy,
+                recent_signatures: Mutex::default(),
+                audit_log,
+            }),
+        })
+    }
+
+    /// Public keys of identities signer holds
+    pub fn public_keys(&self) -> Vec<PublicKey> {
+        self.inner.identities.keys().copied().collect()
+    }
+
+    /// Listen on Unix socket at specified path and serve requests until error happens.
+    ///
+    /// Socket file that might be left after previous run is replaced, new socket is only
+    /// accessible by the current user.
+    pub async fn run(&self, socket_path: &Path) -> io::Result<()> {
+        let listener = bind_private_unix_socket(socket_path)?;
+
+        info!(path = %socket_path.display(), "Signer is listening");
+
+        loop {
+            let (stream, _address) = listener.accept().await?;
+            let signer_server = self.clone();
+
+            tokio::spawn(async move {
+                if let Err(error) = signer_server.handle_connection(stream).await {
+                    debug!(%error, "Signer connection closed with error");
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: UnixStream) -> Result<(), RewardSignerError> {
+        let client_pid = stream
+            .peer_cred()
+            .ok()
+            .and_then(|credentials| credentials.pid());
+        debug!(?client_pid, "New signer connection");
+
+        loop {
+            let request = match read_message::<_, SignerRequest>(&mut stream).await {
+                Ok(request) => request,
+                Err(RewardSignerError::Io(error))
+                    if error.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    debug!(?client_pid, "Signer connection closed");
+                    return Ok(());
+                }
+                Err(error) => {
+                    return Err(error);
+                }
+            };
+
+            let response = self.handle_request(request, client_pid);
+            write_message(&mut stream, &response).await?;
+        }
+    }
+
+    fn handle_request(&self, request: SignerRequest, client_pid: Option<i32>) -> SignerResponse {
+        match request {
+            SignerRequest::PublicKeys => SignerResponse::PublicKeys(self.public_keys()),
+            SignerRequest::SignRewardHash { public_key, hash } => {
+                let result = self.sign_reward_hash(public_key, hash);
+                self.audit(public_key, hash, client_pid, &result);
+
+                match result {
+                    Ok(signature) => SignerResponse::Signature(signature),
+                    Err(rejection) => SignerResponse::Rejected(rejection),
+                }
+            }
+        }
+    }
+
+    fn sign_reward_hash(
+        &self,
+        public_key: PublicKey,
+        hash: Blake3Hash,
+    ) -> Result<RewardSignature, SigningRejection> {
+        let identity = self
+            .inner
+            .identities
+            .get(&public_key)
+            .ok_or(SigningRejection::UnknownPublicKey)?;
+
+        {
+            let mut recent_signatures = self.inner.recent_signatures.lock();
+            let recent_signatures = recent_signatures.entry(public_key).or_default();
+            let now = Instant::now();
+            while let Some(&signed_at) = recent_signatures.front() {
+                if now.duration_since(signed_at) < RATE_LIMIT_WINDOW {
+                    break;
+                }
+                recent_signatures.pop_front();
+            }
+
+            if recent_signatures.len() >= self.inner.policy.max_signatures_per_minute.get() as usize
+            {
+                return Err(SigningRejection::RateLimited);
+            }
+            recent_signatures.push_back(now);
+        }
+
+        Ok(RewardSignature::from(
+            identity.sign_reward_hash(&hash).to_bytes(),
+        ))
+    }
+
+    fn audit(
+        &self,
+        public_key: PublicKey,
+        hash: Blake3Hash,
+        client_pid: Option<i32>,
+        result: &Result<RewardSignature, SigningRejection>,
+    ) {
+        match result {
+            Ok(_) => {
+                info!(%public_key, hash = %hex::encode(hash), ?client_pid, "Signed reward hash");
+            }
+            Err(rejection) => {
+                warn!(
+                    %public_key,
+                    hash = %hex::encode(hash),
+                    ?client_pid,
+                    %rejection,
+                    "Refused to sign reward hash"
+                );
+            }
+        }
+
+        let Some(audit_log) = &self.inner.audit_log else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let entry = serde_json::json!({
+            "timestamp": timestamp,
+            "public_key": public_key.to_string(),
+            "hash": hex::encode(hash),
+            "client_pid": client_pid,
+            "outcome": match result {
+                Ok(_) => "signed".to_string(),
+                Err(rejection) => format!("rejected: {rejection}"),
+            },
+        });
+
+        let mut audit_log = audit_log.lock();
+        if let Err(error) = writeln!(audit_log, "{entry}").and_then(|()| audit_log.flush()) {
+            warn!(%error, "Failed to write audit log entry");
+        }
+    }
+}
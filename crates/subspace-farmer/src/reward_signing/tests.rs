@@ -0,0 +1,138 @@
+use crate::identity::Identity;
+use crate::reward_signing::remote::RemoteSigner;
+use crate::reward_signing::signer_server::{SignerPolicy, SignerServer};
+use crate::reward_signing::{RewardSignerError, RewardSignerProvider, SigningRejection};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::time::Duration;
+use subspace_core_primitives::{PublicKey, REWARD_SIGNING_CONTEXT};
+use tempfile::tempdir;
+use tokio::net::UnixStream;
+
+async fn wait_for_socket(socket_path: &Path) {
+    for _ in 0..100 {
+        if UnixStream::connect(socket_path).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("Signer didn't start listening in time");
+}
+
+#[tokio::test]
+async fn remote_signer() {
+    let directory = tempdir().unwrap();
+    let socket_path = directory.path().join("signer.sock");
+    let audit_log_path = directory.path().join("audit.log");
+
+    let identity = Identity::create(directory.as_ref()).unwrap();
+    let public_key = PublicKey::from(identity.public_key().to_bytes());
+    let signer_server = SignerServer::new(
+        vec![identity.clone()],
+        SignerPolicy {
+            max_signatures_per_minute: NonZeroU32::new(2).unwrap(),
+        },
+        Some(&audit_log_path),
+    )
+    .unwrap();
+
+    tokio::spawn({
+        let socket_path = socket_path.clone();
+
+        async move { signer_server.run(&socket_path).await }
+    });
+    wait_for_socket(&socket_path).await;
+
+    assert!(matches!(
+        RemoteSigner::connect(socket_path.clone(), &[PublicKey::from([1; 32])]).await,
+        Err(RewardSignerError::Rejected(
+            SigningRejection::UnknownPublicKey
+        ))
+    ));
+
+    let remote_signer = RemoteSigner::connect(socket_path, &[]).await.unwrap();
+    let remote_reward_signer = remote_signer.reward_signer(None).unwrap();
+    assert_eq!(remote_reward_signer.public_key(), public_key);
+
+    let hash = [2; 32];
+    for _ in 0..2 {
+        let signature = remote_reward_signer.sign_reward_hash(hash).await.unwrap();
+        let signature = schnorrkel::Signature::from_bytes(signature.as_ref()).unwrap();
+        identity
+            .public_key()
+            .verify(
+                schnorrkel::signing_context(REWARD_SIGNING_CONTEXT).bytes(&hash),
+                &signature,
+            )
+            .unwrap();
+    }
+
+    // Policy only allows two signatures per minute
+    assert!(matches!(
+        remote_reward_signer.sign_reward_hash(hash).await,
+        Err(RewardSignerError::Rejected(SigningRejection::RateLimited))
+    ));
+
+    let audit_log = fs::read_to_string(audit_log_path).unwrap();
+    let outcomes = audit_log
+        .lines()
+        .map(|line| {
+            let entry = serde_json::from_str::<serde_json::Value>(line).unwrap();
+            entry["outcome"].as_str().unwrap().to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(outcomes, vec!["signed", "signed", "rejected: Rate limited"]);
+}
+
+#[tokio::test]
+async fn remote_signer_identity_per_farm() {
+    let directory = tempdir().unwrap();
+    let socket_path = directory.path().join("signer.sock");
+
+    let identities = (0..2)
+        .map(|_| Identity::create(tempdir().unwrap().as_ref()).unwrap())
+        .collect::<Vec<_>>();
+    let public_keys = identities
+        .iter()
+        .map(|identity| PublicKey::from(identity.public_key().to_bytes()))
+        .collect::<Vec<_>>();
+    let signer_server = SignerServer::new(identities, SignerPolicy::default(), None).unwrap();
+
+    tokio::spawn({
+        let socket_path = socket_path.clone();
+
+        async move { signer_server.run(&socket_path).await }
+    });
+    wait_for_socket(&socket_path).await;
+
+    let remote_signer = RemoteSigner::connect(socket_path, &[]).await.unwrap();
+    assert_eq!(remote_signer.reward_signers().count(), 2);
+
+    // Existing farm keeps its identity, new farm gets the other one
+    remote_signer.reserve_public_key(public_keys[1]);
+    assert_eq!(
+        remote_signer
+            .reward_signer(Some(&public_keys[1]))
+            .unwrap()
+            .public_key(),
+        public_keys[1]
+    );
+    assert_eq!(
+        remote_signer.reward_signer(None).unwrap().public_key(),
+        public_keys[0]
+    );
+
+    // All identities are used already
+    assert!(matches!(
+        remote_signer.reward_signer(None),
+        Err(RewardSignerError::NoUnusedPublicKey { public_keys: 2 })
+    ));
+    assert!(matches!(
+        remote_signer.reward_signer(Some(&PublicKey::from([1; 32]))),
+        Err(RewardSignerError::Rejected(
+            SigningRejection::UnknownPublicKey
+        ))
+    ));
+}
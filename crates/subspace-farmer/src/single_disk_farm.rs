@@ -1,13 +1,22 @@
+pub mod download_staging;
 pub mod farming;
 pub mod piece_cache;
 pub mod piece_reader;
 pub mod plot_cache;
+mod plotted_pieces_index;
 mod plotting;
+#[cfg(test)]
+mod tests;
 
-use crate::identity::{Identity, IdentityError};
+use crate::identity::{Identity, IdentityError, IdentitySecret};
 use crate::node_client::NodeClient;
-use crate::reward_signing::reward_signing;
+use crate::reward_signing::local::LocalRewardSigner;
+use crate::reward_signing::{
+    reward_signing, RewardSigner, RewardSignerError, RewardSignerProvider,
+};
+use crate::single_disk_farm::download_staging::{DownloadStaging, DownloadStagingOptions};
 use crate::single_disk_farm::farming::rayon_files::RayonFiles;
+use crate::single_disk_farm::farming::reward_addresses::RewardAddresses;
 pub use crate::single_disk_farm::farming::FarmingError;
 use crate::single_disk_farm::farming::{
     farming, slot_notification_forwarder, FarmingNotification, FarmingOptions, PlotAudit,
@@ -15,6 +24,7 @@ use crate::single_disk_farm::farming::{
 use crate::single_disk_farm::piece_cache::{DiskPieceCache, DiskPieceCacheError};
 use crate::single_disk_farm::piece_reader::PieceReader;
 use crate::single_disk_farm::plot_cache::DiskPlotCache;
+use crate::single_disk_farm::plotted_pieces_index::PlottedPiecesIndex;
 use crate::single_disk_farm::plotting::{
     plotting, plotting_scheduler, PlottingOptions, PlottingSchedulerOptions,
 };
@@ -22,6 +32,7 @@ pub use crate::single_disk_farm::plotting::{
     PlottingError, SectorExpirationDetails, SectorPlottingDetails,
 };
 use crate::thread_pool_manager::PlottingThreadPoolManager;
+use crate::utils::plotting_schedule::PlottingSchedule;
 use crate::utils::{tokio_rayon_spawn_handler, AsyncJoinOnDrop};
 use crate::KNOWN_PEERS_CACHE_SIZE;
 use async_lock::RwLock;
@@ -57,6 +68,7 @@ use subspace_erasure_coding::ErasureCoding;
 use subspace_farmer_components::file_ext::{FileExt, OpenOptionsExt};
 use subspace_farmer_components::plotting::PlottedSector;
 use subspace_farmer_components::sector::{sector_size, SectorMetadata, SectorMetadataChecksummed};
+use subspace_farmer_components::segment_reconstruction::SegmentReconstructionCache;
 use subspace_farmer_components::{FarmerProtocolInfo, PieceGetter};
 use subspace_networking::KnownPeersManager;
 use subspace_proof_of_space::Table;
@@ -101,6 +113,29 @@ pub struct SingleDiskFarmInfoLock {
     _file: File,
 }
 
+/// Cache-only farm opened with [`SingleDiskFarm::open_cache_only()`], directory stays locked for as
+/// long as this instance exists
+#[derive(Debug)]
+pub struct CacheOnlyFarm {
+    piece_cache: DiskPieceCache,
+    capacity: u32,
+    _lock_file: Option<File>,
+}
+
+impl CacheOnlyFarm {
+    const LOCK_FILE_NAME: &'static str = "cache_only_farm.lock";
+
+    /// Piece cache of the farm
+    pub fn piece_cache(&self) -> DiskPieceCache {
+        self.piece_cache.clone()
+    }
+
+    /// Number of pieces piece cache can store
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
 /// Important information about the contents of the `SingleDiskFarm`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -266,8 +301,8 @@ pub struct SingleDiskFarmOptions<NC, PG> {
     pub max_pieces_in_sector: u16,
     /// RPC client connected to Subspace node
     pub node_client: NC,
-    /// Address where farming rewards should go
-    pub reward_address: PublicKey,
+    /// Addresses where farming rewards should go
+    pub reward_addresses: RewardAddresses,
     /// Piece receiver implementation for plotting purposes.
     pub piece_getter: PG,
     /// Kzg instance to use.
@@ -291,8 +326,23 @@ pub struct SingleDiskFarmOptions<NC, PG> {
     /// Notification for plotter to start, can be used to delay plotting until some initialization
     /// has happened externally
     pub plotting_delay: Option<oneshot::Receiver<()>>,
+    /// Schedule of plotting, checked before plotting or replotting of every sector
+    pub plotting_schedule: PlottingSchedule,
+    /// Stage downloaded sectors on disk ahead of encoding instead of downloading at most one sector
+    /// ahead in memory
+    pub download_staging: Option<DownloadStagingOptions>,
+    /// Cache of pieces fetched for reconstruction of missing pieces, shared by all farms
+    pub segment_reconstruction_cache: SegmentReconstructionCache,
     /// Disable farm locking, for example if file system doesn't support it
     pub disable_farm_locking: bool,
+    /// Secret to unlock encrypted identity with (or to encrypt newly created identity)
+    pub identity_secret: Option<IdentitySecret>,
+    /// Provider of reward signer to use instead of identity stored in farm directory, no identity
+    /// is opened or created in farm directory when specified.
+    ///
+    /// Farm doesn't sign rewards itself in this case, this is the responsibility of the owner of the
+    /// provider.
+    pub reward_signer_provider: Option<Arc<dyn RewardSignerProvider>>,
 }
 
 /// Errors happening when trying to create/open single disk farm
@@ -301,6 +351,9 @@ pub enum SingleDiskFarmError {
     /// Failed to open or create identity
     #[error("Failed to open or create identity: {0}")]
     FailedToOpenIdentity(#[from] IdentityError),
+    /// Failed to get reward signer from provider
+    #[error("Failed to get reward signer: {0}")]
+    RewardSigner(#[from] RewardSignerError),
     /// Farm is likely already in use, make sure no other farmer is using it
     #[error("Farm is likely already in use, make sure no other farmer is using it: {0}")]
     LikelyAlreadyInUse(io::Error),
@@ -387,6 +440,26 @@ pub enum SingleDiskFarmError {
         max_space: u64,
         max_sectors: u16,
     },
+    /// Cache-only farm is too large
+    #[error(
+        "Cache-only farm is too large: allocated {allocated_space} bytes, max supported is \
+        {max_space} bytes. Consider creating multiple smaller farms instead."
+    )]
+    CacheOnlyFarmTooLarge {
+        /// Current allocated space
+        allocated_space: u64,
+        /// Max supported allocated space
+        max_space: u64,
+    },
+    /// Cache-only farm can't be opened in directory of regular farm
+    #[error(
+        "Directory {} contains regular farm and can't be used as cache-only farm",
+        directory.display()
+    )]
+    NotCacheOnlyFarm {
+        /// Directory of the farm
+        directory: PathBuf,
+    },
 }
 
 /// Errors happening during scrubbing
@@ -562,6 +635,8 @@ pub struct SingleDiskFarm {
     single_disk_farm_info: SingleDiskFarmInfo,
     /// Metadata of all sectors plotted so far
     sectors_metadata: Arc<RwLock<Vec<SectorMetadataChecksummed>>>,
+    /// Persisted index of pieces plotted in each sector
+    plotted_pieces_index: Arc<PlottedPiecesIndex>,
     pieces_in_sector: u16,
     total_sectors_count: SectorIndex,
     span: Span,
@@ -610,7 +685,7 @@ impl SingleDiskFarm {
             allocated_space,
             max_pieces_in_sector,
             node_client,
-            reward_address,
+            reward_addresses,
             piece_getter,
             kzg,
             erasure_coding,
@@ -620,15 +695,40 @@ impl SingleDiskFarm {
             farming_thread_pool_size,
             plotting_thread_pool_manager,
             plotting_delay,
+            plotting_schedule,
+            download_staging,
+            segment_reconstruction_cache,
             farm_during_initial_plotting,
             disable_farm_locking,
+            identity_secret,
+            reward_signer_provider,
         } = options;
         fs::create_dir_all(&directory)?;
 
-        let identity = Identity::open_or_create(&directory)?;
-        let public_key = identity.public_key().to_bytes().into();
+        let existing_single_disk_farm_info = SingleDiskFarmInfo::load_from(&directory)?;
 
-        let single_disk_farm_info = match SingleDiskFarmInfo::load_from(&directory)? {
+        // Signer from provider signs rewards of all farms with the same public key elsewhere
+        let (reward_signer, sign_rewards) = match reward_signer_provider {
+            Some(reward_signer_provider) => (
+                reward_signer_provider.reward_signer(
+                    existing_single_disk_farm_info
+                        .as_ref()
+                        .map(SingleDiskFarmInfo::public_key),
+                )?,
+                false,
+            ),
+            None => {
+                let identity =
+                    Identity::open_or_create_with_secret(&directory, identity_secret.as_ref())?;
+                (
+                    Arc::new(LocalRewardSigner::new(identity)) as Arc<dyn RewardSigner>,
+                    true,
+                )
+            }
+        };
+        let public_key = reward_signer.public_key();
+
+        let single_disk_farm_info = match existing_single_disk_farm_info {
             Some(mut single_disk_farm_info) => {
                 if &farmer_app_info.genesis_hash != single_disk_farm_info.genesis_hash() {
                     return Err(SingleDiskFarmError::WrongChain {
@@ -866,6 +966,23 @@ impl SingleDiskFarm {
             Arc::new(RwLock::new(sectors_metadata))
         };
 
+        let plotted_pieces_index = Arc::new(PlottedPiecesIndex::open(
+            &directory,
+            pieces_in_sector,
+            target_sector_count,
+        )?);
+
+        let download_staging = download_staging
+            .map(|download_staging_options| {
+                DownloadStaging::open(
+                    &download_staging_options,
+                    single_disk_farm_info.id(),
+                    target_sector_count,
+                )
+                .map(Arc::new)
+            })
+            .transpose()?;
+
         let plot_file = Arc::new(
             OpenOptions::new()
                 .read(true)
@@ -926,6 +1043,7 @@ impl SingleDiskFarm {
 
         let plotting_join_handle = tokio::task::spawn_blocking({
             let sectors_metadata = Arc::clone(&sectors_metadata);
+            let plotted_pieces_index = Arc::clone(&plotted_pieces_index);
             let kzg = kzg.clone();
             let erasure_coding = erasure_coding.clone();
             let handlers = Arc::clone(&handlers);
@@ -948,6 +1066,7 @@ impl SingleDiskFarm {
                     plot_file,
                     metadata_file,
                     sectors_metadata,
+                    plotted_pieces_index,
                     piece_getter: &piece_getter,
                     kzg: &kzg,
                     erasure_coding: &erasure_coding,
@@ -957,6 +1076,10 @@ impl SingleDiskFarm {
                     downloading_semaphore,
                     record_encoding_concurrency,
                     plotting_thread_pool_manager,
+                    plotting_schedule,
+                    download_staging,
+                    target_sector_count,
+                    segment_reconstruction_cache,
                     stop_receiver: stop_receiver.resubscribe(),
                 };
 
@@ -1092,7 +1215,7 @@ impl SingleDiskFarm {
 
                         let farming_options = FarmingOptions {
                             public_key,
-                            reward_address,
+                            reward_addresses,
                             node_client,
                             plot_audit,
                             sectors_metadata,
@@ -1174,26 +1297,29 @@ impl SingleDiskFarm {
             })
         }));
 
-        tasks.push(Box::pin(async move {
-            match reward_signing(node_client, identity).await {
-                Ok(reward_signing_fut) => {
-                    reward_signing_fut.await;
-                }
-                Err(error) => {
-                    return Err(BackgroundTaskError::RewardSigning(
-                        format!("Failed to subscribe to reward signing notifications: {error}")
-                            .into(),
-                    ));
+        if sign_rewards {
+            tasks.push(Box::pin(async move {
+                match reward_signing(node_client, reward_signer).await {
+                    Ok(reward_signing_fut) => {
+                        reward_signing_fut.await;
+                    }
+                    Err(error) => {
+                        return Err(BackgroundTaskError::RewardSigning(
+                            format!("Failed to subscribe to reward signing notifications: {error}")
+                                .into(),
+                        ));
+                    }
                 }
-            }
 
-            Ok(())
-        }));
+                Ok(())
+            }));
+        }
 
         let farm = Self {
             farmer_protocol_info: farmer_app_info.protocol_info,
             single_disk_farm_info,
             sectors_metadata,
+            plotted_pieces_index,
             pieces_in_sector,
             total_sectors_count: target_sector_count,
             span,
@@ -1210,6 +1336,58 @@ impl SingleDiskFarm {
         Ok(farm)
     }
 
+    /// Open piece cache of cache-only farm.
+    ///
+    /// Cache-only farm uses all of allocated space for piece cache and has neither plot nor
+    /// identity, which allows to use fast disks for serving pieces while other disks are plotted.
+    pub fn open_cache_only(
+        directory: &Path,
+        allocated_space: u64,
+        disable_farm_locking: bool,
+    ) -> Result<CacheOnlyFarm, SingleDiskFarmError> {
+        if SingleDiskFarmInfo::load_from(directory)?.is_some() {
+            return Err(SingleDiskFarmError::NotCacheOnlyFarm {
+                directory: directory.to_path_buf(),
+            });
+        }
+
+        let element_size = u64::from(DiskPieceCache::element_size());
+        let capacity = allocated_space / element_size;
+        if capacity == 0 {
+            return Err(SingleDiskFarmError::InsufficientAllocatedSpace {
+                min_space: element_size,
+                allocated_space,
+            });
+        }
+        let capacity = u32::try_from(capacity).map_err(|_error| {
+            SingleDiskFarmError::CacheOnlyFarmTooLarge {
+                allocated_space,
+                max_space: u64::from(u32::MAX) * element_size,
+            }
+        })?;
+
+        // There is no farm info file to lock, so dedicated lock file is used instead
+        let lock_file = if disable_farm_locking {
+            None
+        } else {
+            let lock_file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(directory.join(CacheOnlyFarm::LOCK_FILE_NAME))?;
+            fs4::FileExt::try_lock_exclusive(&lock_file)
+                .map_err(SingleDiskFarmError::LikelyAlreadyInUse)?;
+            Some(lock_file)
+        };
+
+        Ok(CacheOnlyFarm {
+            piece_cache: DiskPieceCache::open(directory, capacity)?,
+            capacity,
+            _lock_file: lock_file,
+        })
+    }
+
     /// Collect summary of single disk farm for presentational purposes
     pub fn collect_summary(directory: PathBuf) -> SingleDiskFarmSummary {
         let single_disk_farm_info = match SingleDiskFarmInfo::load_from(&directory) {
@@ -1307,7 +1485,10 @@ impl SingleDiskFarm {
             .expect("Number of sectors never exceeds `SectorIndex` type; qed")
     }
 
-    /// Read information about sectors plotted so far
+    /// Read information about sectors plotted so far.
+    ///
+    /// Piece indices are read from persisted index where possible, sectors missing from the index
+    /// or with corrupted index entries have their piece indices derived again and written back.
     pub async fn plotted_sectors(
         &self,
     ) -> impl Iterator<Item = Result<PlottedSector, parity_scale_codec::Error>> + '_ {
@@ -1319,19 +1500,46 @@ impl SingleDiskFarm {
             .map(move |(sector_index, sector_metadata)| {
                 let sector_id = SectorId::new(public_key.hash(), sector_index);
 
-                let mut piece_indexes = Vec::with_capacity(usize::from(self.pieces_in_sector));
-                (PieceOffset::ZERO..)
-                    .take(usize::from(self.pieces_in_sector))
-                    .map(|piece_offset| {
-                        sector_id.derive_piece_index(
-                            piece_offset,
+                let maybe_piece_indexes = self
+                    .plotted_pieces_index
+                    .read_sector(sector_index, sector_metadata.history_size)
+                    .unwrap_or_else(|error| {
+                        warn!(%sector_index, %error, "Failed to read plotted pieces index");
+                        None
+                    });
+
+                let piece_indexes = match maybe_piece_indexes {
+                    Some(piece_indexes) => piece_indexes,
+                    None => {
+                        let mut piece_indexes =
+                            Vec::with_capacity(usize::from(self.pieces_in_sector));
+                        (PieceOffset::ZERO..)
+                            .take(usize::from(self.pieces_in_sector))
+                            .map(|piece_offset| {
+                                sector_id.derive_piece_index(
+                                    piece_offset,
+                                    sector_metadata.history_size,
+                                    self.farmer_protocol_info.max_pieces_in_sector,
+                                    self.farmer_protocol_info.recent_segments,
+                                    self.farmer_protocol_info.recent_history_fraction,
+                                )
+                            })
+                            .collect_into(&mut piece_indexes);
+
+                        // In case sector is being replotted concurrently, history size in the
+                        // index will not match sector metadata on next read and piece indices will
+                        // simply be derived again
+                        if let Err(error) = self.plotted_pieces_index.write_sector(
+                            sector_index,
                             sector_metadata.history_size,
-                            self.farmer_protocol_info.max_pieces_in_sector,
-                            self.farmer_protocol_info.recent_segments,
-                            self.farmer_protocol_info.recent_history_fraction,
-                        )
-                    })
-                    .collect_into(&mut piece_indexes);
+                            &piece_indexes,
+                        ) {
+                            warn!(%sector_index, %error, "Failed to write plotted pieces index");
+                        }
+
+                        piece_indexes
+                    }
+                };
 
                 Ok(PlottedSector {
                     sector_id,
@@ -1421,6 +1629,16 @@ impl SingleDiskFarm {
                 fs::remove_file(metadata)?;
             }
         }
+        {
+            let plotted_pieces_index = directory.join(PlottedPiecesIndex::FILE_NAME);
+            if plotted_pieces_index.exists() {
+                info!(
+                    "Deleting plotted pieces index file at {}",
+                    plotted_pieces_index.display()
+                );
+                fs::remove_file(plotted_pieces_index)?;
+            }
+        }
         // TODO: Identity should be able to wipe itself instead of assuming a specific file name
         //  here
         {
@@ -1442,9 +1660,13 @@ impl SingleDiskFarm {
 
     /// Check the farm for corruption and repair errors (caused by disk errors or something else),
     /// returns an error when irrecoverable errors occur.
+    ///
+    /// Farms that use remote signer (`remote_signer == true`) don't have identity file, so it is
+    /// only checked if present, otherwise missing identity file is an error.
     pub fn scrub(
         directory: &Path,
         disable_farm_locking: bool,
+        remote_signer: bool,
     ) -> Result<(), SingleDiskFarmScrubError> {
         let span = Span::current();
 
@@ -1472,26 +1694,37 @@ impl SingleDiskFarm {
             )
         };
 
-        let identity = {
+        // Only public key is checked, such that encrypted identity doesn't need to be unlocked
+        let identity_public_key = {
             let file = directory.join(Identity::FILE_NAME);
             info!(path = %file.display(), "Checking identity file");
 
-            match Identity::open(directory) {
-                Ok(Some(identity)) => identity,
-                Ok(None) => {
-                    return Err(SingleDiskFarmScrubError::IdentityFileDoesNotExist { file });
-                }
+            match Identity::read_public_key(directory) {
+                Ok(maybe_public_key) => maybe_public_key,
                 Err(error) => {
                     return Err(SingleDiskFarmScrubError::IdentityCantBeOpened { file, error });
                 }
             }
         };
 
-        if PublicKey::from(identity.public.to_bytes()) != *info.public_key() {
-            return Err(SingleDiskFarmScrubError::PublicKeyMismatch {
-                identity: PublicKey::from(identity.public.to_bytes()),
-                info: *info.public_key(),
-            });
+        match identity_public_key {
+            Some(identity_public_key) => {
+                if PublicKey::from(identity_public_key.to_bytes()) != *info.public_key() {
+                    return Err(SingleDiskFarmScrubError::PublicKeyMismatch {
+                        identity: PublicKey::from(identity_public_key.to_bytes()),
+                        info: *info.public_key(),
+                    });
+                }
+            }
+            None => {
+                if !remote_signer {
+                    return Err(SingleDiskFarmScrubError::IdentityFileDoesNotExist {
+                        file: directory.join(Identity::FILE_NAME),
+                    });
+                }
+
+                info!("Identity file not found, identity is held by remote signer");
+            }
         }
 
         let sector_metadata_size = SectorMetadataChecksummed::encoded_size();
@@ -0,0 +1,288 @@
+//! Staging of downloaded sectors on disk.
+//!
+//! Downloaded sectors are stored in scratch directory until they are encoded, which allows to
+//! download many sectors ahead of encoding without holding them in RAM and to keep downloaded
+//! sectors across restarts.
+
+#[cfg(test)]
+mod tests;
+
+use crate::single_disk_farm::SingleDiskFarmId;
+use parity_scale_codec::{Decode, Encode, Input, Output};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::{fs, io, mem};
+use subspace_core_primitives::{Blake3Hash, SectorIndex};
+use subspace_farmer_components::plotting::DownloadedSector;
+use tracing::{debug, warn};
+use ulid::Ulid;
+
+/// Extension of staged sector files
+const STAGED_SECTOR_EXTENSION: &str = "bin";
+/// Extension of staged sector files that are not fully written yet
+const TMP_EXTENSION: &str = "tmp";
+
+/// Options of download staging
+#[derive(Debug, Clone)]
+pub struct DownloadStagingOptions {
+    /// Scratch directory where downloaded sectors are stored until they are encoded, each farm uses
+    /// its own subdirectory
+    pub directory: PathBuf,
+    /// How many sectors each farm downloads ahead of encoding
+    pub sectors_ahead: NonZeroUsize,
+}
+
+impl DownloadStagingOptions {
+    /// Remove staged sectors of the farm, for instance when farm is removed from the farmer
+    pub fn remove_farm(&self, farm_id: &SingleDiskFarmId) -> io::Result<()> {
+        match fs::remove_dir_all(self.directory.join(farm_id.to_string())) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove staged sectors of all farms except provided ones, for instance of farms that are no
+    /// longer used by the farmer or were wiped and created again with a different ID.
+    ///
+    /// Only subdirectories named after farm IDs are removed, but scratch directory still must not be
+    /// shared with other farmers.
+    pub fn retain_farms(&self, farm_ids: &[SingleDiskFarmId]) -> io::Result<()> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(());
+            }
+            Err(error) => {
+                return Err(error);
+            }
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let Some(farm_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|file_name| Ulid::from_string(file_name).ok())
+                .map(SingleDiskFarmId::Ulid)
+            else {
+                continue;
+            };
+
+            if entry.file_type()?.is_dir() && !farm_ids.contains(&farm_id) {
+                debug!(%farm_id, "Removing staged sectors of unknown farm");
+                fs::remove_dir_all(entry.path())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Downloaded sectors of a farm staged on disk.
+///
+/// Each sector is stored in its own file containing SCALE-encoded [`DownloadedSector`] followed by
+/// its checksum. Files are written under temporary name and renamed once fully written.
+#[derive(Debug)]
+pub(super) struct DownloadStaging {
+    directory: PathBuf,
+    sectors_ahead: NonZeroUsize,
+}
+
+impl DownloadStaging {
+    /// Open download staging of the farm, removes partially written sectors and sectors that are
+    /// no longer part of the farm (in case farm was shrunk)
+    pub(super) fn open(
+        options: &DownloadStagingOptions,
+        farm_id: &SingleDiskFarmId,
+        target_sector_count: SectorIndex,
+    ) -> io::Result<Self> {
+        let directory = options.directory.join(farm_id.to_string());
+        fs::create_dir_all(&directory)?;
+
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            let outdated = match path.extension().and_then(|extension| extension.to_str()) {
+                Some(TMP_EXTENSION) => true,
+                Some(STAGED_SECTOR_EXTENSION) => path
+                    .file_stem()
+                    .and_then(|file_stem| file_stem.to_str())
+                    .and_then(|file_stem| file_stem.parse::<SectorIndex>().ok())
+                    .is_some_and(|sector_index| sector_index >= target_sector_count),
+                _ => false,
+            };
+
+            if outdated {
+                debug!(path = %path.display(), "Removing outdated staged sector");
+                fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(Self {
+            directory,
+            sectors_ahead: options.sectors_ahead,
+        })
+    }
+
+    /// How many sectors should be downloaded ahead of encoding
+    pub(super) fn sectors_ahead(&self) -> NonZeroUsize {
+        self.sectors_ahead
+    }
+
+    /// Whether sector is staged
+    pub(super) fn contains(&self, sector_index: SectorIndex) -> bool {
+        self.sector_path(sector_index).exists()
+    }
+
+    /// Store downloaded sector
+    pub(super) fn write(
+        &self,
+        sector_index: SectorIndex,
+        downloaded_sector: &DownloadedSector,
+    ) -> io::Result<()> {
+        let path = self.sector_path(sector_index);
+        let tmp_path = path.with_extension(TMP_EXTENSION);
+
+        let mut output = HashingFileOutput {
+            writer: BufWriter::new(File::create(&tmp_path)?),
+            hasher: blake3::Hasher::new(),
+            error: None,
+        };
+        downloaded_sector.encode_to(&mut output);
+        if let Some(error) = output.error {
+            return Err(error);
+        }
+        let checksum = output.hasher.finalize();
+        output.writer.write_all(checksum.as_bytes())?;
+        output.writer.into_inner()?.sync_data()?;
+
+        fs::rename(tmp_path, path)
+    }
+
+    /// Read staged sector, returns `None` if sector is not staged or staged sector is corrupted (in
+    /// which case it is removed)
+    pub(super) fn read(&self, sector_index: SectorIndex) -> io::Result<Option<DownloadedSector>> {
+        let path = self.sector_path(sector_index);
+        let file = match OpenOptions::new().read(true).open(&path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(None);
+            }
+            Err(error) => {
+                return Err(error);
+            }
+        };
+        let Some(remaining) = file
+            .metadata()?
+            .len()
+            .checked_sub(mem::size_of::<Blake3Hash>() as u64)
+        else {
+            warn!(%sector_index, "Staged sector is too small, removing");
+            self.remove(sector_index)?;
+            return Ok(None);
+        };
+
+        let mut input = HashingFileInput {
+            reader: BufReader::new(file),
+            hasher: blake3::Hasher::new(),
+            remaining,
+            error: None,
+        };
+        let downloaded_sector = match DownloadedSector::decode(&mut input) {
+            Ok(downloaded_sector) if input.remaining == 0 => downloaded_sector,
+            result => {
+                if let Some(error) = input.error {
+                    return Err(error);
+                }
+                if let Err(error) = result {
+                    warn!(%sector_index, %error, "Failed to decode staged sector, removing");
+                } else {
+                    warn!(%sector_index, "Staged sector has unexpected size, removing");
+                }
+                self.remove(sector_index)?;
+                return Ok(None);
+            }
+        };
+
+        let mut checksum = Blake3Hash::default();
+        input.reader.read_exact(&mut checksum)?;
+        if input.hasher.finalize().as_bytes() != &checksum {
+            warn!(%sector_index, "Staged sector checksum mismatch, removing");
+            self.remove(sector_index)?;
+            return Ok(None);
+        }
+
+        Ok(Some(downloaded_sector))
+    }
+
+    /// Remove staged sector if it exists
+    pub(super) fn remove(&self, sector_index: SectorIndex) -> io::Result<()> {
+        match fs::remove_file(self.sector_path(sector_index)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn sector_path(&self, sector_index: SectorIndex) -> PathBuf {
+        sector_path(&self.directory, sector_index)
+    }
+}
+
+fn sector_path(directory: &Path, sector_index: SectorIndex) -> PathBuf {
+    directory.join(format!("{sector_index}.{STAGED_SECTOR_EXTENSION}"))
+}
+
+/// SCALE output that writes into file and hashes written bytes, remembers the first error since
+/// SCALE output is infallible
+struct HashingFileOutput {
+    writer: BufWriter<File>,
+    hasher: blake3::Hasher,
+    error: Option<io::Error>,
+}
+
+impl Output for HashingFileOutput {
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        match self.writer.write_all(bytes) {
+            Ok(()) => {
+                self.hasher.update(bytes);
+            }
+            Err(error) => {
+                self.error.replace(error);
+            }
+        }
+    }
+}
+
+/// SCALE input that reads from file (excluding checksum at the end) and hashes read bytes
+struct HashingFileInput {
+    reader: BufReader<File>,
+    hasher: blake3::Hasher,
+    remaining: u64,
+    error: Option<io::Error>,
+}
+
+impl Input for HashingFileInput {
+    fn remaining_len(&mut self) -> Result<Option<usize>, parity_scale_codec::Error> {
+        Ok(usize::try_from(self.remaining).ok())
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), parity_scale_codec::Error> {
+        if into.len() as u64 > self.remaining {
+            return Err("Not enough data to fill buffer".into());
+        }
+
+        if let Err(error) = self.reader.read_exact(into) {
+            self.error.replace(error);
+            return Err("I/O error".into());
+        }
+        self.hasher.update(into);
+        self.remaining -= into.len() as u64;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::single_disk_farm::download_staging::{DownloadStaging, DownloadStagingOptions};
+use crate::single_disk_farm::SingleDiskFarmId;
+use parity_scale_codec::{Compact, Decode, Encode};
+use std::fs::OpenOptions;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::{fs, mem};
+use subspace_core_primitives::{
+    Blake3Hash, HistorySize, PieceIndex, Record, RecordCommitment, RecordWitness, SectorId,
+};
+use subspace_farmer_components::file_ext::FileExt;
+use subspace_farmer_components::plotting::DownloadedSector;
+use subspace_farmer_components::FarmerProtocolInfo;
+use tempfile::tempdir;
+
+const PIECES_IN_SECTOR: u16 = 2;
+const TARGET_SECTOR_COUNT: u16 = 3;
+
+fn downloaded_sector(sector_index: u16) -> DownloadedSector {
+    let history_size = HistorySize::new(NonZeroU64::MIN);
+    let record_metadata_size =
+        RecordCommitment::SIZE + RecordWitness::SIZE + mem::size_of::<Blake3Hash>();
+
+    let mut encoded = SectorId::new(Blake3Hash::default(), sector_index).encode();
+    (0..u64::from(PIECES_IN_SECTOR))
+        .map(PieceIndex::from)
+        .collect::<Vec<_>>()
+        .encode_to(&mut encoded);
+    FarmerProtocolInfo {
+        history_size,
+        max_pieces_in_sector: PIECES_IN_SECTOR,
+        recent_segments: history_size,
+        recent_history_fraction: (history_size, history_size),
+        min_sector_lifetime: history_size,
+    }
+    .encode_to(&mut encoded);
+    encoded.extend(
+        (0..usize::from(PIECES_IN_SECTOR) * Record::SIZE)
+            .map(|byte| (byte + usize::from(sector_index)) as u8),
+    );
+    Compact(u32::from(PIECES_IN_SECTOR)).encode_to(&mut encoded);
+    encoded.resize(
+        encoded.len() + usize::from(PIECES_IN_SECTOR) * record_metadata_size,
+        0,
+    );
+
+    DownloadedSector::decode(&mut encoded.as_slice()).unwrap()
+}
+
+#[test]
+fn basic() {
+    let directory = tempdir().unwrap();
+    let options = DownloadStagingOptions {
+        directory: directory.path().to_path_buf(),
+        sectors_ahead: NonZeroUsize::MIN,
+    };
+    let farm_id = SingleDiskFarmId::new();
+
+    {
+        let download_staging =
+            DownloadStaging::open(&options, &farm_id, TARGET_SECTOR_COUNT).unwrap();
+
+        // Nothing is staged yet
+        assert!(!download_staging.contains(0));
+        assert!(download_staging.read(0).unwrap().is_none());
+
+        download_staging.write(0, &downloaded_sector(0)).unwrap();
+        download_staging.write(2, &downloaded_sector(2)).unwrap();
+        assert!(download_staging.contains(0));
+        assert!(!download_staging.contains(1));
+        assert_eq!(
+            download_staging.read(0).unwrap().unwrap().encode(),
+            downloaded_sector(0).encode()
+        );
+
+        download_staging.remove(0).unwrap();
+        assert!(!download_staging.contains(0));
+        // Removing sector that is not staged is fine
+        download_staging.remove(0).unwrap();
+    }
+
+    // Staged sectors survive reopening, but sectors outside of the farm are removed
+    let download_staging = DownloadStaging::open(&options, &farm_id, 2).unwrap();
+    assert!(!download_staging.contains(2));
+    let download_staging = DownloadStaging::open(&options, &farm_id, TARGET_SECTOR_COUNT).unwrap();
+    download_staging.write(1, &downloaded_sector(1)).unwrap();
+    let download_staging = DownloadStaging::open(&options, &farm_id, TARGET_SECTOR_COUNT).unwrap();
+    assert_eq!(
+        download_staging.read(1).unwrap().unwrap().encode(),
+        downloaded_sector(1).encode()
+    );
+}
+
+#[test]
+fn corruption() {
+    let directory = tempdir().unwrap();
+    let options = DownloadStagingOptions {
+        directory: directory.path().to_path_buf(),
+        sectors_ahead: NonZeroUsize::MIN,
+    };
+    let farm_id = SingleDiskFarmId::new();
+    let farm_directory = directory.path().join(farm_id.to_string());
+    let download_staging = DownloadStaging::open(&options, &farm_id, TARGET_SECTOR_COUNT).unwrap();
+
+    // Flipped byte is detected by checksum and corrupted sector is removed
+    download_staging.write(0, &downloaded_sector(0)).unwrap();
+    {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(farm_directory.join("0.bin"))
+            .unwrap();
+        let mut byte = [0];
+        file.read_exact_at(&mut byte, 100).unwrap();
+        byte[0] ^= 1;
+        file.write_all_at(&byte, 100).unwrap();
+    }
+    assert!(download_staging.read(0).unwrap().is_none());
+    assert!(!download_staging.contains(0));
+
+    // Truncated sector is removed too
+    download_staging.write(1, &downloaded_sector(1)).unwrap();
+    {
+        let path = farm_directory.join("1.bin");
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+    }
+    assert!(download_staging.read(1).unwrap().is_none());
+    assert!(!download_staging.contains(1));
+
+    // Partially written sector is removed on open
+    fs::write(farm_directory.join("2.bin.tmp"), [1, 2, 3]).unwrap();
+    DownloadStaging::open(&options, &farm_id, TARGET_SECTOR_COUNT).unwrap();
+    assert!(!farm_directory.join("2.bin.tmp").exists());
+}
+
+#[test]
+fn farm_removal() {
+    let directory = tempdir().unwrap();
+    let options = DownloadStagingOptions {
+        directory: directory.path().to_path_buf(),
+        sectors_ahead: NonZeroUsize::MIN,
+    };
+    let farm_ids = [
+        SingleDiskFarmId::new(),
+        SingleDiskFarmId::new(),
+        SingleDiskFarmId::new(),
+    ];
+    for farm_id in &farm_ids {
+        DownloadStaging::open(&options, farm_id, TARGET_SECTOR_COUNT)
+            .unwrap()
+            .write(0, &downloaded_sector(0))
+            .unwrap();
+    }
+    // Unrelated contents of scratch directory
+    fs::create_dir(directory.path().join("other")).unwrap();
+    fs::write(
+        directory.path().join(SingleDiskFarmId::new().to_string()),
+        [],
+    )
+    .unwrap();
+
+    options.remove_farm(&farm_ids[0]).unwrap();
+    assert!(!directory.path().join(farm_ids[0].to_string()).exists());
+    // Removing farm without staged sectors is fine
+    options.remove_farm(&farm_ids[0]).unwrap();
+
+    options.retain_farms(&farm_ids[1..2]).unwrap();
+    assert!(
+        DownloadStaging::open(&options, &farm_ids[1], TARGET_SECTOR_COUNT)
+            .unwrap()
+            .contains(0)
+    );
+    assert!(!directory.path().join(farm_ids[2].to_string()).exists());
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 3);
+
+    // Scratch directory that doesn't exist yet is fine
+    DownloadStagingOptions {
+        directory: directory.path().join("missing"),
+        sectors_ahead: NonZeroUsize::MIN,
+    }
+    .retain_farms(&farm_ids)
+    .unwrap();
+}
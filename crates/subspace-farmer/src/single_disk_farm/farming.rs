@@ -1,7 +1,9 @@
 pub mod rayon_files;
+pub mod reward_addresses;
 
 use crate::node_client;
 use crate::node_client::NodeClient;
+use crate::single_disk_farm::farming::reward_addresses::RewardAddresses;
 use crate::single_disk_farm::Handlers;
 use async_lock::RwLock;
 use futures::channel::mpsc;
@@ -321,7 +323,7 @@ where
 
 pub(super) struct FarmingOptions<NC, PlotAudit> {
     pub(super) public_key: PublicKey,
-    pub(super) reward_address: PublicKey,
+    pub(super) reward_addresses: RewardAddresses,
     pub(super) node_client: NC,
     pub(super) plot_audit: PlotAudit,
     pub(super) sectors_metadata: Arc<RwLock<Vec<SectorMetadataChecksummed>>>,
@@ -346,7 +348,7 @@ where
 {
     let FarmingOptions {
         public_key,
-        reward_address,
+        reward_addresses,
         node_client,
         plot_audit,
         sectors_metadata,
@@ -381,7 +383,9 @@ where
 
                 plot_audit.audit(PlotAuditOptions::<PosTable> {
                     public_key: &public_key,
-                    reward_address: &reward_address,
+                    // Actual reward address of each solution is selected below according to
+                    // rotation policy
+                    reward_address: reward_addresses.first(),
                     slot_info,
                     sectors_metadata: &sectors_metadata,
                     kzg: &kzg,
@@ -414,7 +418,10 @@ where
                 let mut start = Instant::now();
                 for maybe_solution in sector_solutions {
                     let solution = match maybe_solution {
-                        Ok(solution) => solution,
+                        Ok(solution) => Solution {
+                            reward_address: reward_addresses.select(),
+                            ..solution
+                        },
                         Err(error) => {
                             error!(%slot, %sector_index, %error, "Failed to prove");
                             // Do not error completely as disk corruption or other reasons why
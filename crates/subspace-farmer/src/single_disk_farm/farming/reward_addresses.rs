@@ -0,0 +1,106 @@
+//! Reward addresses of the farm, multiple addresses can be used according to rotation policy
+
+#[cfg(test)]
+mod tests;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subspace_core_primitives::PublicKey;
+use thiserror::Error;
+
+/// Errors happening when creating reward addresses
+#[derive(Debug, Error)]
+pub enum RewardAddressesError {
+    /// No reward addresses were provided
+    #[error("At least one reward address is required")]
+    NoAddresses,
+    /// Time window of rotation policy is zero
+    #[error("Rotation time window must not be zero")]
+    ZeroTimeWindow,
+}
+
+/// Policy of choosing reward address for solution when multiple addresses are available
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RewardAddressRotation {
+    /// Use next address for each solution (round-robin)
+    PerSolution,
+    /// Use the same address for all solutions within time window, switching to the next address
+    /// for the next window.
+    ///
+    /// Windows are aligned to Unix epoch, so the same address is used after farmer restart.
+    TimeWindow(Duration),
+}
+
+/// Reward addresses used for solutions of the farm
+#[derive(Debug, Clone)]
+pub struct RewardAddresses {
+    addresses: Arc<[PublicKey]>,
+    rotation: RewardAddressRotation,
+    next_index: Arc<AtomicUsize>,
+}
+
+impl From<PublicKey> for RewardAddresses {
+    fn from(reward_address: PublicKey) -> Self {
+        Self {
+            addresses: Arc::new([reward_address]),
+            rotation: RewardAddressRotation::PerSolution,
+            next_index: Arc::default(),
+        }
+    }
+}
+
+impl RewardAddresses {
+    /// Create new instance with multiple addresses and rotation policy
+    pub fn new(
+        addresses: Vec<PublicKey>,
+        rotation: RewardAddressRotation,
+    ) -> Result<Self, RewardAddressesError> {
+        if addresses.is_empty() {
+            return Err(RewardAddressesError::NoAddresses);
+        }
+        if rotation == RewardAddressRotation::TimeWindow(Duration::ZERO) {
+            return Err(RewardAddressesError::ZeroTimeWindow);
+        }
+
+        Ok(Self {
+            addresses: addresses.into(),
+            rotation,
+            next_index: Arc::default(),
+        })
+    }
+
+    /// All reward addresses
+    pub fn addresses(&self) -> &[PublicKey] {
+        &self.addresses
+    }
+
+    /// Rotation policy
+    pub fn rotation(&self) -> RewardAddressRotation {
+        self.rotation
+    }
+
+    /// First reward address
+    pub fn first(&self) -> &PublicKey {
+        self.addresses
+            .first()
+            .expect("Not empty, checked in constructor; qed")
+    }
+
+    /// Select reward address for the next solution according to rotation policy
+    pub fn select(&self) -> PublicKey {
+        self.select_at(SystemTime::now())
+    }
+
+    fn select_at(&self, now: SystemTime) -> PublicKey {
+        let index = match self.rotation {
+            RewardAddressRotation::PerSolution => self.next_index.fetch_add(1, Ordering::Relaxed),
+            RewardAddressRotation::TimeWindow(window) => {
+                let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+                (since_epoch.as_millis() / window.as_millis().max(1)) as usize
+            }
+        };
+
+        self.addresses[index % self.addresses.len()]
+    }
+}
@@ -0,0 +1,47 @@
+use crate::single_disk_farm::farming::reward_addresses::{
+    RewardAddressRotation, RewardAddresses, RewardAddressesError,
+};
+use std::time::{Duration, UNIX_EPOCH};
+use subspace_core_primitives::PublicKey;
+
+#[test]
+fn rotation() {
+    let addresses = vec![
+        PublicKey::from([1; 32]),
+        PublicKey::from([2; 32]),
+        PublicKey::from([3; 32]),
+    ];
+
+    let single = RewardAddresses::from(addresses[0]);
+    assert_eq!(single.select(), addresses[0]);
+    assert_eq!(single.select(), addresses[0]);
+
+    let per_solution =
+        RewardAddresses::new(addresses.clone(), RewardAddressRotation::PerSolution).unwrap();
+    let selected = (0..4).map(|_| per_solution.select()).collect::<Vec<_>>();
+    assert_eq!(
+        selected,
+        vec![addresses[0], addresses[1], addresses[2], addresses[0]]
+    );
+
+    let window = Duration::from_secs(3600);
+    let time_window =
+        RewardAddresses::new(addresses.clone(), RewardAddressRotation::TimeWindow(window)).unwrap();
+    let start = UNIX_EPOCH + window * 3;
+    assert_eq!(time_window.select_at(start), addresses[0]);
+    assert_eq!(
+        time_window.select_at(start + window - Duration::from_secs(1)),
+        addresses[0]
+    );
+    assert_eq!(time_window.select_at(start + window), addresses[1]);
+    assert_eq!(time_window.select_at(start + window * 2), addresses[2]);
+
+    assert!(matches!(
+        RewardAddresses::new(Vec::new(), RewardAddressRotation::PerSolution),
+        Err(RewardAddressesError::NoAddresses)
+    ));
+    assert!(matches!(
+        RewardAddresses::new(addresses, RewardAddressRotation::TimeWindow(Duration::ZERO)),
+        Err(RewardAddressesError::ZeroTimeWindow)
+    ));
+}
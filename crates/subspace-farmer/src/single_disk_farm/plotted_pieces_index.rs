@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod tests;
+
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::{io, mem};
+use subspace_core_primitives::crypto::blake3_hash;
+use subspace_core_primitives::{Blake3Hash, HistorySize, PieceIndex, SectorIndex};
+use subspace_farmer_components::file_ext::{FileExt, OpenOptionsExt};
+
+/// Size of encoded history size of the sector
+const HISTORY_SIZE_SIZE: usize = mem::size_of::<u64>();
+
+/// Persisted index of pieces plotted in each sector, allows to avoid deriving piece indices of all
+/// plotted sectors on every start.
+///
+/// Each sector has a fixed-size slot in the file containing history size of the sector, indices of
+/// pieces plotted in it and checksum. Slot is only used if checksum matches and history size
+/// matches sector metadata, otherwise piece indices need to be derived again.
+#[derive(Debug)]
+pub(super) struct PlottedPiecesIndex {
+    file: File,
+    pieces_in_sector: u16,
+}
+
+impl PlottedPiecesIndex {
+    pub(super) const FILE_NAME: &'static str = "plotted_pieces.bin";
+
+    pub(super) fn open(
+        directory: &Path,
+        pieces_in_sector: u16,
+        target_sector_count: SectorIndex,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .advise_random_access()
+            .open(directory.join(Self::FILE_NAME))?;
+
+        file.advise_random_access()?;
+
+        let index = Self {
+            file,
+            pieces_in_sector,
+        };
+
+        // Remove slots of sectors that no longer exist in case farm was shrunk
+        let expected_max_size = index.slot_size() as u64 * u64::from(target_sector_count);
+        if index.file.metadata()?.len() > expected_max_size {
+            index.file.set_len(expected_max_size)?;
+        }
+
+        Ok(index)
+    }
+
+    /// Read piece indices of the sector, returns `None` if sector is not in the index or index
+    /// entry doesn't correspond to sector with provided history size
+    pub(super) fn read_sector(
+        &self,
+        sector_index: SectorIndex,
+        history_size: HistorySize,
+    ) -> io::Result<Option<Vec<PieceIndex>>> {
+        let slot_size = self.slot_size();
+        let slot_offset = u64::from(sector_index) * slot_size as u64;
+        if self.file.metadata()?.len() < slot_offset + slot_size as u64 {
+            return Ok(None);
+        }
+
+        let mut slot = vec![0; slot_size];
+        self.file.read_exact_at(&mut slot, slot_offset)?;
+
+        let (contents, expected_checksum) = slot.split_at(slot_size - mem::size_of::<Blake3Hash>());
+        // Slots of sectors that were never written and partially written slots end up here
+        if blake3_hash(contents) != expected_checksum {
+            return Ok(None);
+        }
+
+        let (history_size_bytes, piece_indexes_bytes) = contents.split_at(HISTORY_SIZE_SIZE);
+        if history_size_bytes != Self::history_size_bytes(history_size) {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            piece_indexes_bytes
+                .array_chunks::<{ PieceIndex::SIZE }>()
+                .copied()
+                .map(PieceIndex::from_bytes)
+                .collect(),
+        ))
+    }
+
+    /// Write piece indices of the sector plotted with provided history size
+    pub(super) fn write_sector(
+        &self,
+        sector_index: SectorIndex,
+        history_size: HistorySize,
+        piece_indexes: &[PieceIndex],
+    ) -> io::Result<()> {
+        if piece_indexes.len() != usize::from(self.pieces_in_sector) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expected {} piece indices, got {}",
+                    self.pieces_in_sector,
+                    piece_indexes.len()
+                ),
+            ));
+        }
+
+        let slot_size = self.slot_size();
+        let mut slot = Vec::with_capacity(slot_size);
+        slot.extend_from_slice(&Self::history_size_bytes(history_size));
+        for &piece_index in piece_indexes {
+            slot.extend_from_slice(&piece_index.to_bytes());
+        }
+        slot.extend_from_slice(&blake3_hash(&slot));
+
+        self.file
+            .write_all_at(&slot, u64::from(sector_index) * slot_size as u64)
+    }
+
+    /// Invalidate index entry of the sector, such that piece indices are derived again next time
+    /// (useful when writing of the entry failed and its contents are unknown)
+    pub(super) fn invalidate_sector(&self, sector_index: SectorIndex) -> io::Result<()> {
+        let slot_size = self.slot_size();
+        let slot_offset = u64::from(sector_index) * slot_size as u64;
+        if self.file.metadata()?.len() < slot_offset + slot_size as u64 {
+            // Nothing to invalidate
+            return Ok(());
+        }
+
+        // Zero checksum never matches contents
+        self.file.write_all_at(
+            &Blake3Hash::default(),
+            slot_offset + (slot_size - mem::size_of::<Blake3Hash>()) as u64,
+        )
+    }
+
+    fn history_size_bytes(history_size: HistorySize) -> [u8; HISTORY_SIZE_SIZE] {
+        history_size.in_pieces().get().to_le_bytes()
+    }
+
+    fn slot_size(&self) -> usize {
+        HISTORY_SIZE_SIZE
+            + usize::from(self.pieces_in_sector) * PieceIndex::SIZE
+            + mem::size_of::<Blake3Hash>()
+    }
+}
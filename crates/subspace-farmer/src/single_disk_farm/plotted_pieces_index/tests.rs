@@ -0,0 +1,122 @@
+use crate::single_disk_farm::plotted_pieces_index::PlottedPiecesIndex;
+use std::fs::OpenOptions;
+use std::num::NonZeroU64;
+use subspace_core_primitives::{HistorySize, PieceIndex};
+use subspace_farmer_components::file_ext::FileExt;
+use tempfile::tempdir;
+
+const PIECES_IN_SECTOR: u16 = 4;
+const TARGET_SECTOR_COUNT: u16 = 3;
+
+fn piece_indexes(first: u64) -> Vec<PieceIndex> {
+    (first..)
+        .take(usize::from(PIECES_IN_SECTOR))
+        .map(PieceIndex::from)
+        .collect()
+}
+
+#[test]
+fn basic() {
+    let directory = tempdir().unwrap();
+    let history_size = HistorySize::new(NonZeroU64::MIN);
+    let other_history_size = HistorySize::new(NonZeroU64::MAX);
+
+    {
+        let index =
+            PlottedPiecesIndex::open(directory.path(), PIECES_IN_SECTOR, TARGET_SECTOR_COUNT)
+                .unwrap();
+
+        // Nothing is written yet
+        assert_eq!(index.read_sector(0, history_size).unwrap(), None);
+
+        index
+            .write_sector(1, history_size, &piece_indexes(10))
+            .unwrap();
+        // Sector before written one is still not in the index
+        assert_eq!(index.read_sector(0, history_size).unwrap(), None);
+        assert_eq!(
+            index.read_sector(1, history_size).unwrap(),
+            Some(piece_indexes(10))
+        );
+        // Replotted sector has different history size and must not use old entry
+        assert_eq!(index.read_sector(1, other_history_size).unwrap(), None);
+
+        // Wrong number of piece indices
+        assert!(index
+            .write_sector(2, history_size, &piece_indexes(10)[1..])
+            .is_err());
+    }
+
+    // Index survives reopening
+    let index =
+        PlottedPiecesIndex::open(directory.path(), PIECES_IN_SECTOR, TARGET_SECTOR_COUNT).unwrap();
+    assert_eq!(
+        index.read_sector(1, history_size).unwrap(),
+        Some(piece_indexes(10))
+    );
+}
+
+#[test]
+fn corruption() {
+    let directory = tempdir().unwrap();
+    let history_size = HistorySize::new(NonZeroU64::MIN);
+
+    let index =
+        PlottedPiecesIndex::open(directory.path(), PIECES_IN_SECTOR, TARGET_SECTOR_COUNT).unwrap();
+    index
+        .write_sector(0, history_size, &piece_indexes(0))
+        .unwrap();
+    index
+        .write_sector(1, history_size, &piece_indexes(10))
+        .unwrap();
+
+    // Corrupt one byte of the first piece index of the first sector
+    OpenOptions::new()
+        .write(true)
+        .open(directory.path().join(PlottedPiecesIndex::FILE_NAME))
+        .unwrap()
+        .write_all_at(&[0xff], 8)
+        .unwrap();
+
+    assert_eq!(index.read_sector(0, history_size).unwrap(), None);
+    // Other sectors are not affected
+    assert_eq!(
+        index.read_sector(1, history_size).unwrap(),
+        Some(piece_indexes(10))
+    );
+}
+
+#[test]
+fn invalidation() {
+    let directory = tempdir().unwrap();
+    let history_size = HistorySize::new(NonZeroU64::MIN);
+
+    let index =
+        PlottedPiecesIndex::open(directory.path(), PIECES_IN_SECTOR, TARGET_SECTOR_COUNT).unwrap();
+    index
+        .write_sector(0, history_size, &piece_indexes(0))
+        .unwrap();
+    index
+        .write_sector(1, history_size, &piece_indexes(10))
+        .unwrap();
+
+    index.invalidate_sector(0).unwrap();
+    assert_eq!(index.read_sector(0, history_size).unwrap(), None);
+    // Other sectors are not affected
+    assert_eq!(
+        index.read_sector(1, history_size).unwrap(),
+        Some(piece_indexes(10))
+    );
+    // Sectors that are not in the index yet are fine to invalidate
+    index.invalidate_sector(2).unwrap();
+    assert_eq!(index.read_sector(2, history_size).unwrap(), None);
+
+    // Entry is valid again after it is written
+    index
+        .write_sector(0, history_size, &piece_indexes(0))
+        .unwrap();
+    assert_eq!(
+        index.read_sector(0, history_size).unwrap(),
+        Some(piece_indexes(0))
+    );
+}
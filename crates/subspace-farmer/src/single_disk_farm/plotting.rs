@@ -1,7 +1,10 @@
+use crate::single_disk_farm::download_staging::DownloadStaging;
+use crate::single_disk_farm::plotted_pieces_index::PlottedPiecesIndex;
 use crate::single_disk_farm::{
     BackgroundTaskError, Handlers, PlotMetadataHeader, SectorUpdate, RESERVED_PLOT_METADATA,
 };
 use crate::thread_pool_manager::PlottingThreadPoolManager;
+use crate::utils::plotting_schedule::PlottingSchedule;
 use crate::utils::AsyncJoinOnDrop;
 use crate::{node_client, NodeClient};
 use async_lock::RwLock;
@@ -30,7 +33,10 @@ use subspace_farmer_components::plotting::{
     PlottedSector,
 };
 use subspace_farmer_components::sector::SectorMetadataChecksummed;
-use subspace_farmer_components::{plotting, PieceGetter, PieceGetterRetryPolicy};
+use subspace_farmer_components::segment_reconstruction::SegmentReconstructionCache;
+use subspace_farmer_components::{
+    plotting, FarmerProtocolInfo, PieceGetter, PieceGetterRetryPolicy,
+};
 use subspace_proof_of_space::Table;
 use thiserror::Error;
 use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
@@ -55,6 +61,10 @@ pub enum SectorPlottingDetails {
         /// Whether this is the last sector queued so far
         last_queued: bool,
     },
+    /// Plotting of the sector didn't start yet because plotting is paused
+    Paused,
+    /// Plotting of the sector was resumed after being paused for specified time
+    Resumed(Duration),
     /// Downloading sector pieces
     Downloading,
     /// Downloaded sector pieces
@@ -150,6 +160,7 @@ pub(super) struct PlottingOptions<'a, NC, PG> {
     pub(super) plot_file: Arc<File>,
     pub(super) metadata_file: File,
     pub(super) sectors_metadata: Arc<RwLock<Vec<SectorMetadataChecksummed>>>,
+    pub(super) plotted_pieces_index: Arc<PlottedPiecesIndex>,
     pub(super) piece_getter: &'a PG,
     pub(super) kzg: &'a Kzg,
     pub(super) erasure_coding: &'a ErasureCoding,
@@ -161,6 +172,11 @@ pub(super) struct PlottingOptions<'a, NC, PG> {
     pub(crate) downloading_semaphore: Arc<Semaphore>,
     pub(crate) record_encoding_concurrency: NonZeroUsize,
     pub(super) plotting_thread_pool_manager: PlottingThreadPoolManager,
+    pub(super) plotting_schedule: PlottingSchedule,
+    /// Downloaded sectors are staged on disk ahead of encoding if specified
+    pub(super) download_staging: Option<Arc<DownloadStaging>>,
+    pub(super) target_sector_count: SectorIndex,
+    pub(super) segment_reconstruction_cache: SegmentReconstructionCache,
     pub(super) stop_receiver: broadcast::Receiver<()>,
 }
 
@@ -186,6 +202,7 @@ where
         plot_file,
         metadata_file,
         sectors_metadata,
+        plotted_pieces_index,
         piece_getter,
         kzg,
         erasure_coding,
@@ -195,6 +212,10 @@ where
         downloading_semaphore,
         record_encoding_concurrency,
         plotting_thread_pool_manager,
+        plotting_schedule,
+        download_staging,
+        target_sector_count,
+        segment_reconstruction_cache,
         mut stop_receiver,
     } = plotting_options;
 
@@ -221,6 +242,9 @@ where
     let mut maybe_next_downloaded_sector_fut = None::<
         AsyncJoinOnDrop<Result<(OwnedSemaphorePermit, DownloadedSector), plotting::PlottingError>>,
     >;
+    // Sectors that are being downloaded and staged on disk right now
+    let mut staging_tasks =
+        HashMap::<SectorIndex, AsyncJoinOnDrop<Result<(), PlottingError>>>::new();
     while let Some(sector_to_plot) = sectors_to_plot_receiver.next().await {
         let SectorToPlot {
             sector_index,
@@ -234,6 +258,29 @@ where
         } = sector_to_plot;
         trace!(%sector_index, "Preparing to plot sector");
 
+        if let Some(reason) = plotting_schedule.pause_reason() {
+            info!(%sector_index, %reason, "Plotting is paused");
+            handlers.sector_update.call_simple(&(
+                sector_index,
+                SectorUpdate::Plotting(SectorPlottingDetails::Paused),
+            ));
+
+            let start = Instant::now();
+            plotting_schedule.wait_until_allowed().await;
+            let paused_time = start.elapsed();
+
+            info!(%sector_index, ?paused_time, "Plotting is resumed");
+            handlers.sector_update.call_simple(&(
+                sector_index,
+                SectorUpdate::Plotting(SectorPlottingDetails::Resumed(paused_time)),
+            ));
+        }
+
+        let Some(sector_in_progress) = plotting_schedule.start_sector() else {
+            info!(%sector_index, "Plotting is stopped, not starting new sectors");
+            break;
+        };
+
         let maybe_old_sector_metadata = sectors_metadata
             .read()
             .await
@@ -285,8 +332,28 @@ where
             break farmer_app_info;
         };
 
+        let maybe_staged_sector = if let Some(download_staging) = &download_staging {
+            read_staged_sector(
+                download_staging,
+                &mut staging_tasks,
+                &downloading_semaphore,
+                &public_key,
+                sector_index,
+                pieces_in_sector,
+                &farmer_app_info.protocol_info,
+                maybe_old_sector_metadata
+                    .as_ref()
+                    .map(|old_sector_metadata| old_sector_metadata.history_size),
+            )
+            .await?
+        } else {
+            None
+        };
+
         let (_downloading_permit, downloaded_sector) =
-            if let Some(downloaded_sector_fut) = maybe_next_downloaded_sector_fut.take() {
+            if let Some(staged_sector) = maybe_staged_sector {
+                staged_sector
+            } else if let Some(downloaded_sector_fut) = maybe_next_downloaded_sector_fut.take() {
                 downloaded_sector_fut
                     .await
                     .map_err(|_error| PlottingError::BackgroundDownloadingPanicked)??
@@ -313,6 +380,7 @@ where
                     farmer_protocol_info: farmer_app_info.protocol_info,
                     kzg,
                     pieces_in_sector,
+                    segment_reconstruction_cache: Some(&segment_reconstruction_cache),
                 });
 
                 let downloaded_sector = downloaded_sector_fut.await?;
@@ -325,12 +393,98 @@ where
                 (downloading_permit, downloaded_sector)
             };
 
-        // Initiate downloading of pieces for the next segment index if already known
-        if let Some(sector_index) = next_segment_index_hint {
+        if let Some(download_staging) = &download_staging {
+            // Sequential initial plotting can be staged many sectors ahead, otherwise only the
+            // next sector is known
+            let sectors_to_stage =
+                if !replotting && next_segment_index_hint == Some(sector_index + 1) {
+                    (sector_index + 1..target_sector_count)
+                        .take(download_staging.sectors_ahead().get())
+                        .collect::<Vec<_>>()
+                } else {
+                    next_segment_index_hint.into_iter().collect()
+                };
+
+            // Sectors that are no longer expected to be plotted soon are not staged anymore
+            staging_tasks
+                .retain(|sector_index, _staging_task| sectors_to_stage.contains(sector_index));
+
+            for sector_index in sectors_to_stage {
+                if staging_tasks.contains_key(&sector_index)
+                    || download_staging.contains(sector_index)
+                {
+                    continue;
+                }
+
+                let download_staging = Arc::clone(download_staging);
+                let piece_getter = piece_getter.clone();
+                let downloading_semaphore = Arc::clone(&downloading_semaphore);
+                let handlers = Arc::clone(&handlers);
+                let kzg = kzg.clone();
+                let segment_reconstruction_cache = segment_reconstruction_cache.clone();
+
+                staging_tasks.insert(
+                    sector_index,
+                    AsyncJoinOnDrop::new(
+                        tokio::spawn(
+                            async move {
+                                // Permit is only held while sector is in memory
+                                let _downloading_permit = downloading_semaphore
+                                    .acquire_owned()
+                                    .await
+                                    .map_err(plotting::PlottingError::from)?;
+
+                                handlers.sector_update.call_simple(&(
+                                    sector_index,
+                                    SectorUpdate::Plotting(SectorPlottingDetails::Downloading),
+                                ));
+
+                                let start = Instant::now();
+
+                                let downloaded_sector = download_sector(DownloadSectorOptions {
+                                    public_key: &public_key,
+                                    sector_index,
+                                    piece_getter: &piece_getter,
+                                    piece_getter_retry_policy: PieceGetterRetryPolicy::Limited(
+                                        PIECE_GETTER_RETRY_NUMBER.get(),
+                                    ),
+                                    farmer_protocol_info: farmer_app_info.protocol_info,
+                                    kzg: &kzg,
+                                    pieces_in_sector,
+                                    segment_reconstruction_cache: Some(
+                                        &segment_reconstruction_cache,
+                                    ),
+                                })
+                                .await?;
+
+                                tokio::task::spawn_blocking(move || {
+                                    download_staging.write(sector_index, &downloaded_sector)
+                                })
+                                .await
+                                .map_err(|_error| PlottingError::BackgroundDownloadingPanicked)??;
+
+                                handlers.sector_update.call_simple(&(
+                                    sector_index,
+                                    SectorUpdate::Plotting(SectorPlottingDetails::Downloaded(
+                                        start.elapsed(),
+                                    )),
+                                ));
+
+                                Ok(())
+                            }
+                            .in_current_span(),
+                        ),
+                        true,
+                    ),
+                );
+            }
+        } else if let Some(sector_index) = next_segment_index_hint {
+            // Initiate downloading of pieces for the next segment index if already known
             let piece_getter = piece_getter.clone();
             let downloading_semaphore = Arc::clone(&downloading_semaphore);
             let handlers = Arc::clone(&handlers);
             let kzg = kzg.clone();
+            let segment_reconstruction_cache = segment_reconstruction_cache.clone();
 
             maybe_next_downloaded_sector_fut.replace(AsyncJoinOnDrop::new(
                 tokio::spawn(
@@ -357,6 +511,7 @@ where
                             farmer_protocol_info: farmer_app_info.protocol_info,
                             kzg: &kzg,
                             pieces_in_sector,
+                            segment_reconstruction_cache: Some(&segment_reconstruction_cache),
                         });
 
                         let downloaded_sector = downloaded_sector_fut.await?;
@@ -453,6 +608,19 @@ where
                 &sector_metadata,
                 RESERVED_PLOT_METADATA + (u64::from(sector_index) * sector_metadata_size as u64),
             )?;
+            // Index is only an optimization, sector is plotted successfully even if index can't
+            // be updated, piece indices will be derived again on next start in that case
+            if let Err(error) = plotted_pieces_index.write_sector(
+                sector_index,
+                plotted_sector.sector_metadata.history_size,
+                &plotted_sector.piece_indexes,
+            ) {
+                warn!(%sector_index, %error, "Failed to write plotted pieces index");
+
+                if let Err(error) = plotted_pieces_index.invalidate_sector(sector_index) {
+                    warn!(%sector_index, %error, "Failed to invalidate plotted pieces index");
+                }
+            }
 
             handlers.sector_update.call_simple(&(
                 sector_index,
@@ -460,6 +628,12 @@ where
             ));
         }
 
+        if let Some(download_staging) = &download_staging
+            && let Err(error) = download_staging.remove(sector_index)
+        {
+            warn!(%sector_index, %error, "Failed to remove staged sector");
+        }
+
         if sector_index + 1 > metadata_header.plotted_sector_count {
             metadata_header.plotted_sector_count = sector_index + 1;
             metadata_file.write_all_at(&metadata_header.encode(), 0)?;
@@ -524,11 +698,84 @@ where
         handlers
             .sector_update
             .call_simple(&(sector_index, sector_state));
+
+        if plotting_schedule.is_stopped() {
+            // Farmer is about to shut down, make sure sector is persisted before that
+            plot_file.sync_data()?;
+            metadata_file.sync_data()?;
+        }
+        drop(sector_in_progress);
     }
 
     Ok(())
 }
 
+/// Read sector from download staging (waiting for it to be staged if it is being downloaded right
+/// now), returns `None` if sector is not staged or staged sector can't be used to plot this sector
+#[allow(clippy::too_many_arguments)]
+async fn read_staged_sector(
+    download_staging: &Arc<DownloadStaging>,
+    staging_tasks: &mut HashMap<SectorIndex, AsyncJoinOnDrop<Result<(), PlottingError>>>,
+    downloading_semaphore: &Arc<Semaphore>,
+    public_key: &PublicKey,
+    sector_index: SectorIndex,
+    pieces_in_sector: u16,
+    farmer_protocol_info: &FarmerProtocolInfo,
+    maybe_old_history_size: Option<HistorySize>,
+) -> Result<Option<(OwnedSemaphorePermit, DownloadedSector)>, PlottingError> {
+    if let Some(staging_task) = staging_tasks.remove(&sector_index) {
+        match staging_task.await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                warn!(%sector_index, %error, "Failed to stage sector, downloading again");
+            }
+            Err(_error) => {
+                warn!(%sector_index, "Staging of sector panicked, downloading again");
+            }
+        }
+    }
+
+    let downloading_permit = Arc::clone(downloading_semaphore)
+        .acquire_owned()
+        .await
+        .map_err(plotting::PlottingError::from)?;
+
+    let maybe_downloaded_sector = tokio::task::spawn_blocking({
+        let download_staging = Arc::clone(download_staging);
+
+        move || download_staging.read(sector_index)
+    })
+    .await
+    .map_err(|_error| PlottingError::BackgroundDownloadingPanicked)??;
+    let Some(downloaded_sector) = maybe_downloaded_sector else {
+        return Ok(None);
+    };
+
+    let staged_history_size = downloaded_sector.farmer_protocol_info().history_size;
+    let usable = downloaded_sector.sector_id() == SectorId::new(public_key.hash(), sector_index)
+        && downloaded_sector.pieces_in_sector() == usize::from(pieces_in_sector)
+        && maybe_old_history_size
+            .map_or(true, |old_history_size| staged_history_size > old_history_size)
+        // Sector staged long ago (for instance before farmer restart) would be plotted with
+        // history that is already old enough to be checked for expiration, such sector would need
+        // to be replotted right away
+        && staged_history_size
+            .sector_expiration_check(farmer_protocol_info.min_sector_lifetime)
+            .is_some_and(|expiration_check_history_size| {
+                expiration_check_history_size > farmer_protocol_info.history_size
+            });
+
+    if !usable {
+        debug!(%sector_index, "Staged sector can't be used, downloading again");
+        download_staging.remove(sector_index)?;
+        return Ok(None);
+    }
+
+    debug!(%sector_index, "Using staged sector");
+
+    Ok(Some((downloading_permit, downloaded_sector)))
+}
+
 pub(super) struct PlottingSchedulerOptions<NC> {
     pub(super) public_key_hash: Blake3Hash,
     pub(super) sectors_indices_left_to_plot: Range<SectorIndex>,
@@ -0,0 +1,77 @@
+use crate::single_disk_farm::piece_cache::DiskPieceCache;
+use crate::single_disk_farm::{
+    SingleDiskFarm, SingleDiskFarmError, SingleDiskFarmId, SingleDiskFarmInfo,
+};
+use std::assert_matches::assert_matches;
+use subspace_core_primitives::PublicKey;
+use tempfile::tempdir;
+
+#[test]
+fn cache_only_farm() {
+    let directory = tempdir().unwrap();
+    let element_size = u64::from(DiskPieceCache::element_size());
+
+    {
+        // Space that is not a multiple of element size is rounded down
+        let cache_only_farm =
+            SingleDiskFarm::open_cache_only(directory.path(), element_size * 3 + 1, false).unwrap();
+        assert_eq!(cache_only_farm.capacity(), 3);
+        assert_eq!(cache_only_farm.piece_cache().contents().len(), 3);
+
+        // Directory is locked while farm is open
+        assert_matches!(
+            SingleDiskFarm::open_cache_only(directory.path(), element_size * 3, false),
+            Err(SingleDiskFarmError::LikelyAlreadyInUse(_))
+        );
+        // Unless locking is disabled
+        SingleDiskFarm::open_cache_only(directory.path(), element_size * 3, true).unwrap();
+    }
+
+    // Lock is released once farm is dropped
+    SingleDiskFarm::open_cache_only(directory.path(), element_size * 3, false).unwrap();
+}
+
+#[test]
+fn cache_only_farm_allocated_space() {
+    let directory = tempdir().unwrap();
+    let element_size = u64::from(DiskPieceCache::element_size());
+
+    assert_matches!(
+        SingleDiskFarm::open_cache_only(directory.path(), element_size - 1, false),
+        Err(SingleDiskFarmError::InsufficientAllocatedSpace { .. })
+    );
+    // Too large farm is rejected instead of being silently truncated
+    assert_matches!(
+        SingleDiskFarm::open_cache_only(
+            directory.path(),
+            (u64::from(u32::MAX) + 1) * element_size,
+            false
+        ),
+        Err(SingleDiskFarmError::CacheOnlyFarmTooLarge { max_space, .. })
+            if max_space == u64::from(u32::MAX) * element_size
+    );
+}
+
+#[test]
+fn cache_only_farm_in_regular_farm_directory() {
+    let directory = tempdir().unwrap();
+
+    SingleDiskFarmInfo::new(
+        SingleDiskFarmId::new(),
+        [0; 32],
+        PublicKey::default(),
+        1000,
+        u64::from(DiskPieceCache::element_size()) * 10,
+    )
+    .store_to(directory.path())
+    .unwrap();
+
+    assert_matches!(
+        SingleDiskFarm::open_cache_only(
+            directory.path(),
+            u64::from(DiskPieceCache::element_size()),
+            false
+        ),
+        Err(SingleDiskFarmError::NotCacheOnlyFarm { .. })
+    );
+}
@@ -1,5 +1,6 @@
 use parking_lot::{Condvar, Mutex};
 use rayon::{ThreadPool, ThreadPoolBuildError};
+use std::mem;
 use std::num::NonZeroUsize;
 use std::ops::Deref;
 use std::sync::Arc;
@@ -14,6 +15,12 @@ pub struct PlottingThreadPoolPair {
 #[derive(Debug)]
 struct Inner {
     thread_pool_pairs: Vec<PlottingThreadPoolPair>,
+    /// Incremented every time thread pools are replaced
+    generation: u64,
+    /// Number of thread pool pairs that can be used concurrently
+    capacity: usize,
+    /// Number of thread pool pairs that are in use right now (including previous generations)
+    in_use: usize,
 }
 
 /// Wrapper around [`PlottingThreadPoolPair`] that on `Drop` will return thread pool back into corresponding
@@ -22,6 +29,7 @@ struct Inner {
 pub struct PlottingThreadPoolsGuard {
     inner: Arc<(Mutex<Inner>, Condvar)>,
     thread_pool_pair: Option<PlottingThreadPoolPair>,
+    generation: u64,
 }
 
 impl Deref for PlottingThreadPoolsGuard {
@@ -36,14 +44,27 @@ impl Deref for PlottingThreadPoolsGuard {
 
 impl Drop for PlottingThreadPoolsGuard {
     fn drop(&mut self) {
+        let thread_pool_pair = self
+            .thread_pool_pair
+            .take()
+            .expect("Happens only once in `Drop`; qed");
+
         let (mutex, cvar) = &*self.inner;
-        let mut inner = mutex.lock();
-        inner.thread_pool_pairs.push(
-            self.thread_pool_pair
-                .take()
-                .expect("Happens only once in `Drop`; qed"),
-        );
+        let outdated_thread_pool_pair = {
+            let mut inner = mutex.lock();
+            inner.in_use -= 1;
+            if inner.generation == self.generation {
+                inner.thread_pool_pairs.push(thread_pool_pair);
+                None
+            } else {
+                Some(thread_pool_pair)
+            }
+        };
         cvar.notify_one();
+
+        // Thread pools were replaced while this one was in use, it is no longer needed and is
+        // dropped without holding the lock
+        drop(outdated_thread_pool_pair);
     }
 }
 
@@ -80,6 +101,9 @@ impl PlottingThreadPoolManager {
             thread_pool_pairs: (0..thread_pool_pairs.get())
                 .map(create_thread_pools)
                 .collect::<Result<Vec<_>, _>>()?,
+            generation: 0,
+            capacity: thread_pool_pairs.get(),
+            in_use: 0,
         };
 
         Ok(Self {
@@ -87,23 +111,65 @@ impl PlottingThreadPoolManager {
         })
     }
 
+    /// Number of thread pool pairs that can be used concurrently
+    pub fn thread_pool_pairs(&self) -> NonZeroUsize {
+        NonZeroUsize::new(self.inner.0.lock().capacity).expect("Never zero; qed")
+    }
+
+    /// Replace inner thread pool pairs with `thread_pool_pairs` thread pools instantiated using
+    /// `create_thread_pools`, which allows to adjust plotting concurrency while thread pools are
+    /// in use.
+    ///
+    /// Thread pools that are in use right now are dropped once returned, new thread pools are not
+    /// handed out until number of thread pools in use drops below new number of thread pools.
+    pub fn replace_thread_pools<C>(
+        &self,
+        create_thread_pools: C,
+        thread_pool_pairs: NonZeroUsize,
+    ) -> Result<(), ThreadPoolBuildError>
+    where
+        C: FnMut(usize) -> Result<PlottingThreadPoolPair, ThreadPoolBuildError>,
+    {
+        let new_thread_pool_pairs = (0..thread_pool_pairs.get())
+            .map(create_thread_pools)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (mutex, cvar) = &*self.inner;
+        let old_thread_pool_pairs = {
+            let mut inner = mutex.lock();
+            inner.generation += 1;
+            inner.capacity = thread_pool_pairs.get();
+            mem::replace(&mut inner.thread_pool_pairs, new_thread_pool_pairs)
+        };
+        cvar.notify_all();
+
+        // Drop old thread pools without holding the lock
+        drop(old_thread_pool_pairs);
+
+        Ok(())
+    }
+
     /// Get one of inner thread pool pairs, will block until one is available if needed
     #[must_use]
     pub fn get_thread_pools(&self) -> PlottingThreadPoolsGuard {
         let (mutex, cvar) = &*self.inner;
         let mut inner = mutex.lock();
 
-        let thread_pool_pair = inner.thread_pool_pairs.pop().unwrap_or_else(|| {
+        // Thread pool may not be available even after notification if thread pools were replaced
+        while inner.in_use >= inner.capacity || inner.thread_pool_pairs.is_empty() {
             cvar.wait(&mut inner);
+        }
 
-            inner.thread_pool_pairs.pop().expect(
-                "Guaranteed by parking_lot's API to happen when thread pool is inserted; qed",
-            )
-        });
+        let thread_pool_pair = inner
+            .thread_pool_pairs
+            .pop()
+            .expect("Checked that thread pool is available above; qed");
+        inner.in_use += 1;
 
         PlottingThreadPoolsGuard {
             inner: Arc::clone(&self.inner),
             thread_pool_pair: Some(thread_pool_pair),
+            generation: inner.generation,
         }
     }
 }
@@ -1,6 +1,9 @@
 pub mod farmer_piece_getter;
+pub mod piece_serving;
 pub mod piece_validator;
 pub mod plotted_pieces;
+pub mod plotting_memory;
+pub mod plotting_schedule;
 pub mod ss58;
 #[cfg(test)]
 mod tests;
@@ -10,13 +13,21 @@ use futures::channel::oneshot;
 use futures::channel::oneshot::Canceled;
 use futures::future::Either;
 use rayon::{ThreadBuilder, ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+#[cfg(unix)]
+use std::fs;
 use std::future::Future;
 use std::num::{NonZeroUsize, ParseIntError};
 use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::path::Path;
 use std::pin::{pin, Pin};
 use std::str::FromStr;
 use std::task::{Context, Poll};
 use std::{io, thread};
+#[cfg(unix)]
+use tokio::net::UnixListener;
 use tokio::runtime::Handle;
 use tokio::task;
 use tracing::debug;
@@ -179,6 +190,14 @@ impl CpuCoreSet {
         &self.cores
     }
 
+    /// Remove provided CPU cores from this set (for instance because they are used exclusively for
+    /// something else), returns `false` if no CPU cores are left in the set
+    pub fn remove_cpu_cores(&mut self, cpu_cores: &[usize]) -> bool {
+        self.cores.retain(|cpu_core| !cpu_cores.contains(cpu_core));
+
+        !self.cores.is_empty()
+    }
+
     /// Will truncate list of CPU cores to this number.
     ///
     /// If `cores` is zero, call will do nothing since zero number of cores is not allowed.
@@ -235,6 +254,12 @@ pub fn recommended_number_of_farming_threads() -> usize {
     num_cpus::get().min(MAX_DEFAULT_FARMING_THREADS)
 }
 
+/// Recommended number of records to encode concurrently in a single sector using thread pool that
+/// corresponds to provided CPU core set: one record per 2 cores, but not more than 8 in total
+pub fn recommended_record_encoding_concurrency(cpu_core_set: &CpuCoreSet) -> NonZeroUsize {
+    NonZeroUsize::new((cpu_core_set.cpu_cores().len() / 2).clamp(1, 8)).expect("Not zero; qed")
+}
+
 /// Get all cpu cores, grouped into sets according to NUMA nodes or L3 cache groups on large CPUs.
 ///
 /// Returned vector is guaranteed to have at least one element and have non-zero number of CPU cores
@@ -404,7 +429,7 @@ fn create_plotting_thread_pool_manager_thread_pool_pair(
 /// The easiest way to obtain CPUs is using [`all_cpu_cores`], but [`thread_pool_core_indices`] in case
 /// support for user customizations is desired. They will then have to be composed into pairs for this function.
 pub fn create_plotting_thread_pool_manager<I>(
-    mut cpu_core_sets: I,
+    cpu_core_sets: I,
 ) -> Result<PlottingThreadPoolManager, ThreadPoolBuildError>
 where
     I: ExactSizeIterator<Item = (CpuCoreSet, CpuCoreSet)>,
@@ -412,29 +437,57 @@ where
     let total_thread_pools = cpu_core_sets.len();
 
     PlottingThreadPoolManager::new(
-        |thread_pool_index| {
-            let (plotting_cpu_core_set, replotting_cpu_core_set) = cpu_core_sets
-                .next()
-                .expect("Number of thread pools is the same as cpu core sets; qed");
-
-            Ok(PlottingThreadPoolPair {
-                plotting: create_plotting_thread_pool_manager_thread_pool_pair(
-                    "plotting",
-                    thread_pool_index,
-                    plotting_cpu_core_set,
-                )?,
-                replotting: create_plotting_thread_pool_manager_thread_pool_pair(
-                    "replotting",
-                    thread_pool_index,
-                    replotting_cpu_core_set,
-                )?,
-            })
-        },
+        create_plotting_thread_pool_pairs(cpu_core_sets),
+        NonZeroUsize::new(total_thread_pools)
+            .expect("Thread pool is guaranteed to be non-empty; qed"),
+    )
+}
+
+/// Replaces thread pool pairs of plotting thread pool manager created with
+/// [`create_plotting_thread_pool_manager`] with new thread pool pairs for each of CPU core set
+/// pair, used to adjust plotting concurrency while farmer is running.
+pub fn replace_plotting_thread_pools<I>(
+    plotting_thread_pool_manager: &PlottingThreadPoolManager,
+    cpu_core_sets: I,
+) -> Result<(), ThreadPoolBuildError>
+where
+    I: ExactSizeIterator<Item = (CpuCoreSet, CpuCoreSet)>,
+{
+    let total_thread_pools = cpu_core_sets.len();
+
+    plotting_thread_pool_manager.replace_thread_pools(
+        create_plotting_thread_pool_pairs(cpu_core_sets),
         NonZeroUsize::new(total_thread_pools)
             .expect("Thread pool is guaranteed to be non-empty; qed"),
     )
 }
 
+fn create_plotting_thread_pool_pairs<I>(
+    mut cpu_core_sets: I,
+) -> impl FnMut(usize) -> Result<PlottingThreadPoolPair, ThreadPoolBuildError>
+where
+    I: ExactSizeIterator<Item = (CpuCoreSet, CpuCoreSet)>,
+{
+    move |thread_pool_index| {
+        let (plotting_cpu_core_set, replotting_cpu_core_set) = cpu_core_sets
+            .next()
+            .expect("Number of thread pools is the same as cpu core sets; qed");
+
+        Ok(PlottingThreadPoolPair {
+            plotting: create_plotting_thread_pool_manager_thread_pool_pair(
+                "plotting",
+                thread_pool_index,
+                plotting_cpu_core_set,
+            )?,
+            replotting: create_plotting_thread_pool_manager_thread_pool_pair(
+                "replotting",
+                thread_pool_index,
+                replotting_cpu_core_set,
+            )?,
+        })
+    }
+}
+
 /// This function is supposed to be used with [`rayon::ThreadPoolBuilder::spawn_handler()`] to
 /// spawn handler with a custom logic defined by `spawn_hook_builder`.
 ///
@@ -477,3 +530,33 @@ pub fn tokio_rayon_spawn_handler() -> impl FnMut(ThreadBuilder) -> io::Result<()
         }
     })
 }
+
+/// Bind Unix socket at specified path that is only accessible by the current user.
+///
+/// Socket is bound in a temporary directory only accessible by the current user and moved to
+/// `socket_path` after its permissions are restricted, such that other users can't connect in
+/// between. Socket file that might be left after previous run is replaced.
+#[cfg(unix)]
+pub fn bind_private_unix_socket(socket_path: &Path) -> io::Result<UnixListener> {
+    let file_name = socket_path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Socket path {} has no file name", socket_path.display()),
+        )
+    })?;
+    let parent = socket_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    // Temporary directory is created with `0700` permissions
+    let private_directory = tempfile::Builder::new()
+        .prefix(".socket-")
+        .tempdir_in(parent)?;
+    let private_socket_path = private_directory.path().join(file_name);
+    let listener = UnixListener::bind(&private_socket_path)?;
+    fs::set_permissions(&private_socket_path, fs::Permissions::from_mode(0o600))?;
+    fs::rename(&private_socket_path, socket_path)?;
+
+    Ok(listener)
+}
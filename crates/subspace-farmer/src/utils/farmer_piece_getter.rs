@@ -2,11 +2,13 @@ use crate::farmer_cache::FarmerCache;
 use crate::utils::plotted_pieces::PlottedPieces;
 use crate::NodeClient;
 use async_trait::async_trait;
+use futures::stream::FuturesUnordered;
+use futures::{stream, StreamExt};
 use parking_lot::Mutex;
 use std::error::Error;
 use std::sync::{Arc, Weak};
 use subspace_core_primitives::{Piece, PieceIndex};
-use subspace_farmer_components::{PieceGetter, PieceGetterRetryPolicy};
+use subspace_farmer_components::{PieceGetter, PieceGetterRetryPolicy, PiecesStream};
 use subspace_networking::libp2p::kad::RecordKey;
 use subspace_networking::utils::multihash::ToMultihash;
 use subspace_networking::utils::piece_provider::{PieceProvider, PieceValidator, RetryPolicy};
@@ -77,30 +79,118 @@ where
         piece_index: PieceIndex,
         retry_policy: PieceGetterRetryPolicy,
     ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
-        let key = RecordKey::from(piece_index.to_multihash());
+        let mut pieces_stream = self.get_pieces(vec![piece_index], retry_policy).await?;
 
+        match pieces_stream.next().await {
+            Some((_piece_index, piece_result)) => piece_result,
+            None => Ok(None),
+        }
+    }
+
+    async fn get_pieces<'a>(
+        &'a self,
+        piece_indices: Vec<PieceIndex>,
+        retry_policy: PieceGetterRetryPolicy,
+    ) -> Result<PiecesStream<'a>, Box<dyn Error + Send + Sync + 'static>> {
         let inner = &self.inner;
 
-        trace!(%piece_index, "Getting piece from farmer cache");
-        if let Some(piece) = inner.farmer_cache.get_piece(key).await {
-            trace!(%piece_index, "Got piece from farmer cache successfully");
-            return Ok(Some(piece));
+        trace!(pieces = %piece_indices.len(), "Getting pieces from farmer cache");
+        let mut pieces_from_farmer_cache = Vec::new();
+        let mut missing_piece_indices = Vec::new();
+        let mut farmer_cache_results = piece_indices
+            .into_iter()
+            .map(|piece_index| async move {
+                let key = RecordKey::from(piece_index.to_multihash());
+                (piece_index, inner.farmer_cache.get_piece(key).await)
+            })
+            .collect::<FuturesUnordered<_>>();
+        while let Some((piece_index, maybe_piece)) = farmer_cache_results.next().await {
+            match maybe_piece {
+                Some(piece) => {
+                    trace!(%piece_index, "Got piece from farmer cache successfully");
+                    pieces_from_farmer_cache.push((piece_index, Ok(Some(piece))));
+                }
+                None => {
+                    missing_piece_indices.push(piece_index);
+                }
+            }
+        }
+
+        if missing_piece_indices.is_empty() {
+            return Ok(stream::iter(pieces_from_farmer_cache).boxed());
         }
 
-        // L2 piece acquisition
-        trace!(%piece_index, "Getting piece from DSN L2 cache");
-        let maybe_piece = inner
+        // L2 piece acquisition, requests are grouped per provider
+        trace!(
+            pieces = %missing_piece_indices.len(),
+            "Getting pieces from DSN L2 cache"
+        );
+        // Pieces that were not found in DSN L2 cache are retrieved one by one from other sources,
+        // all of them concurrently
+        let fallback_concurrency = missing_piece_indices.len();
+        let pieces_from_dsn_cache = inner
             .piece_provider
-            .get_piece_from_dsn_cache(piece_index, Self::convert_retry_policy(retry_policy))
-            .await?;
+            .get_pieces_from_dsn_cache(missing_piece_indices)
+            .await
+            .map(move |(piece_index, maybe_piece)| async move {
+                if let Some(piece) = maybe_piece {
+                    trace!(%piece_index, "Got piece from DSN L2 cache successfully");
+                    inner
+                        .farmer_cache
+                        .maybe_store_additional_piece(piece_index, &piece)
+                        .await;
+                    return (piece_index, Ok(Some(piece)));
+                }
 
-        if let Some(piece) = maybe_piece {
-            trace!(%piece_index, "Got piece from DSN L2 cache successfully");
-            inner
-                .farmer_cache
-                .maybe_store_additional_piece(piece_index, &piece)
-                .await;
-            return Ok(Some(piece));
+                (
+                    piece_index,
+                    self.get_piece_fallback(piece_index, retry_policy).await,
+                )
+            })
+            .buffer_unordered(fallback_concurrency);
+
+        Ok(stream::iter(pieces_from_farmer_cache)
+            .chain(pieces_from_dsn_cache)
+            .boxed())
+    }
+}
+
+impl<PV, NC> FarmerPieceGetter<PV, NC>
+where
+    PV: PieceValidator + Send + 'static,
+    NC: NodeClient,
+{
+    /// Get piece that wasn't found in DSN L2 cache during batched retrieval from other sources
+    async fn get_piece_fallback(
+        &self,
+        piece_index: PieceIndex,
+        retry_policy: PieceGetterRetryPolicy,
+    ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>> {
+        let inner = &self.inner;
+
+        // Batched request was the first attempt already, retry with remaining retries if any
+        let remaining_retry_policy = match retry_policy {
+            PieceGetterRetryPolicy::Limited(0) => None,
+            PieceGetterRetryPolicy::Limited(retries) => {
+                Some(PieceGetterRetryPolicy::Limited(retries - 1))
+            }
+            PieceGetterRetryPolicy::Unlimited => Some(PieceGetterRetryPolicy::Unlimited),
+        };
+        if let Some(retry_policy) = remaining_retry_policy {
+            trace!(%piece_index, "Retrying to get piece from DSN L2 cache");
+            let maybe_piece = inner
+                .piece_provider
+                .get_piece_from_dsn_cache(piece_index, Self::convert_retry_policy(retry_policy))
+                .await?;
+
+            if let Some(piece) = maybe_piece {
+                trace!(%piece_index, "Got piece from DSN L2 cache successfully");
+                inner
+                    .farmer_cache
+                    .maybe_store_additional_piece(piece_index, &piece)
+                    .await;
+                return Ok(Some(piece));
+            }
         }
 
         // Try node's RPC before reaching to L1 (archival storage on DSN)
@@ -0,0 +1,120 @@
+//! Limits applied to serving pieces to other peers over DSN
+
+#[cfg(test)]
+mod tests;
+
+use async_lock::{Semaphore, SemaphoreGuardArc};
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use subspace_networking::libp2p::PeerId;
+
+/// Window in which number of requests from a single peer is limited
+const PEER_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+/// Number of peers for which recent requests are tracked, least recently seen peers are forgotten
+/// first
+const TRACKED_PEERS: NonZeroUsize = NonZeroUsize::new(1000).expect("Not zero; qed");
+
+#[derive(Debug, Copy, Clone)]
+struct PeerWindow {
+    started_at: Instant,
+    requests: u32,
+}
+
+/// Permit to read piece from plot, piece must be read while it is alive
+#[derive(Debug)]
+pub struct PlotReadPermit {
+    _plot_read: SemaphoreGuardArc,
+    _pending_plot_read: SemaphoreGuardArc,
+}
+
+/// Limiter of piece serving that enforces per-peer request rate and limits concurrency of piece
+/// reads from plots, such that cache hits are not stuck behind slow reads from plots.
+///
+/// Requests waiting for plot reads occupy request processing slots, so number of such requests is
+/// limited too (see [`Self::max_pending_plot_reads()`]), request processing concurrency must be
+/// increased by this number for cache hits to always have their own slots.
+#[derive(Debug, Clone)]
+pub struct PieceServingLimiter {
+    max_requests_per_peer_per_second: Option<NonZeroU32>,
+    peer_windows: Arc<Mutex<LruCache<PeerId, PeerWindow>>>,
+    plot_reads: Arc<Semaphore>,
+    pending_plot_reads: Arc<Semaphore>,
+    max_pending_plot_reads: usize,
+}
+
+impl PieceServingLimiter {
+    /// Create new instance.
+    ///
+    /// Requests from peers are not rate limited if `max_requests_per_peer_per_second` is `None`.
+    /// Up to `plot_read_queue_size` reads from plots can wait for one of `plot_read_concurrency`
+    /// reads in progress to finish, reads beyond that are rejected.
+    pub fn new(
+        max_requests_per_peer_per_second: Option<NonZeroU32>,
+        plot_read_concurrency: NonZeroUsize,
+        plot_read_queue_size: usize,
+    ) -> Self {
+        let max_pending_plot_reads = plot_read_concurrency.get() + plot_read_queue_size;
+
+        Self {
+            max_requests_per_peer_per_second,
+            peer_windows: Arc::new(Mutex::new(LruCache::new(TRACKED_PEERS))),
+            plot_reads: Arc::new(Semaphore::new(plot_read_concurrency.get())),
+            pending_plot_reads: Arc::new(Semaphore::new(max_pending_plot_reads)),
+            max_pending_plot_reads,
+        }
+    }
+
+    /// Max number of reads from plots that are either in progress or waiting
+    pub fn max_pending_plot_reads(&self) -> usize {
+        self.max_pending_plot_reads
+    }
+
+    /// Check whether request from specified peer is allowed, returns `false` if peer exceeded its
+    /// request rate
+    pub fn allow_request(&self, peer_id: PeerId) -> bool {
+        self.allow_request_at(peer_id, Instant::now())
+    }
+
+    fn allow_request_at(&self, peer_id: PeerId, now: Instant) -> bool {
+        let Some(max_requests) = self.max_requests_per_peer_per_second else {
+            return true;
+        };
+
+        let mut peer_windows = self.peer_windows.lock();
+        let peer_window = peer_windows.get_or_insert_mut(peer_id, || PeerWindow {
+            started_at: now,
+            requests: 0,
+        });
+
+        if now.duration_since(peer_window.started_at) >= PEER_RATE_LIMIT_WINDOW {
+            *peer_window = PeerWindow {
+                started_at: now,
+                requests: 0,
+            };
+        }
+
+        if peer_window.requests >= max_requests.get() {
+            return false;
+        }
+        peer_window.requests += 1;
+
+        true
+    }
+
+    /// Wait for permission to read piece from plot, piece must be read while returned permit is
+    /// alive.
+    ///
+    /// Returns `None` right away if there are already too many pending reads from plots.
+    pub async fn plot_read_permit(&self) -> Option<PlotReadPermit> {
+        let pending_plot_read = self.pending_plot_reads.try_acquire_arc()?;
+        let plot_read = self.plot_reads.acquire_arc().await;
+
+        Some(PlotReadPermit {
+            _plot_read: plot_read,
+            _pending_plot_read: pending_plot_read,
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use crate::utils::piece_serving::{PieceServingLimiter, PEER_RATE_LIMIT_WINDOW};
+use futures::FutureExt;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::pin::pin;
+use std::time::Instant;
+use subspace_networking::libp2p::PeerId;
+
+#[test]
+fn peer_rate_limit() {
+    let limiter = PieceServingLimiter::new(NonZeroU32::new(2), NonZeroUsize::MIN, 0);
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let now = Instant::now();
+
+    assert!(limiter.allow_request_at(peer_a, now));
+    assert!(limiter.allow_request_at(peer_a, now));
+    assert!(!limiter.allow_request_at(peer_a, now));
+    // Other peers are not affected
+    assert!(limiter.allow_request_at(peer_b, now));
+
+    // New window starts after previous one expires
+    let later = now + PEER_RATE_LIMIT_WINDOW;
+    assert!(limiter.allow_request_at(peer_a, later));
+    assert!(limiter.allow_request_at(peer_a, later));
+    assert!(!limiter.allow_request_at(peer_a, later));
+}
+
+#[test]
+fn no_peer_rate_limit() {
+    let limiter = PieceServingLimiter::new(None, NonZeroUsize::MIN, 0);
+    let peer = PeerId::random();
+    let now = Instant::now();
+
+    for _ in 0..1000 {
+        assert!(limiter.allow_request_at(peer, now));
+    }
+}
+
+#[tokio::test]
+async fn plot_read_concurrency() {
+    let limiter = PieceServingLimiter::new(None, NonZeroUsize::new(2).unwrap(), 1);
+    assert_eq!(limiter.max_pending_plot_reads(), 3);
+
+    let first_permit = limiter.plot_read_permit().await.unwrap();
+    let _second_permit = limiter.plot_read_permit().await.unwrap();
+    {
+        // Third read waits in the queue
+        let mut waiting_permit = pin!(limiter.plot_read_permit());
+        assert!((&mut waiting_permit).now_or_never().is_none());
+        // Fourth read doesn't fit into the queue and is rejected right away
+        assert!(limiter.plot_read_permit().now_or_never().unwrap().is_none());
+
+        // Waiting read proceeds once one of reads in progress is done
+        drop(first_permit);
+        assert!(waiting_permit.now_or_never().unwrap().is_some());
+    }
+
+    // Queue is free again after waiting read is done
+    assert!(limiter.plot_read_permit().now_or_never().unwrap().is_some());
+}
@@ -1,10 +1,13 @@
 use crate::NodeClient;
 use async_trait::async_trait;
-use subspace_archiving::archiver::is_piece_valid;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::collections::HashMap;
+use subspace_archiving::archiver::{are_pieces_valid, is_piece_valid};
 use subspace_core_primitives::crypto::kzg::Kzg;
-use subspace_core_primitives::{Piece, PieceIndex};
+use subspace_core_primitives::{Piece, PieceIndex, SegmentIndex};
 use subspace_networking::libp2p::PeerId;
-use subspace_networking::utils::piece_provider::PieceValidator;
+use subspace_networking::utils::piece_provider::{PieceValidator, PieceValidity};
 use subspace_networking::Node;
 use tracing::{error, warn};
 
@@ -35,9 +38,9 @@ where
         source_peer_id: PeerId,
         piece_index: PieceIndex,
         piece: Piece,
-    ) -> Option<Piece> {
+    ) -> PieceValidity {
         if source_peer_id == self.dsn_node.id() {
-            return Some(piece);
+            return PieceValidity::Valid(piece);
         }
 
         let segment_index = piece_index.segment_index();
@@ -50,7 +53,7 @@ where
                     ?error,
                     "Failed tor retrieve segment headers from node"
                 );
-                return None;
+                return PieceValidity::Unknown;
             }
         };
 
@@ -62,7 +65,7 @@ where
                     %segment_index,
                     "Segment commitment for segment index wasn't found on node"
                 );
-                return None;
+                return PieceValidity::Unknown;
             }
         };
 
@@ -75,9 +78,9 @@ where
             }
         });
 
-        match is_valid_fut.await.unwrap_or_default() {
-            Some(piece) => Some(piece),
-            None => {
+        match is_valid_fut.await {
+            Ok(Some(piece)) => PieceValidity::Valid(piece),
+            Ok(None) => {
                 warn!(
                     %piece_index,
                     %source_peer_id,
@@ -86,8 +89,136 @@ where
 
                 // We don't care about result here
                 let _ = self.dsn_node.ban_peer(source_peer_id).await;
-                None
+                PieceValidity::Invalid
+            }
+            Err(error) => {
+                error!(%piece_index, %error, "Failed to validate piece");
+                PieceValidity::Unknown
+            }
+        }
+    }
+
+    async fn validate_pieces(
+        &self,
+        pieces: Vec<(PeerId, PieceIndex, Piece)>,
+    ) -> Vec<PieceValidity> {
+        let mut results = Vec::with_capacity(pieces.len());
+        // Pieces that need to be validated, grouped by segment index, each with position in
+        // `results`
+        let mut pieces_by_segment =
+            HashMap::<SegmentIndex, Vec<(usize, PeerId, PieceIndex, Piece)>>::new();
+
+        for (source_peer_id, piece_index, piece) in pieces {
+            if source_peer_id == self.dsn_node.id() {
+                results.push(PieceValidity::Valid(piece));
+            } else {
+                pieces_by_segment
+                    .entry(piece_index.segment_index())
+                    .or_default()
+                    .push((results.len(), source_peer_id, piece_index, piece));
+                results.push(PieceValidity::Unknown);
+            }
+        }
+
+        if pieces_by_segment.is_empty() {
+            return results;
+        }
+
+        let segment_indices = pieces_by_segment.keys().copied().collect::<Vec<_>>();
+        let segment_headers = match self
+            .node_client
+            .segment_headers(segment_indices.clone())
+            .await
+        {
+            Ok(segment_headers) => segment_headers,
+            Err(error) => {
+                error!(
+                    ?segment_indices,
+                    ?error,
+                    "Failed tor retrieve segment headers from node, pieces can't be validated"
+                );
+                return results;
+            }
+        };
+
+        if segment_headers.len() != segment_indices.len() {
+            error!(
+                ?segment_indices,
+                segment_headers = segment_headers.len(),
+                "Node returned unexpected number of segment headers"
+            );
+            return results;
+        }
+
+        // Segments are validated concurrently, each on its own blocking thread
+        let mut validating_segments = segment_indices
+            .into_iter()
+            .zip(segment_headers)
+            .filter_map(|(segment_index, maybe_segment_header)| {
+                let pieces = pieces_by_segment.remove(&segment_index)?;
+
+                let Some(segment_header) = maybe_segment_header else {
+                    error!(
+                        %segment_index,
+                        pieces = pieces.len(),
+                        "Segment commitment for segment index wasn't found on node"
+                    );
+                    return None;
+                };
+                let segment_commitment = segment_header.segment_commitment();
+                let kzg = self.kzg.clone();
+
+                Some(async move {
+                    let is_valid_fut = tokio::task::spawn_blocking(move || {
+                        let validity = are_pieces_valid(
+                            &kzg,
+                            &pieces
+                                .iter()
+                                .map(|(_, _, piece_index, piece)| {
+                                    (piece_index.position(), &**piece)
+                                })
+                                .collect::<Vec<_>>(),
+                            &segment_commitment,
+                        );
+
+                        pieces.into_iter().zip(validity).collect::<Vec<_>>()
+                    });
+
+                    (segment_index, is_valid_fut.await)
+                })
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        while let Some((segment_index, maybe_pieces)) = validating_segments.next().await {
+            let pieces = match maybe_pieces {
+                Ok(pieces) => pieces,
+                Err(error) => {
+                    error!(
+                        %segment_index,
+                        %error,
+                        "Failed to validate pieces of segment"
+                    );
+                    continue;
+                }
+            };
+
+            for ((result_index, source_peer_id, piece_index, piece), valid) in pieces {
+                if valid {
+                    results[result_index] = PieceValidity::Valid(piece);
+                } else {
+                    warn!(
+                        %piece_index,
+                        %source_peer_id,
+                        "Received invalid piece from peer"
+                    );
+
+                    // We don't care about result here
+                    let _ = self.dsn_node.ban_peer(source_peer_id).await;
+                    results[result_index] = PieceValidity::Invalid;
+                }
             }
         }
+
+        results
     }
 }
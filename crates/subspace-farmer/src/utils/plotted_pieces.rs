@@ -1,37 +1,70 @@
+#[cfg(test)]
+mod tests;
+
 use crate::single_disk_farm::piece_reader::PieceReader;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
 use std::future::Future;
+use std::mem;
 use subspace_core_primitives::{Piece, PieceIndex, PieceOffset, SectorIndex};
 use subspace_farmer_components::plotting::PlottedSector;
 use tracing::{trace, warn};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct PieceDetails {
-    disk_farm_index: u8,
+/// Index of the farm among farms of the farmer
+pub type DiskFarmIndex = u16;
+
+/// Location of the plotted piece.
+///
+/// Entries are ordered by piece index first, such that all locations of the same piece are next to
+/// each other in sorted index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct PlottedPiece {
+    piece_index: PieceIndex,
+    disk_farm_index: DiskFarmIndex,
     sector_index: SectorIndex,
     piece_offset: PieceOffset,
 }
 
 /// Wrapper data structure for pieces plotted under multiple plots.
+///
+/// Each farm has its own sorted vector of packed piece locations (16 bytes each) without per-piece
+/// heap allocations, which matters when tens of millions of pieces are plotted. Pieces plotted
+/// multiple times simply have multiple entries. Adding sectors only sorts new entries and merges
+/// them into the index of the farm in place, deleting sectors costs a single linear pass over the
+/// index of the farm, so sectors should be added in bulk with [`Self::add_sectors()`] where
+/// possible.
 #[derive(Debug)]
 pub struct PlottedPieces {
-    readers: Vec<PieceReader>,
-    pieces: HashMap<PieceIndex, Vec<PieceDetails>>,
+    readers: Vec<Option<PieceReader>>,
+    /// Sorted piece locations of each farm, indexed by farm index
+    farms: Vec<Vec<PlottedPiece>>,
 }
 
 impl PlottedPieces {
     /// Initialize with readers for each farm
     pub fn new(readers: Vec<PieceReader>) -> Self {
         Self {
-            readers,
-            pieces: HashMap::new(),
+            readers: readers.into_iter().map(Some).collect(),
+            farms: Vec::new(),
         }
     }
 
     /// Check if piece is known and can be retrieved
     pub fn contains_piece(&self, piece_index: &PieceIndex) -> bool {
-        self.pieces.contains_key(piece_index)
+        self.first_location(piece_index).is_some()
+    }
+
+    fn first_location(&self, piece_index: &PieceIndex) -> Option<&PlottedPiece> {
+        self.farms
+            .iter()
+            .find_map(|pieces| farm_location(pieces, piece_index))
+    }
+
+    fn farm_pieces_mut(&mut self, disk_farm_index: DiskFarmIndex) -> &mut Vec<PlottedPiece> {
+        let disk_farm_index = usize::from(disk_farm_index);
+        if self.farms.len() <= disk_farm_index {
+            self.farms.resize_with(disk_farm_index + 1, Vec::new);
+        }
+
+        &mut self.farms[disk_farm_index]
     }
 
     /// Read plotted piece from oneof the farms.
@@ -42,11 +75,8 @@ impl PlottedPieces {
         &self,
         piece_index: &PieceIndex,
     ) -> Option<impl Future<Output = Option<Piece>> + 'static> {
-        let piece_details = match self.pieces.get(piece_index) {
-            Some(piece_details) => piece_details
-                .first()
-                .copied()
-                .expect("Empty lists are not stored in the map; qed"),
+        let piece_details = match self.first_location(piece_index) {
+            Some(piece_details) => *piece_details,
             None => {
                 trace!(
                     ?piece_index,
@@ -55,7 +85,11 @@ impl PlottedPieces {
                 return None;
             }
         };
-        let mut reader = match self.readers.get(usize::from(piece_details.disk_farm_index)) {
+        let mut reader = match self
+            .readers
+            .get(usize::from(piece_details.disk_farm_index))
+            .and_then(Option::as_ref)
+        {
             Some(reader) => reader.clone(),
             None => {
                 warn!(?piece_index, ?piece_details, "Plot offset is invalid");
@@ -71,61 +105,145 @@ impl PlottedPieces {
     }
 
     /// Add new sector to collect plotted pieces
-    pub fn add_sector(&mut self, disk_farm_index: u8, plotted_sector: &PlottedSector) {
-        for (piece_offset, &piece_index) in
-            (PieceOffset::ZERO..).zip(plotted_sector.piece_indexes.iter())
-        {
-            let piece_details = PieceDetails {
-                disk_farm_index,
-                sector_index: plotted_sector.sector_index,
-                piece_offset,
-            };
-
-            match self.pieces.entry(piece_index) {
-                Entry::Occupied(mut entry) => {
-                    entry.get_mut().push(piece_details);
-                }
-                Entry::Vacant(entry) => {
-                    entry.insert(vec![piece_details]);
-                }
-            }
+    pub fn add_sector(&mut self, disk_farm_index: DiskFarmIndex, plotted_sector: &PlottedSector) {
+        self.add_sectors(disk_farm_index, [plotted_sector]);
+    }
+
+    /// Add multiple sectors of the same farm at once, cheaper than adding them one by one
+    pub fn add_sectors<'a, I>(&mut self, disk_farm_index: DiskFarmIndex, plotted_sectors: I)
+    where
+        I: IntoIterator<Item = &'a PlottedSector>,
+    {
+        let mut new_pieces = plotted_sectors
+            .into_iter()
+            .flat_map(|plotted_sector| sector_pieces(disk_farm_index, plotted_sector))
+            .collect::<Vec<_>>();
+        if new_pieces.is_empty() {
+            return;
         }
+
+        new_pieces.sort_unstable();
+        merge_sorted(self.farm_pieces_mut(disk_farm_index), &new_pieces);
     }
 
     /// Add old sector from plotted pieces (happens on replotting)
-    pub fn delete_sector(&mut self, disk_farm_index: u8, plotted_sector: &PlottedSector) {
-        for (piece_offset, &piece_index) in
-            (PieceOffset::ZERO..).zip(plotted_sector.piece_indexes.iter())
-        {
-            let searching_piece_details = PieceDetails {
-                disk_farm_index,
-                sector_index: plotted_sector.sector_index,
-                piece_offset,
-            };
-
-            if let Entry::Occupied(mut entry) = self.pieces.entry(piece_index) {
-                let piece_details = entry.get_mut();
-                if let Some(index) =
-                    piece_details
-                        .iter()
-                        .enumerate()
-                        .find_map(|(index, piece_details)| {
-                            (piece_details == &searching_piece_details).then_some(index)
-                        })
-                {
-                    piece_details.swap_remove(index);
-                }
-
-                // We do not store empty lists
-                if piece_details.is_empty() {
-                    entry.remove_entry();
-                }
-            }
+    pub fn delete_sector(
+        &mut self,
+        disk_farm_index: DiskFarmIndex,
+        plotted_sector: &PlottedSector,
+    ) {
+        let Some(pieces) = self.farms.get_mut(usize::from(disk_farm_index)) else {
+            return;
+        };
+        let mut deleted_pieces = sector_pieces(disk_farm_index, plotted_sector).collect::<Vec<_>>();
+        deleted_pieces.sort_unstable();
+
+        // Both are sorted, so deleted pieces are found in a single pass
+        let mut deleted_pieces = deleted_pieces.into_iter().peekable();
+        pieces.retain(|plotted_piece| {
+            while deleted_pieces
+                .next_if(|deleted_piece| deleted_piece < plotted_piece)
+                .is_some()
+            {}
+
+            deleted_pieces
+                .next_if(|deleted_piece| deleted_piece == plotted_piece)
+                .is_none()
+        });
+    }
+
+    /// Delete reader and all sectors of the farm from plotted pieces (happens when farm fails or is
+    /// removed)
+    pub fn delete_farm(&mut self, disk_farm_index: DiskFarmIndex) {
+        if let Some(reader) = self.readers.get_mut(usize::from(disk_farm_index)) {
+            reader.take();
+        }
+
+        if let Some(pieces) = self.farms.get_mut(usize::from(disk_farm_index)) {
+            // Free memory rather than just clearing the index
+            mem::take(pieces);
+        }
+    }
+
+    /// Set reader of the farm (happens when farm is added or failed farm is restarted)
+    pub fn set_reader(&mut self, disk_farm_index: DiskFarmIndex, reader: PieceReader) {
+        let disk_farm_index = usize::from(disk_farm_index);
+        if self.readers.len() <= disk_farm_index {
+            self.readers.resize(disk_farm_index + 1, None);
         }
+
+        self.readers[disk_farm_index].replace(reader);
     }
 
     /// Iterator over all unique piece indices plotted
     pub fn piece_indices(&self) -> impl Iterator<Item = &PieceIndex> {
-        self.pieces.keys()
+        self.farms
+            .iter()
+            .enumerate()
+            .flat_map(move |(farm_position, pieces)| {
+                pieces
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(position, plotted_piece)| {
+                        let duplicate = position
+                            .checked_sub(1)
+                            .and_then(|previous_position| pieces.get(previous_position))
+                            .is_some_and(|previous_piece| {
+                                previous_piece.piece_index == plotted_piece.piece_index
+                            })
+                            || self.farms[..farm_position].iter().any(|previous_pieces| {
+                                farm_location(previous_pieces, &plotted_piece.piece_index).is_some()
+                            });
+
+                        (!duplicate).then_some(&plotted_piece.piece_index)
+                    })
+            })
     }
 }
+
+fn farm_location<'a>(
+    pieces: &'a [PlottedPiece],
+    piece_index: &PieceIndex,
+) -> Option<&'a PlottedPiece> {
+    let position = pieces.partition_point(|plotted_piece| plotted_piece.piece_index < *piece_index);
+
+    pieces
+        .get(position)
+        .filter(|plotted_piece| plotted_piece.piece_index == *piece_index)
+}
+
+/// Merge sorted `new_pieces` into sorted `pieces` in place, going from the end such that only
+/// entries greater than the smallest new piece are moved and no temporary copy of the index is
+/// allocated
+fn merge_sorted(pieces: &mut Vec<PlottedPiece>, new_pieces: &[PlottedPiece]) {
+    let mut existing_len = pieces.len();
+    let mut new_len = new_pieces.len();
+    // Placeholder entries that are overwritten below
+    pieces.extend_from_slice(new_pieces);
+
+    let mut write_position = pieces.len();
+    while new_len > 0 {
+        write_position -= 1;
+        if existing_len > 0 && pieces[existing_len - 1] > new_pieces[new_len - 1] {
+            pieces[write_position] = pieces[existing_len - 1];
+            existing_len -= 1;
+        } else {
+            pieces[write_position] = new_pieces[new_len - 1];
+            new_len -= 1;
+        }
+    }
+}
+
+fn sector_pieces(
+    disk_farm_index: DiskFarmIndex,
+    plotted_sector: &PlottedSector,
+) -> impl Iterator<Item = PlottedPiece> + '_ {
+    (PieceOffset::ZERO..)
+        .zip(plotted_sector.piece_indexes.iter())
+        .map(move |(piece_offset, &piece_index)| PlottedPiece {
+            piece_index,
+            disk_farm_index,
+            sector_index: plotted_sector.sector_index,
+            piece_offset,
+        })
+}
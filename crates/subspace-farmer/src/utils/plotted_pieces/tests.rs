@@ -0,0 +1,160 @@
+use crate::utils::plotted_pieces::{DiskFarmIndex, PlottedPieces};
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use subspace_core_primitives::{HistorySize, PieceIndex, PublicKey, Record, SectorId, SectorIndex};
+use subspace_farmer_components::plotting::PlottedSector;
+use subspace_farmer_components::sector::{SectorMetadata, SectorMetadataChecksummed};
+
+fn plotted_sector(sector_index: SectorIndex, piece_indexes: &[u64]) -> PlottedSector {
+    PlottedSector {
+        sector_id: SectorId::new(PublicKey::default().hash(), sector_index),
+        sector_index,
+        sector_metadata: SectorMetadataChecksummed::from(SectorMetadata {
+            sector_index,
+            pieces_in_sector: piece_indexes.len() as u16,
+            s_bucket_sizes: Box::new([0u16; Record::NUM_S_BUCKETS]),
+            history_size: HistorySize::new(NonZeroU64::MIN),
+        }),
+        piece_indexes: piece_indexes
+            .iter()
+            .copied()
+            .map(PieceIndex::from)
+            .collect(),
+    }
+}
+
+fn piece_indices(plotted_pieces: &PlottedPieces) -> HashSet<u64> {
+    plotted_pieces
+        .piece_indices()
+        .map(|piece_index| u64::from(*piece_index))
+        .collect()
+}
+
+#[test]
+fn add_and_delete_sectors() {
+    let mut plotted_pieces = PlottedPieces::new(Vec::new());
+    let sector_0 = plotted_sector(0, &[0, 1, 2]);
+    let sector_1 = plotted_sector(1, &[2, 3]);
+
+    plotted_pieces.add_sector(0, &sector_0);
+    plotted_pieces.add_sector(0, &sector_1);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([0, 1, 2, 3]));
+
+    // Piece plotted in another sector remains available
+    plotted_pieces.delete_sector(0, &sector_0);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([2, 3]));
+
+    plotted_pieces.delete_sector(0, &sector_1);
+    assert!(piece_indices(&plotted_pieces).is_empty());
+}
+
+#[test]
+fn delete_sector_of_another_farm() {
+    let mut plotted_pieces = PlottedPieces::new(Vec::new());
+    let sector = plotted_sector(0, &[0, 1]);
+
+    plotted_pieces.add_sector(0, &sector);
+    plotted_pieces.add_sector(1, &sector);
+
+    plotted_pieces.delete_sector(1, &sector);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([0, 1]));
+
+    // Deleting sector that is not plotted does nothing
+    plotted_pieces.delete_sector(1, &sector);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([0, 1]));
+
+    plotted_pieces.delete_sector(0, &sector);
+    assert!(piece_indices(&plotted_pieces).is_empty());
+}
+
+#[test]
+fn delete_farm() {
+    let mut plotted_pieces = PlottedPieces::new(Vec::new());
+    // Farm indices beyond `u8` are supported
+    let farm_indices: [DiskFarmIndex; 3] = [0, 300, DiskFarmIndex::MAX];
+
+    for (sector_index, &disk_farm_index) in (0..).zip(&farm_indices) {
+        plotted_pieces.add_sector(disk_farm_index, &plotted_sector(sector_index, &[0, 1]));
+    }
+    plotted_pieces.add_sector(300, &plotted_sector(5, &[2]));
+
+    plotted_pieces.delete_farm(300);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([0, 1]));
+
+    plotted_pieces.delete_farm(0);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([0, 1]));
+
+    plotted_pieces.delete_farm(DiskFarmIndex::MAX);
+    assert!(piece_indices(&plotted_pieces).is_empty());
+}
+
+#[test]
+fn read_missing_piece() {
+    let mut plotted_pieces = PlottedPieces::new(Vec::new());
+    plotted_pieces.add_sector(1000, &plotted_sector(0, &[0]));
+
+    assert!(plotted_pieces.read_piece(&PieceIndex::from(1)).is_none());
+    // Piece is known, but farm doesn't have a reader
+    assert!(plotted_pieces.read_piece(&PieceIndex::from(0)).is_none());
+}
+
+#[test]
+fn add_sectors_in_bulk() {
+    let mut plotted_pieces = PlottedPieces::new(Vec::new());
+    plotted_pieces.add_sector(1, &plotted_sector(0, &[5, 3]));
+
+    let sectors = [
+        plotted_sector(0, &[4, 3, 1]),
+        plotted_sector(1, &[9, 3]),
+        // The same piece plotted twice in one sector
+        plotted_sector(2, &[7, 7]),
+    ];
+    plotted_pieces.add_sectors(0, &sectors);
+
+    // Every piece is listed once, no matter how many times it was plotted
+    let mut unique_piece_indices = plotted_pieces
+        .piece_indices()
+        .map(|piece_index| u64::from(*piece_index))
+        .collect::<Vec<_>>();
+    unique_piece_indices.sort_unstable();
+    assert_eq!(unique_piece_indices, [1, 3, 4, 5, 7, 9]);
+    assert!(plotted_pieces.contains_piece(&PieceIndex::from(9)));
+    assert!(!plotted_pieces.contains_piece(&PieceIndex::from(2)));
+
+    // Deleting one of the locations of the piece plotted multiple times keeps the others
+    plotted_pieces.delete_sector(0, &sectors[0]);
+    plotted_pieces.delete_sector(0, &sectors[2]);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([3, 5, 9]));
+
+    plotted_pieces.delete_farm(1);
+    assert_eq!(piece_indices(&plotted_pieces), HashSet::from([3, 9]));
+}
+
+#[test]
+fn add_sectors_incrementally() {
+    let mut plotted_pieces = PlottedPieces::new(Vec::new());
+    let sectors = [
+        plotted_sector(0, &[10, 2, 30]),
+        // Interleaves with pieces that are already indexed
+        plotted_sector(1, &[1, 20, 40, 2]),
+        plotted_sector(2, &[0]),
+    ];
+    for sector in &sectors {
+        plotted_pieces.add_sector(0, sector);
+    }
+
+    assert_eq!(
+        piece_indices(&plotted_pieces),
+        HashSet::from([0, 1, 2, 10, 20, 30, 40])
+    );
+
+    // Index stays sorted after merges, so every location is found and deleted
+    plotted_pieces.delete_sector(0, &sectors[1]);
+    assert_eq!(
+        piece_indices(&plotted_pieces),
+        HashSet::from([0, 2, 10, 30])
+    );
+    plotted_pieces.delete_sector(0, &sectors[0]);
+    plotted_pieces.delete_sector(0, &sectors[2]);
+    assert!(piece_indices(&plotted_pieces).is_empty());
+}
@@ -0,0 +1,157 @@
+//! Derivation of plotting concurrency from memory limit
+
+use std::num::NonZeroUsize;
+use subspace_farmer_components::plotting::{sector_encoding_memory_usage, DownloadedSector};
+use subspace_farmer_components::segment_reconstruction::SegmentReconstructionCache;
+use subspace_proof_of_space::Table;
+use thiserror::Error;
+
+/// Errors that happen during derivation of plotting memory plan
+#[derive(Debug, Error)]
+pub enum PlottingMemoryPlanError {
+    /// Memory limit is too low to plot even one sector at a time
+    #[error(
+        "Plotting memory limit {memory_limit} bytes is too low, at least {min_memory_usage} bytes \
+        are required to plot one sector at a time"
+    )]
+    InsufficientMemoryLimit {
+        /// Memory limit that was requested
+        memory_limit: u64,
+        /// Minimum memory usage of the plotting process
+        min_memory_usage: u64,
+    },
+}
+
+/// Plotting concurrency that fits into memory limit
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PlottingMemoryPlan {
+    /// How many sectors can be downloaded concurrently
+    pub sector_downloading_concurrency: NonZeroUsize,
+    /// How many sectors can be encoded concurrently
+    pub sector_encoding_concurrency: NonZeroUsize,
+    /// How many records can be encoded concurrently in a single sector
+    pub record_encoding_concurrency: NonZeroUsize,
+    /// Estimated peak memory usage in bytes of the plotting process with above concurrency
+    pub estimated_memory_usage: u64,
+}
+
+impl PlottingMemoryPlan {
+    /// Estimated peak memory usage in bytes of the plotting process with provided concurrency.
+    ///
+    /// Every sector being downloaded holds downloaded pieces until it is encoded and written to
+    /// disk, every sector being encoded additionally holds encoded sector and proof of space tables
+    /// for each record that is encoded concurrently. Each farm also keeps its own proof of space
+    /// table generators for each record that is encoded concurrently, even when it is not encoding
+    /// anything. Pieces fetched for reconstruction of missing pieces are kept in cache of
+    /// `segment_reconstruction_cache_capacity` segments that is shared by all farms.
+    pub fn memory_usage<PosTable>(
+        pieces_in_sector: u16,
+        farms: usize,
+        segment_reconstruction_cache_capacity: NonZeroUsize,
+        sector_downloading_concurrency: NonZeroUsize,
+        sector_encoding_concurrency: NonZeroUsize,
+        record_encoding_concurrency: NonZeroUsize,
+    ) -> u64
+    where
+        PosTable: Table,
+    {
+        let downloaded_sector = DownloadedSector::memory_usage(pieces_in_sector) as u64;
+        let sector_encoding = sector_encoding_memory_usage::<PosTable>(
+            pieces_in_sector,
+            record_encoding_concurrency.get(),
+        ) as u64;
+
+        let idle_table_generators = farms as u64
+            * record_encoding_concurrency.get() as u64
+            * PosTable::GENERATOR_IDLE_MEMORY_USAGE as u64;
+
+        let segment_reconstruction_cache =
+            SegmentReconstructionCache::memory_usage(segment_reconstruction_cache_capacity) as u64;
+
+        sector_downloading_concurrency.get() as u64 * downloaded_sector
+            + sector_encoding_concurrency.get() as u64 * sector_encoding
+            + idle_table_generators
+            + segment_reconstruction_cache
+    }
+
+    /// Derive the highest plotting concurrency that fits into `memory_limit`.
+    ///
+    /// Provided concurrency values are upper bounds (from CLI or derived from CPU topology).
+    /// Record encoding concurrency is preferred over sector encoding concurrency since CPU cores of
+    /// multiple thread pools can be regrouped into fewer thread pools without losing CPU
+    /// utilization. One sector is downloaded ahead of time if memory limit allows.
+    ///
+    /// Since memory usage depends on the number of `farms`, plan should be derived again when farms
+    /// are added or removed.
+    pub fn derive<PosTable>(
+        memory_limit: u64,
+        pieces_in_sector: u16,
+        farms: usize,
+        segment_reconstruction_cache_capacity: NonZeroUsize,
+        max_sector_downloading_concurrency: Option<NonZeroUsize>,
+        max_sector_encoding_concurrency: NonZeroUsize,
+        max_record_encoding_concurrency: NonZeroUsize,
+    ) -> Result<Self, PlottingMemoryPlanError>
+    where
+        PosTable: Table,
+    {
+        // Sector encoding is restricted by sector downloading concurrency
+        let max_sector_encoding_concurrency = match max_sector_downloading_concurrency {
+            Some(max_sector_downloading_concurrency) => {
+                max_sector_encoding_concurrency.min(max_sector_downloading_concurrency)
+            }
+            None => max_sector_encoding_concurrency,
+        };
+
+        for record_encoding_concurrency in (1..=max_record_encoding_concurrency.get()).rev() {
+            let record_encoding_concurrency =
+                NonZeroUsize::new(record_encoding_concurrency).expect("Not zero; qed");
+
+            for sector_encoding_concurrency in (1..=max_sector_encoding_concurrency.get()).rev() {
+                let sector_encoding_concurrency =
+                    NonZeroUsize::new(sector_encoding_concurrency).expect("Not zero; qed");
+
+                let ahead_of_time = sector_encoding_concurrency.saturating_add(1);
+                let ahead_of_time = match max_sector_downloading_concurrency {
+                    Some(max_sector_downloading_concurrency) => {
+                        ahead_of_time.min(max_sector_downloading_concurrency)
+                    }
+                    None => ahead_of_time,
+                };
+
+                for sector_downloading_concurrency in [ahead_of_time, sector_encoding_concurrency] {
+                    let estimated_memory_usage = Self::memory_usage::<PosTable>(
+                        pieces_in_sector,
+                        farms,
+                        segment_reconstruction_cache_capacity,
+                        sector_downloading_concurrency,
+                        sector_encoding_concurrency,
+                        record_encoding_concurrency,
+                    );
+
+                    if estimated_memory_usage <= memory_limit {
+                        return Ok(Self {
+                            sector_downloading_concurrency,
+                            sector_encoding_concurrency,
+                            record_encoding_concurrency,
+                            estimated_memory_usage,
+                        });
+                    }
+                }
+            }
+        }
+
+        let one = NonZeroUsize::MIN;
+        Err(PlottingMemoryPlanError::InsufficientMemoryLimit {
+            memory_limit,
+            min_memory_usage: Self::memory_usage::<PosTable>(
+                pieces_in_sector,
+                farms,
+                segment_reconstruction_cache_capacity,
+                one,
+                one,
+                one,
+            ),
+        })
+    }
+}
@@ -0,0 +1,415 @@
+//! Schedule of plotting that allows to pause plotting and replotting of new sectors on shared
+//! machines and to stop it gracefully before shutdown
+
+use futures::{select, FutureExt};
+use parking_lot::Mutex;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(target_os = "linux")]
+use std::sync::Weak;
+#[cfg(target_os = "linux")]
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::Notify;
+use tracing::debug;
+#[cfg(target_os = "linux")]
+use tracing::warn;
+
+/// Interval at which pause conditions are checked again while plotting is paused
+const PAUSE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Interval at which CPU usage is sampled in background, CPU usage is averaged over this interval
+#[cfg(target_os = "linux")]
+const CPU_USAGE_MEASUREMENT_INTERVAL: Duration = Duration::from_secs(1);
+const SECONDS_IN_DAY: u32 = 24 * 60 * 60;
+
+/// Errors that happen during parsing of plotting window
+#[derive(Debug, Error)]
+pub enum PlottingWindowParseError {
+    /// Window must be in `HH:MM-HH:MM` format
+    #[error("Invalid plotting window \"{0}\", must be in `HH:MM-HH:MM` format")]
+    InvalidFormat(String),
+    /// Start and end of the window are the same
+    #[error("Start and end of plotting window \"{0}\" must be different")]
+    Empty(String),
+}
+
+/// Time of day window (in UTC), can cross midnight
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PlottingWindow {
+    /// Start of the window in seconds since midnight, inclusive
+    start: u32,
+    /// End of the window in seconds since midnight, exclusive
+    end: u32,
+}
+
+impl fmt::Display for PlottingWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 3600,
+            self.start % 3600 / 60,
+            self.end / 3600,
+            self.end % 3600 / 60,
+        )
+    }
+}
+
+impl FromStr for PlottingWindow {
+    type Err = PlottingWindowParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_time = |time: &str| -> Option<u32> {
+            let (hours, minutes) = time.split_once(':')?;
+            if hours.len() != 2 || minutes.len() != 2 {
+                return None;
+            }
+            let hours = hours.parse::<u32>().ok().filter(|hours| *hours < 24)?;
+            let minutes = minutes
+                .parse::<u32>()
+                .ok()
+                .filter(|minutes| *minutes < 60)?;
+
+            Some(hours * 3600 + minutes * 60)
+        };
+
+        let (start, end) = s
+            .split_once('-')
+            .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+            .ok_or_else(|| PlottingWindowParseError::InvalidFormat(s.to_string()))?;
+
+        if start == end {
+            return Err(PlottingWindowParseError::Empty(s.to_string()));
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+impl PlottingWindow {
+    /// Whether provided time of day (in seconds since midnight) is within this window
+    pub fn contains(&self, second_of_day: u32) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&second_of_day)
+        } else {
+            second_of_day >= self.start || second_of_day < self.end
+        }
+    }
+}
+
+/// Reason why plotting is paused
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlottingPauseReason {
+    /// Plotting was paused explicitly
+    Manual,
+    /// Current time is within pause window
+    Window(PlottingWindow),
+    /// CPU usage of other processes (in %) is above the threshold
+    CpuUsage(f32),
+}
+
+impl fmt::Display for PlottingPauseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Manual => f.write_str("paused manually"),
+            Self::Window(window) => write!(f, "within pause window {window} UTC"),
+            Self::CpuUsage(cpu_usage) => {
+                write!(f, "CPU usage of other processes is {cpu_usage:.1}%")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SectorsState {
+    stopped: bool,
+    in_progress: usize,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    pause_windows: Vec<PlottingWindow>,
+    max_cpu_usage: Option<f32>,
+    /// Last sampled CPU usage of other processes, `None` if it wasn't sampled yet or can't be
+    /// measured
+    cpu_usage: Mutex<Option<f32>>,
+    manually_paused: AtomicBool,
+    resumed: Notify,
+    sectors_state: Mutex<SectorsState>,
+    sector_finished: Notify,
+}
+
+/// Guard of the sector that is being plotted, sector is considered to be finished once dropped
+#[derive(Debug)]
+pub(crate) struct SectorInProgress {
+    inner: Arc<Inner>,
+}
+
+impl Drop for SectorInProgress {
+    fn drop(&mut self) {
+        self.inner.sectors_state.lock().in_progress -= 1;
+        self.inner.sector_finished.notify_waiters();
+    }
+}
+
+/// Schedule of plotting shared by all farms of the farmer.
+///
+/// Plotting and replotting of new sectors doesn't start while plotting is paused manually, current
+/// time is within one of the pause windows or CPU usage of other processes is above the threshold.
+/// Sectors that are already being plotted are not affected.
+///
+/// Plotting can also be stopped permanently before shutdown, in which case sectors that are
+/// already being plotted can be awaited to be finished and persisted.
+#[derive(Debug, Default, Clone)]
+pub struct PlottingSchedule {
+    inner: Arc<Inner>,
+}
+
+impl PlottingSchedule {
+    /// Create new instance.
+    ///
+    /// `max_cpu_usage` is in % of all CPU cores of the machine, CPU usage of the farmer itself is
+    /// not taken into account. CPU usage is only measured on Linux and ignored elsewhere, it is
+    /// sampled in background thread for as long as the schedule exists.
+    pub fn new(pause_windows: Vec<PlottingWindow>, max_cpu_usage: Option<f32>) -> Self {
+        let inner = Arc::new(Inner {
+            pause_windows,
+            max_cpu_usage,
+            ..Inner::default()
+        });
+
+        #[cfg(target_os = "linux")]
+        if max_cpu_usage.is_some() {
+            spawn_cpu_usage_sampler(Arc::downgrade(&inner));
+        }
+
+        Self { inner }
+    }
+
+    /// Pause plotting and replotting of new sectors until [`Self::resume()`] is called
+    pub fn pause(&self) {
+        self.inner.manually_paused.store(true, Ordering::Release);
+    }
+
+    /// Resume plotting paused with [`Self::pause()`], pause windows and CPU usage threshold still
+    /// apply
+    pub fn resume(&self) {
+        self.inner.manually_paused.store(false, Ordering::Release);
+        self.inner.resumed.notify_waiters();
+    }
+
+    /// Whether plotting was paused with [`Self::pause()`]
+    pub fn is_paused_manually(&self) -> bool {
+        self.inner.manually_paused.load(Ordering::Acquire)
+    }
+
+    /// Stop plotting and replotting of new sectors permanently, typically before shutdown
+    pub fn stop(&self) {
+        self.inner.sectors_state.lock().stopped = true;
+        // Wake up plotting that is paused right now
+        self.inner.resumed.notify_waiters();
+    }
+
+    /// Whether plotting was stopped with [`Self::stop()`]
+    pub fn is_stopped(&self) -> bool {
+        self.inner.sectors_state.lock().stopped
+    }
+
+    /// Number of sectors that are being plotted right now
+    pub fn sectors_in_progress(&self) -> usize {
+        self.inner.sectors_state.lock().in_progress
+    }
+
+    /// Wait for all sectors that are being plotted right now to be finished and persisted on disk.
+    ///
+    /// Only makes sense after [`Self::stop()`], otherwise new sectors may start in the meantime.
+    pub async fn wait_for_sectors_in_progress(&self) {
+        loop {
+            // Created before checking such that notification is not missed
+            let sector_finished = self.inner.sector_finished.notified();
+
+            if self.sectors_in_progress() == 0 {
+                return;
+            }
+
+            sector_finished.await;
+        }
+    }
+
+    /// Mark sector as being plotted, returns `None` if plotting was stopped
+    pub(crate) fn start_sector(&self) -> Option<SectorInProgress> {
+        let mut sectors_state = self.inner.sectors_state.lock();
+        if sectors_state.stopped {
+            return None;
+        }
+        sectors_state.in_progress += 1;
+
+        Some(SectorInProgress {
+            inner: Arc::clone(&self.inner),
+        })
+    }
+
+    /// Reason why plotting of new sectors should be paused right now, `None` if plotting is allowed
+    pub fn pause_reason(&self) -> Option<PlottingPauseReason> {
+        if self.is_paused_manually() {
+            return Some(PlottingPauseReason::Manual);
+        }
+
+        if !self.inner.pause_windows.is_empty() {
+            let second_of_day = (SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                % u64::from(SECONDS_IN_DAY)) as u32;
+
+            if let Some(window) = self
+                .inner
+                .pause_windows
+                .iter()
+                .find(|window| window.contains(second_of_day))
+            {
+                return Some(PlottingPauseReason::Window(*window));
+            }
+        }
+
+        if let Some(max_cpu_usage) = self.inner.max_cpu_usage
+            && let Some(cpu_usage) = *self.inner.cpu_usage.lock()
+            && cpu_usage > max_cpu_usage
+        {
+            return Some(PlottingPauseReason::CpuUsage(cpu_usage));
+        }
+
+        None
+    }
+
+    /// Wait for plotting of new sectors to be allowed or for plotting to be stopped
+    pub(crate) async fn wait_until_allowed(&self) {
+        loop {
+            // Created before checking such that resume notification is not missed
+            let resumed = self.inner.resumed.notified();
+
+            if self.is_stopped() {
+                return;
+            }
+
+            match self.pause_reason() {
+                Some(reason) => {
+                    debug!(%reason, "Plotting is still paused");
+                }
+                None => {
+                    return;
+                }
+            }
+
+            select! {
+                _ = resumed.fuse() => {}
+                _ = tokio::time::sleep(PAUSE_CHECK_INTERVAL).fuse() => {}
+            }
+        }
+    }
+}
+
+/// Sample CPU usage of other processes in background thread until schedule is dropped
+#[cfg(target_os = "linux")]
+fn spawn_cpu_usage_sampler(inner: Weak<Inner>) {
+    let result = thread::Builder::new()
+        .name("cpu-usage-sampler".to_string())
+        .spawn(move || {
+            let mut before = None;
+
+            loop {
+                let after = CpuTimes::read()
+                    .map_err(|error| debug!(%error, "Failed to read CPU times"))
+                    .ok();
+                let cpu_usage = before
+                    .zip(after)
+                    .and_then(|(before, after)| other_processes_cpu_usage(before, after));
+
+                let Some(inner) = inner.upgrade() else {
+                    // Schedule was dropped
+                    return;
+                };
+                *inner.cpu_usage.lock() = cpu_usage;
+                drop(inner);
+
+                before = after;
+                thread::sleep(CPU_USAGE_MEASUREMENT_INTERVAL);
+            }
+        });
+
+    if let Err(error) = result {
+        warn!(%error, "Failed to spawn CPU usage sampler, CPU usage threshold will be ignored");
+    }
+}
+
+/// CPU usage of other processes in % of all CPU cores between two measurements, `None` if it can't
+/// be measured
+#[cfg(target_os = "linux")]
+fn other_processes_cpu_usage(before: CpuTimes, after: CpuTimes) -> Option<f32> {
+    let total = after.total.checked_sub(before.total)?;
+    if total == 0 {
+        return None;
+    }
+    let busy = after.busy.saturating_sub(before.busy);
+    let own = after.own.saturating_sub(before.own);
+
+    Some(busy.saturating_sub(own) as f32 / total as f32 * 100.0)
+}
+
+/// CPU times in clock ticks
+#[cfg(target_os = "linux")]
+#[derive(Debug, Copy, Clone)]
+struct CpuTimes {
+    /// Total time of all CPU cores
+    total: u64,
+    /// Time all CPU cores were busy
+    busy: u64,
+    /// Time spent by this process
+    own: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl CpuTimes {
+    fn read() -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+
+        let invalid_data = |file: &str| Error::new(ErrorKind::InvalidData, format!("Bad {file}"));
+
+        // First line is `cpu user nice system idle iowait irq softirq steal guest guest_nice`,
+        // guest time is already included into user time
+        let stat = std::fs::read_to_string("/proc/stat")?;
+        let cpu_times = stat
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("cpu "))
+            .ok_or_else(|| invalid_data("/proc/stat"))?
+            .split_whitespace()
+            .take(8)
+            .map(u64::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_error| invalid_data("/proc/stat"))?;
+        let &[user, nice, system, idle, iowait, irq, softirq, steal] = cpu_times.as_slice() else {
+            return Err(invalid_data("/proc/stat"));
+        };
+        let busy = user + nice + system + irq + softirq + steal;
+
+        // Process name can contain spaces, `utime` and `stime` are 12th and 13th fields after it
+        let self_stat = std::fs::read_to_string("/proc/self/stat")?;
+        let own = self_stat
+            .rsplit_once(')')
+            .map(|(_name, fields)| fields.split_whitespace().skip(11).take(2))
+            .ok_or_else(|| invalid_data("/proc/self/stat"))?
+            .map(u64::from_str)
+            .sum::<Result<u64, _>>()
+            .map_err(|_error| invalid_data("/proc/self/stat"))?;
+
+        Ok(Self {
+            total: busy + idle + iowait,
+            busy,
+            own,
+        })
+    }
+}
@@ -17,7 +17,7 @@
 //! Modified version of SS58 parser extracted from Substrate in order to not pull the whole
 //! `sp-core` into farmer application
 
-use base58::FromBase58;
+use base58::{FromBase58, FromBase58Error, ToBase58};
 use blake2::digest::typenum::U64;
 use blake2::digest::FixedOutput;
 use blake2::{Blake2b, Digest};
@@ -26,33 +26,103 @@ use subspace_core_primitives::{PublicKey, PUBLIC_KEY_LENGTH};
 use thiserror::Error;
 
 const PREFIX: &[u8] = b"SS58PRE";
-const CHECKSUM_LEN: usize = 2;
+/// Checksum length of addresses that contain 32 bytes public key
+const PUBLIC_KEY_CHECKSUM_LEN: usize = 2;
+/// Max prefix that can be encoded in SS58 format (14 bits)
+const MAX_PREFIX: u16 = 0b0011_1111_1111_1111;
 
 /// An error type for SS58 decoding.
 #[derive(Debug, Error)]
 pub enum Ss58ParsingError {
     /// Base 58 requirement is violated
-    #[error("Base 58 requirement is violated")]
-    BadBase58,
+    #[error("Base 58 requirement is violated: {0}")]
+    BadBase58(String),
     /// Length is bad
-    #[error("Length is bad")]
-    BadLength,
+    #[error("Length of {length} bytes is not valid for SS58 address")]
+    BadLength {
+        /// Length of decoded address
+        length: usize,
+    },
     /// Invalid SS58 prefix byte
-    #[error("Invalid SS58 prefix byte")]
-    InvalidPrefix,
+    #[error("Invalid SS58 prefix byte {0}")]
+    InvalidPrefix(u8),
     /// Disallowed SS58 Address Format for this datatype
-    #[error("Disallowed SS58 Address Format for this datatype")]
-    FormatNotAllowed,
+    #[error("Disallowed SS58 address format {0}, it is reserved")]
+    FormatNotAllowed(Ss58AddressFormat),
     /// Invalid checksum
-    #[error("Invalid checksum")]
+    #[error("Invalid checksum, address was likely mistyped or truncated")]
     InvalidChecksum,
+    /// Address is valid, but doesn't contain a public key
+    #[error(
+        "Address contains {length} bytes account instead of {PUBLIC_KEY_LENGTH} bytes public key"
+    )]
+    UnexpectedAccountLength {
+        /// Account length
+        length: usize,
+    },
+    /// Address belongs to a different network
+    #[error(
+        "Address is for network {address_format} (prefix {}), but {expected_format} (prefix {}) \
+        is expected, make sure to use address for the correct network",
+        address_format.prefix(),
+        expected_format.prefix()
+    )]
+    WrongNetwork {
+        /// Format of the address
+        address_format: Ss58AddressFormat,
+        /// Expected format
+        expected_format: Ss58AddressFormat,
+    },
 }
 
-/// Some if the string is a properly encoded SS58Check address.
-pub fn parse_ss58_reward_address(s: &str) -> Result<PublicKey, Ss58ParsingError> {
-    let data = s.from_base58().map_err(|_| Ss58ParsingError::BadBase58)?;
+/// An error type for SS58 encoding.
+#[derive(Debug, Error)]
+pub enum Ss58EncodingError {
+    /// Prefix can't be encoded
+    #[error("Prefix {0} is too large, max supported is {MAX_PREFIX}")]
+    PrefixTooLarge(u16),
+    /// Unsupported account length
+    #[error("Account of {0} bytes can't be encoded in SS58 format")]
+    UnsupportedAccountLength(usize),
+}
+
+/// Decoded SS58 address
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Ss58Address {
+    /// Address format (network)
+    pub format: Ss58AddressFormat,
+    /// Account bytes (public key in most cases)
+    pub account: Vec<u8>,
+}
+
+/// Length of account and checksum for SS58 address of specified length (without prefix)
+fn account_and_checksum_len(length: usize) -> Option<(usize, usize)> {
+    Some(match length {
+        2 => (1, 1),
+        3..=4 => (2, length - 2),
+        5..=8 => (4, length - 4),
+        9..=16 => (8, length - 8),
+        34 => (32, 2),
+        35 => (33, 2),
+        _ => {
+            return None;
+        }
+    })
+}
+
+/// Decode any properly encoded SS58Check address (one or two bytes prefix, any of the standard
+/// account lengths and corresponding checksum lengths).
+pub fn decode_ss58(s: &str) -> Result<Ss58Address, Ss58ParsingError> {
+    let data = s.from_base58().map_err(|error| {
+        Ss58ParsingError::BadBase58(match error {
+            FromBase58Error::InvalidBase58Character(character, index) => {
+                format!("invalid character '{character}' at position {index}")
+            }
+            FromBase58Error::InvalidBase58Length => "invalid length".to_string(),
+        })
+    })?;
     if data.len() < 2 {
-        return Err(Ss58ParsingError::BadLength);
+        return Err(Ss58ParsingError::BadLength { length: data.len() });
     }
     let (prefix_len, ident) = match data[0] {
         0..=63 => (1, data[0] as u16),
@@ -66,32 +136,111 @@ pub fn parse_ss58_reward_address(s: &str) -> Result<PublicKey, Ss58ParsingError>
             let upper = data[1] & 0b00111111;
             (2, (lower as u16) | ((upper as u16) << 8))
         }
-        _ => return Err(Ss58ParsingError::InvalidPrefix),
+        prefix => return Err(Ss58ParsingError::InvalidPrefix(prefix)),
     };
-    if data.len() != prefix_len + PUBLIC_KEY_LENGTH + CHECKSUM_LEN {
-        return Err(Ss58ParsingError::BadLength);
-    }
-    let format: Ss58AddressFormat = ident.into();
+    let (account_len, checksum_len) = account_and_checksum_len(data.len() - prefix_len)
+        .ok_or(Ss58ParsingError::BadLength { length: data.len() })?;
+    let format = Ss58AddressFormat::custom(ident);
     if format.is_reserved() {
-        return Err(Ss58ParsingError::FormatNotAllowed);
+        return Err(Ss58ParsingError::FormatNotAllowed(format));
     }
 
-    let hash = ss58hash(&data[0..PUBLIC_KEY_LENGTH + prefix_len]);
-    let checksum = &hash[0..CHECKSUM_LEN];
-    if data[PUBLIC_KEY_LENGTH + prefix_len..PUBLIC_KEY_LENGTH + prefix_len + CHECKSUM_LEN]
-        != *checksum
-    {
+    let (body, checksum) = data.split_at(prefix_len + account_len);
+    if ss58hash(body)[..checksum_len] != *checksum {
         // Invalid checksum.
         return Err(Ss58ParsingError::InvalidChecksum);
     }
 
-    let bytes: [u8; PUBLIC_KEY_LENGTH] = data[prefix_len..][..PUBLIC_KEY_LENGTH]
-        .try_into()
-        .map_err(|_| Ss58ParsingError::BadLength)?;
+    Ok(Ss58Address {
+        format,
+        account: body[prefix_len..].to_vec(),
+    })
+}
+
+/// Some if the string is a properly encoded SS58Check address.
+pub fn parse_ss58_reward_address(s: &str) -> Result<PublicKey, Ss58ParsingError> {
+    let Ss58Address { account, .. } = decode_ss58(s)?;
+
+    public_key_from_account(&account)
+}
+
+/// Same as [`parse_ss58_reward_address`], but also returns address format of the address.
+pub fn parse_ss58_reward_address_with_format(
+    s: &str,
+) -> Result<(PublicKey, Ss58AddressFormat), Ss58ParsingError> {
+    let Ss58Address { format, account } = decode_ss58(s)?;
+
+    Ok((public_key_from_account(&account)?, format))
+}
+
+/// Same as [`parse_ss58_reward_address`], but also checks that address belongs to the network with
+/// expected format.
+pub fn parse_ss58_reward_address_for_network(
+    s: &str,
+    expected_format: Ss58AddressFormat,
+) -> Result<PublicKey, Ss58ParsingError> {
+    let Ss58Address { format, account } = decode_ss58(s)?;
+    if format != expected_format {
+        return Err(Ss58ParsingError::WrongNetwork {
+            address_format: format,
+            expected_format,
+        });
+    }
+
+    public_key_from_account(&account)
+}
+
+fn public_key_from_account(account: &[u8]) -> Result<PublicKey, Ss58ParsingError> {
+    let bytes: [u8; PUBLIC_KEY_LENGTH] =
+        account
+            .try_into()
+            .map_err(|_| Ss58ParsingError::UnexpectedAccountLength {
+                length: account.len(),
+            })?;
 
     Ok(PublicKey::from(bytes))
 }
 
+/// Encode account in SS58Check format with specified address format.
+pub fn encode_ss58(format: Ss58AddressFormat, account: &[u8]) -> Result<String, Ss58EncodingError> {
+    let prefix = format.prefix();
+    let mut data = match prefix {
+        0..=63 => vec![prefix as u8],
+        64..=MAX_PREFIX => {
+            // upper six bits of the lower byte(!)
+            let first = ((prefix & 0b0000_0000_1111_1100) as u8) >> 2;
+            // lower two bits of the lower byte in the high pos,
+            // lower bits of the upper byte in the low pos
+            let second = ((prefix >> 8) as u8) | (((prefix & 0b0000_0000_0000_0011) as u8) << 6);
+            vec![first | 0b01000000, second]
+        }
+        _ => {
+            return Err(Ss58EncodingError::PrefixTooLarge(prefix));
+        }
+    };
+    let checksum_len = match account.len() {
+        32 | 33 => PUBLIC_KEY_CHECKSUM_LEN,
+        1 | 2 | 4 | 8 => 1,
+        length => {
+            return Err(Ss58EncodingError::UnsupportedAccountLength(length));
+        }
+    };
+
+    data.extend_from_slice(account);
+    let hash = ss58hash(&data);
+    data.extend_from_slice(&hash[..checksum_len]);
+
+    Ok(data.to_base58())
+}
+
+/// Encode reward address in SS58Check format with specified address format.
+pub fn encode_ss58_reward_address(
+    format: Ss58AddressFormat,
+    public_key: &PublicKey,
+) -> Result<String, Ss58EncodingError> {
+    encode_ss58(format, public_key.as_ref())
+}
+
 fn ss58hash(data: &[u8]) -> [u8; 64] {
     let mut state = Blake2b::<U64>::new();
     state.update(PREFIX);
@@ -101,11 +250,72 @@ fn ss58hash(data: &[u8]) -> [u8; 64] {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_ss58_reward_address;
+    use super::{
+        decode_ss58, encode_ss58, encode_ss58_reward_address, parse_ss58_reward_address,
+        parse_ss58_reward_address_for_network, Ss58ParsingError,
+    };
+    use ss58_registry::Ss58AddressFormat;
 
     #[test]
     fn basic() {
         // Alice
         parse_ss58_reward_address("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap();
     }
+
+    #[test]
+    fn formats() {
+        // Alice
+        let public_key =
+            parse_ss58_reward_address("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap();
+
+        // One byte prefix, two bytes prefix and the largest possible prefix
+        for prefix in [0, 42, 64, 2254, 16383] {
+            let format = Ss58AddressFormat::custom(prefix);
+            let address = encode_ss58_reward_address(format, &public_key).unwrap();
+            let decoded = decode_ss58(&address).unwrap();
+            assert_eq!(decoded.format, format);
+            assert_eq!(decoded.account, public_key.as_ref());
+            assert_eq!(parse_ss58_reward_address(&address).unwrap(), public_key);
+        }
+        assert_eq!(
+            encode_ss58_reward_address(Ss58AddressFormat::custom(42), &public_key).unwrap(),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+
+        // Short accounts with all possible checksum lengths are decoded, but are not public keys
+        for account_length in [1, 2, 4, 8] {
+            let address =
+                encode_ss58(Ss58AddressFormat::custom(42), &vec![1; account_length]).unwrap();
+            assert_eq!(decode_ss58(&address).unwrap().account.len(), account_length);
+            assert!(matches!(
+                parse_ss58_reward_address(&address),
+                Err(Ss58ParsingError::UnexpectedAccountLength { length }) if length == account_length
+            ));
+        }
+    }
+
+    #[test]
+    fn diagnostics() {
+        let address = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+        assert!(matches!(
+            parse_ss58_reward_address_for_network(address, Ss58AddressFormat::custom(2254)),
+            Err(Ss58ParsingError::WrongNetwork { .. })
+        ));
+        parse_ss58_reward_address_for_network(address, Ss58AddressFormat::custom(42)).unwrap();
+
+        // Last character changed
+        assert!(matches!(
+            parse_ss58_reward_address("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ"),
+            Err(Ss58ParsingError::InvalidChecksum)
+        ));
+        assert!(matches!(
+            parse_ss58_reward_address("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKut"),
+            Err(Ss58ParsingError::BadLength { .. })
+        ));
+        assert!(matches!(
+            parse_ss58_reward_address("0GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"),
+            Err(Ss58ParsingError::BadBase58(_))
+        ));
+    }
 }
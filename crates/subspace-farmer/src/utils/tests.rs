@@ -1,5 +1,18 @@
-use crate::utils::run_future_in_dedicated_thread;
+#[cfg(unix)]
+use crate::utils::bind_private_unix_socket;
+use crate::utils::plotting_memory::{PlottingMemoryPlan, PlottingMemoryPlanError};
+use crate::utils::plotting_schedule::{
+    PlottingPauseReason, PlottingSchedule, PlottingWindow, PlottingWindowParseError,
+};
+use crate::utils::{parse_cpu_cores_sets, run_future_in_dedicated_thread};
+use std::assert_matches::assert_matches;
+#[cfg(unix)]
+use std::fs;
 use std::future;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Duration;
+use subspace_proof_of_space::shim::ShimTable;
 use tokio::sync::oneshot;
 
 #[tokio::test]
@@ -46,3 +59,294 @@ fn run_future_in_dedicated_thread_tokio_on_drop() {
         ));
     });
 }
+
+#[test]
+fn remove_cpu_cores() {
+    let mut cpu_core_sets = parse_cpu_cores_sets("0,1,2,3 4,5").unwrap();
+
+    assert!(cpu_core_sets[0].remove_cpu_cores(&[1, 3, 4]));
+    assert_eq!(cpu_core_sets[0].cpu_cores(), [0, 2]);
+    assert!(!cpu_core_sets[1].remove_cpu_cores(&[4, 5]));
+    assert!(cpu_core_sets[1].cpu_cores().is_empty());
+}
+
+#[test]
+fn plotting_memory_plan() {
+    let pieces_in_sector = 10;
+    let farms = 2;
+    let n = |n| NonZeroUsize::new(n).unwrap();
+    let cache_capacity = n(2);
+
+    // Everything fits, one sector is downloaded ahead of time
+    let plan = PlottingMemoryPlan::derive::<ShimTable>(
+        u64::MAX,
+        pieces_in_sector,
+        farms,
+        cache_capacity,
+        None,
+        n(2),
+        n(8),
+    )
+    .unwrap();
+    assert_eq!(plan.sector_downloading_concurrency, n(3));
+    assert_eq!(plan.sector_encoding_concurrency, n(2));
+    assert_eq!(plan.record_encoding_concurrency, n(8));
+
+    // Explicit downloading concurrency restricts encoding concurrency
+    let plan = PlottingMemoryPlan::derive::<ShimTable>(
+        u64::MAX,
+        pieces_in_sector,
+        farms,
+        cache_capacity,
+        Some(n(1)),
+        n(2),
+        n(8),
+    )
+    .unwrap();
+    assert_eq!(plan.sector_downloading_concurrency, n(1));
+    assert_eq!(plan.sector_encoding_concurrency, n(1));
+
+    // Exactly enough memory for one sector at a time with one record encoded at a time
+    let min_memory_usage = PlottingMemoryPlan::memory_usage::<ShimTable>(
+        pieces_in_sector,
+        farms,
+        cache_capacity,
+        n(1),
+        n(1),
+        n(1),
+    );
+    let plan = PlottingMemoryPlan::derive::<ShimTable>(
+        min_memory_usage,
+        pieces_in_sector,
+        farms,
+        cache_capacity,
+        None,
+        n(2),
+        n(8),
+    )
+    .unwrap();
+    assert_eq!(plan.sector_downloading_concurrency, n(1));
+    assert_eq!(plan.sector_encoding_concurrency, n(1));
+    assert_eq!(plan.record_encoding_concurrency, n(1));
+    assert_eq!(plan.estimated_memory_usage, min_memory_usage);
+
+    // Table generators of every farm are accounted for
+    assert!(
+        PlottingMemoryPlan::memory_usage::<ShimTable>(
+            pieces_in_sector,
+            farms + 1,
+            cache_capacity,
+            n(1),
+            n(1),
+            n(1)
+        ) > min_memory_usage
+    );
+    // Segment reconstruction cache is accounted for
+    assert!(
+        PlottingMemoryPlan::memory_usage::<ShimTable>(
+            pieces_in_sector,
+            farms,
+            n(3),
+            n(1),
+            n(1),
+            n(1)
+        ) > min_memory_usage
+    );
+    assert!(matches!(
+        PlottingMemoryPlan::derive::<ShimTable>(
+            min_memory_usage,
+            pieces_in_sector,
+            farms + 1,
+            cache_capacity,
+            None,
+            n(2),
+            n(8)
+        ),
+        Err(PlottingMemoryPlanError::InsufficientMemoryLimit { .. })
+    ));
+
+    // Record encoding concurrency is preferred over sector encoding concurrency
+    let memory_limit = PlottingMemoryPlan::memory_usage::<ShimTable>(
+        pieces_in_sector,
+        farms,
+        cache_capacity,
+        n(2),
+        n(1),
+        n(8),
+    );
+    let plan = PlottingMemoryPlan::derive::<ShimTable>(
+        memory_limit,
+        pieces_in_sector,
+        farms,
+        cache_capacity,
+        None,
+        n(2),
+        n(8),
+    )
+    .unwrap();
+    assert_eq!(plan.sector_encoding_concurrency, n(1));
+    assert_eq!(plan.record_encoding_concurrency, n(8));
+    assert!(plan.estimated_memory_usage <= memory_limit);
+
+    // Not enough memory
+    assert!(matches!(
+        PlottingMemoryPlan::derive::<ShimTable>(
+            min_memory_usage - 1,
+            pieces_in_sector,
+            farms,
+            cache_capacity,
+            None,
+            n(2),
+            n(8)
+        ),
+        Err(PlottingMemoryPlanError::InsufficientMemoryLimit { .. })
+    ));
+}
+
+#[test]
+fn plotting_window() {
+    let window = PlottingWindow::from_str("09:00-17:30").unwrap();
+    assert_eq!(window.to_string(), "09:00-17:30");
+    assert!(!window.contains(9 * 3600 - 1));
+    assert!(window.contains(9 * 3600));
+    assert!(window.contains(17 * 3600 + 29 * 60));
+    assert!(!window.contains(17 * 3600 + 30 * 60));
+
+    // Crosses midnight
+    let window = PlottingWindow::from_str("22:00-06:00").unwrap();
+    assert!(window.contains(23 * 3600));
+    assert!(window.contains(0));
+    assert!(window.contains(6 * 3600 - 1));
+    assert!(!window.contains(6 * 3600));
+    assert!(!window.contains(12 * 3600));
+
+    for invalid in [
+        "",
+        "09:00",
+        "9:00-17:00",
+        "09:00-24:00",
+        "09:60-10:00",
+        "a:bc-10:00",
+    ] {
+        assert_matches!(
+            PlottingWindow::from_str(invalid),
+            Err(PlottingWindowParseError::InvalidFormat(_))
+        );
+    }
+    assert_matches!(
+        PlottingWindow::from_str("10:00-10:00"),
+        Err(PlottingWindowParseError::Empty(_))
+    );
+}
+
+#[tokio::test]
+async fn plotting_schedule_manual_pause() {
+    let plotting_schedule = PlottingSchedule::default();
+    assert_eq!(plotting_schedule.pause_reason(), None);
+
+    plotting_schedule.pause();
+    assert_eq!(
+        plotting_schedule.pause_reason(),
+        Some(PlottingPauseReason::Manual)
+    );
+
+    let mut wait_fut = tokio::spawn({
+        let plotting_schedule = plotting_schedule.clone();
+
+        async move { plotting_schedule.wait_until_allowed().await }
+    });
+    // Still paused after a while
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), &mut wait_fut)
+            .await
+            .is_err()
+    );
+
+    plotting_schedule.resume();
+    tokio::time::timeout(Duration::from_secs(5), wait_fut)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn plotting_schedule_cpu_usage() {
+    // Any CPU usage is above negative threshold
+    let plotting_schedule = PlottingSchedule::new(Vec::new(), Some(-1.0));
+
+    // CPU usage is sampled in background, so it becomes known after the first sampling interval
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Some(reason) = plotting_schedule.pause_reason() {
+                assert_matches!(reason, PlottingPauseReason::CpuUsage(cpu_usage) if cpu_usage >= 0.0);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn plotting_schedule_stop() {
+    let plotting_schedule = PlottingSchedule::default();
+
+    let sector_in_progress = plotting_schedule.start_sector().unwrap();
+    assert_eq!(plotting_schedule.sectors_in_progress(), 1);
+
+    plotting_schedule.pause();
+    plotting_schedule.stop();
+    assert!(plotting_schedule.is_stopped());
+    // No new sectors after stop
+    assert!(plotting_schedule.start_sector().is_none());
+    // Paused plotting is woken up by stop
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        plotting_schedule.wait_until_allowed(),
+    )
+    .await
+    .unwrap();
+
+    let wait_fut = tokio::spawn({
+        let plotting_schedule = plotting_schedule.clone();
+
+        async move { plotting_schedule.wait_for_sectors_in_progress().await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!wait_fut.is_finished());
+
+    drop(sector_in_progress);
+    tokio::time::timeout(Duration::from_secs(5), wait_fut)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(plotting_schedule.sectors_in_progress(), 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn private_unix_socket() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = tempfile::tempdir().unwrap();
+    let socket_path = directory.path().join("control.sock");
+    // Stale file from previous run is replaced
+    fs::write(&socket_path, b"stale").unwrap();
+
+    let listener = bind_private_unix_socket(&socket_path).unwrap();
+    assert_eq!(
+        fs::metadata(&socket_path).unwrap().permissions().mode() & 0o777,
+        0o600
+    );
+    // Temporary directory is removed
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 1);
+
+    // Socket moved into place is still connected to the listener
+    let (_client, accepted) = tokio::join!(
+        tokio::net::UnixStream::connect(&socket_path),
+        listener.accept()
+    );
+    accepted.unwrap();
+}
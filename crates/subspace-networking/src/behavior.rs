@@ -21,10 +21,13 @@ use libp2p::gossipsub::{
 };
 use libp2p::identify::{Behaviour as Identify, Config as IdentifyConfig, Event as IdentifyEvent};
 use libp2p::kad::{Behaviour as Kademlia, Config as KademliaConfig, Event as KademliaEvent};
+use libp2p::mdns::tokio::Behaviour as Mdns;
+use libp2p::mdns::{Config as MdnsConfig, Event as MdnsEvent};
 use libp2p::ping::{Behaviour as Ping, Event as PingEvent};
 use libp2p::swarm::behaviour::toggle::Toggle;
 use libp2p::swarm::NetworkBehaviour;
 use libp2p::PeerId;
+use tracing::warn;
 use void::Void as VoidEvent;
 
 type BlockListBehaviour = AllowBlockListBehaviour<BlockedPeers>;
@@ -48,6 +51,8 @@ pub(crate) struct BehaviorConfig<RecordStore> {
     pub(crate) reserved_peers: ReservedPeersConfig,
     /// Autonat configuration.
     pub(crate) autonat: AutonatWrapperConfig,
+    /// The configuration for the [`Mdns`] behaviour, disabled if `None`.
+    pub(crate) mdns: Option<MdnsConfig>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -76,6 +81,7 @@ pub(crate) struct Behavior<RecordStore> {
     // pub(crate) special_connected_peers:
     //     Toggle<ConnectedPeersBehaviour<SpecialConnectedPeersInstance>>,
     pub(crate) autonat: AutonatWrapper,
+    pub(crate) mdns: Toggle<Mdns>,
 }
 
 impl<RecordStore> Behavior<RecordStore>
@@ -101,6 +107,18 @@ where
             })
             .into();
 
+        let mdns = config
+            .mdns
+            .and_then(|mdns_config| match Mdns::new(mdns_config, config.peer_id) {
+                Ok(mdns) => Some(mdns),
+                Err(error) => {
+                    warn!(%error, "Failed to start mDNS, local network discovery is disabled");
+
+                    None
+                }
+            })
+            .into();
+
         // TODO: Restore or remove connected peer later
         // let peer_info = config
         //     .peer_info_provider
@@ -120,6 +138,7 @@ where
             block_list: BlockListBehaviour::default(),
             reserved_peers: ReservedPeersBehaviour::new(config.reserved_peers),
             autonat: AutonatWrapper::new(config.autonat),
+            mdns,
         }
     }
 }
@@ -135,4 +154,5 @@ pub(crate) enum Event {
     VoidEventStub(VoidEvent),
     ReservedPeers(ReservedPeersEvent),
     Autonat(AutonatEvent),
+    Mdns(MdnsEvent),
 }
@@ -1,5 +1,6 @@
 use super::persistent_parameters::remove_known_peer_addresses_internal;
 use crate::behavior::persistent_parameters::{append_p2p_suffix, remove_p2p_suffix};
+use crate::constructor::mdns_config;
 use crate::{
     Config, GenericRequest, GenericRequestHandler, KnownPeersManager, KnownPeersManagerConfig,
     KnownPeersRegistry,
@@ -315,3 +316,64 @@ async fn test_known_peers_removal_address_after_specified_interval() {
     // We removed address after the configured interval.
     assert!(!known_peers.contains_address(&peer_id, &address));
 }
+
+#[tokio::test]
+async fn test_mdns_discovers_local_network_peers() {
+    let config_1 = Config {
+        listen_on: vec!["/ip4/0.0.0.0/tcp/0".parse().unwrap()],
+        allow_non_global_addresses_in_dht: true,
+        enable_mdns: true,
+        request_response_protocols: vec![GenericRequestHandler::create(
+            |_, &ExampleRequest| async {
+                let fut = FuturePolledTwice::default();
+
+                Some(ExampleResponse { counter: fut.await })
+            },
+        )],
+        ..Config::default()
+    };
+    let (node_1, mut node_runner_1) = crate::construct(config_1).unwrap();
+
+    tokio::spawn(async move {
+        node_runner_1.run().await;
+    });
+
+    // No bootstrap addresses, peers can only find each other using mDNS
+    let config_2 = Config {
+        listen_on: vec!["/ip4/0.0.0.0/tcp/0".parse().unwrap()],
+        allow_non_global_addresses_in_dht: true,
+        enable_mdns: true,
+        request_response_protocols: vec![GenericRequestHandler::<ExampleRequest>::create(
+            |_, _| async { None },
+        )],
+        ..Config::default()
+    };
+    let (node_2, mut node_runner_2) = crate::construct(config_2).unwrap();
+
+    tokio::spawn(async move {
+        node_runner_2.run().await;
+    });
+
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while !node_2.local_network_peers().contains(&node_1.id()) {
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("Peer must be discovered in local network");
+
+    let resp = node_2
+        .send_generic_request(node_1.id(), ExampleRequest)
+        .await
+        .unwrap();
+
+    assert_eq!(resp.counter, 1);
+}
+
+#[test]
+fn test_mdns_requires_non_global_addresses() {
+    assert!(mdns_config(true, true).is_some());
+    assert!(mdns_config(true, false).is_none());
+    assert!(mdns_config(false, true).is_none());
+    assert!(mdns_config(false, false).is_none());
+}
@@ -1,5 +1,5 @@
 pub(crate) mod temporary_bans;
-mod transport;
+pub(crate) mod transport;
 
 use crate::behavior::persistent_parameters::{KnownPeersRegistry, StubNetworkingParametersManager};
 use crate::behavior::{Behavior, BehaviorConfig};
@@ -27,6 +27,7 @@ use libp2p::kad::{
     store, BucketInserts, Config as KademliaConfig, Mode, ProviderRecord, Record, RecordKey,
     StoreInserts,
 };
+use libp2p::mdns::Config as MdnsConfig;
 use libp2p::metrics::Metrics;
 use libp2p::multiaddr::Protocol;
 use libp2p::yamux::Config as YamuxConfig;
@@ -42,7 +43,9 @@ use std::time::{Duration, Instant};
 use std::{fmt, io, iter};
 use subspace_core_primitives::{crypto, Piece};
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+pub use crate::constructor::transport::BandwidthLimits;
 
 const DEFAULT_NETWORK_PROTOCOL_VERSION: &str = "dev";
 const KADEMLIA_PROTOCOL: &str = "/subspace/kad/0.1.0";
@@ -210,6 +213,9 @@ pub struct Config<LocalRecordProvider> {
     pub yamux_config: YamuxConfig,
     /// Should non-global addresses be added to the DHT?
     pub allow_non_global_addresses_in_dht: bool,
+    /// Discover peers in local network using mDNS and prefer them as piece providers, only takes
+    /// effect when `allow_non_global_addresses_in_dht` is enabled.
+    pub enable_mdns: bool,
     /// How frequently should random queries be done using Kademlia DHT to populate routing table.
     pub initial_random_query_interval: Duration,
     /// A reference to the `NetworkingParametersRegistry` implementation.
@@ -230,6 +236,8 @@ pub struct Config<LocalRecordProvider> {
     pub temporary_bans_cache_size: NonZeroUsize,
     /// Backoff policy for temporary banning of unreachable peers.
     pub temporary_ban_backoff: ExponentialBackoff,
+    /// Upload and download rate limits of the node.
+    pub bandwidth_limits: BandwidthLimits,
     /// Optional libp2p prometheus metrics. None will disable metrics gathering.
     pub libp2p_metrics: Option<Metrics>,
     /// Internal prometheus metrics. None will disable metrics gathering.
@@ -351,6 +359,7 @@ where
             gossipsub,
             local_records_provider,
             allow_non_global_addresses_in_dht: false,
+            enable_mdns: false,
             initial_random_query_interval: Duration::from_secs(1),
             networking_parameters_registry: StubNetworkingParametersManager.boxed(),
             request_response_protocols: Vec::new(),
@@ -362,6 +371,7 @@ where
             max_pending_outgoing_connections: SWARM_MAX_PENDING_OUTGOING_CONNECTIONS,
             temporary_bans_cache_size: TEMPORARY_BANS_CACHE_SIZE,
             temporary_ban_backoff,
+            bandwidth_limits: BandwidthLimits::default(),
             libp2p_metrics,
             metrics,
             protocol_version,
@@ -416,6 +426,7 @@ where
         local_records_provider,
         yamux_config,
         allow_non_global_addresses_in_dht,
+        enable_mdns,
         initial_random_query_interval,
         networking_parameters_registry,
         request_response_protocols,
@@ -426,6 +437,7 @@ where
         max_pending_outgoing_connections,
         temporary_bans_cache_size,
         temporary_ban_backoff,
+        bandwidth_limits,
         libp2p_metrics,
         metrics,
         protocol_version,
@@ -438,6 +450,7 @@ where
 
     info!(
         %allow_non_global_addresses_in_dht,
+        %enable_mdns,
         peer_id = %local_peer_id,
         %protocol_version,
         "DSN instance configured."
@@ -465,6 +478,8 @@ where
         "Autonat boot delay set."
     );
 
+    let mdns = mdns_config(enable_mdns, allow_non_global_addresses_in_dht);
+
     let mut behaviour = Behavior::new(BehaviorConfig {
         peer_id: local_peer_id,
         identify,
@@ -488,6 +503,7 @@ where
             local_peer_id,
             servers: bootstrap_addresses.clone(),
         },
+        mdns,
     });
 
     match (kademlia_mode, external_addresses.is_empty()) {
@@ -507,6 +523,8 @@ where
         temporary_ban_backoff,
     )));
 
+    let bandwidth_metrics = metrics.as_ref().map(|metrics| metrics.bandwidth().clone());
+
     let mut swarm = SwarmBuilder::with_existing_identity(keypair)
         .with_tokio()
         .with_other_transport(|keypair| {
@@ -516,6 +534,8 @@ where
                 Arc::clone(&temporary_bans),
                 timeout,
                 yamux_config,
+                bandwidth_limits,
+                bandwidth_metrics,
             )?)
         })
         .map_err(|error| CreationError::TransportCreationError(error.into()))?
@@ -598,3 +618,20 @@ where
 
     Ok((node, node_runner))
 }
+
+/// Configuration of mDNS behaviour, `None` if it is disabled.
+///
+/// Local network addresses are useless unless non-global addresses are allowed, so mDNS is only
+/// enabled together with them.
+pub(crate) fn mdns_config(
+    enable_mdns: bool,
+    allow_non_global_addresses_in_dht: bool,
+) -> Option<MdnsConfig> {
+    if enable_mdns && !allow_non_global_addresses_in_dht {
+        warn!("mDNS requires non-global addresses to be allowed in DHT, ignoring");
+
+        return None;
+    }
+
+    enable_mdns.then(MdnsConfig::default)
+}
@@ -1,7 +1,12 @@
+#[cfg(test)]
+mod tests;
+
 use crate::constructor::temporary_bans::TemporaryBans;
 use futures::future::Either;
+use futures::{ready, AsyncRead, AsyncWrite, Future};
+use futures_timer::Delay;
 use libp2p::core::multiaddr::{Multiaddr, Protocol};
-use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::muxing::{StreamMuxer, StreamMuxerBox, StreamMuxerEvent, SubstreamBox};
 use libp2p::core::transport::{Boxed, ListenerId, TransportError, TransportEvent};
 use libp2p::core::Transport;
 use libp2p::dns::tokio::Transport as TokioTransport;
@@ -12,21 +17,831 @@ use libp2p::tcp::Config as GenTcpConfig;
 use libp2p::yamux::Config as YamuxConfig;
 use libp2p::{core, identity, noise, PeerId};
 use parking_lot::Mutex;
+use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::registry::Registry;
+use std::collections::HashMap;
 use std::io;
+use std::num::NonZeroU64;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::debug;
 
+/// Max number of bytes that can be read or written at once when rate limit is applied, such that
+/// concurrent substreams interleave instead of one substream consuming the whole budget.
+const MAX_RATE_LIMITED_CHUNK: usize = 16 * 1024;
+/// Writes of this size or smaller (requests, acknowledgements, etc.) are not delayed by upload
+/// rate limit (though still accounted for), such that serving large responses doesn't starve
+/// outgoing requests of plotting.
+const MAX_UNTHROTTLED_WRITE: usize = 1024;
+/// Min delay before retrying to acquire bandwidth after running out of it.
+const MIN_RATE_LIMIT_DELAY: Duration = Duration::from_millis(1);
+/// Max number of bytes written to substream that are inspected in order to find its protocol.
+const MAX_PROTOCOL_NEGOTIATION_BYTES: usize = 1024;
+/// Header message of multistream-select protocol negotiation.
+const MULTISTREAM_HEADER: &[u8] = b"/multistream/1.0.0\n";
+/// Message of multistream-select protocol negotiation that rejects proposed protocol.
+const MULTISTREAM_NOT_AVAILABLE: &[u8] = b"na\n";
+/// Protocol label of substreams whose protocol couldn't be determined.
+const UNKNOWN_PROTOCOL: &str = "unknown";
+
+/// Upload and download rate limits of the transport.
+///
+/// Limits are shared by all protocols, each protocol with open substreams is guaranteed an equal
+/// share of the limit, while bandwidth not used by some protocols can be used by others. Small
+/// writes are not delayed by upload rate limit.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct BandwidthLimits {
+    /// Max upload rate in bytes per second, unlimited if `None`.
+    pub upload: Option<NonZeroU64>,
+    /// Max download rate in bytes per second, unlimited if `None`.
+    pub download: Option<NonZeroU64>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, EncodeLabelValue)]
+enum TransportKind {
+    Tcp,
+    Quic,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, EncodeLabelValue)]
+enum Direction {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, EncodeLabelSet)]
+struct BandwidthLabels {
+    transport: TransportKind,
+    direction: Direction,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, EncodeLabelSet)]
+struct ProtocolBandwidthLabels {
+    /// Protocol negotiated for substream
+    protocol: String,
+    transport: TransportKind,
+    direction: Direction,
+}
+
+/// Bandwidth metrics of the transport.
+#[derive(Debug, Clone)]
+pub(crate) struct BandwidthMetrics {
+    bytes: Family<ProtocolBandwidthLabels, Counter>,
+    throttled: Family<BandwidthLabels, Counter>,
+}
+
+impl BandwidthMetrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let bytes = Family::default();
+        registry.register(
+            "bandwidth_bytes",
+            "Number of bytes sent and received by protocol and transport",
+            bytes.clone(),
+        );
+
+        let throttled = Family::default();
+        registry.register(
+            "bandwidth_throttled",
+            "Number of times reads or writes were delayed by rate limit",
+            throttled.clone(),
+        );
+
+        Self { bytes, throttled }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucketState {
+    /// Can be negative after small writes that are not throttled
+    available: i64,
+    last_refill: Instant,
+}
+
+impl TokenBucketState {
+    fn new(capacity: i64) -> Self {
+        Self {
+            available: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, bytes_per_second: i64, capacity: i64) {
+        let now = Instant::now();
+        let refill = (now.duration_since(self.last_refill).as_nanos() * bytes_per_second as u128
+            / 1_000_000_000) as i64;
+        if refill > 0 {
+            self.available = self.available.saturating_add(refill).min(capacity);
+            self.last_refill = now;
+        }
+    }
+
+    /// Acquire up to `wanted` bytes (no more than [`MAX_RATE_LIMITED_CHUNK`]) if any are available
+    fn acquire_available(&mut self, wanted: usize) -> Option<usize> {
+        if self.available <= 0 {
+            return None;
+        }
+
+        let acquired = wanted
+            .min(MAX_RATE_LIMITED_CHUNK)
+            .min(self.available as usize);
+        self.available -= acquired as i64;
+
+        Some(acquired)
+    }
+
+    /// How long to wait until `wanted` bytes (no more than [`MAX_RATE_LIMITED_CHUNK`]) are
+    /// available
+    fn delay(&self, bytes_per_second: i64, wanted: usize) -> Duration {
+        let target = wanted.min(MAX_RATE_LIMITED_CHUNK) as i64;
+        let missing = (target - self.available) as u64;
+        let delay =
+            Duration::from_nanos(missing.saturating_mul(1_000_000_000) / bytes_per_second as u64);
+
+        delay.max(MIN_RATE_LIMIT_DELAY)
+    }
+}
+
+/// Token bucket rate limiter with a capacity of one second worth of tokens.
+#[derive(Debug)]
+struct TokenBucket {
+    bytes_per_second: i64,
+    capacity: i64,
+    state: Mutex<TokenBucketState>,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_second: NonZeroU64) -> Self {
+        let bytes_per_second = i64::try_from(bytes_per_second.get()).unwrap_or(i64::MAX);
+        let capacity = bytes_per_second.max(MAX_RATE_LIMITED_CHUNK as i64);
+
+        Self {
+            bytes_per_second,
+            capacity,
+            state: Mutex::new(TokenBucketState::new(capacity)),
+        }
+    }
+
+    /// Guaranteed share of each of `protocols` protocols: bytes per second and capacity
+    fn share(&self, protocols: usize) -> (i64, i64) {
+        let bytes_per_second = (self.bytes_per_second / protocols.max(1) as i64).max(1);
+
+        (
+            bytes_per_second,
+            bytes_per_second.max(MAX_RATE_LIMITED_CHUNK as i64),
+        )
+    }
+
+    /// Try to acquire up to `wanted` bytes, returns number of bytes acquired or how long to wait
+    /// before trying again.
+    fn try_acquire(&self, wanted: usize, allow_debt: bool) -> Result<usize, Duration> {
+        if wanted == 0 {
+            return Ok(0);
+        }
+
+        let mut state = self.state.lock();
+        state.refill(self.bytes_per_second, self.capacity);
+
+        if allow_debt && state.available > -self.capacity {
+            state.available -= wanted as i64;
+            return Ok(wanted);
+        }
+
+        if let Some(acquired) = state.acquire_available(wanted) {
+            return Ok(acquired);
+        }
+
+        Err(state.delay(self.bytes_per_second, wanted))
+    }
+
+    /// Acquire bytes that were guaranteed to a protocol, goes into debt if necessary, such that
+    /// other protocols have to wait, but no more than one second worth of bytes.
+    fn acquire_guaranteed(&self, bytes: usize) {
+        let mut state = self.state.lock();
+        state.refill(self.bytes_per_second, self.capacity);
+        state.available = (state.available - bytes as i64).max(-self.capacity);
+    }
+
+    /// Return bytes that were acquired, but not used.
+    fn refund(&self, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock();
+        state.available = state
+            .available
+            .saturating_add(bytes as i64)
+            .min(self.capacity);
+    }
+}
+
+/// Share of bandwidth limits guaranteed to a protocol, bytes that protocol didn't use in time are
+/// available to other protocols.
+#[derive(Debug)]
+struct ProtocolShare {
+    /// Number of open substreams of the protocol
+    substreams: usize,
+    upload: TokenBucketState,
+    download: TokenBucketState,
+}
+
+/// Bytes acquired for reading or writing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Acquired {
+    bytes: usize,
+    /// Bytes were acquired from share guaranteed to protocol of the substream
+    guaranteed: bool,
+}
+
+/// Bandwidth accounting and shaping shared by all connections of the transport.
+#[derive(Debug)]
+struct Bandwidth {
+    upload: Option<TokenBucket>,
+    download: Option<TokenBucket>,
+    /// Shares of protocols that have open substreams, only tracked when there are limits
+    protocols: Mutex<HashMap<String, ProtocolShare>>,
+    metrics: Option<BandwidthMetrics>,
+}
+
+impl Bandwidth {
+    fn new(limits: BandwidthLimits, metrics: Option<BandwidthMetrics>) -> Self {
+        Self {
+            upload: limits.upload.map(TokenBucket::new),
+            download: limits.download.map(TokenBucket::new),
+            protocols: Mutex::default(),
+            metrics,
+        }
+    }
+
+    /// Substream of the protocol was opened, the protocol gets its share of bandwidth
+    fn add_protocol_substream(&self, protocol: &str) {
+        if self.upload.is_none() && self.download.is_none() {
+            return;
+        }
+
+        let mut protocols = self.protocols.lock();
+        if let Some(protocol_share) = protocols.get_mut(protocol) {
+            protocol_share.substreams += 1;
+            return;
+        }
+
+        // New protocol starts with a full share, shares of other protocols decrease
+        let protocols_count = protocols.len() + 1;
+        let capacity = |token_bucket: Option<&TokenBucket>| {
+            token_bucket
+                .map(|token_bucket| token_bucket.share(protocols_count).1)
+                .unwrap_or_default()
+        };
+        let upload_capacity = capacity(self.upload.as_ref());
+        let download_capacity = capacity(self.download.as_ref());
+        for protocol_share in protocols.values_mut() {
+            protocol_share.upload.available = protocol_share.upload.available.min(upload_capacity);
+            protocol_share.download.available =
+                protocol_share.download.available.min(download_capacity);
+        }
+        protocols.insert(
+            protocol.to_string(),
+            ProtocolShare {
+                substreams: 1,
+                upload: TokenBucketState::new(upload_capacity),
+                download: TokenBucketState::new(download_capacity),
+            },
+        );
+    }
+
+    /// Substream of the protocol was closed, the protocol no longer gets its share of bandwidth
+    /// once all of its substreams are closed
+    fn remove_protocol_substream(&self, protocol: &str) {
+        let mut protocols = self.protocols.lock();
+        if let Some(protocol_share) = protocols.get_mut(protocol) {
+            protocol_share.substreams -= 1;
+            if protocol_share.substreams == 0 {
+                protocols.remove(protocol);
+            }
+        }
+    }
+
+    fn token_bucket(&self, direction: Direction) -> Option<&TokenBucket> {
+        match direction {
+            Direction::Inbound => self.download.as_ref(),
+            Direction::Outbound => self.upload.as_ref(),
+        }
+    }
+
+    /// Try to acquire up to `wanted` bytes for reading or writing by substream of `protocol` (if
+    /// known), returns acquired bytes or how long to wait before trying again.
+    ///
+    /// Bytes guaranteed to the protocol are acquired first, then bytes not used by other protocols.
+    fn try_acquire(
+        &self,
+        protocol: Option<&str>,
+        direction: Direction,
+        wanted: usize,
+    ) -> Result<Acquired, Duration> {
+        let Some(token_bucket) = self.token_bucket(direction) else {
+            return Ok(Acquired {
+                bytes: wanted,
+                guaranteed: false,
+            });
+        };
+        if wanted == 0 {
+            return Ok(Acquired {
+                bytes: 0,
+                guaranteed: false,
+            });
+        }
+        let allow_debt = direction == Direction::Outbound && wanted <= MAX_UNTHROTTLED_WRITE;
+
+        let mut share_delay = None;
+        if let Some(protocol) = protocol {
+            let mut protocols = self.protocols.lock();
+            let protocols_count = protocols.len();
+            if let Some(protocol_share) = protocols.get_mut(protocol) {
+                let (bytes_per_second, capacity) = token_bucket.share(protocols_count);
+                let state = match direction {
+                    Direction::Inbound => &mut protocol_share.download,
+                    Direction::Outbound => &mut protocol_share.upload,
+                };
+                state.refill(bytes_per_second, capacity);
+
+                match state.acquire_available(wanted) {
+                    Some(acquired) => {
+                        drop(protocols);
+                        token_bucket.acquire_guaranteed(acquired);
+
+                        return Ok(Acquired {
+                            bytes: acquired,
+                            guaranteed: true,
+                        });
+                    }
+                    None => {
+                        share_delay.replace(state.delay(bytes_per_second, wanted));
+                    }
+                }
+            }
+        }
+
+        match token_bucket.try_acquire(wanted, allow_debt) {
+            Ok(acquired) => Ok(Acquired {
+                bytes: acquired,
+                guaranteed: false,
+            }),
+            Err(delay) => Err(share_delay.map_or(delay, |share_delay| share_delay.min(delay))),
+        }
+    }
+
+    /// Acquire up to `wanted` bytes for reading or writing by substream of `protocol` (if known),
+    /// `delay` is used to wait for bandwidth to become available.
+    fn poll_acquire(
+        &self,
+        cx: &mut Context<'_>,
+        transport: TransportKind,
+        protocol: Option<&str>,
+        direction: Direction,
+        delay: &mut Option<Delay>,
+        wanted: usize,
+    ) -> Poll<Acquired> {
+        loop {
+            if let Some(existing_delay) = delay {
+                ready!(Pin::new(existing_delay).poll(cx));
+                delay.take();
+            }
+
+            match self.try_acquire(protocol, direction, wanted) {
+                Ok(acquired) => {
+                    return Poll::Ready(acquired);
+                }
+                Err(duration) => {
+                    if let Some(metrics) = &self.metrics {
+                        metrics
+                            .throttled
+                            .get_or_create(&BandwidthLabels {
+                                transport,
+                                direction,
+                            })
+                            .inc();
+                    }
+                    delay.replace(Delay::new(duration));
+                }
+            }
+        }
+    }
+
+    /// Return bytes that were acquired, but not actually read or written.
+    fn refund(
+        &self,
+        protocol: Option<&str>,
+        direction: Direction,
+        acquired: Acquired,
+        used: usize,
+    ) {
+        let Some(token_bucket) = self.token_bucket(direction) else {
+            return;
+        };
+        let unused = acquired.bytes.saturating_sub(used);
+        if unused == 0 {
+            return;
+        }
+
+        token_bucket.refund(unused);
+
+        if acquired.guaranteed {
+            if let Some(protocol) = protocol {
+                let mut protocols = self.protocols.lock();
+                let protocols_count = protocols.len();
+                if let Some(protocol_share) = protocols.get_mut(protocol) {
+                    let (_bytes_per_second, capacity) = token_bucket.share(protocols_count);
+                    let state = match direction {
+                        Direction::Inbound => &mut protocol_share.download,
+                        Direction::Outbound => &mut protocol_share.upload,
+                    };
+                    state.available = state.available.saturating_add(unused as i64).min(capacity);
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of looking for negotiated protocol in multistream-select messages.
+#[derive(Debug, Eq, PartialEq)]
+enum ProtocolNegotiation {
+    /// Protocol was found
+    Protocol(String),
+    /// More bytes are needed
+    Incomplete,
+    /// Bytes are not multistream-select messages
+    Invalid,
+}
+
+/// Find protocol in multistream-select messages written by local node to substream: protocol that
+/// was proposed for outbound substream or confirmed for inbound substream.
+///
+/// Since only messages written by local node are inspected, protocols are limited to those local
+/// node supports, which keeps the number of metric labels bounded.
+fn negotiated_protocol(mut bytes: &[u8]) -> ProtocolNegotiation {
+    loop {
+        let (length, rest) = match unsigned_varint::decode::usize(bytes) {
+            Ok(decoded) => decoded,
+            Err(unsigned_varint::decode::Error::Insufficient) => {
+                return ProtocolNegotiation::Incomplete;
+            }
+            Err(_) => {
+                return ProtocolNegotiation::Invalid;
+            }
+        };
+        if rest.len() < length {
+            return ProtocolNegotiation::Incomplete;
+        }
+        let (message, rest) = rest.split_at(length);
+        bytes = rest;
+
+        if message == MULTISTREAM_HEADER || message == MULTISTREAM_NOT_AVAILABLE {
+            continue;
+        }
+
+        return match message
+            .strip_suffix(b"\n")
+            .and_then(|protocol| std::str::from_utf8(protocol).ok())
+        {
+            Some(protocol) => ProtocolNegotiation::Protocol(protocol.to_string()),
+            None => ProtocolNegotiation::Invalid,
+        };
+    }
+}
+
+/// Protocol of substream, found in multistream-select messages written to it by local node.
+#[derive(Debug)]
+enum NegotiatedProtocol {
+    Negotiating { written_bytes: Vec<u8> },
+    Known(String),
+    Unknown,
+}
+
+impl NegotiatedProtocol {
+    /// Known protocol, `None` while negotiating or if protocol couldn't be determined
+    fn protocol(&self) -> Option<&str> {
+        match self {
+            Self::Known(protocol) => Some(protocol),
+            Self::Negotiating { .. } | Self::Unknown => None,
+        }
+    }
+
+    /// Inspect bytes written to substream, returns protocol once it is found or it becomes clear
+    /// that it can't be found (in which case [`UNKNOWN_PROTOCOL`] is returned).
+    fn inspect_written(&mut self, written: &[u8]) -> Option<String> {
+        let Self::Negotiating { written_bytes } = self else {
+            return None;
+        };
+
+        let inspected_bytes = written
+            .len()
+            .min(MAX_PROTOCOL_NEGOTIATION_BYTES - written_bytes.len());
+        written_bytes.extend_from_slice(&written[..inspected_bytes]);
+
+        match negotiated_protocol(written_bytes) {
+            ProtocolNegotiation::Protocol(protocol) => {
+                *self = Self::Known(protocol.clone());
+                Some(protocol)
+            }
+            ProtocolNegotiation::Incomplete
+                if written_bytes.len() < MAX_PROTOCOL_NEGOTIATION_BYTES =>
+            {
+                None
+            }
+            ProtocolNegotiation::Incomplete | ProtocolNegotiation::Invalid => {
+                *self = Self::Unknown;
+                Some(UNKNOWN_PROTOCOL.to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SubstreamProtocol {
+    /// Protocol is not known yet, bytes are accounted for once it is
+    Negotiating {
+        pending_read: u64,
+        pending_written: u64,
+    },
+    Known {
+        read: Counter,
+        written: Counter,
+    },
+}
+
+/// Accounting of bytes read from and written to substream by its protocol.
+#[derive(Debug)]
+struct SubstreamAccounting {
+    metrics: BandwidthMetrics,
+    transport: TransportKind,
+    protocol: SubstreamProtocol,
+}
+
+impl Drop for SubstreamAccounting {
+    fn drop(&mut self) {
+        if let SubstreamProtocol::Negotiating { .. } = self.protocol {
+            self.set_protocol(UNKNOWN_PROTOCOL.to_string());
+        }
+    }
+}
+
+impl SubstreamAccounting {
+    fn new(metrics: BandwidthMetrics, transport: TransportKind) -> Self {
+        Self {
+            metrics,
+            transport,
+            protocol: SubstreamProtocol::Negotiating {
+                pending_read: 0,
+                pending_written: 0,
+            },
+        }
+    }
+
+    fn record_read(&mut self, read: usize) {
+        match &mut self.protocol {
+            SubstreamProtocol::Negotiating { pending_read, .. } => {
+                *pending_read += read as u64;
+            }
+            SubstreamProtocol::Known { read: counter, .. } => {
+                counter.inc_by(read as u64);
+            }
+        }
+    }
+
+    fn record_written(&mut self, written: usize) {
+        match &mut self.protocol {
+            SubstreamProtocol::Negotiating {
+                pending_written, ..
+            } => {
+                *pending_written += written as u64;
+            }
+            SubstreamProtocol::Known {
+                written: counter, ..
+            } => {
+                counter.inc_by(written as u64);
+            }
+        }
+    }
+
+    /// Set protocol once it is known and account for bytes read and written so far
+    fn set_protocol(&mut self, protocol: String) {
+        let counter = |direction| {
+            self.metrics
+                .bytes
+                .get_or_create(&ProtocolBandwidthLabels {
+                    protocol: protocol.clone(),
+                    transport: self.transport,
+                    direction,
+                })
+                .clone()
+        };
+        let read = counter(Direction::Inbound);
+        let written = counter(Direction::Outbound);
+
+        if let SubstreamProtocol::Negotiating {
+            pending_read,
+            pending_written,
+            ..
+        } = self.protocol
+        {
+            read.inc_by(pending_read);
+            written.inc_by(pending_written);
+        }
+
+        self.protocol = SubstreamProtocol::Known { read, written };
+    }
+}
+
+/// Stream muxer that accounts and shapes bandwidth of all of its substreams.
+struct ShapedMuxer {
+    inner: StreamMuxerBox,
+    bandwidth: Arc<Bandwidth>,
+    transport: TransportKind,
+}
+
+impl ShapedMuxer {
+    fn new(inner: StreamMuxerBox, bandwidth: Arc<Bandwidth>, transport: TransportKind) -> Self {
+        Self {
+            inner,
+            bandwidth,
+            transport,
+        }
+    }
+
+    fn wrap_substream(&self, substream: SubstreamBox) -> ShapedSubstream {
+        ShapedSubstream {
+            inner: substream,
+            bandwidth: Arc::clone(&self.bandwidth),
+            transport: self.transport,
+            protocol: NegotiatedProtocol::Negotiating {
+                written_bytes: Vec::new(),
+            },
+            read_delay: None,
+            write_delay: None,
+            accounting: self
+                .bandwidth
+                .metrics
+                .clone()
+                .map(|metrics| SubstreamAccounting::new(metrics, self.transport)),
+        }
+    }
+}
+
+impl StreamMuxer for ShapedMuxer {
+    type Substream = ShapedSubstream;
+    type Error = io::Error;
+
+    fn poll_inbound(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let substream = ready!(Pin::new(&mut self.inner).poll_inbound(cx))?;
+        Poll::Ready(Ok(self.wrap_substream(substream)))
+    }
+
+    fn poll_outbound(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let substream = ready!(Pin::new(&mut self.inner).poll_outbound(cx))?;
+        Poll::Ready(Ok(self.wrap_substream(substream)))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        Pin::new(&mut self.inner).poll(cx)
+    }
+}
+
+/// Substream that accounts and shapes its bandwidth.
+struct ShapedSubstream {
+    inner: SubstreamBox,
+    bandwidth: Arc<Bandwidth>,
+    transport: TransportKind,
+    protocol: NegotiatedProtocol,
+    read_delay: Option<Delay>,
+    write_delay: Option<Delay>,
+    accounting: Option<SubstreamAccounting>,
+}
+
+impl Drop for ShapedSubstream {
+    fn drop(&mut self) {
+        if let Some(protocol) = self.protocol.protocol() {
+            self.bandwidth.remove_protocol_substream(protocol);
+        }
+    }
+}
+
+impl AsyncRead for ShapedSubstream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        let acquired = ready!(this.bandwidth.poll_acquire(
+            cx,
+            this.transport,
+            this.protocol.protocol(),
+            Direction::Inbound,
+            &mut this.read_delay,
+            buf.len(),
+        ));
+        let result = Pin::new(&mut this.inner).poll_read(cx, &mut buf[..acquired.bytes]);
+        let read = match &result {
+            Poll::Ready(Ok(read)) => *read,
+            _ => 0,
+        };
+        this.bandwidth
+            .refund(this.protocol.protocol(), Direction::Inbound, acquired, read);
+        if read > 0 {
+            if let Some(accounting) = &mut this.accounting {
+                accounting.record_read(read);
+            }
+        }
+
+        result
+    }
+}
+
+impl AsyncWrite for ShapedSubstream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        let acquired = ready!(this.bandwidth.poll_acquire(
+            cx,
+            this.transport,
+            this.protocol.protocol(),
+            Direction::Outbound,
+            &mut this.write_delay,
+            buf.len(),
+        ));
+        let result = Pin::new(&mut this.inner).poll_write(cx, &buf[..acquired.bytes]);
+        let written = match &result {
+            Poll::Ready(Ok(written)) => *written,
+            _ => 0,
+        };
+        this.bandwidth.refund(
+            this.protocol.protocol(),
+            Direction::Outbound,
+            acquired,
+            written,
+        );
+        if written > 0 {
+            if let Some(accounting) = &mut this.accounting {
+                accounting.record_written(written);
+            }
+            if let Some(protocol) = this.protocol.inspect_written(&buf[..written]) {
+                if protocol != UNKNOWN_PROTOCOL {
+                    this.bandwidth.add_protocol_substream(&protocol);
+                }
+                if let Some(accounting) = &mut this.accounting {
+                    accounting.set_protocol(protocol);
+                }
+            }
+        }
+
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
 // Builds the transport stack that LibP2P will communicate over along with a relay client.
+#[allow(clippy::too_many_arguments)]
 pub(super) fn build_transport(
     allow_non_global_addresses_in_dht: bool,
     keypair: &identity::Keypair,
     temporary_bans: Arc<Mutex<TemporaryBans>>,
     timeout: Duration,
     yamux_config: YamuxConfig,
+    bandwidth_limits: BandwidthLimits,
+    bandwidth_metrics: Option<BandwidthMetrics>,
 ) -> io::Result<Boxed<(PeerId, StreamMuxerBox)>> {
+    // Shared by all transports, such that limits apply to the node as a whole
+    let bandwidth = Arc::new(Bandwidth::new(bandwidth_limits, bandwidth_metrics));
+
     let wrapped_tcp = {
         let tcp_config = GenTcpConfig::default().nodelay(true);
 
@@ -40,12 +855,21 @@ pub(super) fn build_transport(
     let tcp_upgraded = {
         let noise =
             noise::Config::new(keypair).expect("Signing libp2p-noise static DH keypair failed.");
+        let bandwidth = Arc::clone(&bandwidth);
 
         wrapped_tcp
             .upgrade(core::upgrade::Version::V1Lazy)
             .authenticate(noise)
             .multiplex(yamux_config)
             .timeout(timeout)
+            .map(move |(peer_id, muxer), _| {
+                let muxer = ShapedMuxer::new(
+                    StreamMuxerBox::new(muxer),
+                    Arc::clone(&bandwidth),
+                    TransportKind::Tcp,
+                );
+                (peer_id, StreamMuxerBox::new(muxer))
+            })
             .boxed()
     };
 
@@ -54,8 +878,14 @@ pub(super) fn build_transport(
         quic_config = quic_config.disable_path_mtu_discovery();
     }
 
-    let quic = QuicTransport::new(quic_config)
-        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)));
+    let quic = QuicTransport::new(quic_config).map(move |(peer_id, muxer), _| {
+        let muxer = ShapedMuxer::new(
+            StreamMuxerBox::new(muxer),
+            Arc::clone(&bandwidth),
+            TransportKind::Quic,
+        );
+        (peer_id, StreamMuxerBox::new(muxer))
+    });
 
     let wrapped_quic =
         CustomTransportWrapper::new(quic, allow_non_global_addresses_in_dht, temporary_bans);
@@ -0,0 +1,122 @@
+use super::{
+    negotiated_protocol, Bandwidth, BandwidthLimits, Direction, ProtocolNegotiation, TokenBucket,
+    MAX_RATE_LIMITED_CHUNK, MULTISTREAM_HEADER, MULTISTREAM_NOT_AVAILABLE,
+};
+use std::num::NonZeroU64;
+
+fn multistream_message(message: &[u8]) -> Vec<u8> {
+    let mut length = unsigned_varint::encode::usize_buffer();
+    let mut bytes = unsigned_varint::encode::usize(message.len(), &mut length).to_vec();
+    bytes.extend_from_slice(message);
+    bytes
+}
+
+#[test]
+fn token_bucket() {
+    let bytes_per_second = 64 * 1024;
+    let token_bucket = TokenBucket::new(NonZeroU64::new(bytes_per_second).unwrap());
+
+    // Reads and writes are split into chunks
+    assert_eq!(
+        token_bucket.try_acquire(usize::MAX, false),
+        Ok(MAX_RATE_LIMITED_CHUNK)
+    );
+    // Drain the rest of the bucket
+    let mut acquired = MAX_RATE_LIMITED_CHUNK;
+    while let Ok(bytes) = token_bucket.try_acquire(MAX_RATE_LIMITED_CHUNK, false) {
+        acquired += bytes;
+        // Refill should not be significant during this test
+        assert!(acquired <= bytes_per_second as usize * 2);
+    }
+    assert!(acquired >= bytes_per_second as usize);
+
+    // Small writes are allowed to go into debt
+    assert_eq!(token_bucket.try_acquire(100, true), Ok(100));
+    // While larger are delayed
+    let delay = token_bucket.try_acquire(100, false).unwrap_err();
+    assert!(delay.as_millis() > 0);
+
+    // Refunded bytes can be acquired again
+    token_bucket.refund(MAX_RATE_LIMITED_CHUNK);
+    assert!(token_bucket.try_acquire(100, false).is_ok());
+}
+
+#[test]
+fn protocol_fair_share() {
+    let bytes_per_second = 64 * 1024;
+    let bandwidth = Bandwidth::new(
+        BandwidthLimits {
+            upload: NonZeroU64::new(bytes_per_second),
+            download: None,
+        },
+        None,
+    );
+    bandwidth.add_protocol_substream("/a");
+    bandwidth.add_protocol_substream("/b");
+
+    // Protocol A uses its own share first and then bytes not used by other protocols
+    let mut acquired_guaranteed = 0;
+    let mut acquired = 0;
+    while let Ok(bytes) = bandwidth.try_acquire(Some("/a"), Direction::Outbound, usize::MAX) {
+        if bytes.guaranteed {
+            acquired_guaranteed += bytes.bytes;
+        }
+        acquired += bytes.bytes;
+        // Refill should not be significant during this test
+        assert!(acquired <= bytes_per_second as usize * 2);
+    }
+    assert!(acquired_guaranteed >= bytes_per_second as usize / 2);
+    assert!(acquired >= bytes_per_second as usize);
+
+    // Protocol B still gets its share even though protocol A used all bytes
+    let bytes = bandwidth
+        .try_acquire(Some("/b"), Direction::Outbound, usize::MAX)
+        .unwrap();
+    assert!(bytes.guaranteed);
+    assert_eq!(bytes.bytes, MAX_RATE_LIMITED_CHUNK);
+    // But substreams with unknown protocol have to wait
+    assert!(bandwidth
+        .try_acquire(None, Direction::Outbound, usize::MAX)
+        .is_err());
+    // As well as substreams of protocols that were closed
+    bandwidth.remove_protocol_substream("/b");
+    assert!(bandwidth
+        .try_acquire(Some("/b"), Direction::Outbound, usize::MAX)
+        .is_err());
+
+    // Download is not limited
+    assert_eq!(
+        bandwidth
+            .try_acquire(Some("/a"), Direction::Inbound, usize::MAX)
+            .unwrap()
+            .bytes,
+        usize::MAX
+    );
+}
+
+#[test]
+fn protocol_negotiation() {
+    let protocol = "/subspace/pieces-by-indexes/0.1.0";
+
+    let mut bytes = multistream_message(MULTISTREAM_HEADER);
+    assert_eq!(negotiated_protocol(&bytes), ProtocolNegotiation::Incomplete);
+
+    // Inbound substream with rejected protocol first
+    bytes.extend(multistream_message(MULTISTREAM_NOT_AVAILABLE));
+    let protocol_message = multistream_message(format!("{protocol}\n").as_bytes());
+    bytes.extend_from_slice(&protocol_message[..protocol_message.len() / 2]);
+    assert_eq!(negotiated_protocol(&bytes), ProtocolNegotiation::Incomplete);
+    bytes.extend_from_slice(&protocol_message[protocol_message.len() / 2..]);
+    // Data that follows protocol negotiation is ignored
+    bytes.extend_from_slice(&[0xff; 100]);
+    assert_eq!(
+        negotiated_protocol(&bytes),
+        ProtocolNegotiation::Protocol(protocol.to_string())
+    );
+
+    // Not a multistream-select message
+    assert_eq!(
+        negotiated_protocol(&multistream_message(b"not a protocol")),
+        ProtocolNegotiation::Invalid
+    );
+}
@@ -36,15 +36,19 @@ pub use crate::node::{
 };
 pub use crate::node_runner::NodeRunner;
 pub use constructor::{
-    construct, peer_id, Config, CreationError, KademliaMode, LocalRecordProvider,
+    construct, peer_id, BandwidthLimits, Config, CreationError, KademliaMode, LocalRecordProvider,
 };
 pub use libp2p;
 pub use protocols::request_response::handlers::generic_request_handler::{
-    GenericRequest, GenericRequestHandler,
+    GenericRequest, GenericRequestHandler, RequestHandlerLimits,
 };
 pub use protocols::request_response::handlers::piece_by_index::{
     PieceByIndexRequest, PieceByIndexRequestHandler, PieceByIndexResponse,
 };
+pub use protocols::request_response::handlers::pieces_by_indexes::{
+    PiecesByIndexesRequest, PiecesByIndexesRequestHandler, PiecesByIndexesResponse,
+    MAX_PIECES_PER_RESPONSE, MAX_PIECE_INDEXES_PER_REQUEST,
+};
 pub use protocols::request_response::handlers::segment_header::{
     SegmentHeaderBySegmentIndexesRequestHandler, SegmentHeaderRequest, SegmentHeaderResponse,
 };
@@ -501,6 +501,18 @@ impl Node {
         self.shared.external_addresses.lock().clone()
     }
 
+    /// Peers discovered in local network using mDNS, they are preferred as piece providers.
+    ///
+    /// Always empty unless mDNS discovery is enabled.
+    pub fn local_network_peers(&self) -> Vec<PeerId> {
+        self.shared
+            .local_network_peers
+            .lock()
+            .iter()
+            .copied()
+            .collect()
+    }
+
     /// Callback is called when node starts listening on new address.
     pub fn on_new_listener(&self, callback: HandlerFn<Multiaddr>) -> HandlerId {
         self.shared.handlers.new_listener.add(callback)
@@ -25,6 +25,7 @@ use libp2p::kad::{
     GetClosestPeersOk, GetProvidersError, GetProvidersOk, GetRecordError, GetRecordOk,
     InboundRequest, PeerRecord, ProgressStep, PutRecordOk, QueryId, QueryResult, Quorum, Record,
 };
+use libp2p::mdns::Event as MdnsEvent;
 use libp2p::metrics::{Metrics, Recorder};
 use libp2p::multiaddr::Protocol;
 use libp2p::swarm::{DialError, SwarmEvent};
@@ -433,6 +434,9 @@ where
             SwarmEvent::Behaviour(Event::Autonat(event)) => {
                 self.handle_autonat_event(event).await;
             }
+            SwarmEvent::Behaviour(Event::Mdns(event)) => {
+                self.handle_mdns_event(event);
+            }
             SwarmEvent::NewListenAddr { address, .. } => {
                 let shared = match self.shared_weak.upgrade() {
                     Some(shared) => shared,
@@ -1195,6 +1199,46 @@ where
         }
     }
 
+    fn handle_mdns_event(&mut self, event: MdnsEvent) {
+        trace!(?event, "mDNS event received.");
+
+        let Some(shared) = self.shared_weak.upgrade() else {
+            return;
+        };
+
+        match event {
+            MdnsEvent::Discovered(peers) => {
+                for (peer_id, address) in peers {
+                    debug!(%peer_id, %address, "Discovered peer in local network.");
+
+                    // Make address known to the swarm, such that peer can be dialed by its ID
+                    self.swarm
+                        .behaviour_mut()
+                        .kademlia
+                        .add_address(&peer_id, address);
+                    shared.local_network_peers.lock().insert(peer_id);
+                }
+            }
+            MdnsEvent::Expired(peers) => {
+                for (peer_id, address) in peers {
+                    debug!(%peer_id, %address, "Local network peer address expired.");
+
+                    let behaviour = self.swarm.behaviour_mut();
+                    behaviour.kademlia.remove_address(&peer_id, &address);
+
+                    // Peer might still be reachable using other local addresses
+                    let still_discovered = behaviour.mdns.as_ref().is_some_and(|mdns| {
+                        mdns.discovered_nodes()
+                            .any(|discovered_peer_id| discovered_peer_id == &peer_id)
+                    });
+                    if !still_discovered {
+                        shared.local_network_peers.lock().remove(&peer_id);
+                    }
+                }
+            }
+        }
+    }
+
     fn handle_command(&mut self, command: Command) {
         match command {
             Command::GetValue {
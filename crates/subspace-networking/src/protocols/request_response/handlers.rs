@@ -1,3 +1,4 @@
 pub mod generic_request_handler;
 pub mod piece_by_index;
+pub mod pieces_by_indexes;
 pub mod segment_header;
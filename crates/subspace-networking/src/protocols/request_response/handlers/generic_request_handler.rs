@@ -22,6 +22,7 @@ use futures::channel::mpsc;
 use futures::prelude::*;
 use libp2p::PeerId;
 use parity_scale_codec::{Decode, Encode};
+use std::num::NonZeroUsize;
 use std::pin::Pin;
 use std::sync::Arc;
 use tracing::{debug, trace};
@@ -50,11 +51,30 @@ pub type RequestHandlerFn<Request> = Arc<
         + 'static,
 >;
 
+/// Limits of request processing by generic request handler
+#[derive(Debug, Copy, Clone)]
+pub struct RequestHandlerLimits {
+    /// Max number of requests processed concurrently
+    pub max_concurrent_requests: NonZeroUsize,
+    /// Max number of requests waiting to be processed, requests beyond this limit are rejected
+    pub queue_size: usize,
+}
+
+impl Default for RequestHandlerLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: NonZeroUsize::MIN,
+            queue_size: REQUESTS_BUFFER_SIZE,
+        }
+    }
+}
+
 /// Defines generic request-response protocol handler.
 pub struct GenericRequestHandler<Request: GenericRequest> {
     request_receiver: mpsc::Receiver<IncomingRequest>,
     request_handler: RequestHandlerFn<Request>,
     protocol_config: ProtocolConfig,
+    limits: RequestHandlerLimits,
 }
 
 impl<Request: GenericRequest> GenericRequestHandler<Request> {
@@ -64,7 +84,20 @@ impl<Request: GenericRequest> GenericRequestHandler<Request> {
         RH: (Fn(PeerId, &Request) -> Fut) + Send + Sync + 'static,
         Fut: Future<Output = Option<Request::Response>> + Send + 'static,
     {
-        let (request_sender, request_receiver) = mpsc::channel(REQUESTS_BUFFER_SIZE);
+        Self::create_with_limits(RequestHandlerLimits::default(), request_handler)
+    }
+
+    /// Creates new [`GenericRequestHandler`] by given handler with custom limits of request
+    /// processing.
+    pub fn create_with_limits<RH, Fut>(
+        limits: RequestHandlerLimits,
+        request_handler: RH,
+    ) -> Box<dyn RequestHandler>
+    where
+        RH: (Fn(PeerId, &Request) -> Fut) + Send + Sync + 'static,
+        Fut: Future<Output = Option<Request::Response>> + Send + 'static,
+    {
+        let (request_sender, request_receiver) = mpsc::channel(limits.queue_size);
 
         let mut protocol_config = ProtocolConfig::new(Request::PROTOCOL_NAME);
         protocol_config.inbound_queue = Some(request_sender);
@@ -75,21 +108,76 @@ impl<Request: GenericRequest> GenericRequestHandler<Request> {
                 Box::pin(request_handler(peer_id, request))
             }),
             protocol_config,
+            limits,
         })
     }
+}
+
+/// Invokes external protocol handler.
+async fn handle_request<Request: GenericRequest>(
+    request_handler: &RequestHandlerFn<Request>,
+    peer: PeerId,
+    payload: Vec<u8>,
+) -> Result<Vec<u8>, RequestHandlerError> {
+    trace!(%peer, protocol=Request::LOG_TARGET, "Handling request...");
+    let request = Request::decode(&mut payload.as_slice())
+        .map_err(|_| RequestHandlerError::InvalidRequestFormat)?;
+    let response = request_handler(peer, &request).await;
+
+    Ok(response.ok_or(RequestHandlerError::NoResponse)?.encode())
+}
 
-    /// Invokes external protocol handler.
-    async fn handle_request(
-        &mut self,
-        peer: PeerId,
-        payload: Vec<u8>,
-    ) -> Result<Vec<u8>, RequestHandlerError> {
-        trace!(%peer, protocol=Request::LOG_TARGET, "Handling request...");
-        let request = Request::decode(&mut payload.as_slice())
-            .map_err(|_| RequestHandlerError::InvalidRequestFormat)?;
-        let response = (self.request_handler)(peer, &request).await;
-
-        Ok(response.ok_or(RequestHandlerError::NoResponse)?.encode())
+/// Handles request and sends response back
+async fn process_request<Request: GenericRequest>(
+    request_handler: &RequestHandlerFn<Request>,
+    request: IncomingRequest,
+) {
+    let IncomingRequest {
+        peer,
+        payload,
+        pending_response,
+    } = request;
+
+    match handle_request(request_handler, peer, payload).await {
+        Ok(response_data) => {
+            let response = OutgoingResponse {
+                result: Ok(response_data),
+                sent_feedback: None,
+            };
+
+            match pending_response.send(response) {
+                Ok(()) => trace!(target = Request::LOG_TARGET, %peer, "Handled request",),
+                Err(_) => debug!(
+                    target = Request::LOG_TARGET,
+                    protocol = Request::PROTOCOL_NAME,
+                    %peer,
+                    "Failed to handle request: {}",
+                    RequestHandlerError::SendResponse
+                ),
+            };
+        }
+        Err(e) => {
+            debug!(
+                target = Request::LOG_TARGET,
+                protocol = Request::PROTOCOL_NAME,
+                %e,
+                "Failed to handle request.",
+            );
+
+            let response = OutgoingResponse {
+                result: Err(()),
+                sent_feedback: None,
+            };
+
+            if pending_response.send(response).is_err() {
+                debug!(
+                    target = Request::LOG_TARGET,
+                    protocol = Request::PROTOCOL_NAME,
+                    %peer,
+                    "Failed to handle request: {}", RequestHandlerError::SendResponse
+                );
+            };
+        }
     }
 }
 
@@ -97,55 +185,13 @@ impl<Request: GenericRequest> GenericRequestHandler<Request> {
 impl<Request: GenericRequest> RequestHandler for GenericRequestHandler<Request> {
     /// Run [`RequestHandler`].
     async fn run(&mut self) {
-        while let Some(request) = self.request_receiver.next().await {
-            let IncomingRequest {
-                peer,
-                payload,
-                pending_response,
-            } = request;
-
-            match self.handle_request(peer, payload).await {
-                Ok(response_data) => {
-                    let response = OutgoingResponse {
-                        result: Ok(response_data),
-                        sent_feedback: None,
-                    };
-
-                    match pending_response.send(response) {
-                        Ok(()) => trace!(target = Request::LOG_TARGET, %peer, "Handled request",),
-                        Err(_) => debug!(
-                            target = Request::LOG_TARGET,
-                            protocol = Request::PROTOCOL_NAME,
-                            %peer,
-                            "Failed to handle request: {}",
-                            RequestHandlerError::SendResponse
-                        ),
-                    };
-                }
-                Err(e) => {
-                    debug!(
-                        target = Request::LOG_TARGET,
-                        protocol = Request::PROTOCOL_NAME,
-                        %e,
-                        "Failed to handle request.",
-                    );
-
-                    let response = OutgoingResponse {
-                        result: Err(()),
-                        sent_feedback: None,
-                    };
-
-                    if pending_response.send(response).is_err() {
-                        debug!(
-                            target = Request::LOG_TARGET,
-                            protocol = Request::PROTOCOL_NAME,
-                            %peer,
-                            "Failed to handle request: {}", RequestHandlerError::SendResponse
-                        );
-                    };
-                }
-            }
-        }
+        let request_handler = &self.request_handler;
+
+        (&mut self.request_receiver)
+            .for_each_concurrent(self.limits.max_concurrent_requests.get(), |request| {
+                process_request(request_handler, request)
+            })
+            .await;
     }
 
     fn protocol_config(&self) -> ProtocolConfig {
@@ -157,7 +203,7 @@ impl<Request: GenericRequest> RequestHandler for GenericRequestHandler<Request>
     }
 
     fn clone_box(&self) -> Box<dyn RequestHandler> {
-        let (request_sender, request_receiver) = mpsc::channel(REQUESTS_BUFFER_SIZE);
+        let (request_sender, request_receiver) = mpsc::channel(self.limits.queue_size);
 
         let mut protocol_config = ProtocolConfig::new(Request::PROTOCOL_NAME);
         protocol_config.inbound_queue = Some(request_sender);
@@ -166,6 +212,7 @@ impl<Request: GenericRequest> RequestHandler for GenericRequestHandler<Request>
             request_receiver,
             request_handler: Arc::clone(&self.request_handler),
             protocol_config,
+            limits: self.limits,
         })
     }
 }
@@ -0,0 +1,44 @@
+//! Helper for incoming batched pieces requests.
+//!
+//! Handle (i.e. answer) incoming requests for multiple pieces from a remote peer received via
+//! `RequestResponsesBehaviour` with generic [`GenericRequestHandler`].
+//!
+//! Response size is limited, so responses are partial: they contain pieces for a prefix of
+//! requested piece indexes and requester is expected to request remaining piece indexes again,
+//! effectively streaming pieces with multiple request-response round trips.
+
+use super::generic_request_handler::{GenericRequest, GenericRequestHandler};
+use parity_scale_codec::{Decode, Encode};
+use subspace_core_primitives::{Piece, PieceIndex};
+
+/// Max number of piece indexes in a single request.
+pub const MAX_PIECE_INDEXES_PER_REQUEST: usize = 256;
+/// Max number of pieces (not including missing pieces) in a single response, such that response
+/// fits into default max response size.
+pub const MAX_PIECES_PER_RESPONSE: usize = 8;
+
+/// Pieces-by-indexes protocol request.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub struct PiecesByIndexesRequest {
+    /// Request key - piece indexes, at most [`MAX_PIECE_INDEXES_PER_REQUEST`]
+    pub piece_indexes: Vec<PieceIndex>,
+}
+
+impl GenericRequest for PiecesByIndexesRequest {
+    const PROTOCOL_NAME: &'static str = "/subspace/pieces-by-indexes/0.1.0";
+    const LOG_TARGET: &'static str = "pieces-by-indexes-request-response-handler";
+    type Response = PiecesByIndexesResponse;
+}
+
+/// Pieces-by-indexes protocol response.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct PiecesByIndexesResponse {
+    /// Returned data, corresponds to the prefix of requested piece indexes in the same order,
+    /// `None` for pieces that peer doesn't have.
+    ///
+    /// Piece indexes beyond returned prefix were not processed and should be requested again.
+    pub pieces: Vec<Option<Piece>>,
+}
+
+/// Create a new pieces-by-indexes request handler.
+pub type PiecesByIndexesRequestHandler = GenericRequestHandler<PiecesByIndexesRequest>;
@@ -11,6 +11,7 @@ use libp2p::gossipsub::{PublishError, Sha256Topic, SubscriptionError};
 use libp2p::kad::PeerRecord;
 use libp2p::{Multiaddr, PeerId};
 use parking_lot::Mutex;
+use std::collections::HashSet;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use tokio::sync::OwnedSemaphorePermit;
@@ -123,6 +124,8 @@ pub(crate) struct Shared {
     /// Addresses on which node is listening for incoming requests.
     pub(crate) listeners: Mutex<Vec<Multiaddr>>,
     pub(crate) external_addresses: Mutex<Vec<Multiaddr>>,
+    /// Peers discovered in local network using mDNS.
+    pub(crate) local_network_peers: Mutex<HashSet<PeerId>>,
     pub(crate) num_established_peer_connections: Arc<AtomicUsize>,
     /// Sender end of the channel for sending commands to the swarm.
     pub(crate) command_sender: mpsc::Sender<Command>,
@@ -140,6 +143,7 @@ impl Shared {
             id,
             listeners: Mutex::default(),
             external_addresses: Mutex::default(),
+            local_network_peers: Mutex::default(),
             num_established_peer_connections: Arc::new(AtomicUsize::new(0)),
             command_sender,
             rate_limiter,
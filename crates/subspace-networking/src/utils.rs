@@ -1,12 +1,14 @@
 //! Miscellaneous utilities for networking.
 
 pub mod multihash;
+pub mod peer_reputation;
 pub mod piece_provider;
 pub(crate) mod rate_limiter;
 #[cfg(test)]
 mod tests;
 pub(crate) mod unique_record_binary_heap;
 
+use crate::constructor::transport::BandwidthMetrics;
 use event_listener_primitives::Bag;
 use futures::future::{Fuse, FusedFuture, FutureExt};
 use libp2p::multiaddr::Protocol;
@@ -28,6 +30,7 @@ const NETWORKING_REGISTRY_PREFIX: &str = "subspace";
 /// Metrics for Subspace networking
 pub struct SubspaceMetrics {
     established_connections: Gauge,
+    bandwidth: BandwidthMetrics,
 }
 
 impl SubspaceMetrics {
@@ -42,11 +45,18 @@ impl SubspaceMetrics {
             gauge.clone(),
         );
 
+        let bandwidth = BandwidthMetrics::new(sub_registry);
+
         Self {
             established_connections: gauge,
+            bandwidth,
         }
     }
 
+    pub(crate) fn bandwidth(&self) -> &BandwidthMetrics {
+        &self.bandwidth
+    }
+
     pub(crate) fn inc_established_connections(&mut self) {
         self.established_connections.inc();
     }
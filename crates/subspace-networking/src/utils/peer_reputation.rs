@@ -0,0 +1,464 @@
+//! Reputation of peers based on their behavior as piece providers.
+//!
+//! Tracks latency, success rate of requests and invalid piece incidents per peer, which allows to
+//! prefer peers that are more likely to return valid piece quickly. Penalty for invalid pieces
+//! decays over time, such that peers are not punished forever for occasional incidents.
+
+#[cfg(test)]
+mod tests;
+
+use crate::utils::{AsyncJoinOnDrop, NETWORKING_REGISTRY_PREFIX};
+use libp2p::PeerId;
+use lru::LruCache;
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+use std::cmp::Reverse;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, fs, io, mem};
+use subspace_core_primitives::crypto::blake3_hash;
+use subspace_core_primitives::Blake3Hash;
+use thiserror::Error;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// Number of peers reputation is tracked for by default.
+const PEER_REPUTATION_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(1000).expect("Not zero; qed");
+/// Pause duration between reputation saves.
+const DATA_FLUSH_DURATION: Duration = Duration::from_secs(30);
+/// Success rate corresponding to 100% of successful requests, in parts per million.
+const MAX_SUCCESS_RATE: u32 = 1_000_000;
+/// Weight of the latest observation in exponentially weighted moving averages, in percent.
+const EWMA_WEIGHT_PERCENT: u64 = 10;
+/// Score penalty for each invalid piece returned by peer.
+const INVALID_PIECE_PENALTY: u32 = 50;
+/// Invalid piece penalty points removed every [`DATA_FLUSH_DURATION`], such that penalty for a
+/// single invalid piece expires in 25 minutes.
+const INVALID_PIECE_PENALTY_DECAY: u32 = 1;
+/// Latency that results in score penalty of one point.
+const LATENCY_PENALTY_STEP_MS: i64 = 100;
+/// Max score penalty for latency.
+const MAX_LATENCY_PENALTY: i64 = 50;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Encode, Decode)]
+struct PeerStats {
+    /// Exponentially weighted moving average of request success rate in parts per million
+    success_rate: u32,
+    /// Exponentially weighted moving average of successful request latency in milliseconds
+    latency_ms: u32,
+    /// Penalty for invalid pieces returned by peer, decays over time
+    invalid_piece_penalty: u32,
+}
+
+impl Default for PeerStats {
+    fn default() -> Self {
+        Self {
+            // Unknown peers are neither good nor bad
+            success_rate: MAX_SUCCESS_RATE / 2,
+            latency_ms: 0,
+            invalid_piece_penalty: 0,
+        }
+    }
+}
+
+impl PeerStats {
+    fn score(&self) -> i64 {
+        let success_score = i64::from(self.success_rate) * 100 / i64::from(MAX_SUCCESS_RATE);
+        let latency_penalty =
+            (i64::from(self.latency_ms) / LATENCY_PENALTY_STEP_MS).min(MAX_LATENCY_PENALTY);
+
+        success_score - latency_penalty - i64::from(self.invalid_piece_penalty)
+    }
+
+    fn record_request(&mut self, success: bool) {
+        let observation = if success { MAX_SUCCESS_RATE } else { 0 };
+        self.success_rate = ewma(self.success_rate, observation);
+    }
+
+    fn record_latency(&mut self, latency: Duration) {
+        let latency_ms = u32::try_from(latency.as_millis()).unwrap_or(u32::MAX);
+        self.latency_ms = if self.latency_ms == 0 {
+            latency_ms
+        } else {
+            ewma(self.latency_ms, latency_ms)
+        };
+    }
+}
+
+fn ewma(average: u32, observation: u32) -> u32 {
+    ((u64::from(average) * (100 - EWMA_WEIGHT_PERCENT)
+        + u64::from(observation) * EWMA_WEIGHT_PERCENT)
+        / 100) as u32
+}
+
+#[derive(Debug, Encode, Decode)]
+struct EncodablePeerReputation {
+    // Each entry is a tuple of peer ID + its stats
+    peers: Vec<(Vec<u8>, PeerStats)>,
+}
+
+struct PeerReputationMetrics {
+    successful_requests: Counter,
+    failed_requests: Counter,
+    invalid_pieces: Counter,
+    tracked_peers: Gauge,
+    request_latency: Histogram,
+}
+
+impl PeerReputationMetrics {
+    fn new(registry: &mut Registry) -> Self {
+        let sub_registry = registry
+            .sub_registry_with_prefix(NETWORKING_REGISTRY_PREFIX)
+            .sub_registry_with_prefix("peer_reputation");
+
+        let successful_requests = Counter::default();
+        sub_registry.register(
+            "successful_requests",
+            "Number of successful piece requests to peers",
+            successful_requests.clone(),
+        );
+        let failed_requests = Counter::default();
+        sub_registry.register(
+            "failed_requests",
+            "Number of failed or empty piece requests to peers",
+            failed_requests.clone(),
+        );
+        let invalid_pieces = Counter::default();
+        sub_registry.register(
+            "invalid_pieces",
+            "Number of invalid pieces returned by peers",
+            invalid_pieces.clone(),
+        );
+        let tracked_peers = Gauge::default();
+        sub_registry.register(
+            "tracked_peers",
+            "Number of peers reputation is tracked for",
+            tracked_peers.clone(),
+        );
+        let request_latency = Histogram::new(exponential_buckets(0.01, 2.0, 12));
+        sub_registry.register(
+            "request_latency_seconds",
+            "Latency of successful piece requests to peers",
+            request_latency.clone(),
+        );
+
+        Self {
+            successful_requests,
+            failed_requests,
+            invalid_pieces,
+            tracked_peers,
+            request_latency,
+        }
+    }
+}
+
+/// Configuration for [`PeerReputation`].
+#[derive(Debug, Clone)]
+pub struct PeerReputationConfig {
+    /// Number of peers reputation is tracked for, least recently updated peers are evicted first.
+    pub cache_size: NonZeroUsize,
+    /// Defines whether we enable reputation persistence.
+    pub path: Option<Box<Path>>,
+}
+
+impl Default for PeerReputationConfig {
+    fn default() -> Self {
+        Self {
+            cache_size: PEER_REPUTATION_CACHE_SIZE,
+            path: None,
+        }
+    }
+}
+
+/// Peer reputation persistence errors.
+#[derive(Debug, Error)]
+pub enum PeerReputationPersistenceError {
+    /// I/O error.
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+struct Inner {
+    peers: LruCache<PeerId, PeerStats>,
+    need_saving: bool,
+    path: Option<Box<Path>>,
+    metrics: Option<PeerReputationMetrics>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.persist();
+    }
+}
+
+impl Inner {
+    fn update<F>(&mut self, peer_id: PeerId, f: F)
+    where
+        F: FnOnce(&mut PeerStats),
+    {
+        f(self.peers.get_or_insert_mut(peer_id, PeerStats::default));
+        self.need_saving = true;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.tracked_peers.set(self.peers.len() as i64);
+        }
+    }
+
+    /// Decay invalid piece penalty of all peers
+    fn decay_penalties(&mut self) {
+        for (_peer_id, peer_stats) in self.peers.iter_mut() {
+            if peer_stats.invalid_piece_penalty > 0 {
+                peer_stats.invalid_piece_penalty = peer_stats
+                    .invalid_piece_penalty
+                    .saturating_sub(INVALID_PIECE_PENALTY_DECAY);
+                self.need_saving = true;
+            }
+        }
+    }
+
+    fn persist(&mut self) {
+        let Some((path, bytes)) = self.snapshot() else {
+            return;
+        };
+
+        if let Err(error) = write_snapshot(&path, &bytes) {
+            warn!(%error, path = %path.display(), "Failed to persist peer reputation");
+            self.need_saving = true;
+        }
+    }
+
+    /// Encoded reputation along with path it needs to be written to, `None` if nothing changed
+    /// since last time or persistence is disabled.
+    ///
+    /// Reputation is considered to be saved afterwards, `need_saving` needs to be set again if
+    /// writing fails.
+    fn snapshot(&mut self) -> Option<(Box<Path>, Vec<u8>)> {
+        if !self.need_saving {
+            return None;
+        }
+        let path = self.path.clone()?;
+
+        let encodable_peer_reputation = EncodablePeerReputation {
+            peers: self
+                .peers
+                .iter()
+                .map(|(peer_id, peer_stats)| (peer_id.to_bytes(), *peer_stats))
+                .collect(),
+        };
+        let mut bytes = encodable_peer_reputation.encode();
+        let checksum = blake3_hash(&bytes);
+        bytes.extend_from_slice(&checksum);
+
+        self.need_saving = false;
+
+        Some((path, bytes))
+    }
+}
+
+/// Write encoded reputation into temporary file first, such that file is never partially written
+fn write_snapshot(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Reputation of peers as piece providers.
+///
+/// Cheap to clone, all clones share the same state.
+#[derive(Clone)]
+pub struct PeerReputation {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl fmt::Debug for PeerReputation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PeerReputation").finish_non_exhaustive()
+    }
+}
+
+impl PeerReputation {
+    /// Create new instance, loading previously persisted reputation if path is specified.
+    pub fn new(
+        config: PeerReputationConfig,
+        prometheus_registry: Option<&mut Registry>,
+    ) -> Result<Self, PeerReputationPersistenceError> {
+        let mut peers = LruCache::new(config.cache_size);
+
+        if let Some(path) = &config.path {
+            match fs::read(path) {
+                Ok(bytes) => {
+                    for (peer_id, peer_stats) in Self::decode(&bytes).unwrap_or_default() {
+                        peers.push(peer_id, peer_stats);
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    // Nothing persisted yet
+                }
+                Err(error) => {
+                    return Err(error.into());
+                }
+            }
+        }
+
+        let metrics = prometheus_registry.map(PeerReputationMetrics::new);
+        if let Some(metrics) = &metrics {
+            metrics.tracked_peers.set(peers.len() as i64);
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                peers,
+                need_saving: false,
+                path: config.path,
+                metrics,
+            })),
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Vec<(PeerId, PeerStats)>> {
+        if bytes.len() < mem::size_of::<Blake3Hash>() {
+            debug!(
+                len = %bytes.len(),
+                "Not enough bytes to decode checksum, file was likely corrupted"
+            );
+            return None;
+        }
+
+        let (encoded_bytes, checksum) = bytes.split_at(bytes.len() - mem::size_of::<Blake3Hash>());
+        if blake3_hash(encoded_bytes) != checksum {
+            debug!("Peer reputation checksum doesn't match, possible disk corruption, ignoring");
+            return None;
+        }
+
+        let encodable_peer_reputation = match EncodablePeerReputation::decode(&mut &*encoded_bytes)
+        {
+            Ok(encodable_peer_reputation) => encodable_peer_reputation,
+            Err(error) => {
+                debug!(%error, "Failed to decode peer reputation, ignoring");
+                return None;
+            }
+        };
+
+        Some(
+            encodable_peer_reputation
+                .peers
+                .into_iter()
+                .filter_map(|(peer_id, peer_stats)| match PeerId::from_bytes(&peer_id) {
+                    Ok(peer_id) => Some((peer_id, peer_stats)),
+                    Err(error) => {
+                        debug!(%error, "Failed to decode peer ID, skipping peer entry");
+                        None
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Record successful request to the peer with its latency.
+    pub fn record_success(&self, peer_id: PeerId, latency: Duration) {
+        let mut inner = self.inner.lock();
+        inner.update(peer_id, |peer_stats| {
+            peer_stats.record_request(true);
+            peer_stats.record_latency(latency);
+        });
+
+        if let Some(metrics) = &inner.metrics {
+            metrics.successful_requests.inc();
+            metrics.request_latency.observe(latency.as_secs_f64());
+        }
+    }
+
+    /// Record failed request to the peer (including the case when peer didn't have the piece).
+    pub fn record_failure(&self, peer_id: PeerId) {
+        let mut inner = self.inner.lock();
+        inner.update(peer_id, |peer_stats| {
+            peer_stats.record_request(false);
+        });
+
+        if let Some(metrics) = &inner.metrics {
+            metrics.failed_requests.inc();
+        }
+    }
+
+    /// Record invalid piece returned by the peer, must only be used when piece was proven to be
+    /// invalid and not when its validity couldn't be checked.
+    pub fn record_invalid_piece(&self, peer_id: PeerId) {
+        let mut inner = self.inner.lock();
+        inner.update(peer_id, |peer_stats| {
+            peer_stats.record_request(false);
+            peer_stats.invalid_piece_penalty = peer_stats
+                .invalid_piece_penalty
+                .saturating_add(INVALID_PIECE_PENALTY);
+        });
+
+        if let Some(metrics) = &inner.metrics {
+            metrics.invalid_pieces.inc();
+        }
+    }
+
+    /// Score of the peer, higher is better. Peers without history have neutral score.
+    pub fn score(&self, peer_id: &PeerId) -> i64 {
+        self.inner
+            .lock()
+            .peers
+            .peek(peer_id)
+            .copied()
+            .unwrap_or_default()
+            .score()
+    }
+
+    /// Sort peers by their score, such that peers with the highest score come first.
+    pub fn sort_by_score(&self, peer_ids: &mut [PeerId]) {
+        let inner = self.inner.lock();
+        peer_ids.sort_by_cached_key(|peer_id| {
+            Reverse(
+                inner
+                    .peers
+                    .peek(peer_id)
+                    .copied()
+                    .unwrap_or_default()
+                    .score(),
+            )
+        });
+    }
+
+    /// Decay invalid piece penalties and persist reputation to disk periodically (if path was
+    /// specified), never resolves.
+    pub async fn run(&self) {
+        loop {
+            sleep(DATA_FLUSH_DURATION).await;
+
+            let snapshot = {
+                let mut inner = self.inner.lock();
+                inner.decay_penalties();
+                inner.snapshot()
+            };
+            let Some((path, bytes)) = snapshot else {
+                continue;
+            };
+
+            // Write to disk without holding the lock and blocking async runtime
+            let write_snapshot_fut = AsyncJoinOnDrop::new(tokio::task::spawn_blocking({
+                let path = path.clone();
+
+                move || write_snapshot(&path, &bytes)
+            }));
+            let error = match write_snapshot_fut.await {
+                Ok(Ok(())) => {
+                    continue;
+                }
+                Ok(Err(error)) => error.to_string(),
+                Err(error) => error.to_string(),
+            };
+
+            warn!(%error, path = %path.display(), "Failed to persist peer reputation");
+            self.inner.lock().need_saving = true;
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use super::{
+    PeerReputation, PeerReputationConfig, INVALID_PIECE_PENALTY, INVALID_PIECE_PENALTY_DECAY,
+};
+use libp2p::PeerId;
+use std::time::Duration;
+use std::{env, fs};
+
+#[test]
+fn peers_sorted_by_score() {
+    let peer_reputation = PeerReputation::new(PeerReputationConfig::default(), None).unwrap();
+
+    let fast_peer = PeerId::random();
+    let slow_peer = PeerId::random();
+    let unknown_peer = PeerId::random();
+    let failing_peer = PeerId::random();
+    let malicious_peer = PeerId::random();
+
+    for _ in 0..10 {
+        peer_reputation.record_success(fast_peer, Duration::from_millis(50));
+        peer_reputation.record_success(slow_peer, Duration::from_secs(2));
+        peer_reputation.record_failure(failing_peer);
+    }
+    peer_reputation.record_success(malicious_peer, Duration::from_millis(50));
+    peer_reputation.record_invalid_piece(malicious_peer);
+
+    let mut peers = vec![
+        malicious_peer,
+        unknown_peer,
+        failing_peer,
+        slow_peer,
+        fast_peer,
+    ];
+    peer_reputation.sort_by_score(&mut peers);
+
+    assert_eq!(
+        peers,
+        vec![
+            fast_peer,
+            slow_peer,
+            unknown_peer,
+            failing_peer,
+            malicious_peer
+        ]
+    );
+}
+
+#[test]
+fn invalid_piece_penalty_decays() {
+    let peer_reputation = PeerReputation::new(PeerReputationConfig::default(), None).unwrap();
+
+    let failing_peer = PeerId::random();
+    let malicious_peer = PeerId::random();
+
+    for peer_id in [failing_peer, malicious_peer] {
+        peer_reputation.record_success(peer_id, Duration::from_millis(50));
+    }
+    peer_reputation.record_failure(failing_peer);
+    peer_reputation.record_invalid_piece(malicious_peer);
+
+    assert!(peer_reputation.score(&malicious_peer) < peer_reputation.score(&failing_peer));
+
+    for _ in 0..INVALID_PIECE_PENALTY / INVALID_PIECE_PENALTY_DECAY {
+        peer_reputation.inner.lock().decay_penalties();
+    }
+
+    // Once penalty is gone, invalid piece is no worse than failed request
+    assert_eq!(
+        peer_reputation.score(&malicious_peer),
+        peer_reputation.score(&failing_peer)
+    );
+}
+
+#[test]
+fn reputation_persistence() {
+    let path = env::temp_dir().join(format!("peer_reputation_{}.bin", PeerId::random()));
+    let config = PeerReputationConfig {
+        path: Some(path.clone().into_boxed_path()),
+        ..PeerReputationConfig::default()
+    };
+
+    let good_peer = PeerId::random();
+    let bad_peer = PeerId::random();
+
+    let (good_peer_score, bad_peer_score) = {
+        let peer_reputation = PeerReputation::new(config.clone(), None).unwrap();
+        peer_reputation.record_success(good_peer, Duration::from_millis(200));
+        peer_reputation.record_invalid_piece(bad_peer);
+
+        (
+            peer_reputation.score(&good_peer),
+            peer_reputation.score(&bad_peer),
+        )
+    };
+
+    // Reputation is persisted on drop
+    let peer_reputation = PeerReputation::new(config.clone(), None).unwrap();
+    assert_eq!(peer_reputation.score(&good_peer), good_peer_score);
+    assert_eq!(peer_reputation.score(&bad_peer), bad_peer_score);
+    drop(peer_reputation);
+
+    // Corrupted file is ignored
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[0] ^= 1;
+    fs::write(&path, bytes).unwrap();
+    let peer_reputation = PeerReputation::new(config, None).unwrap();
+    assert_eq!(
+        peer_reputation.score(&good_peer),
+        peer_reputation.score(&PeerId::random())
+    );
+    drop(peer_reputation);
+
+    fs::remove_file(path).unwrap();
+}
@@ -1,17 +1,26 @@
 //! Provides methods to retrieve pieces from DSN.
 
+#[cfg(test)]
+mod tests;
+
 use crate::utils::multihash::ToMultihash;
-use crate::{Node, PieceByIndexRequest, PieceByIndexResponse};
+use crate::utils::peer_reputation::PeerReputation;
+use crate::{
+    Node, PieceByIndexRequest, PieceByIndexResponse, PiecesByIndexesRequest,
+    PiecesByIndexesResponse, MAX_PIECE_INDEXES_PER_REQUEST,
+};
 use async_trait::async_trait;
 use backoff::future::retry;
 use backoff::ExponentialBackoff;
-use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use futures::{stream, Stream, StreamExt};
 use libp2p::PeerId;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, mem};
 use subspace_core_primitives::{Piece, PieceIndex};
 use tracing::{debug, trace, warn};
 
@@ -20,6 +29,35 @@ const GET_PIECE_INITIAL_INTERVAL: Duration = Duration::from_secs(5);
 /// Defines max duration between get_piece calls.
 const GET_PIECE_MAX_INTERVAL: Duration = Duration::from_secs(40);
 
+/// Max number of piece providers sorted by preference at once, providers are tried as they are
+/// discovered, but those discovered at about the same time are sorted first.
+const PROVIDERS_SORTING_WINDOW: usize = 10;
+
+/// Max number of pieces that are retrieved concurrently from other providers after batched request
+/// to a provider didn't return them
+const FALLBACK_PIECE_REQUESTS_CONCURRENCY: usize = 10;
+
+/// Result of piece validation.
+#[derive(Debug, Clone)]
+pub enum PieceValidity {
+    /// Piece is valid
+    Valid(Piece),
+    /// Piece was proven to be invalid
+    Invalid,
+    /// Piece validity couldn't be checked (for example because segment commitment is not known)
+    Unknown,
+}
+
+impl PieceValidity {
+    /// Returns piece if it is valid.
+    pub fn into_valid(self) -> Option<Piece> {
+        match self {
+            Self::Valid(piece) => Some(piece),
+            Self::Invalid | Self::Unknown => None,
+        }
+    }
+}
+
 /// Validates piece against using its commitment.
 #[async_trait]
 pub trait PieceValidator: Sync + Send {
@@ -29,7 +67,28 @@ pub trait PieceValidator: Sync + Send {
         source_peer_id: PeerId,
         piece_index: PieceIndex,
         piece: Piece,
-    ) -> Option<Piece>;
+    ) -> PieceValidity;
+
+    /// Validates multiple pieces at once, returns results in the same order as pieces were
+    /// provided.
+    ///
+    /// Default implementation validates pieces one by one, implementations are encouraged to
+    /// override it with more efficient batched version.
+    async fn validate_pieces(
+        &self,
+        pieces: Vec<(PeerId, PieceIndex, Piece)>,
+    ) -> Vec<PieceValidity> {
+        let mut results = Vec::with_capacity(pieces.len());
+
+        for (source_peer_id, piece_index, piece) in pieces {
+            results.push(
+                self.validate_piece(source_peer_id, piece_index, piece)
+                    .await,
+            );
+        }
+
+        results
+    }
 }
 
 /// Stub implementation for piece validation.
@@ -53,8 +112,8 @@ impl Default for RetryPolicy {
 
 #[async_trait]
 impl PieceValidator for NoPieceValidator {
-    async fn validate_piece(&self, _: PeerId, _: PieceIndex, piece: Piece) -> Option<Piece> {
-        Some(piece)
+    async fn validate_piece(&self, _: PeerId, _: PieceIndex, piece: Piece) -> PieceValidity {
+        PieceValidity::Valid(piece)
     }
 }
 
@@ -62,6 +121,7 @@ impl PieceValidator for NoPieceValidator {
 pub struct PieceProvider<PV> {
     node: Node,
     piece_validator: Option<PV>,
+    peer_reputation: Option<PeerReputation>,
 }
 
 impl<PV> fmt::Debug for PieceProvider<PV> {
@@ -79,6 +139,77 @@ where
         Self {
             node,
             piece_validator,
+            peer_reputation: None,
+        }
+    }
+
+    /// Track reputation of piece providers, such that providers with higher score are preferred
+    /// when retrieving pieces from piece cache (L2).
+    pub fn with_peer_reputation(mut self, peer_reputation: PeerReputation) -> Self {
+        self.peer_reputation.replace(peer_reputation);
+        self
+    }
+
+    /// Validate piece received from piece provider and update its reputation accordingly.
+    ///
+    /// `request_latency` is the time it took to receive the response, not including validation.
+    async fn validate_piece_from_provider(
+        &self,
+        provider_id: PeerId,
+        piece_index: PieceIndex,
+        piece: Piece,
+        request_latency: Duration,
+    ) -> Option<Piece> {
+        let piece_validity = match &self.piece_validator {
+            Some(validator) => {
+                validator
+                    .validate_piece(provider_id, piece_index, piece)
+                    .await
+            }
+            None => PieceValidity::Valid(piece),
+        };
+
+        self.record_piece_validity(provider_id, &piece_validity, request_latency);
+
+        piece_validity.into_valid()
+    }
+
+    /// Update reputation of the provider according to validity of the piece it returned, provider
+    /// is only penalized for pieces that were proven to be invalid.
+    fn record_piece_validity(
+        &self,
+        provider_id: PeerId,
+        piece_validity: &PieceValidity,
+        request_latency: Duration,
+    ) {
+        if let Some(peer_reputation) = &self.peer_reputation {
+            match piece_validity {
+                PieceValidity::Valid(_) => {
+                    peer_reputation.record_success(provider_id, request_latency);
+                }
+                PieceValidity::Invalid => {
+                    peer_reputation.record_invalid_piece(provider_id);
+                }
+                PieceValidity::Unknown => {
+                    // Not provider's fault
+                }
+            }
+        }
+    }
+
+    /// Sort providers such that the most preferred ones come first: peers in local network first,
+    /// then peers with higher reputation.
+    fn sort_providers(&self, providers: &mut [PeerId], local_network_peers: &HashSet<PeerId>) {
+        if let Some(peer_reputation) = &self.peer_reputation {
+            peer_reputation.sort_by_score(providers);
+        }
+        // Stable sort, so reputation order is preserved within each group
+        providers.sort_by_key(|provider_id| !local_network_peers.contains(provider_id));
+    }
+
+    fn record_provider_failure(&self, provider_id: PeerId) {
+        if let Some(peer_reputation) = &self.peer_reputation {
+            peer_reputation.record_failure(provider_id);
         }
     }
 
@@ -90,31 +221,48 @@ where
         let get_providers_result = request_batch.get_providers(key).await;
 
         match get_providers_result {
-            Ok(mut get_providers_stream) => {
-                while let Some(provider_id) = get_providers_stream.next().await {
+            Ok(get_providers_stream) => {
+                let local_network_peers = HashSet::from_iter(self.node.local_network_peers());
+                // Providers are tried as they are discovered, those that were discovered at about
+                // the same time are tried in order of preference (peers in local network first).
+                // Peers in local network are only tried if they announced themselves as providers,
+                // such that request is not wasted on peers that don't have the piece.
+                let mut providers = get_providers_stream
+                    .ready_chunks(PROVIDERS_SORTING_WINDOW)
+                    .flat_map(|mut providers| {
+                        self.sort_providers(&mut providers, &local_network_peers);
+                        stream::iter(providers)
+                    });
+
+                while let Some(provider_id) = providers.next().await {
                     trace!(%piece_index, %provider_id, "get_providers returned an item");
 
+                    let request_start = Instant::now();
                     let request_result = request_batch
                         .send_generic_request(provider_id, PieceByIndexRequest { piece_index })
                         .await;
+                    let request_latency = request_start.elapsed();
 
                     match request_result {
                         Ok(PieceByIndexResponse { piece: Some(piece) }) => {
                             trace!(%provider_id, %piece_index, ?key, "Piece request succeeded.");
 
-                            if let Some(validator) = &self.piece_validator {
-                                return validator
-                                    .validate_piece(provider_id, piece_index, piece)
-                                    .await;
-                            } else {
-                                return Some(piece);
-                            }
+                            return self
+                                .validate_piece_from_provider(
+                                    provider_id,
+                                    piece_index,
+                                    piece,
+                                    request_latency,
+                                )
+                                .await;
                         }
                         Ok(PieceByIndexResponse { piece: None }) => {
                             debug!(%provider_id, %piece_index, ?key, "Piece request returned empty piece.");
+                            self.record_provider_failure(provider_id);
                         }
                         Err(error) => {
                             debug!(%provider_id, %piece_index, ?key, ?error, "Piece request failed.");
+                            self.record_provider_failure(provider_id);
                         }
                     }
                 }
@@ -199,7 +347,10 @@ where
                 trace!(%peer_id, %piece_index, "Piece request succeeded.");
 
                 if let Some(validator) = &self.piece_validator {
-                    return validator.validate_piece(peer_id, piece_index, piece).await;
+                    return validator
+                        .validate_piece(peer_id, piece_index, piece)
+                        .await
+                        .into_valid();
                 } else {
                     return Some(piece);
                 }
@@ -215,6 +366,263 @@ where
         None
     }
 
+    /// Same as [`Self::get_piece_from_peer()`], but for peers that announced themselves as piece
+    /// providers, such that their reputation is updated with request outcome.
+    async fn get_piece_from_provider(
+        &self,
+        provider_id: PeerId,
+        piece_index: PieceIndex,
+    ) -> Option<Piece> {
+        let request_start = Instant::now();
+        let request_result = self
+            .node
+            .send_generic_request(provider_id, PieceByIndexRequest { piece_index })
+            .await;
+        let request_latency = request_start.elapsed();
+
+        match request_result {
+            Ok(PieceByIndexResponse { piece: Some(piece) }) => {
+                trace!(%provider_id, %piece_index, "Piece request succeeded.");
+
+                return self
+                    .validate_piece_from_provider(provider_id, piece_index, piece, request_latency)
+                    .await;
+            }
+            Ok(PieceByIndexResponse { piece: None }) => {
+                debug!(%provider_id, %piece_index, "Piece request returned empty piece.");
+            }
+            Err(error) => {
+                debug!(%provider_id, %piece_index, ?error, "Piece request failed.");
+            }
+        }
+
+        self.record_provider_failure(provider_id);
+
+        None
+    }
+
+    /// Get pieces from a particular peer using batched requests.
+    ///
+    /// Pieces are yielded as partial responses arrive, each requested piece index is yielded
+    /// exactly once, with `None` if peer didn't have the piece, returned invalid piece or request
+    /// failed.
+    pub fn get_pieces_from_peer(
+        &self,
+        peer_id: PeerId,
+        piece_indexes: Vec<PieceIndex>,
+    ) -> impl Stream<Item = (PieceIndex, Option<Piece>)> + '_ {
+        stream::unfold(piece_indexes, move |mut piece_indexes| async move {
+            if piece_indexes.is_empty() {
+                return None;
+            }
+
+            let request_piece_indexes = piece_indexes
+                .iter()
+                .take(MAX_PIECE_INDEXES_PER_REQUEST)
+                .copied()
+                .collect::<Vec<_>>();
+            let request_piece_indexes_count = request_piece_indexes.len();
+
+            let request_start = Instant::now();
+            let request_result = self
+                .node
+                .send_generic_request(
+                    peer_id,
+                    PiecesByIndexesRequest {
+                        piece_indexes: request_piece_indexes,
+                    },
+                )
+                .await;
+            let request_latency = request_start.elapsed();
+
+            let pieces = match request_result {
+                Ok(PiecesByIndexesResponse { pieces })
+                    if !pieces.is_empty() && pieces.len() <= request_piece_indexes_count =>
+                {
+                    trace!(
+                        %peer_id,
+                        requested = %request_piece_indexes_count,
+                        processed = %pieces.len(),
+                        "Pieces request succeeded."
+                    );
+
+                    pieces
+                }
+                Ok(PiecesByIndexesResponse { pieces }) => {
+                    debug!(
+                        %peer_id,
+                        requested = %request_piece_indexes_count,
+                        processed = %pieces.len(),
+                        "Pieces request returned unexpected number of pieces."
+                    );
+
+                    Vec::new()
+                }
+                Err(error) => {
+                    debug!(%peer_id, ?error, "Pieces request failed.");
+
+                    Vec::new()
+                }
+            };
+
+            if pieces.is_empty() {
+                self.record_provider_failure(peer_id);
+
+                // Give up on the rest of pieces
+                let pieces = mem::take(&mut piece_indexes)
+                    .into_iter()
+                    .map(|piece_index| (piece_index, None))
+                    .collect::<Vec<_>>();
+                return Some((stream::iter(pieces), piece_indexes));
+            }
+
+            // Latency of the request itself, invalid pieces are accounted for during validation
+            if let Some(peer_reputation) = &self.peer_reputation {
+                peer_reputation.record_success(peer_id, request_latency);
+            }
+            let processed_piece_indexes = piece_indexes.drain(..pieces.len()).collect::<Vec<_>>();
+            let pieces = self
+                .validate_pieces_from_peer(peer_id, &processed_piece_indexes, pieces)
+                .await;
+
+            Some((
+                stream::iter(
+                    processed_piece_indexes
+                        .into_iter()
+                        .zip(pieces)
+                        .collect::<Vec<_>>(),
+                ),
+                piece_indexes,
+            ))
+        })
+        .flatten()
+    }
+
+    async fn validate_pieces_from_peer(
+        &self,
+        peer_id: PeerId,
+        piece_indexes: &[PieceIndex],
+        mut pieces: Vec<Option<Piece>>,
+    ) -> Vec<Option<Piece>> {
+        let Some(validator) = &self.piece_validator else {
+            return pieces;
+        };
+
+        let (positions, pieces_to_validate) = piece_indexes
+            .iter()
+            .zip(&mut pieces)
+            .enumerate()
+            .filter_map(|(position, (&piece_index, maybe_piece))| {
+                let piece = maybe_piece.take()?;
+                Some((position, (peer_id, piece_index, piece)))
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        for (position, piece_validity) in positions
+            .into_iter()
+            .zip(validator.validate_pieces(pieces_to_validate).await)
+        {
+            // Only proven invalid pieces are penalized, success was already recorded for request
+            if let PieceValidity::Invalid = piece_validity {
+                if let Some(peer_reputation) = &self.peer_reputation {
+                    peer_reputation.record_invalid_piece(peer_id);
+                }
+            }
+            pieces[position] = piece_validity.into_valid();
+        }
+
+        pieces
+    }
+
+    /// Get pieces from piece cache (L2) with requests grouped per provider peer.
+    ///
+    /// Providers are looked up for each piece first, then pieces are requested in batches from
+    /// providers that can provide the most pieces, pieces that were not retrieved from such
+    /// provider are requested from other providers of corresponding piece (concurrently for
+    /// different pieces, but one provider at a time for each piece). Each
+    /// requested piece index is yielded exactly once, with `None` if piece was not found.
+    pub async fn get_pieces_from_dsn_cache(
+        &self,
+        piece_indexes: Vec<PieceIndex>,
+    ) -> impl Stream<Item = (PieceIndex, Option<Piece>)> + '_ {
+        let local_network_peers = HashSet::from_iter(self.node.local_network_peers());
+        let local_network_peers = &local_network_peers;
+        let piece_providers = piece_indexes
+            .into_iter()
+            .map(|piece_index| async move {
+                let mut providers = match self.node.get_providers(piece_index.to_multihash()).await
+                {
+                    Ok(providers) => providers.collect::<Vec<_>>().await,
+                    Err(error) => {
+                        warn!(%piece_index, ?error, "get_providers returned an error");
+
+                        Vec::new()
+                    }
+                };
+                // Peers in local network are preferred, but only for pieces they announced, such
+                // that requests are not wasted on peers that don't have the piece. Fallback
+                // providers are tried in order, so try the best ones first.
+                self.sort_providers(&mut providers, local_network_peers);
+                trace!(%piece_index, providers = %providers.len(), "Found piece providers");
+
+                (piece_index, providers)
+            })
+            .collect::<FuturesUnordered<_>>()
+            .collect::<HashMap<_, _>>()
+            .await;
+
+        let (piece_indexes_by_provider, piece_indexes_without_providers) =
+            group_piece_indexes_by_provider(&piece_providers, local_network_peers);
+        let piece_providers = Arc::new(piece_providers);
+
+        let pieces_from_providers =
+            piece_indexes_by_provider
+                .into_iter()
+                .map(|(provider_id, piece_indexes)| {
+                    let piece_providers = Arc::clone(&piece_providers);
+
+                    self.get_pieces_from_peer(provider_id, piece_indexes)
+                    .map(move |(piece_index, maybe_piece)| {
+                        let piece_providers = Arc::clone(&piece_providers);
+
+                        async move {
+                            if maybe_piece.is_some() {
+                                return (piece_index, maybe_piece);
+                            }
+
+                            // Fall back to other providers of this piece
+                            let other_providers = piece_providers
+                                .get(&piece_index)
+                                .into_iter()
+                                .flatten()
+                                .filter(|&&other_provider_id| other_provider_id != provider_id);
+                            for &other_provider_id in other_providers {
+                                let maybe_piece = self
+                                    .get_piece_from_provider(other_provider_id, piece_index)
+                                    .await;
+
+                                if maybe_piece.is_some() {
+                                    return (piece_index, maybe_piece);
+                                }
+                            }
+
+                            debug!(%piece_index, "Couldn't get a piece from any of its providers.");
+
+                            (piece_index, None)
+                        }
+                    })
+                    .buffer_unordered(FALLBACK_PIECE_REQUESTS_CONCURRENCY)
+                    .boxed()
+                });
+
+        stream::iter(
+            piece_indexes_without_providers
+                .into_iter()
+                .map(|piece_index| (piece_index, None)),
+        )
+        .chain(stream::select_all(pieces_from_providers))
+    }
+
     /// Get piece from archival storage (L1). The algorithm tries to get a piece from currently
     /// connected peers and falls back to random walking.
     pub async fn get_piece_from_archival_storage(
@@ -323,7 +731,10 @@ where
                             trace!(%peer_id, %piece_index, ?key, %round,  "Piece request succeeded.");
 
                             if let Some(validator) = &self.piece_validator {
-                                return validator.validate_piece(peer_id, piece_index, piece).await;
+                                return validator
+                                    .validate_piece(peer_id, piece_index, piece)
+                                    .await
+                                    .into_valid();
                             } else {
                                 return Some(piece);
                             }
@@ -345,3 +756,49 @@ where
         None
     }
 }
+
+/// Group piece indexes by provider, such that each piece index is assigned to the provider that can
+/// provide the most pieces, this allows to minimize number of round trips with batched requests.
+/// Preferred providers (like peers in local network) are chosen over others regardless of number of
+/// pieces they can provide.
+///
+/// Returns groups of piece indexes by provider and piece indexes that have no providers.
+fn group_piece_indexes_by_provider(
+    piece_providers: &HashMap<PieceIndex, Vec<PeerId>>,
+    preferred_providers: &HashSet<PeerId>,
+) -> (HashMap<PeerId, Vec<PieceIndex>>, Vec<PieceIndex>) {
+    let mut pieces_per_provider = HashMap::<PeerId, usize>::new();
+    for provider_id in piece_providers.values().flatten() {
+        *pieces_per_provider.entry(*provider_id).or_default() += 1;
+    }
+
+    let mut piece_indexes_by_provider = HashMap::<PeerId, Vec<PieceIndex>>::new();
+    let mut piece_indexes_without_providers = Vec::new();
+    for (&piece_index, providers) in piece_providers {
+        let best_provider = providers.iter().max_by_key(|provider_id| {
+            (
+                preferred_providers.contains(provider_id),
+                pieces_per_provider
+                    .get(provider_id)
+                    .copied()
+                    .unwrap_or_default(),
+                // Make choice deterministic in case of the same number of pieces
+                **provider_id,
+            )
+        });
+
+        match best_provider {
+            Some(provider_id) => {
+                piece_indexes_by_provider
+                    .entry(*provider_id)
+                    .or_default()
+                    .push(piece_index);
+            }
+            None => {
+                piece_indexes_without_providers.push(piece_index);
+            }
+        }
+    }
+
+    (piece_indexes_by_provider, piece_indexes_without_providers)
+}
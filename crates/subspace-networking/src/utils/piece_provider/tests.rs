@@ -0,0 +1,57 @@
+use super::group_piece_indexes_by_provider;
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet};
+use subspace_core_primitives::PieceIndex;
+
+#[test]
+fn grouping_by_provider() {
+    let peer_a = PeerId::random();
+    let peer_b = PeerId::random();
+    let peer_c = PeerId::random();
+
+    let piece_providers = HashMap::from([
+        (PieceIndex::from(0), vec![peer_a, peer_b]),
+        (PieceIndex::from(1), vec![peer_b]),
+        (PieceIndex::from(2), vec![peer_c, peer_b]),
+        (PieceIndex::from(3), vec![peer_c]),
+        (PieceIndex::from(4), vec![]),
+    ]);
+
+    let (mut piece_indexes_by_provider, piece_indexes_without_providers) =
+        group_piece_indexes_by_provider(&piece_providers, &HashSet::new());
+
+    assert_eq!(piece_indexes_without_providers, vec![PieceIndex::from(4)]);
+    // Peer B can provide the most pieces, so it is preferred for all pieces it has
+    let mut peer_b_piece_indexes = piece_indexes_by_provider.remove(&peer_b).unwrap();
+    peer_b_piece_indexes.sort();
+    assert_eq!(
+        peer_b_piece_indexes,
+        vec![
+            PieceIndex::from(0),
+            PieceIndex::from(1),
+            PieceIndex::from(2)
+        ]
+    );
+    assert_eq!(
+        piece_indexes_by_provider.remove(&peer_c),
+        Some(vec![PieceIndex::from(3)])
+    );
+    assert!(piece_indexes_by_provider.is_empty());
+
+    // Preferred provider is chosen even if it can provide fewer pieces
+    let (mut piece_indexes_by_provider, _) =
+        group_piece_indexes_by_provider(&piece_providers, &HashSet::from([peer_c]));
+    let mut peer_c_piece_indexes = piece_indexes_by_provider.remove(&peer_c).unwrap();
+    peer_c_piece_indexes.sort();
+    assert_eq!(
+        peer_c_piece_indexes,
+        vec![PieceIndex::from(2), PieceIndex::from(3)]
+    );
+    let mut peer_b_piece_indexes = piece_indexes_by_provider.remove(&peer_b).unwrap();
+    peer_b_piece_indexes.sort();
+    assert_eq!(
+        peer_b_piece_indexes,
+        vec![PieceIndex::from(0), PieceIndex::from(1)]
+    );
+    assert!(piece_indexes_by_provider.is_empty());
+}
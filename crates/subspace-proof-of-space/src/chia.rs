@@ -1,5 +1,5 @@
 //! Chia proof of space implementation
-use crate::chiapos::{Tables, TablesCache};
+use crate::chiapos::{tables_cache_memory_usage, tables_memory_usage, Tables, TablesCache};
 use crate::{PosTableType, Table, TableGenerator};
 use core::mem;
 use subspace_core_primitives::{PosProof, PosSeed};
@@ -9,9 +9,21 @@ const K: u8 = 20;
 /// Subspace proof of space table generator.
 ///
 /// Chia implementation.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default)]
 pub struct ChiaTableGenerator {
     tables_cache: TablesCache<K>,
+    /// Table that is recreated in place by [`TableGenerator::generate_in_place()`]
+    table: Option<ChiaTable>,
+}
+
+impl Clone for ChiaTableGenerator {
+    fn clone(&self) -> Self {
+        Self {
+            tables_cache: self.tables_cache.clone(),
+            // Table is just a memory buffer, no need to clone it
+            table: None,
+        }
+    }
 }
 
 impl TableGenerator<ChiaTable> for ChiaTableGenerator {
@@ -27,6 +39,37 @@ impl TableGenerator<ChiaTable> for ChiaTableGenerator {
             tables: Tables::<K>::create_parallel((*seed).into(), &mut self.tables_cache),
         }
     }
+
+    fn generate_in_place(&mut self, seed: &PosSeed) -> &ChiaTable {
+        if let Some(table) = &mut self.table {
+            table
+                .tables
+                .recreate((*seed).into(), &mut self.tables_cache);
+        } else {
+            let table = self.generate(seed);
+            self.table.replace(table);
+        }
+
+        self.table.as_ref().expect("Initialized above; qed")
+    }
+
+    #[cfg(any(feature = "parallel", test))]
+    fn generate_parallel_in_place(&mut self, seed: &PosSeed) -> &ChiaTable {
+        if let Some(table) = &mut self.table {
+            table
+                .tables
+                .recreate_parallel((*seed).into(), &mut self.tables_cache);
+        } else {
+            let table = self.generate_parallel(seed);
+            self.table.replace(table);
+        }
+
+        self.table.as_ref().expect("Initialized above; qed")
+    }
+
+    fn release_memory(&mut self) {
+        self.table.take();
+    }
 }
 
 /// Subspace proof of space table.
@@ -39,6 +82,8 @@ pub struct ChiaTable {
 
 impl Table for ChiaTable {
     const TABLE_TYPE: PosTableType = PosTableType::Chia;
+    const GENERATOR_MEMORY_USAGE: usize = tables_memory_usage(K);
+    const GENERATOR_IDLE_MEMORY_USAGE: usize = tables_cache_memory_usage(K);
     type Generator = ChiaTableGenerator;
 
     fn generate(seed: &PosSeed) -> ChiaTable {
@@ -97,4 +142,34 @@ mod tests {
             assert!(ChiaTable::is_proof_valid(&seed, challenge_index, &proof));
         }
     }
+
+    #[test]
+    fn in_place() {
+        let seed = PosSeed::from([
+            35, 2, 52, 4, 51, 55, 23, 84, 91, 10, 111, 12, 13, 222, 151, 16, 228, 211, 254, 45, 92,
+            198, 204, 10, 9, 10, 11, 129, 139, 171, 15, 23,
+        ]);
+        let other_seed = PosSeed::from([1; 32]);
+        let challenge_index = 600426542;
+
+        let mut generator = ChiaTable::generator();
+        let expected_proof = ChiaTable::generate(&seed).find_proof(challenge_index);
+        assert!(expected_proof.is_some());
+
+        // First call allocates, subsequent calls reuse memory of the first table
+        generator.generate_in_place(&other_seed);
+        assert_eq!(
+            generator
+                .generate_in_place(&seed)
+                .find_proof(challenge_index),
+            expected_proof
+        );
+        generator.generate_parallel_in_place(&other_seed);
+        assert_eq!(
+            generator
+                .generate_parallel_in_place(&seed)
+                .find_proof(challenge_index),
+            expected_proof
+        );
+    }
 }
@@ -8,7 +8,7 @@ mod tests;
 mod utils;
 
 use crate::chiapos::table::metadata_size_bytes;
-pub use crate::chiapos::table::TablesCache;
+pub use crate::chiapos::table::{tables_cache_memory_usage, tables_memory_usage, TablesCache};
 use crate::chiapos::tables::TablesGeneric;
 use crate::chiapos::utils::EvaluatableUsize;
 
@@ -52,6 +52,22 @@ impl Tables<$k> {
         ))
     }
 
+    /// Recreate Chia proof of space tables in place for a new seed. There also exists
+    /// [`Self::recreate_parallel()`] that trades CPU efficiency and memory usage for lower latency.
+    ///
+    /// The same as [`Self::create()`], but reuses memory of existing tables instead of allocating
+    /// new one, which makes memory usage predictable when many tables are created one after another.
+    pub fn recreate(&mut self, seed: Seed, cache: &mut TablesCache<$k>) {
+        self.0.recreate(seed, cache);
+    }
+
+    /// Almost the same as [`Self::recreate()`], but uses parallelism internally for better
+    /// performance (though not efficiency of CPU and memory usage).
+    #[cfg(any(feature = "parallel", test))]
+    pub fn recreate_parallel(&mut self, seed: Seed, cache: &mut TablesCache<$k>) {
+        self.0.recreate_parallel(seed, cache);
+    }
+
     /// Create Chia proof of space tables.
     ///
     /// Simpler version of [`Self::create`].
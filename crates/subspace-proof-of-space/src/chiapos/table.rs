@@ -48,6 +48,56 @@ pub(super) const fn metadata_size_bits(k: u8, table_number: u8) -> usize {
         }
 }
 
+/// Estimated memory usage in bytes of [`Tables`](super::Tables) for `k` together with
+/// [`TablesCache`] used to create them, including temporary allocations done during creation.
+///
+/// Number of entries in each table is approximately `2^k`, so this is an estimate rather than an
+/// exact upper bound.
+pub const fn tables_memory_usage(k: u8) -> usize {
+    let num_entries = 1_usize << k;
+    let first_table_entry_size = mem::size_of::<Y>() + mem::size_of::<X>();
+
+    let mut memory_usage = num_entries * first_table_entry_size;
+    let mut largest_table_size = 0;
+    let mut table_number = 2;
+    while table_number <= 7 {
+        let table_size = num_entries
+            * (mem::size_of::<Y>()
+                + mem::size_of::<[Position; 2]>()
+                + metadata_size_bytes(k, table_number));
+        memory_usage += table_size;
+        if table_size > largest_table_size {
+            largest_table_size = table_size;
+        }
+        table_number += 1;
+    }
+
+    // Partial `y`s and first table entries while first table is being created
+    memory_usage += num_entries * k as usize / u8::BITS as usize;
+    memory_usage += num_entries * first_table_entry_size;
+    // Entries of the table that is being created before they are split into separate vectors
+    memory_usage += largest_table_size;
+    memory_usage += tables_cache_memory_usage(k);
+
+    memory_usage
+}
+
+/// Estimated memory usage in bytes of [`TablesCache`] for `k`, which is kept between creation of
+/// tables.
+pub const fn tables_cache_memory_usage(k: u8) -> usize {
+    let param_bc = PARAM_BC as usize;
+    let left_targets = 2
+        * (mem::size_of::<Vec<Vec<Position>>>()
+            + param_bc
+                * (mem::size_of::<Vec<Position>>()
+                    + PARAM_M as usize * mem::size_of::<Position>()));
+    // `y`s are up to `k + PARAM_EXT` bits
+    let buckets = ((1_usize << (k + PARAM_EXT)) / param_bc + 1) * mem::size_of::<Bucket>();
+    let rmap_scratch = param_bc * mem::size_of::<RmapItem>();
+
+    left_targets + buckets + rmap_scratch
+}
+
 /// ChaCha8 [`Vec`] sufficient for the whole first table for [`K`].
 /// Prefer [`partial_y`] if you need partial y just for a single `x`.
 fn partial_ys<const K: u8>(seed: Seed) -> Vec<u8> {
@@ -476,6 +526,40 @@ fn match_and_compute_fn<'a, const K: u8, const TABLE_NUMBER: u8, const PARENT_TA
     });
 }
 
+/// Compute unsorted entries of the first table.
+///
+/// Entries only live until they are sorted and moved into the table, such that the generator
+/// doesn't keep memory for them between tables.
+fn compute_first_table_entries<const K: u8>(seed: Seed) -> Vec<(Y, X)>
+where
+    EvaluatableUsize<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>: Sized,
+{
+    let partial_ys = partial_ys::<K>(seed);
+
+    let mut t_1 = Vec::with_capacity(1_usize << K);
+    for (x_start, partial_ys) in X::all::<K>().step_by(COMPUTE_F1_SIMD_FACTOR).zip(
+        partial_ys
+            .array_chunks::<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>()
+            .copied(),
+    ) {
+        let xs: [_; COMPUTE_F1_SIMD_FACTOR] = seq!(N in 0..8 {
+            [
+            #(
+            #[allow(clippy::erasing_op, clippy::identity_op)]
+            {
+                x_start + X::from(N)
+            },
+            )*
+            ]
+        });
+
+        let ys = compute_f1_simd::<K>(xs, &partial_ys);
+        t_1.extend(ys.into_iter().zip(xs));
+    }
+
+    t_1
+}
+
 #[derive(Debug)]
 pub(super) enum Table<const K: u8, const TABLE_NUMBER: u8>
 where
@@ -508,70 +592,67 @@ where
     where
         EvaluatableUsize<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>: Sized,
     {
-        let partial_ys = partial_ys::<K>(seed);
-
-        let mut t_1 = Vec::with_capacity(1_usize << K);
-        for (x_start, partial_ys) in X::all::<K>().step_by(COMPUTE_F1_SIMD_FACTOR).zip(
-            partial_ys
-                .array_chunks::<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>()
-                .copied(),
-        ) {
-            let xs: [_; COMPUTE_F1_SIMD_FACTOR] = seq!(N in 0..8 {
-                [
-                #(
-                #[allow(clippy::erasing_op, clippy::identity_op)]
-                {
-                    x_start + X::from(N)
-                },
-                )*
-                ]
-            });
+        let mut table = Self::First {
+            ys: Vec::new(),
+            xs: Vec::new(),
+        };
+        table.recreate(seed);
+        table
+    }
 
-            let ys = compute_f1_simd::<K>(xs, &partial_ys);
-            t_1.extend(ys.into_iter().zip(xs));
-        }
+    /// Create the table, leverages available parallelism
+    #[cfg(any(feature = "parallel", test))]
+    pub(super) fn create_parallel(seed: Seed) -> Self
+    where
+        EvaluatableUsize<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>: Sized,
+    {
+        let mut table = Self::First {
+            ys: Vec::new(),
+            xs: Vec::new(),
+        };
+        table.recreate_parallel(seed);
+        table
+    }
 
-        t_1.sort_unstable();
+    /// Recreate the table in place for a new seed, reusing existing allocations
+    pub(super) fn recreate(&mut self, seed: Seed)
+    where
+        EvaluatableUsize<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>: Sized,
+    {
+        let mut t_1 = compute_first_table_entries::<K>(seed);
 
-        let (ys, xs) = t_1.into_iter().unzip();
+        t_1.sort_unstable();
 
-        Self::First { ys, xs }
+        self.fill_from_entries(&t_1);
     }
 
-    /// Create the table, leverages available parallelism
+    /// Almost the same as [`Self::recreate()`], but leverages available parallelism
     #[cfg(any(feature = "parallel", test))]
-    pub(super) fn create_parallel(seed: Seed) -> Self
+    pub(super) fn recreate_parallel(&mut self, seed: Seed)
     where
         EvaluatableUsize<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>: Sized,
     {
-        let partial_ys = partial_ys::<K>(seed);
-
-        let mut t_1 = Vec::with_capacity(1_usize << K);
-        for (x_start, partial_ys) in X::all::<K>().step_by(COMPUTE_F1_SIMD_FACTOR).zip(
-            partial_ys
-                .array_chunks::<{ K as usize * COMPUTE_F1_SIMD_FACTOR / u8::BITS as usize }>()
-                .copied(),
-        ) {
-            let xs: [_; COMPUTE_F1_SIMD_FACTOR] = seq!(N in 0..8 {
-                [
-                #(
-                #[allow(clippy::erasing_op, clippy::identity_op)]
-                {
-                    x_start + X::from(N)
-                },
-                )*
-                ]
-            });
-
-            let ys = compute_f1_simd::<K>(xs, &partial_ys);
-            t_1.extend(ys.into_iter().zip(xs));
-        }
+        let mut t_1 = compute_first_table_entries::<K>(seed);
 
         t_1.par_sort_unstable();
 
-        let (ys, xs) = t_1.into_iter().unzip();
+        self.fill_from_entries(&t_1);
+    }
 
-        Self::First { ys, xs }
+    fn fill_from_entries(&mut self, t_1: &[(Y, X)]) {
+        let Table::First { ys, xs } = self else {
+            unreachable!("Only first table can be recreated from seed; qed");
+        };
+
+        ys.clear();
+        ys.reserve(t_1.len());
+        xs.clear();
+        xs.reserve(t_1.len());
+
+        for &(y, x) in t_1 {
+            ys.push(y);
+            xs.push(x);
+        }
     }
 
     /// All `x`s as [`BitSlice`], for individual `x`s needs to be slices into [`K`] bits slices
@@ -632,6 +713,44 @@ where
     ) -> Self
     where
         EvaluatableUsize<{ metadata_size_bytes(K, PARENT_TABLE_NUMBER) }>: Sized,
+    {
+        let mut table = Self::Other {
+            ys: Vec::new(),
+            positions: Vec::new(),
+            metadatas: Vec::new(),
+        };
+        table.recreate(last_table, cache);
+        table
+    }
+
+    /// Almost the same as [`Self::create()`], but uses parallelism internally for better
+    /// performance (though not efficiency of CPU and memory usage), if you create multiple tables
+    /// in parallel, prefer [`Self::create()`] for better overall performance.
+    #[cfg(any(feature = "parallel", test))]
+    pub(super) fn create_parallel<const PARENT_TABLE_NUMBER: u8>(
+        last_table: &Table<K, PARENT_TABLE_NUMBER>,
+        cache: &mut TablesCache<K>,
+    ) -> Self
+    where
+        EvaluatableUsize<{ metadata_size_bytes(K, PARENT_TABLE_NUMBER) }>: Sized,
+    {
+        let mut table = Self::Other {
+            ys: Vec::new(),
+            positions: Vec::new(),
+            metadatas: Vec::new(),
+        };
+        table.recreate_parallel(last_table, cache);
+        table
+    }
+
+    /// Recreate [`TABLE_NUMBER`] table in place from a new parent table, reusing existing
+    /// allocations
+    pub(super) fn recreate<const PARENT_TABLE_NUMBER: u8>(
+        &mut self,
+        last_table: &Table<K, PARENT_TABLE_NUMBER>,
+        cache: &mut TablesCache<K>,
+    ) where
+        EvaluatableUsize<{ metadata_size_bytes(K, PARENT_TABLE_NUMBER) }>: Sized,
     {
         let buckets = &mut cache.buckets;
         let rmap_scratch = &mut cache.rmap_scratch;
@@ -689,36 +808,18 @@ where
 
         t_n.sort_unstable();
 
-        let mut ys = Vec::with_capacity(t_n.len());
-        let mut positions = Vec::with_capacity(t_n.len());
-        let mut metadatas = Vec::with_capacity(t_n.len());
-
-        for (y, [left_position, right_position], metadata) in t_n {
-            ys.push(y);
-            positions.push([left_position, right_position]);
-            // Last table doesn't have metadata
-            if metadata_size_bits(K, TABLE_NUMBER) > 0 {
-                metadatas.push(metadata);
-            }
-        }
-
-        Self::Other {
-            ys,
-            positions,
-            metadatas,
-        }
+        self.fill_from_entries(t_n.drain(..));
     }
 
-    /// Almost the same as [`Self::create()`], but uses parallelism internally for better
+    /// Almost the same as [`Self::recreate()`], but uses parallelism internally for better
     /// performance (though not efficiency of CPU and memory usage), if you create multiple tables
-    /// in parallel, prefer [`Self::create()`] for better overall performance.
-    // TODO: Cache more allocations in this function to improve performance further
+    /// in parallel, prefer [`Self::recreate()`] for better overall performance.
     #[cfg(any(feature = "parallel", test))]
-    pub(super) fn create_parallel<const PARENT_TABLE_NUMBER: u8>(
+    pub(super) fn recreate_parallel<const PARENT_TABLE_NUMBER: u8>(
+        &mut self,
         last_table: &Table<K, PARENT_TABLE_NUMBER>,
         cache: &mut TablesCache<K>,
-    ) -> Self
-    where
+    ) where
         EvaluatableUsize<{ metadata_size_bytes(K, PARENT_TABLE_NUMBER) }>: Sized,
     {
         let buckets = &mut cache.buckets;
@@ -787,28 +888,44 @@ where
         let mut t_n = t_n.into_iter().flatten().collect::<Vec<_>>();
         t_n.par_sort_unstable();
 
-        let mut ys = Vec::with_capacity(t_n.len());
-        let mut positions = Vec::with_capacity(t_n.len());
-        let mut metadatas = Vec::with_capacity(t_n.len());
-
-        for (y, [left_position, right_position], metadata) in t_n.drain(..) {
-            ys.push(y);
-            positions.push([left_position, right_position]);
-            // Last table doesn't have metadata
-            if metadata_size_bits(K, TABLE_NUMBER) > 0 {
-                metadatas.push(metadata);
-            }
-        }
+        self.fill_from_entries(t_n.drain(..));
 
         // Drop from a background thread, which typically helps with overall concurrency
         rayon::spawn(move || {
             drop(t_n);
         });
+    }
 
-        Self::Other {
+    fn fill_from_entries<I>(&mut self, t_n: I)
+    where
+        I: ExactSizeIterator<Item = (Y, [Position; 2], Metadata<K, TABLE_NUMBER>)>,
+    {
+        let Table::Other {
             ys,
             positions,
             metadatas,
+        } = self
+        else {
+            unreachable!("Only other tables can be recreated from parent table; qed");
+        };
+
+        ys.clear();
+        ys.reserve(t_n.len());
+        positions.clear();
+        positions.reserve(t_n.len());
+        metadatas.clear();
+        // Last table doesn't have metadata
+        if metadata_size_bits(K, TABLE_NUMBER) > 0 {
+            metadatas.reserve(t_n.len());
+        }
+
+        for (y, [left_position, right_position], metadata) in t_n {
+            ys.push(y);
+            positions.push([left_position, right_position]);
+            // Last table doesn't have metadata
+            if metadata_size_bits(K, TABLE_NUMBER) > 0 {
+                metadatas.push(metadata);
+            }
         }
     }
 }
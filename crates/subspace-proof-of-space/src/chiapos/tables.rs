@@ -107,6 +107,31 @@ where
         }
     }
 
+    /// Recreate Chia proof of space tables in place for a new seed, reusing memory allocated for
+    /// tables previously.
+    pub(super) fn recreate(&mut self, seed: Seed, cache: &mut TablesCache<K>) {
+        self.table_1.recreate(seed);
+        self.table_2.recreate(&self.table_1, cache);
+        self.table_3.recreate(&self.table_2, cache);
+        self.table_4.recreate(&self.table_3, cache);
+        self.table_5.recreate(&self.table_4, cache);
+        self.table_6.recreate(&self.table_5, cache);
+        self.table_7.recreate(&self.table_6, cache);
+    }
+
+    /// Almost the same as [`Self::recreate()`], but uses parallelism internally for better
+    /// performance (though not efficiency of CPU and memory usage).
+    #[cfg(any(feature = "parallel", test))]
+    pub(super) fn recreate_parallel(&mut self, seed: Seed, cache: &mut TablesCache<K>) {
+        self.table_1.recreate_parallel(seed);
+        self.table_2.recreate_parallel(&self.table_1, cache);
+        self.table_3.recreate_parallel(&self.table_2, cache);
+        self.table_4.recreate_parallel(&self.table_3, cache);
+        self.table_5.recreate_parallel(&self.table_4, cache);
+        self.table_6.recreate_parallel(&self.table_5, cache);
+        self.table_7.recreate_parallel(&self.table_6, cache);
+    }
+
     /// Find proof of space quality for given challenge.
     pub(super) fn find_quality<'a>(
         &'a self,
@@ -41,3 +41,33 @@ fn self_verification() {
         }
     }
 }
+
+#[test]
+fn recreate() {
+    let seed_1 = [1; 32];
+    let seed_2 = [2; 32];
+    let mut cache = TablesCache::default();
+
+    let mut tables = Tables::<K>::create(seed_1, &mut cache);
+    let mut tables_parallel = Tables::<K>::create_parallel(seed_1, &mut cache);
+    tables.recreate(seed_2, &mut cache);
+    tables_parallel.recreate_parallel(seed_2, &mut cache);
+
+    let expected_tables = Tables::<K>::create_simple(seed_2);
+
+    for challenge_index in 0..1000_u32 {
+        let mut challenge = [0; 32];
+        challenge[..mem::size_of::<u32>()].copy_from_slice(&challenge_index.to_le_bytes());
+        let expected_proofs = expected_tables.find_proof(&challenge).collect::<Vec<_>>();
+        assert_eq!(
+            expected_proofs,
+            tables.find_proof(&challenge).collect::<Vec<_>>(),
+            "challenge index {challenge_index}"
+        );
+        assert_eq!(
+            expected_proofs,
+            tables_parallel.find_proof(&challenge).collect::<Vec<_>>(),
+            "challenge index {challenge_index}"
+        );
+    }
+}
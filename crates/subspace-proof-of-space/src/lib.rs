@@ -44,12 +44,42 @@ pub trait TableGenerator<T: Table>: fmt::Debug + Default + Clone + Send + Sized
     fn generate_parallel(&mut self, seed: &PosSeed) -> T {
         self.generate(seed)
     }
+
+    /// Generate table with 32 bytes seed, reusing memory of the table generated by this generator
+    /// previously.
+    ///
+    /// Returned table is stored inside of the generator and is overwritten by the next call, this
+    /// avoids allocating memory for every table and keeps memory usage of the generator
+    /// predictable. There is also [`Self::generate_parallel_in_place()`] that can achieve lower
+    /// latency.
+    fn generate_in_place(&mut self, seed: &PosSeed) -> &T;
+
+    /// Generate table with 32 bytes seed using parallelism, reusing memory of the table generated by
+    /// this generator previously.
+    ///
+    /// This implementation will trade efficiency of CPU and memory usage for lower latency, prefer
+    /// [`Self::generate_in_place()`] unless lower latency is critical.
+    #[cfg(any(feature = "parallel", test))]
+    fn generate_parallel_in_place(&mut self, seed: &PosSeed) -> &T {
+        self.generate_in_place(seed)
+    }
+
+    /// Release memory of the table kept by [`Self::generate_in_place()`], should be called once
+    /// there are no more tables to generate for a while, such that idle generator doesn't hold
+    /// memory of the whole table.
+    fn release_memory(&mut self);
 }
 
 /// Proof of space kind
 pub trait Table: Sized + Send + Sync + 'static {
     /// Proof of space table type
     const TABLE_TYPE: PosTableType;
+    /// Estimated memory usage in bytes of [`Self::Generator`] that is generating tables in place,
+    /// including the table itself
+    const GENERATOR_MEMORY_USAGE: usize;
+    /// Estimated memory usage in bytes of [`Self::Generator`] between tables, after
+    /// [`TableGenerator::release_memory()`] was called
+    const GENERATOR_IDLE_MEMORY_USAGE: usize;
     /// Instance that can be used to generate tables with better performance
     type Generator: TableGenerator<Self>;
 
@@ -2,7 +2,7 @@
 //! purposes to reduce memory and CPU usage
 
 use crate::{PosTableType, Table, TableGenerator};
-use core::iter;
+use core::{iter, mem};
 use subspace_core_primitives::crypto::blake3_hash;
 use subspace_core_primitives::{PosProof, PosSeed, U256};
 
@@ -10,24 +10,37 @@ use subspace_core_primitives::{PosProof, PosSeed, U256};
 ///
 /// Shim implementation.
 #[derive(Debug, Default, Clone)]
-pub struct ShimTableGenerator;
+pub struct ShimTableGenerator {
+    /// Table that is recreated in place by [`TableGenerator::generate_in_place()`]
+    table: Option<ShimTable>,
+}
 
 impl TableGenerator<ShimTable> for ShimTableGenerator {
     fn generate(&mut self, seed: &PosSeed) -> ShimTable {
         ShimTable::generate(seed)
     }
+
+    fn generate_in_place(&mut self, seed: &PosSeed) -> &ShimTable {
+        self.table.insert(ShimTable::generate(seed))
+    }
+
+    fn release_memory(&mut self) {
+        self.table.take();
+    }
 }
 
 /// Subspace proof of space table.
 ///
 /// Shim implementation.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ShimTable {
     seed: PosSeed,
 }
 
 impl Table for ShimTable {
     const TABLE_TYPE: PosTableType = PosTableType::Shim;
+    const GENERATOR_MEMORY_USAGE: usize = mem::size_of::<ShimTable>();
+    const GENERATOR_IDLE_MEMORY_USAGE: usize = mem::size_of::<ShimTableGenerator>();
     type Generator = ShimTableGenerator;
     fn generate(seed: &PosSeed) -> ShimTable {
         Self { seed: *seed }
@@ -41,6 +41,9 @@ pub struct FarmerAppInfo {
     pub farming_timeout: Duration,
     /// Protocol info for farmer
     pub protocol_info: FarmerProtocolInfo,
+    /// SS58 address format (prefix) of the chain, `None` if node doesn't report it
+    #[serde(default)]
+    pub ss58_format: Option<u16>,
 }
 
 /// Information about new slot that just arrived
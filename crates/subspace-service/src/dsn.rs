@@ -9,7 +9,7 @@ use subspace_networking::utils::strip_peer_id;
 use subspace_networking::{
     CreationError, KademliaMode, KnownPeersManager, KnownPeersManagerConfig,
     KnownPeersManagerPersistenceError, Node, NodeRunner, PieceByIndexRequestHandler,
-    SegmentHeaderBySegmentIndexesRequestHandler,
+    PiecesByIndexesRequestHandler, SegmentHeaderBySegmentIndexesRequestHandler,
 };
 use thiserror::Error;
 use tracing::{error, trace};
@@ -108,6 +108,7 @@ pub(crate) fn create_dsn_instance(
         request_response_protocols: vec![
             // We need to enable protocol to request pieces
             PieceByIndexRequestHandler::create(|_, _| async { None }),
+            PiecesByIndexesRequestHandler::create(|_, _| async { None }),
             SegmentHeaderBySegmentIndexesRequestHandler::create(move |_, _| async move { None }),
         ],
         max_established_incoming_connections: dsn_config.max_in_connections,
@@ -5,7 +5,7 @@ use subspace_archiving::archiver::is_piece_valid;
 use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::{Piece, PieceIndex};
 use subspace_networking::libp2p::PeerId;
-use subspace_networking::utils::piece_provider::PieceValidator;
+use subspace_networking::utils::piece_provider::{PieceValidator, PieceValidity};
 use subspace_networking::Node;
 use tracing::{error, warn};
 
@@ -43,9 +43,9 @@ where
         source_peer_id: PeerId,
         piece_index: PieceIndex,
         piece: Piece,
-    ) -> Option<Piece> {
+    ) -> PieceValidity {
         if source_peer_id == self.dsn_node.id() {
-            return Some(piece);
+            return PieceValidity::Valid(piece);
         }
 
         let segment_index = piece_index.segment_index();
@@ -56,7 +56,7 @@ where
             None => {
                 error!(%segment_index, "No segment commitment in the cache.");
 
-                return None;
+                return PieceValidity::Unknown;
             }
         };
 
@@ -69,9 +69,9 @@ where
             }
         });
 
-        match is_valid_fut.await.unwrap_or_default() {
-            Some(piece) => Some(piece),
-            None => {
+        match is_valid_fut.await {
+            Ok(Some(piece)) => PieceValidity::Valid(piece),
+            Ok(None) => {
                 warn!(
                     %piece_index,
                     %source_peer_id,
@@ -80,7 +80,11 @@ where
 
                 // We don't care about result here
                 let _ = self.dsn_node.ban_peer(source_peer_id).await;
-                None
+                PieceValidity::Invalid
+            }
+            Err(error) => {
+                error!(%piece_index, %error, "Failed to validate piece");
+                PieceValidity::Unknown
             }
         }
     }